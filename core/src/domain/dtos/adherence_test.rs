@@ -16,6 +16,32 @@ pub struct AdherenceTest {
     pub rest: i32,
 }
 
+impl AdherenceTest {
+    /// Get the identifier of the tested clade.
+    pub fn clade_id(&self) -> u64 {
+        match &self.clade {
+            UntaggedParent::Record(clade) => clade.id,
+            UntaggedParent::Id(id) => *id,
+        }
+    }
+
+    /// Check if the clade wins the one-vs-rest test.
+    ///
+    /// The clade wins if `one` exceeds `rest` by at least `min_margin` kmers
+    /// and, if a ratio is given, if `one` is at least `min_ratio` times
+    /// `rest`.
+    pub fn wins(&self, min_margin: i32, min_ratio: Option<f64>) -> bool {
+        if self.one <= self.rest || self.one - self.rest < min_margin {
+            return false;
+        }
+
+        match min_ratio {
+            None => true,
+            Some(ratio) => self.one as f64 >= ratio * self.rest as f64,
+        }
+    }
+}
+
 impl std::fmt::Display for AdherenceTest {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -25,3 +51,29 @@ impl std::fmt::Display for AdherenceTest {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adherence_test_wins_with_thresholds() {
+        let marginal = AdherenceTest {
+            clade: UntaggedParent::Id(1),
+            one: 3,
+            rest: 2,
+        };
+
+        assert!(marginal.wins(0, None));
+        assert!(!marginal.wins(10, None));
+        assert!(!marginal.wins(0, Some(2.0)));
+
+        let clear = AdherenceTest {
+            clade: UntaggedParent::Id(1),
+            one: 40,
+            rest: 10,
+        };
+
+        assert!(clear.wins(10, Some(2.0)));
+    }
+}
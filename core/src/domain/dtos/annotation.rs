@@ -31,6 +31,45 @@ pub struct Annotation {
     /// A simple list of tags associated with the annotation.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub meta: Option<Vec<Tag>>,
+
+    /// The provenance of the annotation (e.g. the annotator or the source
+    /// database).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+
+    /// The version of the annotation source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+impl Annotation {
+    /// Get the scientific name of the annotated clade, if any.
+    pub fn sci_name(&self) -> Option<&str> {
+        self.meta.iter().flatten().find_map(|tag| match tag {
+            Tag::SciName(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Build a taxonomic lineage from annotations sorted from root to tip.
+    ///
+    /// The scientific names of the annotated clades are joined by the
+    /// delimiter (e.g. `Fungi;Ascomycota;Colletotrichum`). Returns `None` if
+    /// no annotation contains a scientific name.
+    pub fn lineage(
+        annotations: &[Annotation],
+        delimiter: &str,
+    ) -> Option<String> {
+        let names = annotations
+            .iter()
+            .filter_map(|annotation| annotation.sci_name())
+            .collect::<Vec<&str>>();
+
+        match names.is_empty() {
+            true => None,
+            false => Some(names.join(delimiter)),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -47,6 +86,8 @@ mod tests {
                 Tag::SciName("Colletotrichum higginsianum".to_string()),
                 Tag::Note("any other tag".to_string()),
             ]),
+            source: None,
+            version: None,
         };
 
         let annotations = vec![annotation.clone(), annotation.clone()];
@@ -58,4 +99,44 @@ mod tests {
         let mut file = std::fs::File::create(&path).unwrap();
         file.write_all(yaml.as_bytes()).unwrap();
     }
+
+    #[test]
+    fn test_annotation_without_provenance() {
+        let annotations: Vec<Annotation> = serde_yaml::from_str(
+            "# Annotations without provenance\n\
+             - clade: 1\n  meta:\n  - SciName: Colletotrichum\n\
+             - clade: 2\n",
+        )
+        .unwrap();
+
+        assert_eq!(annotations.len(), 2);
+        assert_eq!(annotations[0].sci_name(), Some("Colletotrichum"));
+        assert!(annotations
+            .iter()
+            .all(|i| i.source.is_none() && i.version.is_none()));
+
+        let yaml = serde_yaml::to_string(&annotations).unwrap();
+
+        assert!(!yaml.contains("source"));
+        assert!(!yaml.contains("version"));
+    }
+
+    #[test]
+    fn test_annotation_with_provenance() {
+        let annotations: Vec<Annotation> = serde_yaml::from_str(
+            "# Annotations with provenance\n\
+             - clade: 1\n  meta:\n  - Rank: genus\n  \
+             source: NCBI Taxonomy\n  version: '2024-01-01'\n",
+        )
+        .unwrap();
+
+        assert_eq!(annotations[0].source.as_deref(), Some("NCBI Taxonomy"));
+        assert_eq!(annotations[0].version.as_deref(), Some("2024-01-01"));
+
+        let round_trip: Vec<Annotation> =
+            serde_yaml::from_str(&serde_yaml::to_string(&annotations).unwrap())
+                .unwrap();
+
+        assert_eq!(round_trip, annotations);
+    }
 }
@@ -0,0 +1,203 @@
+use super::{kmers_map::KmersMap, msa_window::MsaWindow};
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildOptions {
+    /// The kmers size.
+    pub k_size: u64,
+
+    /// The minimizers size.
+    pub m_size: u64,
+
+    /// Branches with support below this value are removed from the tree.
+    pub min_branch_support: f64,
+
+    /// If true, runs of identical bases are collapsed before building kmers.
+    pub homopolymer_compress: bool,
+
+    /// If true, kmers overlapping soft-masked (lowercase) bases of the MSA are
+    /// not indexed.
+    pub exclude_soft_masked: bool,
+
+    /// If true, reverse complement kmers are not indexed. Used for stranded
+    /// protocols.
+    pub forward_only: bool,
+
+    /// If true, the MSA contains amino acid sequences and protein kmers are
+    /// indexed. Nucleotide queries should be translated before placement.
+    #[serde(default)]
+    pub protein: bool,
+
+    /// If provided, only the MSA columns within the window are indexed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub msa_window: Option<MsaWindow>,
+
+    /// If provided, MSA columns with a fraction of gaps (and undetermined
+    /// bases) above this value are not indexed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_gap_fraction: Option<f64>,
+
+    /// If true, MSA columns containing a gap in any record are not indexed,
+    /// then kmers of all records are built over the same alignment columns.
+    #[serde(default)]
+    pub aligned_kmers: bool,
+
+    /// If provided, kmers containing IUPAC ambiguity codes are expanded to
+    /// their ACGT variants, and kmers with more variants than this value are
+    /// skipped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_ambiguity_expansions: Option<usize>,
+
+    /// The zero based index of the tree used from Nexus files containing
+    /// multiple trees.
+    pub tree_index: Option<usize>,
+
+    /// If true, a representative string of each kmer hash is stored in the
+    /// database. It allows reporting the kmers driving placements at the cost
+    /// of a larger database.
+    pub retain_kmer_strings: bool,
+
+    /// If true, collisions of the 64-bit kmer hashes are counted and reported
+    /// after the indexing. Kmer strings are kept in memory during the build.
+    pub detect_collisions: bool,
+
+    /// If provided, the partial index is spilled to disk whenever it holds
+    /// more than this number of kmers, and spilled partial indexes are merged
+    /// after the MSA reading.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_resident_kmers: Option<usize>,
+
+    /// The directory of the spilled partial indexes. The system temporary
+    /// directory is used if not provided.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spill_directory: Option<PathBuf>,
+}
+
+impl Default for BuildOptions {
+    fn default() -> Self {
+        BuildOptions {
+            k_size: KmersMap::DEFAULT_K,
+            m_size: KmersMap::DEFAULT_M,
+            min_branch_support: 70.0,
+            homopolymer_compress: false,
+            exclude_soft_masked: false,
+            forward_only: false,
+            protein: false,
+            msa_window: None,
+            max_gap_fraction: None,
+            aligned_kmers: false,
+            max_ambiguity_expansions: None,
+            tree_index: None,
+            retain_kmer_strings: false,
+            detect_collisions: false,
+            max_resident_kmers: None,
+            spill_directory: None,
+        }
+    }
+}
+
+impl BuildOptions {
+    /// Set the kmers size.
+    ///
+    /// If `None` is given, the current value is kept.
+    pub fn with_k_size(mut self, value: Option<u64>) -> Self {
+        if let Some(value) = value {
+            self.k_size = value;
+        }
+
+        self
+    }
+
+    /// Set the minimizers size.
+    ///
+    /// If `None` is given, the current value is kept.
+    pub fn with_m_size(mut self, value: Option<u64>) -> Self {
+        if let Some(value) = value {
+            self.m_size = value;
+        }
+
+        self
+    }
+
+    /// Set the minimum branch support.
+    ///
+    /// If `None` is given, the current value is kept.
+    pub fn with_min_branch_support(mut self, value: Option<f64>) -> Self {
+        if let Some(value) = value {
+            self.min_branch_support = value;
+        }
+
+        self
+    }
+
+    pub fn with_homopolymer_compress(mut self, value: bool) -> Self {
+        self.homopolymer_compress = value;
+        self
+    }
+
+    pub fn with_exclude_soft_masked(mut self, value: bool) -> Self {
+        self.exclude_soft_masked = value;
+        self
+    }
+
+    pub fn with_forward_only(mut self, value: bool) -> Self {
+        self.forward_only = value;
+        self
+    }
+
+    pub fn with_protein(mut self, value: bool) -> Self {
+        self.protein = value;
+        self
+    }
+
+    pub fn with_msa_window(mut self, value: Option<MsaWindow>) -> Self {
+        self.msa_window = value;
+        self
+    }
+
+    pub fn with_max_gap_fraction(mut self, value: Option<f64>) -> Self {
+        self.max_gap_fraction = value;
+        self
+    }
+
+    pub fn with_aligned_kmers(mut self, value: bool) -> Self {
+        self.aligned_kmers = value;
+        self
+    }
+
+    pub fn with_max_ambiguity_expansions(
+        mut self,
+        value: Option<usize>,
+    ) -> Self {
+        self.max_ambiguity_expansions = value;
+        self
+    }
+
+    pub fn with_tree_index(mut self, value: Option<usize>) -> Self {
+        self.tree_index = value;
+        self
+    }
+
+    pub fn with_retain_kmer_strings(mut self, value: bool) -> Self {
+        self.retain_kmer_strings = value;
+        self
+    }
+
+    pub fn with_detect_collisions(mut self, value: bool) -> Self {
+        self.detect_collisions = value;
+        self
+    }
+
+    pub fn with_max_resident_kmers(mut self, value: Option<usize>) -> Self {
+        self.max_resident_kmers = value;
+        self
+    }
+
+    pub fn with_spill_directory(mut self, value: Option<PathBuf>) -> Self {
+        self.spill_directory = value;
+        self
+    }
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// The key used to partition placement results into separate files
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "camelCase")]
+pub enum ChunkBy {
+    /// One file per placement status (e.g. `IdentityFound.jsonl`)
+    Status,
+
+    /// One file per placed clade (e.g. `clade-42.jsonl`). Queries without a
+    /// placed clade are written to `unplaced.jsonl`.
+    Clade,
+}
@@ -37,6 +37,40 @@ pub struct Clade {
     pub children: Option<Vec<Self>>,
 }
 
+/// The branch support of a clade along a placement path
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CladeSupport {
+    pub clade: u64,
+
+    /// The original branch support, not available for the root and leaves
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub support: Option<f64>,
+}
+
+/// A pre-order iterator over borrowed clades. See `Clade::iter`.
+pub struct CladeIter<'a> {
+    stack: Vec<&'a Clade>,
+}
+
+impl<'a> Iterator for CladeIter<'a> {
+    type Item = &'a Clade;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let clade = self.stack.pop()?;
+
+        //
+        // Children are stacked in reverse order, then the first child is
+        // visited first
+        //
+        if let Some(children) = &clade.children {
+            self.stack.extend(children.iter().rev());
+        }
+
+        Some(clade)
+    }
+}
+
 impl std::fmt::Display for Clade {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Clade: {}", self.id)
@@ -92,6 +126,82 @@ impl Clade {
         }
     }
 
+    /// Graft subtrees as children of a new shared root.
+    ///
+    /// The subtree roots are converted to internal nodes of the new root,
+    /// then their ids should not collide with the root id (zero) nor between
+    /// subtrees (see `renumber`).
+    pub fn graft(subtrees: Vec<Clade>) -> Clade {
+        let children = subtrees
+            .into_iter()
+            .map(|mut subtree| {
+                subtree.parent = Some(0);
+                subtree.kind = NodeType::Node;
+                subtree
+            })
+            .collect();
+
+        Clade::new_root(0.0, Some(children))
+    }
+
+    /// Add an offset to the ids of the clade subtree.
+    ///
+    /// Parent ids are updated accordingly.
+    pub fn renumber(&mut self, offset: u64) {
+        self.id += offset;
+        self.parent = self.parent.map(|parent| parent + offset);
+
+        if let Some(children) = self.children.as_mut() {
+            for child in children {
+                child.renumber(offset);
+            }
+        }
+    }
+
+    /// Get the greatest id of the clade subtree.
+    pub fn max_id(&self) -> u64 {
+        self.children
+            .iter()
+            .flatten()
+            .map(|child| child.max_id())
+            .fold(self.id, u64::max)
+    }
+
+    /// Count the leaves of the clade subtree.
+    pub fn count_leaves(&self) -> usize {
+        self.count_nodes(|clade| clade.is_leaf())
+    }
+
+    /// Count the non-leaf nodes of the clade subtree, including the clade
+    /// itself.
+    pub fn count_internal(&self) -> usize {
+        self.count_nodes(|clade| !clade.is_leaf())
+    }
+
+    /// Count the subtree nodes matching the predicate.
+    fn count_nodes(&self, predicate: impl Fn(&Clade) -> bool) -> usize {
+        self.iter().filter(|clade| predicate(clade)).count()
+    }
+
+    /// Iterate over the clade subtree in pre-order, including the clade
+    /// itself.
+    ///
+    /// Nodes are borrowed, then the subtree is never cloned. The subtree is
+    /// traversed iteratively, avoiding recursion limits on deep trees.
+    pub fn iter(&self) -> CladeIter<'_> {
+        CladeIter { stack: vec![self] }
+    }
+
+    /// Get the direct children of the subtree node with the given id.
+    ///
+    /// Returns an empty slice if the node does not exist or is a leaf.
+    pub fn children_of(&self, id: u64) -> &[Clade] {
+        self.iter()
+            .find(|clade| clade.id == id)
+            .and_then(|clade| clade.children.as_deref())
+            .unwrap_or_default()
+    }
+
     pub fn get_node_by_id(&self, id: u64) -> Option<&Clade> {
         if self.id == id {
             return Some(self);
@@ -108,6 +218,18 @@ impl Clade {
         None
     }
 
+    /// Get a mutable reference to the subtree node with the given id.
+    pub fn get_node_by_id_mut(&mut self, id: u64) -> Option<&mut Clade> {
+        if self.id == id {
+            return Some(self);
+        }
+
+        self.children
+            .iter_mut()
+            .flatten()
+            .find_map(|child| child.get_node_by_id_mut(id))
+    }
+
     pub fn get_path_to_root(&self, root: &Clade) -> HashSet<u64> {
         let mut path = HashSet::<u64>::new();
 
@@ -155,6 +277,45 @@ impl Clade {
         leaves
     }
 
+    /// Export the clade subtree in Newick format, without the trailing
+    /// semicolon.
+    ///
+    /// Leaves are labeled by name. Internal nodes are labeled by the given
+    /// function, and branch lengths are kept.
+    pub fn to_newick(
+        &self,
+        label: &impl Fn(&Clade) -> Option<String>,
+    ) -> String {
+        let mut newick = String::new();
+
+        if let Some(children) = &self.children {
+            newick.push('(');
+            newick.push_str(
+                &children
+                    .iter()
+                    .map(|child| child.to_newick(label))
+                    .collect::<Vec<String>>()
+                    .join(","),
+            );
+            newick.push(')');
+        }
+
+        let name = match self.is_leaf() {
+            true => self.name.to_owned(),
+            false => label(self),
+        };
+
+        if let Some(name) = name {
+            newick.push_str(&name);
+        }
+
+        if let Some(length) = self.length {
+            newick.push_str(&format!(":{length}"));
+        }
+
+        newick
+    }
+
     pub fn is_root(&self) -> bool {
         if let NodeType::Root = self.kind {
             true
@@ -179,3 +340,46 @@ impl Clade {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clade_iter_in_pre_order() {
+        let root = Clade::new_root(
+            0.0,
+            Some(vec![
+                Clade::new_internal(
+                    1,
+                    0,
+                    None,
+                    None,
+                    None,
+                    Some(vec![
+                        Clade::new_leaf(2, 1, "A".to_string(), None),
+                        Clade::new_leaf(3, 1, "B".to_string(), None),
+                    ]),
+                ),
+                Clade::new_leaf(4, 0, "C".to_string(), None),
+            ]),
+        );
+
+        assert_eq!(
+            root.iter().map(|clade| clade.id).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4]
+        );
+
+        assert_eq!(
+            root.children_of(1)
+                .iter()
+                .map(|clade| clade.id)
+                .collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+
+        assert!(root.children_of(4).is_empty());
+        assert!(root.children_of(99).is_empty());
+        assert_eq!(root.count_leaves(), 3);
+    }
+}
@@ -5,12 +5,17 @@
 /// implementation.
 ///
 ///
-use super::sequence::{Sequence, SequenceBody};
+use super::{
+    msa_columns::MsaColumns,
+    quality_filter::QualityFilter,
+    sequence::{Sequence, SequenceBody, ValidationReport},
+};
 
+use std::borrow::Cow;
 use std::io::{self, BufRead};
 use std::marker::PhantomData;
 use std::str::FromStr;
-use std::sync::atomic::AtomicBool;
+use std::sync::{atomic::AtomicBool, mpsc::SyncSender};
 use thiserror::Error;
 static STDIN_HAS_BEEN_USED: AtomicBool = AtomicBool::new(false);
 
@@ -22,13 +27,31 @@ pub enum StdinError {
     StdIn(#[from] io::Error),
     #[error("unable to parse from_str: {0}")]
     FromStr(String),
+    #[error("unable to read from url: {0}")]
+    Url(String),
 }
 
-/// Source of the value contents will be either from `stdin` or a CLI arg provided value
+/// Source of the value contents will be either from `stdin`, a CLI arg
+/// provided value, or a remote URL
 #[derive(Clone)]
 pub enum Source {
     Stdin,
     Arg(String),
+
+    /// An `http(s)://` or `s3://` URL. Reading requires the `remote-input`
+    /// feature.
+    Url(String),
+}
+
+impl Source {
+    /// The schemes read as remote sources
+    const URL_SCHEMES: [&'static str; 3] = ["http://", "https://", "s3://"];
+
+    fn is_url(value: &str) -> bool {
+        Self::URL_SCHEMES
+            .iter()
+            .any(|scheme| value.starts_with(scheme))
+    }
 }
 
 impl FromStr for Source {
@@ -46,6 +69,7 @@ impl FromStr for Source {
                     .store(true, std::sync::atomic::Ordering::SeqCst);
                 Ok(Self::Stdin)
             }
+            arg if Self::is_url(arg) => Ok(Self::Url(arg.to_owned())),
             arg => Ok(Self::Arg(arg.to_owned())),
         }
     }
@@ -56,6 +80,7 @@ impl std::fmt::Debug for Source {
         match self {
             Source::Stdin => write!(f, "stdin"),
             Source::Arg(v) => v.fmt(f),
+            Source::Url(v) => v.fmt(f),
         }
     }
 }
@@ -67,31 +92,163 @@ pub struct FileOrStdin<T = String> {
 }
 
 impl FileOrStdin {
+    /// The default number of records buffered between the reader and the
+    /// consumer of a sequences channel
+    pub const CHANNEL_BOUND: usize = 1024;
+
     /// Read content and build a fasta sequence
     ///
     /// Each fasta sequence should be returned through a channel received by the
     /// caller. The channel is used to avoid memory overhead when reading large
-    /// files.
+    /// files. The channel is bounded, then the reader blocks while the consumer
+    /// is behind, and the memory of unconsumed records stays bounded.
     ///
     pub fn sequence_content_by_channel(
         self,
-        chan: std::sync::mpsc::Sender<Sequence>,
+        chan: SyncSender<Sequence>,
+    ) -> Result<(), StdinError> {
+        self.sequence_content_by_channel_with_quality(
+            chan,
+            &QualityFilter::default(),
+        )
+    }
+
+    /// Read MSA content restricted to a set of alignment columns
+    ///
+    /// Works as `sequence_content_by_channel`, but bases out of the kept
+    /// columns are dropped before the gaps removal. Then, columns are counted
+    /// over the aligned sequences.
+    ///
+    pub fn msa_content_by_channel(
+        self,
+        chan: SyncSender<Sequence>,
+        columns: &MsaColumns,
+    ) -> Result<(), StdinError> {
+        let lines = self.into_msa_lines()?;
+
+        Self::fasta_content_by_channel(lines, chan, Some(columns), false)
+    }
+
+    /// Read protein MSA content
+    ///
+    /// Works as `msa_content_by_channel`, but amino acids are kept instead of
+    /// the IUPAC nucleotides, and all columns are read if not restricted.
+    ///
+    pub fn protein_msa_content_by_channel(
+        self,
+        chan: SyncSender<Sequence>,
+        columns: Option<&MsaColumns>,
+    ) -> Result<(), StdinError> {
+        let lines = self.into_msa_lines()?;
+
+        Self::fasta_content_by_channel(lines, chan, columns, true)
+    }
+
+    /// Read the non-empty MSA lines without trailing spaces
+    fn into_msa_lines(
+        self,
+    ) -> Result<impl Iterator<Item = io::Result<String>>, StdinError> {
+        Ok(self
+            .into_chunked_reader()?
+            .lines()
+            .map(|line| {
+                line.map(|mut line| {
+                    line.truncate(line.trim_end().len());
+                    line
+                })
+            })
+            .filter(|line| !matches!(line, Ok(line) if line.is_empty())))
+    }
+
+    /// Read FASTA or FASTQ content and build sequences
+    ///
+    /// The content is parsed as FASTQ if the first record starts with `@`.
+    /// FASTQ records are expected to contain single line sequences, and the
+    /// quality filter is applied to each read before sending it through the
+    /// channel. Reads rejected by the filter are skipped.
+    ///
+    pub fn sequence_content_by_channel_with_quality(
+        self,
+        chan: SyncSender<Sequence>,
+        filter: &QualityFilter,
+    ) -> Result<(), StdinError> {
+        Self::sequence_content_from_reader(
+            self.into_chunked_reader()?,
+            chan,
+            filter,
+        )
+    }
+
+    /// Read FASTA or FASTQ content from any reader and build sequences
+    ///
+    /// Works as `sequence_content_by_channel_with_quality`, allowing in-memory
+    /// content (e.g. request bodies) to be parsed.
+    ///
+    pub fn sequence_content_from_reader(
+        reader: impl BufRead,
+        chan: SyncSender<Sequence>,
+        filter: &QualityFilter,
+    ) -> Result<(), StdinError> {
+        //
+        // Trailing carriage returns and spaces are removed, then CRLF files
+        // produce the same headers and sequences as LF ones
+        //
+        let mut lines = reader
+            .lines()
+            .map(|line| {
+                line.map(|mut line| {
+                    line.truncate(line.trim_end().len());
+                    line
+                })
+            })
+            .filter(|line| !matches!(line, Ok(line) if line.is_empty()))
+            .peekable();
+
+        match lines.peek() {
+            Some(Ok(line)) if line.starts_with('@') => {
+                Self::fastq_content_by_channel(lines, chan, filter)
+            }
+            _ => Self::fasta_content_by_channel(lines, chan, None, false),
+        }
+    }
+
+    fn fasta_content_by_channel(
+        lines: impl Iterator<Item = io::Result<String>>,
+        chan: SyncSender<Sequence>,
+        columns: Option<&MsaColumns>,
+        protein: bool,
     ) -> Result<(), StdinError> {
-        let reader = self.into_chunked_reader()?;
         let mut header = String::new();
         let mut sequence = String::new();
+        let mut report = ValidationReport::default();
 
-        for line in reader.lines() {
-            let line = line?;
+        //
+        // The number of alignment columns read from the current record
+        //
+        let mut column = 0;
 
-            if line.is_empty() {
-                continue;
-            }
+        for line in lines {
+            let line = line?;
 
             if line.starts_with('>') {
                 if !header.is_empty() {
-                    chan.send(Sequence::new(header.clone(), sequence.clone()))
-                        .unwrap();
+                    //
+                    // The receiver is dropped when the consumer stops early
+                    // (e.g. on a failed record), then the remaining records
+                    // are not read
+                    //
+                    if chan
+                        .send(
+                            Sequence::new(header.clone(), sequence.clone())
+                                .with_validation_report(std::mem::take(
+                                    &mut report,
+                                )),
+                        )
+                        .is_err()
+                    {
+                        return Ok(());
+                    }
+
                     sequence.clear();
                 } else if !sequence.is_empty() {
                     return Err(StdinError::FromStr(
@@ -99,71 +256,240 @@ impl FileOrStdin {
                     ));
                 }
 
-                header = line.replace(">", "");
+                header = line.replace(">", "").trim().to_owned();
+                column = 0;
             } else {
+                let content = match columns {
+                    None => Cow::Borrowed(line.as_str()),
+                    Some(columns) => columns.select(&line, column),
+                };
+
+                column += line.len();
+
+                //
+                // Amino acids are not IUPAC nucleotides, then protein records
+                // are not validated
+                //
+                if protein {
+                    sequence.push_str(
+                        SequenceBody::remove_non_amino_acids_keeping_case(
+                            &content,
+                        )
+                        .as_str(),
+                    );
+                    continue;
+                }
+
+                report.update(&content);
                 sequence.push_str(
-                    SequenceBody::remove_non_iupac_from_sequence(&line)
+                    SequenceBody::remove_non_iupac_keeping_case(&content)
                         .as_str(),
                 );
             }
         }
 
         if !header.is_empty() && !sequence.is_empty() {
-            chan.send(Sequence::new(header, sequence)).unwrap();
+            let _ = chan.send(
+                Sequence::new(header, sequence).with_validation_report(report),
+            );
         };
 
         Ok(())
     }
 
-    /// Read content and build a fasta sequence
+    fn fastq_content_by_channel(
+        mut lines: impl Iterator<Item = io::Result<String>>,
+        chan: SyncSender<Sequence>,
+        filter: &QualityFilter,
+    ) -> Result<(), StdinError> {
+        while let Some(header) = lines.next() {
+            let header = header?;
+
+            let header = match header.strip_prefix('@') {
+                Some(header) => header.to_owned(),
+                None => {
+                    return Err(StdinError::FromStr(format!(
+                        "unexpected FASTQ header: {header}"
+                    )))
+                }
+            };
+
+            let (sequence, separator, quality) =
+                match (lines.next(), lines.next(), lines.next()) {
+                    (Some(sequence), Some(separator), Some(quality)) => {
+                        (sequence?, separator?, quality?)
+                    }
+                    _ => {
+                        return Err(StdinError::FromStr(format!(
+                            "truncated FASTQ record: {header}"
+                        )))
+                    }
+                };
+
+            if !separator.starts_with('+') || sequence.len() != quality.len() {
+                return Err(StdinError::FromStr(format!(
+                    "invalid FASTQ record: {header}"
+                )));
+            }
+
+            if let Some(filtered) = filter.apply(&sequence, &quality) {
+                //
+                // Stop reading if the receiver was dropped
+                //
+                if chan
+                    .send(
+                        Sequence::new(header, filtered).with_validation_report(
+                            ValidationReport::from_sequence(&sequence),
+                        ),
+                    )
+                    .is_err()
+                {
+                    return Ok(());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get the fraction of gaps of each MSA column
     ///
-    /// Content should be a multi fasta file. Each fasta record can contain a
-    /// fasta header starting with `>` and a sequence of a single line or
-    /// multiline sequence.
+    /// Gaps (`-`, `.` and `*`) and undetermined bases (`N`) are counted along
+    /// all records. Records shorter than the alignment count as gaps for the
+    /// missing columns. The content is read without being consumed, then
+    /// stdin is not supported.
     ///
-    #[deprecated(
-        since = "0.3.0",
-        note = "Use `sequence_content_by_channel` instead."
-    )]
-    pub fn sequence_content(self) -> Result<Vec<Sequence>, StdinError> {
-        let reader = self.into_chunked_reader()?;
+    pub fn msa_gap_fractions(&self) -> Result<Vec<f64>, StdinError> {
+        if let Source::Stdin = self.source {
+            return Err(StdinError::FromStr(
+                "MSA columns could not be read twice from stdin".to_owned(),
+            ));
+        }
 
-        let mut sequences = Vec::<Sequence>::new();
-        let mut header = String::new();
-        let mut sequence = String::new();
+        //
+        // The number of records with a base at each column
+        //
+        let mut bases = Vec::<usize>::new();
+        let mut records = 0;
+        let mut column = 0;
 
-        for line in reader.lines() {
+        for line in self.into_chunked_reader()?.lines() {
             let line = line?;
+            let line = line.trim_end();
 
             if line.is_empty() {
                 continue;
             }
 
             if line.starts_with('>') {
-                if !header.is_empty() {
-                    sequences
-                        .push(Sequence::new(header.clone(), sequence.clone()));
-                    sequence.clear();
-                } else if !sequence.is_empty() {
-                    return Err(StdinError::FromStr(
-                        "unexpected sequence without header".to_owned(),
-                    ));
+                records += 1;
+                column = 0;
+                continue;
+            }
+
+            if bases.len() < column + line.len() {
+                bases.resize(column + line.len(), 0);
+            }
+
+            for (index, base) in line.bytes().enumerate() {
+                if !matches!(base, b'-' | b'.' | b'*' | b'N' | b'n') {
+                    bases[column + index] += 1;
                 }
+            }
 
-                header = line.replace(">", "");
-            } else {
-                sequence.push_str(
-                    SequenceBody::remove_non_iupac_from_sequence(&line)
-                        .as_str(),
-                );
+            column += line.len();
+        }
+
+        if records == 0 {
+            return Ok(vec![]);
+        }
+
+        Ok(bases
+            .into_iter()
+            .map(|count| 1.0 - count as f64 / records as f64)
+            .collect())
+    }
+
+    /// Flag the MSA columns containing a gap in any record
+    ///
+    /// Gaps (`-`, `.` and `*`) are flagged, while undetermined bases are not.
+    /// Records shorter than the alignment flag the missing columns. As done by
+    /// `msa_gap_fractions`, stdin is not supported.
+    ///
+    pub fn msa_gap_columns(&self) -> Result<Vec<bool>, StdinError> {
+        if let Source::Stdin = self.source {
+            return Err(StdinError::FromStr(
+                "MSA columns could not be read twice from stdin".to_owned(),
+            ));
+        }
+
+        let mut gaps = Vec::<bool>::new();
+        let mut records = 0;
+        let mut column = 0;
+
+        //
+        // The number of alignment columns of the shortest record
+        //
+        let mut shortest = usize::MAX;
+
+        for line in self.into_chunked_reader()?.lines() {
+            let line = line?;
+            let line = line.trim_end();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with('>') {
+                if records > 0 {
+                    shortest = shortest.min(column);
+                }
+
+                records += 1;
+                column = 0;
+                continue;
+            }
+
+            if gaps.len() < column + line.len() {
+                gaps.resize(column + line.len(), false);
+            }
+
+            for (index, base) in line.bytes().enumerate() {
+                if matches!(base, b'-' | b'.' | b'*') {
+                    gaps[column + index] = true;
+                }
             }
+
+            column += line.len();
         }
 
-        if !header.is_empty() && !sequence.is_empty() {
-            sequences.push(Sequence::new(header, sequence));
+        if records == 0 {
+            return Ok(vec![]);
         }
 
-        Ok(sequences)
+        for gap in gaps.iter_mut().skip(shortest.min(column)) {
+            *gap = true;
+        }
+
+        Ok(gaps)
+    }
+
+    /// Read pre-extracted kmers, one per line
+    ///
+    /// Only the first column of each line is kept, then outputs of kmer
+    /// counters (e.g. `kmer<TAB>count`) are accepted. The content is a single
+    /// query, then it is read at once.
+    ///
+    pub fn kmers_content(self) -> Result<Vec<String>, StdinError> {
+        let mut kmers = Vec::new();
+
+        for line in self.into_chunked_reader()?.lines() {
+            if let Some(kmer) = line?.split_whitespace().next() {
+                kmers.push(kmer.to_owned());
+            }
+        }
+
+        Ok(kmers)
     }
 
     fn into_chunked_reader(&self) -> Result<impl std::io::BufRead, StdinError> {
@@ -173,11 +499,57 @@ impl FileOrStdin {
                 let f = std::fs::File::open(filepath)?;
                 Box::new(f)
             }
+            Source::Url(url) => Self::url_reader(url)?,
         };
 
         Ok(std::io::BufReader::new(input))
     }
 
+    /// Stream the content of a remote URL
+    ///
+    /// Public S3 objects are fetched through the HTTPS endpoint of the bucket.
+    /// Gzip compressed content is detected from the magic bytes and
+    /// decompressed while read.
+    #[cfg(feature = "remote-input")]
+    fn url_reader(
+        url: &str,
+    ) -> Result<Box<dyn std::io::Read + 'static>, StdinError> {
+        let url = match url.strip_prefix("s3://") {
+            None => url.to_owned(),
+            Some(path) => match path.split_once('/') {
+                Some((bucket, key)) => {
+                    format!("https://{bucket}.s3.amazonaws.com/{key}")
+                }
+                None => {
+                    return Err(StdinError::Url(format!(
+                        "S3 url without object key: {url}"
+                    )))
+                }
+            },
+        };
+
+        let response = ureq::get(&url)
+            .call()
+            .map_err(|err| StdinError::Url(format!("{url}: {err}")))?;
+
+        let mut reader = io::BufReader::new(response.into_reader());
+
+        if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+            return Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader)));
+        }
+
+        Ok(Box::new(reader))
+    }
+
+    #[cfg(not(feature = "remote-input"))]
+    fn url_reader(
+        url: &str,
+    ) -> Result<Box<dyn std::io::Read + 'static>, StdinError> {
+        Err(StdinError::Url(format!(
+            "{url}: reading from urls requires the `remote-input` feature"
+        )))
+    }
+
     pub fn from_file(file: &str) -> Self {
         Self {
             source: Source::Arg(file.to_string()),
@@ -197,3 +569,172 @@ impl<T> FromStr for FileOrStdin<T> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufReader, Read},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            mpsc::sync_channel,
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    /// A reader counting the records started while reading
+    struct CountingReader<R> {
+        inner: R,
+        records: Arc<AtomicUsize>,
+    }
+
+    impl<R: Read> Read for CountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let size = self.inner.read(buf)?;
+
+            self.records.fetch_add(
+                buf[..size].iter().filter(|byte| **byte == b'>').count(),
+                Ordering::SeqCst,
+            );
+
+            Ok(size)
+        }
+    }
+
+    #[test]
+    fn test_sequence_channel_buffers_at_most_the_bound() {
+        let bound = 8;
+        let total = 5_000;
+
+        let content = (0..total)
+            .map(|index| format!(">query_{index}\n{}\n", "ACGT".repeat(50)))
+            .collect::<String>();
+
+        let records = Arc::new(AtomicUsize::new(0));
+
+        //
+        // A small read buffer avoids reading many records ahead of the parser
+        //
+        let reader = BufReader::with_capacity(
+            64,
+            CountingReader {
+                inner: io::Cursor::new(content.into_bytes()),
+                records: records.to_owned(),
+            },
+        );
+
+        let (sender, receiver) = sync_channel(bound);
+
+        let producer = thread::spawn(move || {
+            FileOrStdin::sequence_content_from_reader(
+                reader,
+                sender,
+                &QualityFilter::default(),
+            )
+        });
+
+        let mut received = 0;
+        let mut peak = 0;
+
+        for _ in receiver {
+            //
+            // A slow consumer at the start lets the reader fill the channel
+            //
+            if received < 20 {
+                thread::sleep(Duration::from_millis(5));
+            }
+
+            peak = peak.max(records.load(Ordering::SeqCst) - received);
+            received += 1;
+        }
+
+        producer.join().unwrap().unwrap();
+
+        assert_eq!(received, total);
+
+        //
+        // Besides the channel, the received record, one record blocked on send
+        // and the next record being parsed were started
+        //
+        assert!(peak >= bound, "The reader did not fill the channel");
+        assert!(peak <= bound + 3, "Peak buffered records: {peak}");
+    }
+
+    #[test]
+    fn test_url_arguments_are_read_as_remote_sources() {
+        for url in ["http://host/a.fasta", "s3://bucket/a.fasta"] {
+            let input = url.parse::<FileOrStdin>().unwrap();
+
+            assert!(matches!(input.source, Source::Url(ref i) if i == url));
+        }
+
+        let input = "a.fasta".parse::<FileOrStdin>().unwrap();
+
+        assert!(matches!(input.source, Source::Arg(_)));
+    }
+
+    #[cfg(feature = "remote-input")]
+    #[test]
+    fn test_sequence_content_from_url() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::{io::Write, net::TcpListener};
+
+        let msa_path = "src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta";
+        let content = std::fs::read(msa_path).unwrap();
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&content).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        //
+        // A local server answering one request per body, in order
+        //
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let bodies = vec![content.to_owned(), compressed];
+
+        let server = thread::spawn(move || {
+            for body in bodies {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = BufReader::new(stream.try_clone().unwrap());
+
+                let mut line = String::new();
+                while request.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\
+                    Connection: close\r\n\r\n",
+                    body.len()
+                )
+                .unwrap();
+
+                stream.write_all(&body).unwrap();
+            }
+        });
+
+        let expected = content.iter().filter(|byte| **byte == b'>').count();
+
+        for _ in 0..2 {
+            let input = format!("http://{address}/sample.fasta")
+                .parse::<FileOrStdin>()
+                .unwrap();
+
+            let (sender, receiver) = sync_channel(FileOrStdin::CHANNEL_BOUND);
+
+            let reader = thread::spawn(move || {
+                input.sequence_content_by_channel(sender)
+            });
+
+            assert_eq!(receiver.into_iter().count(), expected);
+
+            reader.join().unwrap().unwrap();
+        }
+
+        server.join().unwrap();
+    }
+}
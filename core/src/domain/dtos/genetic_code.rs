@@ -0,0 +1,180 @@
+use super::kmers_map::KmersMap;
+
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, str::FromStr};
+
+//
+// The amino acids of the 64 codons in the NCBI `TCAG` order (`TTT`, `TTC`,
+// `TTA`, `TTG`, `TCT`, ...). Stop codons are translated as `*`.
+//
+const STANDARD: &[u8; 64] =
+    b"FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+const VERTEBRATE_MITOCHONDRIAL: &[u8; 64] =
+    b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG";
+const YEAST_MITOCHONDRIAL: &[u8; 64] =
+    b"FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+const MOLD_MITOCHONDRIAL: &[u8; 64] =
+    b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+const INVERTEBRATE_MITOCHONDRIAL: &[u8; 64] =
+    b"FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG";
+const CILIATE_NUCLEAR: &[u8; 64] =
+    b"FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG";
+
+/// A genetic code of the NCBI translation tables
+///
+/// Codes are identified by their NCBI table number (e.g. `11` for bacteria,
+/// archaea and plastids). Only the codes below are supported:
+///
+/// - `1`: The standard code
+/// - `2`: The vertebrate mitochondrial code
+/// - `3`: The yeast mitochondrial code
+/// - `4`: The mold, protozoan and coelenterate mitochondrial code
+/// - `5`: The invertebrate mitochondrial code
+/// - `6`: The ciliate, dasycladacean and hexamita nuclear code
+/// - `11`: The bacterial, archaeal and plant plastid code
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GeneticCode(u8);
+
+impl GeneticCode {
+    /// The standard genetic code
+    pub const STANDARD: GeneticCode = GeneticCode(1);
+
+    /// The NCBI table number of the code
+    pub fn id(&self) -> u8 {
+        self.0
+    }
+
+    /// The amino acids of the codons of the genetic code
+    fn table(&self) -> &'static [u8; 64] {
+        match self.0 {
+            2 => VERTEBRATE_MITOCHONDRIAL,
+            3 => YEAST_MITOCHONDRIAL,
+            4 => MOLD_MITOCHONDRIAL,
+            5 => INVERTEBRATE_MITOCHONDRIAL,
+            6 => CILIATE_NUCLEAR,
+            _ => STANDARD,
+        }
+    }
+
+    /// Translate a single codon
+    ///
+    /// Codons containing bases other than ACGT (or U) are translated as `X`.
+    fn translate_codon(&self, codon: &[u8]) -> u8 {
+        let mut index = 0;
+
+        for base in codon {
+            index = index * 4
+                + match base.to_ascii_uppercase() {
+                    b'T' | b'U' => 0,
+                    b'C' => 1,
+                    b'A' => 2,
+                    b'G' => 3,
+                    _ => return b'X',
+                };
+        }
+
+        self.table()[index]
+    }
+
+    /// Translate a nucleotide sequence from its first base
+    ///
+    /// A partial codon at the sequence end is dropped. Stop codons are
+    /// translated as `*` and ambiguous codons as `X`, then kmers spanning them
+    /// are not built from the translated sequence.
+    pub fn translate(&self, sequence: &str) -> String {
+        sequence
+            .as_bytes()
+            .chunks_exact(3)
+            .map(|codon| self.translate_codon(codon) as char)
+            .collect()
+    }
+
+    /// Translate a nucleotide sequence in the six reading frames
+    ///
+    /// Frames are numbered `1` to `3` for the forward strand, starting at the
+    /// first to the third base, and `-1` to `-3` for the reverse complement.
+    /// Uracil is read as thymine.
+    pub fn six_frames(&self, sequence: &str) -> Vec<(i8, String)> {
+        let forward = sequence.to_uppercase().replace('U', "T");
+        let reverse = KmersMap::reverse_complement(forward.to_owned());
+
+        [(1, forward.as_str()), (-1, reverse.as_str())]
+            .into_iter()
+            .flat_map(|(strand, sequence)| {
+                (0..3).map(move |offset| {
+                    (
+                        strand * (offset as i8 + 1),
+                        self.translate(sequence.get(offset..).unwrap_or("")),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+impl Default for GeneticCode {
+    fn default() -> Self {
+        GeneticCode::STANDARD
+    }
+}
+
+impl Display for GeneticCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for GeneticCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().parse::<u8>() {
+            Ok(id @ (1..=6 | 11)) => Ok(GeneticCode(id)),
+            _ => Err(format!(
+                "Unsupported genetic code {s:?}. Supported codes are 1, 2, 3, \
+                4, 5, 6 and 11."
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translate_with_partial_codons_and_stops() {
+        let code = GeneticCode::default();
+
+        assert_eq!(code.translate("ATGGCCTAAGG"), "MA*");
+        assert_eq!(code.translate("ATGNNNAUG"), "MXM");
+        assert_eq!(code.translate("AT"), "");
+
+        //
+        // The vertebrate mitochondrial code reads TGA as tryptophan and AGA
+        // as a stop codon
+        //
+        let mitochondrial = "2".parse::<GeneticCode>().unwrap();
+
+        assert_eq!(code.translate("TGAAGA"), "*R");
+        assert_eq!(mitochondrial.translate("TGAAGA"), "W*");
+        assert!("7".parse::<GeneticCode>().is_err());
+    }
+
+    #[test]
+    fn test_six_frames() {
+        let frames = GeneticCode::default().six_frames("ATGAAACCCGGG");
+
+        assert_eq!(
+            frames,
+            vec![
+                (1, "MKPG".to_string()),
+                (2, "*NP".to_string()),
+                (3, "ETR".to_string()),
+                (-1, "PGFH".to_string()),
+                (-2, "PGF".to_string()),
+                (-3, "RVS".to_string()),
+            ]
+        );
+    }
+}
@@ -1,7 +1,15 @@
+use super::msa_window::MsaWindow;
+
 use mur3::murmurhash3_x64_128;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// The amino acids indexed in protein kmers
+///
+/// Ambiguous residues (e.g. `X`) and stops (`*`) are not indexed, then kmers
+/// spanning them are skipped.
+const AMINO_ACIDS: &[u8; 20] = b"ACDEFGHIKLMNPQRSTVWY";
 
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash)]
 pub struct MinimizerKey(pub u64);
@@ -74,6 +82,29 @@ impl MinimizerValue {
     }
 }
 
+/// The 64-bit hash collisions found while indexing kmers
+#[derive(Clone, Debug, PartialEq)]
+pub struct HashCollisions {
+    /// The number of hashes shared by more than one kmer
+    pub colliding_hashes: usize,
+
+    /// The number of distinct kmers sharing a hash with another kmer
+    pub colliding_kmers: usize,
+
+    /// The number of distinct kmers indexed
+    pub distinct_kmers: usize,
+}
+
+impl HashCollisions {
+    /// Get the fraction of distinct kmers sharing a hash with another kmer
+    pub fn rate(&self) -> f64 {
+        match self.distinct_kmers {
+            0 => 0.0,
+            count => self.colliding_kmers as f64 / count as f64,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct KmersMap {
     #[serde(rename = "kSize")]
@@ -83,10 +114,78 @@ pub struct KmersMap {
     #[serde(rename = "mSize")]
     m_size: u64,
 
+    /// If true, homopolymer runs are collapsed before kmers building
+    ///
+    /// Runs of identical bases (e.g. `AAAA`) are collapsed to a single base
+    /// both at the indexing and querying time. It mitigates the homopolymer
+    /// length errors of long reads but reduces the resolution between taxa
+    /// distinguished only by homopolymer lengths.
+    #[serde(rename = "hpCompress", default)]
+    homopolymer_compress: bool,
+
+    /// If true, kmers overlapping soft-masked (lowercase) bases are skipped
+    ///
+    /// Reference sequences commonly soft-mask repetitive regions, which drive
+    /// false placements when indexed as any other region. Queries are
+    /// uppercased before placement, then the option only affects indexing.
+    #[serde(rename = "excludeSoftMasked", default)]
+    exclude_soft_masked: bool,
+
+    /// If true, only forward strand kmers are indexed
+    ///
+    /// Stranded protocols produce reads of a known orientation, then reverse
+    /// complement kmers only reduce the placement specificity. Queries follow
+    /// the database strand mode unless overridden by the placement options.
+    #[serde(rename = "forwardOnly", default)]
+    forward_only: bool,
+
+    /// The MSA columns indexed, if the database is restricted to a window
+    ///
+    /// Queries are not aligned, then they are not cropped. Query kmers out of
+    /// the window simply do not match the database.
+    #[serde(
+        rename = "msaWindow",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    msa_window: Option<MsaWindow>,
+
+    /// If true, kmers are built from amino acid sequences
+    ///
+    /// Protein kmers have no reverse complement, then protein maps are always
+    /// forward only. Nucleotide queries should be translated before placement.
+    #[serde(default)]
+    protein: bool,
+
     map: HashMap<MinimizerKey, MinimizerValue>,
+
+    /// A representative string of each kmer hash
+    ///
+    /// Only available when the database is built retaining kmer strings. The
+    /// table allows reporting which sequence motifs drove a placement, at the
+    /// cost of roughly the kmer size in bytes per indexed kmer.
+    #[serde(
+        rename = "kmerStrings",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    kmer_strings: Option<HashMap<u64, String>>,
+
+    /// Distinct kmer strings sharing the same hash
+    ///
+    /// Collisions are detected at the insertion time only when kmer strings
+    /// are retained.
+    #[serde(skip)]
+    collisions: HashMap<u64, HashSet<String>>,
 }
 
 impl KmersMap {
+    /// The default kmer size used to build and inspect kmers.
+    pub const DEFAULT_K: u64 = 35;
+
+    /// The default minimizer size used to build databases.
+    pub const DEFAULT_M: u64 = 4;
+
     /// The constructor for a new KmersMap.
     ///
     /// Returns a new KmersMap with the given kmer size.
@@ -95,8 +194,209 @@ impl KmersMap {
         KmersMap {
             k_size,
             m_size,
+            homopolymer_compress: false,
+            exclude_soft_masked: false,
+            forward_only: false,
+            msa_window: None,
+            protein: false,
             map: HashMap::new(),
+            kmer_strings: None,
+            collisions: HashMap::new(),
+        }
+    }
+
+    /// Set the homopolymer compression.
+    ///
+    /// Returns the KmersMap with the homopolymer compression option set.
+    ///
+    pub fn with_homopolymer_compression(mut self, value: bool) -> Self {
+        self.homopolymer_compress = value;
+        self
+    }
+
+    /// Set the soft-masked kmers exclusion.
+    ///
+    /// Returns the KmersMap skipping kmers which overlap lowercase bases if
+    /// the value is true.
+    ///
+    pub fn with_soft_masked_exclusion(mut self, value: bool) -> Self {
+        self.exclude_soft_masked = value;
+        self
+    }
+
+    /// Set the forward strand only indexing.
+    ///
+    /// Returns the KmersMap skipping reverse complement kmers if the value is
+    /// true.
+    ///
+    pub fn with_forward_only(mut self, value: bool) -> Self {
+        self.forward_only = value;
+        self
+    }
+
+    /// Set the indexed MSA window.
+    ///
+    /// Returns the KmersMap recording the MSA columns used to build kmers.
+    ///
+    pub fn with_msa_window(mut self, value: Option<MsaWindow>) -> Self {
+        self.msa_window = value;
+        self
+    }
+
+    /// Set the protein kmers building.
+    ///
+    /// Returns the KmersMap building kmers from amino acid sequences if the
+    /// value is true. Protein maps are forward only.
+    ///
+    pub fn with_protein(mut self, value: bool) -> Self {
+        self.protein = value;
+        self.forward_only = self.forward_only || value;
+        self
+    }
+
+    /// Set the kmer strings retention.
+    ///
+    /// Returns the KmersMap storing a representative string of each inserted
+    /// kmer hash if the value is true.
+    ///
+    pub fn with_retained_kmer_strings(mut self, value: bool) -> Self {
+        self.kmer_strings = match value {
+            true => Some(self.kmer_strings.unwrap_or_default()),
+            false => None,
+        };
+
+        self
+    }
+
+    /// Check if kmers of both maps are built with the same parameters.
+    ///
+    /// Kmers built with distinct sizes or sequence transformations are not
+    /// comparable, then the maps should not be merged.
+    ///
+    pub fn is_compatible_with(&self, other: &KmersMap) -> bool {
+        self.k_size == other.k_size
+            && self.m_size == other.m_size
+            && self.homopolymer_compress == other.homopolymer_compress
+            && self.exclude_soft_masked == other.exclude_soft_masked
+            && self.forward_only == other.forward_only
+            && self.protein == other.protein
+    }
+
+    /// Add an offset to the node ids of all kmers.
+    ///
+    /// Used together with `Clade::renumber` to avoid node ids collisions
+    /// before merging maps.
+    ///
+    pub fn offset_nodes(mut self, offset: u64) -> Self {
+        for value in self.map.values_mut() {
+            for nodes in value.0.values_mut() {
+                *nodes = nodes.iter().map(|node| node + offset).collect();
+            }
+        }
+
+        self
+    }
+
+    /// Add an ancestor node to all kmers mapped to the node.
+    ///
+    /// Used when a new internal node is inserted above an existing clade,
+    /// since kmers of a clade are mapped to all clades of its root path.
+    ///
+    pub fn add_ancestor(&mut self, node: u64, ancestor: u64) {
+        for value in self.map.values_mut() {
+            for nodes in value.0.values_mut() {
+                if nodes.contains(&node) {
+                    nodes.insert(ancestor);
+                }
+            }
+        }
+    }
+
+    /// Merge the kmers of another map.
+    ///
+    /// Node sets of kmers shared by both maps are combined, and the shared
+    /// root is added to all kmers, since it is an ancestor of all nodes of the
+    /// merged tree. Node ids of both maps should not collide (see
+    /// `offset_nodes`). Kmer strings are kept only if both maps retain them.
+    ///
+    pub fn merge(mut self, other: KmersMap, shared_root: u64) -> Self {
+        for (key, other_value) in other.map {
+            self.insert_or_append_bucket(key, other_value);
+        }
+
+        for value in self.map.values_mut() {
+            for nodes in value.0.values_mut() {
+                nodes.insert(shared_root);
+            }
+        }
+
+        //
+        // Windows are columns of distinct MSAs, then only a common window is
+        // kept
+        //
+        if self.msa_window != other.msa_window {
+            self.msa_window = None;
         }
+
+        self.kmer_strings = match (self.kmer_strings, other.kmer_strings) {
+            (Some(mut strings), Some(other_strings)) => {
+                for (hash, kmer) in other_strings {
+                    strings.entry(hash).or_insert(kmer);
+                }
+
+                Some(strings)
+            }
+            _ => None,
+        };
+
+        self
+    }
+
+    /// Take the minimizer buckets out of the map.
+    ///
+    /// Used to spill partial indexes to disk during the build. The map
+    /// settings and the kmer strings are kept.
+    ///
+    pub(crate) fn take_buckets(
+        &mut self,
+    ) -> HashMap<MinimizerKey, MinimizerValue> {
+        std::mem::take(&mut self.map)
+    }
+
+    /// Insert a minimizer bucket into the map.
+    ///
+    /// Node sets of kmers already indexed are combined with the ones of the
+    /// bucket.
+    ///
+    pub(crate) fn insert_or_append_bucket(
+        &mut self,
+        key: MinimizerKey,
+        bucket: MinimizerValue,
+    ) {
+        let value = self.map.entry(key).or_insert_with(MinimizerValue::new);
+
+        for (hash, nodes) in bucket.0 {
+            value.insert_or_append(hash, nodes);
+        }
+    }
+
+    /// Get the hash collisions found while inserting kmers.
+    ///
+    /// Returns `None` if the kmer strings are not retained, since collisions
+    /// can not be detected without them.
+    ///
+    pub fn get_hash_collisions(&self) -> Option<HashCollisions> {
+        let strings = self.kmer_strings.as_ref()?;
+
+        let colliding_kmers =
+            self.collisions.values().map(|i| i.len()).sum::<usize>();
+
+        Some(HashCollisions {
+            colliding_hashes: self.collisions.len(),
+            colliding_kmers,
+            distinct_kmers: strings.len() + colliding_kmers
+                - self.collisions.len(),
+        })
     }
 
     /// Get the map of kmers.
@@ -108,6 +408,88 @@ impl KmersMap {
         &self.map
     }
 
+    /// Get the number of indexed kmers.
+    ///
+    /// Kmers are counted over all minimizer buckets.
+    ///
+    pub fn total_kmers(&self) -> usize {
+        self.map.values().map(|bucket| bucket.0.len()).sum()
+    }
+
+    /// Get the number of minimizer buckets.
+    pub fn minimizer_count(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Get the distribution of the minimizer bucket sizes.
+    ///
+    /// Keys are the number of kmers of a bucket, and values the number of
+    /// buckets with such a size.
+    ///
+    pub fn minimizer_size_histogram(&self) -> BTreeMap<usize, usize> {
+        self.map
+            .values()
+            .fold(BTreeMap::new(), |mut histogram, bucket| {
+                *histogram.entry(bucket.0.len()).or_insert(0) += 1;
+                histogram
+            })
+    }
+
+    /// Count the kmers indexed for each clade and for the rest of its siblings
+    ///
+    /// Clades are given with their parent, then the siblings of a clade are
+    /// the clades sharing its parent. The map is traversed once, counting for
+    /// each parent the kmers indexed for any of its children and the kmers
+    /// indexed for a single child.
+    ///
+    /// Returns a map of the clade id to the `(one, rest)` sizes.
+    ///
+    pub(crate) fn count_sibling_kmers(
+        &self,
+        parents: &HashMap<u64, u64>,
+    ) -> HashMap<u64, (usize, usize)> {
+        let mut clade_kmers = HashMap::<u64, usize>::new();
+        let mut exclusive_kmers = HashMap::<u64, usize>::new();
+        let mut parent_kmers = HashMap::<u64, usize>::new();
+
+        for nodes in self.map.values().flat_map(|bucket| bucket.0.values()) {
+            let mut children = HashMap::<u64, Vec<u64>>::new();
+
+            for node in nodes {
+                if let Some(parent) = parents.get(node) {
+                    children.entry(*parent).or_default().push(*node);
+                }
+            }
+
+            for (parent, children) in children {
+                *parent_kmers.entry(parent).or_default() += 1;
+
+                for child in children.iter() {
+                    *clade_kmers.entry(*child).or_default() += 1;
+                }
+
+                if let [child] = children.as_slice() {
+                    *exclusive_kmers.entry(*child).or_default() += 1;
+                }
+            }
+        }
+
+        let count = |counts: &HashMap<u64, usize>, id: &u64| {
+            counts.get(id).copied().unwrap_or_default()
+        };
+
+        parents
+            .iter()
+            .map(|(clade, parent)| {
+                let one = count(&clade_kmers, clade);
+                let rest = count(&parent_kmers, parent)
+                    - count(&exclusive_kmers, clade);
+
+                (*clade, (one, rest))
+            })
+            .collect()
+    }
+
     pub fn get_kmer_size(&self) -> u64 {
         self.k_size
     }
@@ -116,6 +498,42 @@ impl KmersMap {
         self.m_size
     }
 
+    pub fn get_homopolymer_compression(&self) -> bool {
+        self.homopolymer_compress
+    }
+
+    pub fn get_soft_masked_exclusion(&self) -> bool {
+        self.exclude_soft_masked
+    }
+
+    pub fn get_forward_only(&self) -> bool {
+        self.forward_only
+    }
+
+    pub fn get_msa_window(&self) -> Option<&MsaWindow> {
+        self.msa_window.as_ref()
+    }
+
+    pub fn is_protein(&self) -> bool {
+        self.protein
+    }
+
+    pub fn retains_kmer_strings(&self) -> bool {
+        self.kmer_strings.is_some()
+    }
+
+    /// Get the representative string of a kmer hash.
+    ///
+    /// Returns `None` if the kmer strings are not retained or the hash is not
+    /// indexed.
+    ///
+    pub fn get_kmer_string(&self, hash: u64) -> Option<&str> {
+        self.kmer_strings
+            .as_ref()
+            .and_then(|strings| strings.get(&hash))
+            .map(|kmer| kmer.as_str())
+    }
+
     /// Insert a kmer into the map.
     ///
     /// If the kmer is already present, the node will be added to the existing
@@ -128,6 +546,20 @@ impl KmersMap {
         hash: u64,
         nodes: HashSet<u64>,
     ) -> bool {
+        if let Some(strings) = self.kmer_strings.as_mut() {
+            let representative =
+                strings.entry(hash).or_insert_with(|| kmer.to_owned());
+
+            if *representative != kmer {
+                self.collisions
+                    .entry(hash)
+                    .or_insert_with(|| {
+                        HashSet::from([representative.to_owned()])
+                    })
+                    .insert(kmer.to_owned());
+            }
+        }
+
         let key = if self.m_size == 0 {
             // If the minimizer size is 0, use zero as the key
             MinimizerKey(0)
@@ -158,6 +590,17 @@ impl KmersMap {
         murmurhash3_x64_128(kmer.as_bytes(), 0).0
     }
 
+    /// Hash a kmer using the full 128-bit murmur3 value.
+    ///
+    /// Indexes store only the low 64 bits (see `hash_kmer`) to keep databases
+    /// compact. The full value is useful to tell apart kmers colliding after
+    /// the truncation.
+    ///
+    pub fn hash_kmer_full(kmer: &str) -> u128 {
+        let (low, high) = murmurhash3_x64_128(kmer.as_bytes(), 0);
+        ((high as u128) << 64) | low as u128
+    }
+
     /// Get all kmers that contain a given node.
     ///
     /// Returns an empty set if the node is not present in any kmer. This method
@@ -231,15 +674,10 @@ impl KmersMap {
     /// Filter map keys by a set of kmers.
     ///
     /// Returns a new KmersMap with only the kmers that are present in the given
-    /// set. This method is used to filter the kmers map by a set of kmers.
-    ///
-    /// TODO: This method is not used and should be removed.
+    /// set. This method is used to filter the kmers map by a set of kmers
+    /// given as hashes, then all minimizer keys are scanned.
     ///
-    #[allow(dead_code)]
-    pub(crate) fn get_overlapping_hashes(
-        &mut self,
-        hashes: &HashSet<u64>,
-    ) -> Self {
+    pub(crate) fn get_overlapping_hashes(&self, hashes: &HashSet<u64>) -> Self {
         let mut map = Self::new(self.k_size, self.m_size);
 
         map.map = self
@@ -271,7 +709,7 @@ impl KmersMap {
     /// set. This method is used to filter the kmers map by a set of kmers.
     ///
     pub(crate) fn get_overlapping_hashed_kmers(
-        &mut self,
+        &self,
         hashed_kmers: Vec<(String, u64)>,
     ) -> Self {
         let mut map = Self::new(self.k_size, self.m_size);
@@ -376,42 +814,197 @@ impl KmersMap {
         &self,
         sequence: String,
         k_size: Option<u64>,
+    ) -> Vec<(String, u64)> {
+        self.build_stranded_kmer_from_string(
+            sequence,
+            k_size,
+            self.forward_only,
+        )
+    }
+
+    /// Build kmers from a string with the given strand mode
+    ///
+    /// Same as `build_kmer_from_string`, but reverse complement kmers are
+    /// skipped if `forward_only` is true, despite the map strand mode.
+    ///
+    pub fn build_stranded_kmer_from_string(
+        &self,
+        sequence: String,
+        k_size: Option<u64>,
+        forward_only: bool,
     ) -> Vec<(String, u64)> {
         let mut kmers = Vec::new();
         let size = k_size.unwrap_or(self.k_size);
+        let sequence = self.prepare_sequence(sequence);
 
-        if sequence.len() < self.k_size as usize {
+        if sequence.len() < size as usize {
             return vec![];
         }
 
         kmers.extend(KmersMap::build_kmers_from_sequence(
             sequence.to_owned(),
             size,
+            self.exclude_soft_masked,
+            self.protein,
         ));
 
+        //
+        // Protein kmers have no reverse complement
+        //
+        if forward_only || self.protein {
+            return kmers;
+        }
+
         kmers.extend(KmersMap::build_kmers_from_sequence(
             KmersMap::reverse_complement(sequence),
             size,
+            self.exclude_soft_masked,
+            false,
         ));
 
         kmers
     }
 
+    /// Build kmers from a string expanding IUPAC ambiguity codes
+    ///
+    /// Works as `build_kmer_from_string`, but kmers containing ambiguity codes
+    /// (e.g. `N`) are expanded to all their ACGT variants instead of skipped.
+    /// Kmers with more than `max_expansions` variants are skipped, then the
+    /// expansion stays bounded for sequences with runs of ambiguity codes.
+    ///
+    /// Returns the kmers and the number of kmers skipped due to the cap.
+    ///
+    pub fn build_expanded_kmer_from_string(
+        &self,
+        sequence: String,
+        max_expansions: usize,
+    ) -> (Vec<(String, u64)>, usize) {
+        let mut kmers = self.build_kmer_from_string(sequence.to_owned(), None);
+        let sequence = self.prepare_sequence(sequence);
+
+        let mut strands = vec![sequence.to_owned()];
+
+        if !self.forward_only {
+            strands.push(KmersMap::reverse_complement(sequence));
+        }
+
+        let mut skipped = 0;
+
+        for strand in strands {
+            let (expanded, strand_skipped) = KmersMap::expand_ambiguous_kmers(
+                &strand,
+                self.k_size,
+                self.exclude_soft_masked,
+                max_expansions,
+            );
+
+            kmers.extend(expanded);
+            skipped += strand_skipped;
+        }
+
+        (kmers, skipped)
+    }
+
+    /// Prepare a sequence for the kmers building
+    ///
+    /// Uracil is indexed as thymine, then RNA queries match DNA references.
+    /// Homopolymers are collapsed if the map was built compressing them.
+    /// Protein sequences are kept as given.
+    ///
+    fn prepare_sequence(&self, sequence: String) -> String {
+        if self.protein {
+            return sequence;
+        }
+
+        let sequence = sequence.replace('U', "T").replace('u', "t");
+
+        match self.homopolymer_compress {
+            true => KmersMap::compress_homopolymers(&sequence),
+            false => sequence,
+        }
+    }
+
+    /// Hash pre-extracted kmers
+    ///
+    /// Kmers of a size other than the map kmer size, or containing bases other
+    /// than ACGT (amino acids for protein maps), are skipped. The reverse
+    /// complement hashes are included unless `forward_only` is true, as done
+    /// for query sequences.
+    ///
+    pub fn hash_kmers(
+        &self,
+        kmers: &[String],
+        forward_only: bool,
+    ) -> HashSet<u64> {
+        kmers
+            .iter()
+            .map(|kmer| match self.protein {
+                true => kmer.to_uppercase(),
+                false => kmer.to_uppercase().replace('U', "T"),
+            })
+            .filter(|kmer| {
+                kmer.len() == self.k_size as usize
+                    && kmer.bytes().all(|residue| {
+                        KmersMap::is_indexed_residue(residue, self.protein)
+                    })
+            })
+            .flat_map(|kmer| {
+                let mut hashes = vec![KmersMap::hash_kmer(&kmer)];
+
+                if !forward_only && !self.protein {
+                    hashes.push(KmersMap::hash_kmer(
+                        &KmersMap::reverse_complement(kmer),
+                    ));
+                }
+
+                hashes
+            })
+            .collect()
+    }
+
     /// Build kmers from a sequence
     ///
     /// Returns a vector of kmers from a given sequence. This method is used to
-    /// build kmers from a given sequence.
+    /// build kmers from a given sequence. Windows containing IUPAC ambiguity
+    /// codes (e.g. `N`) are skipped. If `exclude_soft_masked` is true, windows
+    /// containing lowercase bases are skipped as well before uppercasing.
+    /// Protein windows are skipped if containing residues other than the
+    /// standard amino acids (e.g. `X` or stops).
     ///
     fn build_kmers_from_sequence(
         sequence: String,
         size: u64,
+        exclude_soft_masked: bool,
+        protein: bool,
     ) -> Vec<(String, u64)> {
         let mut kmers = Vec::new();
+        let size = size as usize;
+
         let binding = sequence.to_uppercase();
+        let masks = sequence.as_bytes();
         let sequence = binding.as_bytes();
-        let size = size as usize;
+
+        let is_skipped = |position: usize| {
+            (exclude_soft_masked && masks[position].is_ascii_lowercase())
+                || !KmersMap::is_indexed_residue(sequence[position], protein)
+        };
+
+        //
+        // The position of the last skipped base seen, used to skip windows
+        // overlapping it without rescanning each window
+        //
+        let mut last_skipped =
+            (0..size.saturating_sub(1)).rev().find(|i| is_skipped(*i));
 
         for i in 0..sequence.len() - size + 1 {
+            if is_skipped(i + size - 1) {
+                last_skipped = Some(i + size - 1);
+            }
+
+            if last_skipped.is_some_and(|position| position >= i) {
+                continue;
+            }
+
             let kmer = match String::from_utf8(sequence[i..i + size].to_vec()) {
                 Ok(kmer) => kmer,
                 Err(_) => panic!("Invalid character in sequence"),
@@ -423,20 +1016,174 @@ impl KmersMap {
         kmers
     }
 
+    /// Check if an uppercase residue is indexed in kmers
+    fn is_indexed_residue(residue: u8, protein: bool) -> bool {
+        match protein {
+            true => AMINO_ACIDS.contains(&residue),
+            false => matches!(residue, b'A' | b'C' | b'G' | b'T'),
+        }
+    }
+
+    /// Expand the kmers containing IUPAC ambiguity codes
+    ///
+    /// Only windows containing ambiguity codes are expanded, since the
+    /// unambiguous ones are built by `build_kmers_from_sequence`. Windows with
+    /// more than `max_expansions` variants are skipped and counted.
+    ///
+    fn expand_ambiguous_kmers(
+        sequence: &str,
+        size: u64,
+        exclude_soft_masked: bool,
+        max_expansions: usize,
+    ) -> (Vec<(String, u64)>, usize) {
+        let mut kmers = Vec::new();
+        let mut skipped = 0;
+        let size = size as usize;
+
+        let binding = sequence.to_uppercase();
+        let masks = sequence.as_bytes();
+        let sequence = binding.as_bytes();
+
+        if sequence.len() < size {
+            return (kmers, skipped);
+        }
+
+        for i in 0..sequence.len() - size + 1 {
+            if exclude_soft_masked
+                && masks[i..i + size].iter().any(u8::is_ascii_lowercase)
+            {
+                continue;
+            }
+
+            let variants = match sequence[i..i + size]
+                .iter()
+                .map(|base| KmersMap::ambiguity_variants(*base))
+                .collect::<Option<Vec<&[u8]>>>()
+            {
+                Some(variants) => variants,
+                None => continue,
+            };
+
+            match variants.iter().try_fold(1_usize, |count, bases| {
+                count.checked_mul(bases.len())
+            }) {
+                Some(1) => continue,
+                Some(count) if count <= max_expansions => (),
+                _ => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+
+            let mut expanded = vec![Vec::<u8>::with_capacity(size)];
+
+            for bases in variants {
+                expanded = expanded
+                    .into_iter()
+                    .flat_map(|prefix| {
+                        bases.iter().map(move |base| {
+                            let mut kmer = prefix.to_owned();
+                            kmer.push(*base);
+                            kmer
+                        })
+                    })
+                    .collect();
+            }
+
+            for kmer in expanded {
+                let kmer = match String::from_utf8(kmer) {
+                    Ok(kmer) => kmer,
+                    Err(_) => panic!("Invalid character in sequence"),
+                };
+
+                let hash = KmersMap::hash_kmer(&kmer);
+                kmers.push((kmer, hash));
+            }
+        }
+
+        (kmers, skipped)
+    }
+
+    /// Get the ACGT bases represented by an uppercase IUPAC code
+    fn ambiguity_variants(base: u8) -> Option<&'static [u8]> {
+        Some(match base {
+            b'A' => b"A",
+            b'C' => b"C",
+            b'G' => b"G",
+            b'T' => b"T",
+            b'R' => b"AG",
+            b'Y' => b"CT",
+            b'S' => b"CG",
+            b'W' => b"AT",
+            b'K' => b"GT",
+            b'M' => b"AC",
+            b'B' => b"CGT",
+            b'D' => b"AGT",
+            b'H' => b"ACT",
+            b'V' => b"ACG",
+            b'N' => b"ACGT",
+            _ => return None,
+        })
+    }
+
+    /// Collapse homopolymer runs of a sequence
+    ///
+    /// Returns the sequence with each run of identical bases replaced by a
+    /// single base (e.g. `AAACCG` results in `ACG`).
+    ///
+    fn compress_homopolymers(sequence: &str) -> String {
+        let mut compressed = String::with_capacity(sequence.len());
+        let mut last: Option<char> = None;
+
+        //
+        // The case of the first base of each run is kept, preserving the
+        // soft-masking information
+        //
+        for c in sequence.chars() {
+            if last != Some(c.to_ascii_uppercase()) {
+                compressed.push(c);
+                last = Some(c.to_ascii_uppercase());
+            }
+        }
+
+        compressed
+    }
+
     /// Reverse complement a sequence
     ///
     /// Returns the reverse complement of a given sequence. This method is used
     /// to get the reverse complement of a given sequence.
     ///
-    fn reverse_complement(sequence: String) -> String {
+    pub(crate) fn reverse_complement(sequence: String) -> String {
         sequence
             .chars()
             .rev()
             .map(|c| match c {
-                'a' | 'A' => 'T',
-                't' | 'T' => 'A',
-                'c' | 'C' => 'G',
-                'g' | 'G' => 'C',
+                'A' => 'T',
+                'T' => 'A',
+                'C' => 'G',
+                'G' => 'C',
+                'a' => 't',
+                't' => 'a',
+                'c' => 'g',
+                'g' => 'c',
+                'R' => 'Y',
+                'Y' => 'R',
+                'K' => 'M',
+                'M' => 'K',
+                'B' => 'V',
+                'V' => 'B',
+                'D' => 'H',
+                'H' => 'D',
+                'r' => 'y',
+                'y' => 'r',
+                'k' => 'm',
+                'm' => 'k',
+                'b' => 'v',
+                'v' => 'b',
+                'd' => 'h',
+                'h' => 'd',
+                'S' | 'W' | 'N' | 's' | 'w' | 'n' => c,
                 _ => panic!("Invalid character in sequence"),
             })
             .collect()
@@ -450,8 +1197,158 @@ mod test {
     #[test]
     fn test_build_kmers_from_sequence() {
         let sequence = "ATCG".to_string();
-        let kmers = KmersMap::build_kmers_from_sequence(sequence.to_owned(), 2);
+        let kmers = KmersMap::build_kmers_from_sequence(
+            sequence.to_owned(),
+            2,
+            false,
+            false,
+        );
 
         println!("{:?}", kmers);
     }
+
+    #[test]
+    fn test_build_kmers_with_homopolymer_compression() {
+        let kmers_map = KmersMap::new(5, 0).with_homopolymer_compression(true);
+
+        let reference =
+            kmers_map.build_kmer_from_string("ACGTAGCTTGCA".to_string(), None);
+
+        let expanded = kmers_map
+            .build_kmer_from_string("ACGGGGTAGCCCTTTTGCA".to_string(), None);
+
+        assert_eq!(reference, expanded);
+    }
+
+    #[test]
+    fn test_build_protein_kmers() {
+        let kmers_map = KmersMap::new(3, 0).with_protein(true);
+
+        assert!(kmers_map.get_forward_only());
+
+        //
+        // Kmers spanning stops and ambiguous residues are skipped, and no
+        // reverse complement is built
+        //
+        let kmers = kmers_map
+            .build_stranded_kmer_from_string(
+                "MKW*PGXHEQ".to_string(),
+                None,
+                false,
+            )
+            .into_iter()
+            .map(|(kmer, _)| kmer)
+            .collect::<Vec<String>>();
+
+        assert_eq!(kmers, vec!["MKW", "HEQ"]);
+    }
+
+    #[test]
+    fn test_build_kmers_expanding_ambiguity_codes() {
+        let kmers_map = KmersMap::new(5, 0).with_forward_only(true);
+        let sequence = "AAAACNNNGTTTT".to_string();
+
+        //
+        // Windows with one, two, and three `N` result in 4, 16 and 64
+        // variants, then the three windows with three `N` exceed the cap
+        //
+        let (kmers, skipped) =
+            kmers_map.build_expanded_kmer_from_string(sequence.to_owned(), 16);
+
+        assert_eq!(skipped, 3);
+        assert_eq!(kmers.len(), 2 + 2 * 4 + 2 * 16);
+        assert!(kmers
+            .iter()
+            .all(|(kmer, _)| kmer.bytes().all(|base| b"ACGT".contains(&base))));
+
+        let (kmers, skipped) =
+            kmers_map.build_expanded_kmer_from_string(sequence.to_owned(), 64);
+
+        assert_eq!(skipped, 0);
+        assert_eq!(kmers.len(), 2 + 2 * 4 + 2 * 16 + 3 * 64);
+
+        //
+        // Without expansions, ambiguous kmers are skipped as usual
+        //
+        let (kmers, skipped) =
+            kmers_map.build_expanded_kmer_from_string(sequence.to_owned(), 0);
+
+        assert_eq!(skipped, 7);
+        assert_eq!(kmers, kmers_map.build_kmer_from_string(sequence, None));
+    }
+
+    #[test]
+    fn test_hash_collisions_detection() {
+        let mut map = KmersMap::new(8, 0).with_retained_kmer_strings(true);
+
+        let kmers = (0..300)
+            .map(|i| format!("{:08b}", i).replace('0', "A").replace('1', "C"))
+            .collect::<Vec<_>>();
+
+        //
+        // Truncating hashes to 8 bits forces collisions between the 300
+        // distinct kmers, as done by the 64 bits truncation at larger scales.
+        //
+        for kmer in kmers.iter() {
+            map.insert_or_append_kmer_hash(
+                kmer.to_owned(),
+                KmersMap::hash_kmer(kmer) & 0xFF,
+                HashSet::from([1]),
+            );
+        }
+
+        let collisions = map.get_hash_collisions().unwrap();
+
+        assert_eq!(collisions.distinct_kmers, kmers.len());
+        assert!(collisions.colliding_hashes > 0);
+        assert!(collisions.rate() > 0.0);
+
+        //
+        // The full hashes keep the kmers distinct
+        //
+        let full_hashes = kmers
+            .iter()
+            .map(|kmer| KmersMap::hash_kmer_full(kmer))
+            .collect::<HashSet<_>>();
+
+        assert_eq!(full_hashes.len(), kmers.len());
+        assert!(KmersMap::new(8, 0).get_hash_collisions().is_none());
+    }
+
+    #[test]
+    fn test_kmer_and_minimizer_counts() {
+        let mut map = KmersMap::new(4, 2);
+
+        assert_eq!(map.total_kmers(), 0);
+        assert_eq!(map.minimizer_count(), 0);
+        assert!(map.minimizer_size_histogram().is_empty());
+
+        //
+        // Kmers sharing the first dinucleotide share the minimizer, then
+        // buckets of 3, 2 and 1 kmers are built.
+        //
+        for kmer in ["AAAC", "AAAG", "AAAT", "CCCA", "CCCG", "GGGA"] {
+            map.insert_or_append_kmer_hash(
+                kmer.to_string(),
+                KmersMap::hash_kmer(kmer),
+                HashSet::from([1]),
+            );
+        }
+
+        //
+        // Repeated kmers append nodes without increasing the counts
+        //
+        map.insert_or_append_kmer_hash(
+            "AAAC".to_string(),
+            KmersMap::hash_kmer("AAAC"),
+            HashSet::from([2]),
+        );
+
+        assert_eq!(map.total_kmers(), 6);
+        assert_eq!(map.minimizer_count(), 3);
+        assert_eq!(
+            map.minimizer_size_histogram(),
+            BTreeMap::from([(1, 1), (2, 1), (3, 1)])
+        );
+    }
 }
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+/// The minimum match coverage between the query and the database sequences.
+///
+/// The coverage is used to check if the query has enough kmers overlapping
+/// the tree before the tree introspection.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchCoverage {
+    /// A fraction of the query kmers overlapping the tree. Values outside the
+    /// `[0, 1]` interval are clamped.
+    Fraction(f64),
+
+    /// An absolute number of query kmers overlapping the tree.
+    Absolute(usize),
+}
+
+impl Default for MatchCoverage {
+    fn default() -> Self {
+        MatchCoverage::Fraction(0.7)
+    }
+}
+
+impl MatchCoverage {
+    /// Get the minimum number of kmers expected to overlap the tree.
+    ///
+    /// The `query_kmers_len` is the number of query kmers overlapping the
+    /// tree, used as the base to the fraction coverage.
+    pub fn expected_min_coverage(&self, query_kmers_len: usize) -> f64 {
+        match self {
+            MatchCoverage::Fraction(value) => {
+                (query_kmers_len as f64 * value.clamp(0.0, 1.0)).round()
+            }
+            MatchCoverage::Absolute(value) => *value as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expected_min_coverage() {
+        assert_eq!(MatchCoverage::default().expected_min_coverage(10), 7.0);
+        assert_eq!(
+            MatchCoverage::Fraction(1.5).expected_min_coverage(10),
+            10.0
+        );
+        assert_eq!(
+            MatchCoverage::Fraction(-1.0).expected_min_coverage(10),
+            0.0
+        );
+        assert_eq!(MatchCoverage::Absolute(20).expected_min_coverage(10), 20.0);
+    }
+}
@@ -1,10 +1,24 @@
 pub mod adherence_test;
 pub mod annotation;
+pub mod build_options;
+pub mod chunk_by;
 pub mod clade;
 pub mod file_or_stdin;
+pub mod genetic_code;
 pub mod kmers_map;
+pub mod match_coverage;
+pub mod msa_columns;
+pub mod msa_window;
+pub mod multigene_response;
+pub mod nexus;
+pub mod output_field;
 pub mod output_format;
+pub mod output_order;
+pub mod placement_config;
+pub mod placement_options;
 pub mod placement_response;
+pub mod quality_filter;
 pub mod sequence;
+pub mod strand;
 pub mod telemetry_code;
 pub mod tree;
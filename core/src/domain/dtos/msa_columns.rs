@@ -0,0 +1,120 @@
+use super::msa_window::MsaWindow;
+
+use std::borrow::Cow;
+
+/// The alignment columns of the MSA used to build kmers.
+///
+/// Columns out of the window and columns flagged as skipped (e.g. gap-rich
+/// columns) are dropped from the records before the gaps removal.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MsaColumns {
+    window: Option<MsaWindow>,
+
+    /// Skipped flags of the zero based alignment columns. Columns beyond the
+    /// flags are kept.
+    skipped: Vec<bool>,
+}
+
+impl MsaColumns {
+    pub fn with_window(mut self, value: Option<MsaWindow>) -> Self {
+        self.window = value;
+        self
+    }
+
+    /// Skip the columns with a gap fraction above the maximum.
+    ///
+    /// `fractions` contains the gap fraction of each zero based column (see
+    /// `FileOrStdin::msa_gap_fractions`).
+    pub fn with_max_gap_fraction(
+        mut self,
+        fractions: &[f64],
+        max: f64,
+    ) -> Self {
+        self.skipped =
+            fractions.iter().map(|fraction| *fraction > max).collect();
+        self
+    }
+
+    /// Skip the columns flagged as gaps, keeping the already skipped ones.
+    ///
+    /// `gaps` contains the flags of each zero based column (see
+    /// `FileOrStdin::msa_gap_columns`).
+    pub fn with_gap_columns(mut self, gaps: &[bool]) -> Self {
+        if self.skipped.len() < gaps.len() {
+            self.skipped.resize(gaps.len(), false);
+        }
+
+        for (skipped, gap) in self.skipped.iter_mut().zip(gaps) {
+            *skipped |= *gap;
+        }
+
+        self
+    }
+
+    /// The number of skipped columns.
+    pub fn skipped_count(&self) -> usize {
+        self.skipped.iter().filter(|skipped| **skipped).count()
+    }
+
+    /// Check if a zero based column is used to build kmers.
+    pub fn is_kept(&self, column: usize) -> bool {
+        self.window
+            .as_ref()
+            .map_or(true, |window| window.contains(column + 1))
+            && !self.skipped.get(column).copied().unwrap_or(false)
+    }
+
+    /// Get the bases of an alignment line within the kept columns.
+    ///
+    /// `offset` is the number of alignment columns before the line, since
+    /// sequences may span multiple lines.
+    pub fn select<'a>(&self, line: &'a str, offset: usize) -> Cow<'a, str> {
+        if self.skipped.is_empty() {
+            return match &self.window {
+                None => Cow::Borrowed(line),
+                Some(window) => Cow::Borrowed(window.crop(line, offset)),
+            };
+        }
+
+        Cow::Owned(
+            line.bytes()
+                .enumerate()
+                .filter(|(index, _)| self.is_kept(offset + index))
+                .map(|(_, base)| base as char)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_msa_columns_skip_gap_rich_columns() {
+        let columns = MsaColumns::default()
+            .with_max_gap_fraction(&[0.0, 0.9, 0.5, 1.0, 0.0], 0.5);
+
+        assert_eq!(columns.skipped_count(), 2);
+        assert_eq!(columns.select("ACGTA", 0), "AGA");
+
+        //
+        // The same alignment split in lines, and combined with a window
+        //
+        assert_eq!(columns.select("AC", 0), "A");
+        assert_eq!(columns.select("GTA", 2), "GA");
+
+        let columns = columns.with_window(Some(MsaWindow::new(2, 5)));
+
+        assert_eq!(columns.select("ACGTA", 0), "GA");
+
+        //
+        // Gap columns are skipped in addition to the gap-rich ones
+        //
+        let columns = columns.with_gap_columns(&[true, false, false, false]);
+
+        assert_eq!(columns.skipped_count(), 3);
+        assert_eq!(columns.select("ACGTA", 0), "GA");
+        assert_eq!(columns.with_window(None).select("ACGTA", 0), "GA");
+    }
+}
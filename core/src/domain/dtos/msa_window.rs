@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+/// A window of alignment columns of the MSA.
+///
+/// Columns are one based and inclusive, as reported by alignment viewers.
+/// Only bases within the window are used to build kmers, restricting the
+/// database to a region (e.g. the amplified region of amplicon data).
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MsaWindow {
+    /// The first column of the window.
+    pub start: usize,
+
+    /// The last column of the window.
+    pub end: usize,
+}
+
+impl MsaWindow {
+    pub fn new(start: usize, end: usize) -> Self {
+        MsaWindow { start, end }
+    }
+
+    /// Check if the window contains at least one column.
+    pub fn is_valid(&self) -> bool {
+        self.start >= 1 && self.start <= self.end
+    }
+
+    /// The number of columns of the window.
+    pub fn width(&self) -> usize {
+        (self.end + 1).saturating_sub(self.start)
+    }
+
+    /// Check if the window contains a one based column.
+    pub fn contains(&self, column: usize) -> bool {
+        self.start <= column && column <= self.end
+    }
+
+    /// Get the part of an alignment line within the window.
+    ///
+    /// `offset` is the number of alignment columns before the line, since
+    /// sequences may span multiple lines.
+    pub fn crop<'a>(&self, line: &'a str, offset: usize) -> &'a str {
+        let start = self
+            .start
+            .saturating_sub(1)
+            .saturating_sub(offset)
+            .min(line.len());
+        let end = self.end.saturating_sub(offset).min(line.len());
+
+        match start < end {
+            true => &line[start..end],
+            false => "",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_msa_window_crop_multiline_records() {
+        let window = MsaWindow::new(4, 7);
+
+        assert_eq!(window.width(), 4);
+        assert_eq!(window.crop("ACGTACGT", 0), "TACG");
+
+        //
+        // The same alignment split in lines of three columns
+        //
+        assert_eq!(window.crop("ACG", 0), "");
+        assert_eq!(window.crop("TAC", 3), "TAC");
+        assert_eq!(window.crop("GT", 6), "G");
+    }
+}
@@ -0,0 +1,93 @@
+use super::placement_response::{PlacementResponse, PlacementStatus};
+
+use serde::{Deserialize, Serialize};
+
+/// The placement of a single gene of a multi-gene specimen
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GenePlacement {
+    gene: String,
+
+    /// True if the gene lineage starts with the consensus lineage
+    ///
+    /// Genes without a lineage (e.g. unclassifiable queries) do not vote,
+    /// then never agree with the consensus.
+    agrees: bool,
+
+    response: PlacementResponse<PlacementStatus>,
+}
+
+impl GenePlacement {
+    pub fn new(
+        gene: String,
+        agrees: bool,
+        response: PlacementResponse<PlacementStatus>,
+    ) -> Self {
+        GenePlacement {
+            gene,
+            agrees,
+            response,
+        }
+    }
+
+    pub fn gene(&self) -> &str {
+        &self.gene
+    }
+
+    pub fn agrees(&self) -> bool {
+        self.agrees
+    }
+
+    pub fn response(&self) -> &PlacementResponse<PlacementStatus> {
+        &self.response
+    }
+}
+
+/// The consensus of the placements of several genes of the same specimen
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MultigeneResponse {
+    specimen: String,
+
+    /// The longest lineage prefix supported by a strict majority of the voting
+    /// genes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    consensus: Option<String>,
+
+    /// The number of lineage names in the consensus
+    consensus_depth: usize,
+
+    genes: Vec<GenePlacement>,
+}
+
+impl MultigeneResponse {
+    pub fn new(
+        specimen: String,
+        consensus: Option<String>,
+        consensus_depth: usize,
+        genes: Vec<GenePlacement>,
+    ) -> Self {
+        MultigeneResponse {
+            specimen,
+            consensus,
+            consensus_depth,
+            genes,
+        }
+    }
+
+    pub fn specimen(&self) -> &str {
+        &self.specimen
+    }
+
+    pub fn consensus(&self) -> Option<&str> {
+        self.consensus.as_deref()
+    }
+
+    pub fn consensus_depth(&self) -> usize {
+        self.consensus_depth
+    }
+
+    pub fn genes(&self) -> &[GenePlacement] {
+        &self.genes
+    }
+}
@@ -0,0 +1,184 @@
+use mycelium_base::utils::errors::{execution_err, MappedErrors};
+use std::collections::HashMap;
+
+/// Extract a Newick tree from a Nexus content
+///
+/// The trees block is parsed and the tree at `tree_index` (zero based) is
+/// returned in Newick format, with leaf names resolved from the translate
+/// table, if present. Comments (e.g. `[&R]` or FigTree annotations) are
+/// discarded. Contents with multiple trees are rejected unless the index is
+/// given.
+pub(crate) fn nexus_to_newick(
+    content: &str,
+    tree_index: Option<usize>,
+) -> Result<String, MappedErrors> {
+    let content = remove_comments(content);
+    let lowercase = content.to_lowercase();
+
+    let block_start = match lowercase.find("begin trees;") {
+        Some(index) => index + "begin trees;".len(),
+        None => {
+            return execution_err(
+                "The Nexus file does not contain a trees block",
+            )
+            .as_error()
+        }
+    };
+
+    let block_end = match lowercase[block_start..].find("end;") {
+        Some(index) => block_start + index,
+        None => {
+            return execution_err("The Nexus trees block is not terminated")
+                .as_error()
+        }
+    };
+
+    let mut translate = HashMap::<String, String>::new();
+    let mut trees = Vec::<String>::new();
+
+    for statement in content[block_start..block_end].split(';') {
+        let statement = statement.trim();
+        let lowercase = statement.to_lowercase();
+
+        if lowercase.starts_with("translate") {
+            for pair in statement["translate".len()..].split(',') {
+                let mut parts = pair.split_whitespace();
+
+                if let (Some(key), Some(label)) = (parts.next(), parts.next()) {
+                    translate.insert(
+                        key.to_string(),
+                        label.trim_matches('\'').to_string(),
+                    );
+                }
+            }
+        } else if lowercase.starts_with("tree ")
+            || lowercase.starts_with("utree ")
+        {
+            match statement.split_once('=') {
+                Some((_, newick)) => trees.push(format!("{};", newick.trim())),
+                None => {
+                    return execution_err(format!(
+                        "Invalid Nexus tree statement: {statement}"
+                    ))
+                    .as_error()
+                }
+            }
+        }
+    }
+
+    let newick = match (tree_index, trees.len()) {
+        (_, 0) => {
+            return execution_err(
+                "The Nexus trees block does not contain trees",
+            )
+            .as_error()
+        }
+        (None, 1) => trees.remove(0),
+        (None, count) => {
+            return execution_err(format!(
+                "The Nexus file contains {count} trees. Provide the index of \
+                the tree to be used."
+            ))
+            .as_error()
+        }
+        (Some(index), count) if index >= count => {
+            return execution_err(format!(
+                "Tree index {index} out of range. The Nexus file contains \
+                {count} trees."
+            ))
+            .as_error()
+        }
+        (Some(index), _) => trees.remove(index),
+    };
+
+    Ok(translate_leaf_labels(&newick, &translate))
+}
+
+/// Remove bracketed comments from a Nexus content
+fn remove_comments(content: &str) -> String {
+    let mut depth = 0;
+
+    content
+        .chars()
+        .filter(|c| match c {
+            '[' => {
+                depth += 1;
+                false
+            }
+            ']' if depth > 0 => {
+                depth -= 1;
+                false
+            }
+            _ => depth == 0,
+        })
+        .collect()
+}
+
+/// Replace leaf labels of a Newick string using the translate table
+///
+/// Only labels following `(` or `,` are leaf labels. Internal node labels
+/// (following `)`) and branch lengths (following `:`) are kept.
+fn translate_leaf_labels(
+    newick: &str,
+    translate: &HashMap<String, String>,
+) -> String {
+    if translate.is_empty() {
+        return newick.to_string();
+    }
+
+    let mut translated = String::with_capacity(newick.len());
+    let mut token = String::new();
+    let mut previous_delimiter = '(';
+
+    for c in newick.chars() {
+        if "(),:;".contains(c) {
+            let label = token.trim();
+
+            match (previous_delimiter, translate.get(label)) {
+                ('(' | ',', Some(name)) => translated.push_str(name),
+                _ => translated.push_str(&token),
+            }
+
+            token.clear();
+            translated.push(c);
+            previous_delimiter = c;
+        } else {
+            token.push(c);
+        }
+    }
+
+    translated.push_str(&token);
+    translated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nexus_to_newick() {
+        let content = "#NEXUS\n\
+            BEGIN TREES;\n\
+            \tTRANSLATE\n\
+            \t\t1 Seq_a,\n\
+            \t\t2 'Seq_b',\n\
+            \t\t3 Seq_c\n\
+            \t;\n\
+            \tTREE tree_1 = [&R] ((1:0.1,2:0.2)95:0.3,3:0.4);\n\
+            \tTREE tree_2 = [&R] ((1:0.1,3:0.2)80:0.3,2:0.4);\n\
+            END;\n";
+
+        assert!(nexus_to_newick(content, None).is_err());
+        assert!(nexus_to_newick(content, Some(2)).is_err());
+
+        assert_eq!(
+            nexus_to_newick(content, Some(0)).unwrap(),
+            "((Seq_a:0.1,Seq_b:0.2)95:0.3,Seq_c:0.4);"
+        );
+
+        assert_eq!(
+            nexus_to_newick(content, Some(1)).unwrap(),
+            "((Seq_a:0.1,Seq_c:0.2)80:0.3,Seq_b:0.4);"
+        );
+    }
+}
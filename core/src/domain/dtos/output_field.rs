@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+/// The fields of the placement response which can be selected for output
+#[derive(
+    Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, clap::ValueEnum,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputField {
+    /// The query header. Always included to identify the record.
+    Query,
+
+    /// The placement code. Always included to identify the record.
+    Code,
+
+    /// The placed clade or the inconclusive clades
+    Placement,
+
+    /// The annotations of the placed clade
+    Annotations,
+
+    /// The taxonomic lineage of the placed clade
+    Lineage,
+
+    /// The weakest margin, if requested by the placement options
+    Margin,
+
+    /// The number of tree levels introspected before the placement
+    Depth,
+
+    /// The reference leaves under the placed clade, if requested by the
+    /// placement options
+    Leaves,
+
+    /// The discriminating kmers, if requested by the placement options
+    Kmers,
+
+    /// The candidate clades of the deciding level, if requested by the
+    /// placement options
+    Evidence,
+
+    /// The inferred query strand, if requested by the placement options
+    Strand,
+
+    /// The placed reading frame of translated queries
+    Frame,
+
+    /// The branch supports along the placement path, if requested by the
+    /// placement options
+    PathSupport,
+}
@@ -1,11 +1,106 @@
+use mycelium_base::utils::errors::{creation_err, MappedErrors};
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Serialize, Deserialize, clap::ValueEnum)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, clap::ValueEnum)]
 #[serde(rename_all = "camelCase")]
 pub enum OutputFormat {
     /// JSONL format
     Jsonl,
 
-    /// YAML format
+    /// YAML format, as a multi-document stream with one document per record
     Yaml,
+
+    /// YAML format, as a single document containing an array of records
+    YamlArray,
+}
+
+impl OutputFormat {
+    /// The extension of the output files
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Yaml | OutputFormat::YamlArray => "yaml",
+        }
+    }
+
+    /// Serialize a record as a chunk of the output stream
+    ///
+    /// Chunks always end with a newline, then the output stays valid after
+    /// each appended record. YAML documents start with the `---` separator,
+    /// while array items are indented under a `- ` marker.
+    pub fn format_record<T: Serialize>(
+        &self,
+        record: &T,
+    ) -> Result<String, MappedErrors> {
+        let content = match self {
+            OutputFormat::Jsonl => {
+                serde_json::to_string(record).map_err(|err| err.to_string())
+            }
+            OutputFormat::Yaml | OutputFormat::YamlArray => {
+                serde_yaml::to_string(record).map_err(|err| err.to_string())
+            }
+        };
+
+        let content = match content {
+            Ok(content) => content,
+            Err(err) => {
+                return creation_err(format!(
+                    "Unable to serialize the record: {err}"
+                ))
+                .as_error()
+            }
+        };
+
+        let content = content.strip_suffix('\n').unwrap_or(&content);
+
+        Ok(match self {
+            OutputFormat::Jsonl => format!("{content}\n"),
+            OutputFormat::Yaml => format!("---\n{content}\n"),
+            OutputFormat::YamlArray => content
+                .lines()
+                .enumerate()
+                .map(|(index, line)| match (index, line.is_empty()) {
+                    (0, _) => format!("- {line}\n"),
+                    (_, true) => "\n".to_string(),
+                    (_, false) => format!("  {line}\n"),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_format_record_as_yaml_streams() {
+        let records = vec![
+            json!({"query": "a", "code": "IdentityFound"}),
+            json!({"query": "b", "lineage": "x\ny", "leaves": ["c", "d"]}),
+        ];
+
+        let stream = |format: OutputFormat| {
+            records
+                .iter()
+                .map(|record| format.format_record(record).unwrap())
+                .collect::<String>()
+        };
+
+        let documents =
+            serde_yaml::Deserializer::from_str(&stream(OutputFormat::Yaml))
+                .map(Value::deserialize)
+                .collect::<Result<Vec<Value>, _>>()
+                .unwrap();
+
+        assert_eq!(documents, records);
+
+        let array = serde_yaml::from_str::<Vec<Value>>(&stream(
+            OutputFormat::YamlArray,
+        ))
+        .unwrap();
+
+        assert_eq!(array, records);
+    }
 }
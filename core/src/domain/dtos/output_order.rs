@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// The order in which placement results are written
+#[derive(
+    Clone, Debug, Default, Serialize, Deserialize, PartialEq, clap::ValueEnum,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputOrder {
+    /// Results are written as soon as queries are placed. The order is not
+    /// deterministic, since queries are placed in parallel.
+    #[default]
+    Arrival,
+
+    /// Results are buffered and sorted by the query header
+    Header,
+
+    /// Results are buffered and sorted by the query position in the input
+    Input,
+}
@@ -0,0 +1,226 @@
+use super::{
+    chunk_by::ChunkBy, output_format::OutputFormat, output_order::OutputOrder,
+    placement_options::PlacementOptions, quality_filter::QualityFilter,
+};
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The configuration of a multiple sequences placement run
+///
+/// Query-level options are shared by reference with the placement of each
+/// query, while the remaining fields control how queries are read and how
+/// results are written.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlacementConfig {
+    /// The options of the placement of each query.
+    #[serde(default)]
+    pub options: PlacementOptions,
+
+    /// If true, existing output files are overwritten.
+    pub overwrite: bool,
+
+    /// If true, queries already placed in an existing output file are skipped
+    /// and new results are appended to it.
+    pub resume: bool,
+
+    /// If true, results are appended to an existing output file as placed.
+    /// Otherwise, results are written to a temporary file moved to the output
+    /// path when the run completes.
+    #[serde(default)]
+    pub append: bool,
+
+    /// If provided, queries resulting in `Unclassifiable` placements are
+    /// written to this FASTA file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unclassifiable_fasta: Option<PathBuf>,
+
+    /// If provided, the length and the GC content of each query are written
+    /// to this TSV file alongside the placement status.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub qc_report: Option<PathBuf>,
+
+    /// If provided, the placements are also written to this SQLite database
+    ///
+    /// Requires the `sqlite` feature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sqlite_out: Option<PathBuf>,
+
+    /// If true, `Unclassifiable` placements are not written to the results.
+    #[serde(default)]
+    pub drop_unclassifiable: bool,
+
+    /// If true, repeated query headers are renamed appending the occurrence
+    /// index (e.g. `query_2`). Otherwise, repeated queries are flagged as
+    /// `duplicate` in the placement response.
+    #[serde(default)]
+    pub rename_duplicates: bool,
+
+    /// The quality thresholds applied to FASTQ queries.
+    #[serde(default)]
+    pub quality_filter: QualityFilter,
+
+    /// The format used to persist placement results.
+    pub output_format: OutputFormat,
+
+    /// The order of the written results. Sorted orders buffer all results in
+    /// memory before writing, then should be avoided for streaming use.
+    #[serde(default)]
+    pub output_order: OutputOrder,
+
+    /// If provided, results are partitioned into separate files of an output
+    /// directory instead of written to a single output file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_by: Option<ChunkBy>,
+
+    /// If provided, the full introspection trace of the query with this
+    /// header is written to a dedicated trace file, despite the global log
+    /// level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_query: Option<String>,
+
+    /// The maximum number of query records read ahead of the placement. If
+    /// not provided, `FileOrStdin::CHANNEL_BOUND` records are buffered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_buffer_size: Option<usize>,
+}
+
+impl Default for PlacementConfig {
+    fn default() -> Self {
+        PlacementConfig {
+            options: PlacementOptions::default(),
+            overwrite: false,
+            resume: false,
+            append: false,
+            unclassifiable_fasta: None,
+            qc_report: None,
+            sqlite_out: None,
+            drop_unclassifiable: false,
+            rename_duplicates: false,
+            quality_filter: QualityFilter::default(),
+            output_format: OutputFormat::Yaml,
+            output_order: OutputOrder::default(),
+            chunk_by: None,
+            trace_query: None,
+            query_buffer_size: None,
+        }
+    }
+}
+
+impl PlacementConfig {
+    pub fn with_options(mut self, value: PlacementOptions) -> Self {
+        self.options = value;
+        self
+    }
+
+    pub fn with_overwrite(mut self, value: bool) -> Self {
+        self.overwrite = value;
+        self
+    }
+
+    pub fn with_resume(mut self, value: bool) -> Self {
+        self.resume = value;
+        self
+    }
+
+    pub fn with_append(mut self, value: bool) -> Self {
+        self.append = value;
+        self
+    }
+
+    pub fn with_unclassifiable_fasta(mut self, value: Option<PathBuf>) -> Self {
+        self.unclassifiable_fasta = value;
+        self
+    }
+
+    pub fn with_qc_report(mut self, value: Option<PathBuf>) -> Self {
+        self.qc_report = value;
+        self
+    }
+
+    pub fn with_sqlite_out(mut self, value: Option<PathBuf>) -> Self {
+        self.sqlite_out = value;
+        self
+    }
+
+    pub fn with_drop_unclassifiable(mut self, value: bool) -> Self {
+        self.drop_unclassifiable = value;
+        self
+    }
+
+    pub fn with_rename_duplicates(mut self, value: bool) -> Self {
+        self.rename_duplicates = value;
+        self
+    }
+
+    /// Set the minimum Phred score of FASTQ query bases.
+    ///
+    /// If `None` is given, the current value is kept.
+    pub fn with_min_base_quality(mut self, value: Option<u8>) -> Self {
+        if let Some(value) = value {
+            self.quality_filter.min_base_quality = Some(value);
+        }
+
+        self
+    }
+
+    /// Set the minimum mean Phred score of FASTQ queries.
+    ///
+    /// If `None` is given, the current value is kept.
+    pub fn with_min_read_quality(mut self, value: Option<f64>) -> Self {
+        if let Some(value) = value {
+            self.quality_filter.min_read_quality = Some(value);
+        }
+
+        self
+    }
+
+    pub fn with_output_format(mut self, value: OutputFormat) -> Self {
+        self.output_format = value;
+        self
+    }
+
+    pub fn with_output_order(mut self, value: OutputOrder) -> Self {
+        self.output_order = value;
+        self
+    }
+
+    pub fn with_chunk_by(mut self, value: Option<ChunkBy>) -> Self {
+        self.chunk_by = value;
+        self
+    }
+
+    pub fn with_trace_query(mut self, value: Option<String>) -> Self {
+        self.trace_query = value;
+        self
+    }
+
+    pub fn with_query_buffer_size(mut self, value: Option<usize>) -> Self {
+        self.query_buffer_size = value;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::dtos::match_coverage::MatchCoverage;
+
+    #[test]
+    fn test_placement_config_builder() {
+        let config = PlacementConfig::default()
+            .with_options(
+                PlacementOptions::default().with_min_match_coverage(Some(0.5)),
+            )
+            .with_overwrite(true)
+            .with_output_format(OutputFormat::Jsonl);
+
+        assert!(config.overwrite);
+        assert_eq!(config.output_format, OutputFormat::Jsonl);
+        assert_eq!(
+            config.options.min_match_coverage,
+            MatchCoverage::Fraction(0.5)
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use super::{
+    genetic_code::GeneticCode, match_coverage::MatchCoverage,
+    output_field::OutputField,
+};
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PlacementOptions {
+    /// The maximum number of iterations to traverse the tree.
+    pub max_iterations: i32,
+
+    /// If provided, the tree introspection of a single query is aborted once
+    /// this wall-clock time is exceeded, then the query is `Unclassifiable`
+    /// and the remaining queries are still placed. The elapsed time is checked
+    /// between introspection levels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_sequence_timeout: Option<Duration>,
+
+    /// The minimum match coverage between the query and the database
+    /// sequences, expressed as a fraction of the query kmers or as an absolute
+    /// number of kmers.
+    pub min_match_coverage: MatchCoverage,
+
+    /// If true, calculate the one-vs-rest difference without the shared kmers.
+    pub remove_intersection: bool,
+
+    /// The minimum `one - rest` difference required to accept a clade
+    /// proposal. Smaller wins are considered noise. Expressed in kmers, or in
+    /// parts per million if `normalize_by_clade_size` is true.
+    #[serde(default)]
+    pub min_adherence_margin: i32,
+
+    /// If provided, a clade proposal is only accepted if `one` is at least
+    /// this number of times `rest`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_adherence_ratio: Option<f64>,
+
+    /// If true, `one` and `rest` are divided by the number of kmers indexed
+    /// for the respective clades, then the one-vs-rest test compares coverage
+    /// fractions instead of raw counts. Normalized values are expressed in
+    /// parts per million.
+    #[serde(default)]
+    pub normalize_by_clade_size: bool,
+
+    /// If true, queries with fewer kmers than the minimum match coverage are
+    /// placed at the first accepted clade instead of being unclassifiable.
+    #[serde(default)]
+    pub classify_low_coverage_as_partial: bool,
+
+    /// If provided, the placement stops at the first accepted clade annotated
+    /// with the given rank (e.g. "genus"). Requires rank annotations.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_at_rank: Option<String>,
+
+    /// If provided, overrides the database strand mode when building the
+    /// query kmers. If true, reverse complement kmers of queries are skipped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_forward_only: Option<bool>,
+
+    /// If provided, nucleotide queries are translated in the six reading
+    /// frames with the genetic code, each frame is placed, and the best
+    /// scoring placement is kept. Requires protein databases.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translate: Option<GeneticCode>,
+
+    /// If true, the weakest decision margin of the placement path is included
+    /// in the placement response.
+    pub report_margin: bool,
+
+    /// If true, the names of the reference leaves under the placed clade are
+    /// included in the placement response.
+    #[serde(default)]
+    pub report_leaves: bool,
+
+    /// If true, the one-vs-rest counts of all candidate clades of the level
+    /// deciding the placement are included in the placement response.
+    #[serde(default)]
+    pub report_evidence: bool,
+
+    /// If true, the query strand inferred from the forward and the reverse
+    /// complement kmers is included in the placement response.
+    #[serde(default)]
+    pub report_strand: bool,
+
+    /// If true, the branch supports of the clades from the root to the placed
+    /// clade are included in the placement response.
+    #[serde(default)]
+    pub report_path_support: bool,
+
+    /// If true, a human-readable trace of the decisions taken at each
+    /// introspected level is built for each placement.
+    #[serde(default)]
+    pub explain: bool,
+
+    /// If provided, the top discriminating kmers of the placed clade are
+    /// included in the placement response, limited to this number. Requires
+    /// databases built retaining kmer strings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explain_kmers: Option<usize>,
+
+    /// The placement response fields included in the output. All fields are
+    /// included if not provided.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_fields: Option<Vec<OutputField>>,
+
+    /// The delimiter of the taxonomic lineage names.
+    #[serde(default = "default_lineage_delimiter")]
+    pub lineage_delimiter: String,
+}
+
+fn default_lineage_delimiter() -> String {
+    ";".to_string()
+}
+
+impl Default for PlacementOptions {
+    fn default() -> Self {
+        PlacementOptions {
+            max_iterations: 1000,
+            per_sequence_timeout: None,
+            min_match_coverage: MatchCoverage::default(),
+            remove_intersection: false,
+            min_adherence_margin: 0,
+            min_adherence_ratio: None,
+            normalize_by_clade_size: false,
+            classify_low_coverage_as_partial: false,
+            stop_at_rank: None,
+            query_forward_only: None,
+            translate: None,
+            report_margin: false,
+            report_leaves: false,
+            report_evidence: false,
+            report_strand: false,
+            report_path_support: false,
+            explain: false,
+            explain_kmers: None,
+            output_fields: None,
+            lineage_delimiter: default_lineage_delimiter(),
+        }
+    }
+}
+
+impl PlacementOptions {
+    /// Set the maximum number of iterations.
+    ///
+    /// If `None` is given, the current value is kept.
+    pub fn with_max_iterations(mut self, value: Option<i32>) -> Self {
+        if let Some(value) = value {
+            self.max_iterations = value;
+        }
+
+        self
+    }
+
+    pub fn with_per_sequence_timeout(
+        mut self,
+        value: Option<Duration>,
+    ) -> Self {
+        self.per_sequence_timeout = value;
+        self
+    }
+
+    /// Set the minimum match coverage as a fraction of the query kmers.
+    ///
+    /// If `None` is given, the current value is kept.
+    pub fn with_min_match_coverage(mut self, value: Option<f64>) -> Self {
+        if let Some(value) = value {
+            self.min_match_coverage = MatchCoverage::Fraction(value);
+        }
+
+        self
+    }
+
+    /// Set the minimum match coverage.
+    pub fn with_match_coverage(mut self, value: MatchCoverage) -> Self {
+        self.min_match_coverage = value;
+        self
+    }
+
+    /// Set the one-vs-rest intersection removal.
+    ///
+    /// If `None` is given, the current value is kept.
+    pub fn with_remove_intersection(mut self, value: Option<bool>) -> Self {
+        if let Some(value) = value {
+            self.remove_intersection = value;
+        }
+
+        self
+    }
+
+    /// Set the minimum one-vs-rest margin.
+    ///
+    /// If `None` is given, the current value is kept.
+    pub fn with_min_adherence_margin(mut self, value: Option<i32>) -> Self {
+        if let Some(value) = value {
+            self.min_adherence_margin = value;
+        }
+
+        self
+    }
+
+    pub fn with_min_adherence_ratio(mut self, value: Option<f64>) -> Self {
+        self.min_adherence_ratio = value;
+        self
+    }
+
+    pub fn with_normalize_by_clade_size(mut self, value: bool) -> Self {
+        self.normalize_by_clade_size = value;
+        self
+    }
+
+    pub fn with_classify_low_coverage_as_partial(
+        mut self,
+        value: bool,
+    ) -> Self {
+        self.classify_low_coverage_as_partial = value;
+        self
+    }
+
+    pub fn with_stop_at_rank(mut self, value: Option<String>) -> Self {
+        self.stop_at_rank = value;
+        self
+    }
+
+    pub fn with_report_margin(mut self, value: bool) -> Self {
+        self.report_margin = value;
+        self
+    }
+
+    pub fn with_query_forward_only(mut self, value: Option<bool>) -> Self {
+        self.query_forward_only = value;
+        self
+    }
+
+    pub fn with_translate(mut self, value: Option<GeneticCode>) -> Self {
+        self.translate = value;
+        self
+    }
+
+    pub fn with_report_leaves(mut self, value: bool) -> Self {
+        self.report_leaves = value;
+        self
+    }
+
+    pub fn with_report_evidence(mut self, value: bool) -> Self {
+        self.report_evidence = value;
+        self
+    }
+
+    pub fn with_report_strand(mut self, value: bool) -> Self {
+        self.report_strand = value;
+        self
+    }
+
+    pub fn with_report_path_support(mut self, value: bool) -> Self {
+        self.report_path_support = value;
+        self
+    }
+
+    pub fn with_explain(mut self, value: bool) -> Self {
+        self.explain = value;
+        self
+    }
+
+    pub fn with_explain_kmers(mut self, value: Option<usize>) -> Self {
+        self.explain_kmers = value;
+        self
+    }
+
+    pub fn with_output_fields(
+        mut self,
+        value: Option<Vec<OutputField>>,
+    ) -> Self {
+        self.output_fields = value;
+        self
+    }
+
+    /// Set the lineage delimiter.
+    ///
+    /// If `None` is given, the current value is kept.
+    pub fn with_lineage_delimiter(mut self, value: Option<String>) -> Self {
+        if let Some(value) = value {
+            self.lineage_delimiter = value;
+        }
+
+        self
+    }
+
+    /// Check if the field should be included in the placement response
+    pub fn includes_field(&self, field: &OutputField) -> bool {
+        match &self.output_fields {
+            None => true,
+            Some(fields) => fields.contains(field),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placement_options_builder() {
+        let options = PlacementOptions::default()
+            .with_max_iterations(Some(10))
+            .with_min_match_coverage(None)
+            .with_remove_intersection(Some(true));
+
+        assert_eq!(options.max_iterations, 10);
+        assert_eq!(options.min_match_coverage, MatchCoverage::Fraction(0.7));
+        assert!(options.remove_intersection);
+    }
+}
@@ -1,5 +1,8 @@
 use self::PlacementStatus::*;
-use super::{adherence_test::AdherenceTest, annotation::Annotation};
+use super::{
+    adherence_test::AdherenceTest, annotation::Annotation, clade::CladeSupport,
+    strand::Strand,
+};
 
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
@@ -27,6 +30,86 @@ pub enum PlacementStatus {
     Inconclusive(Vec<AdherenceTest>, String),
 }
 
+impl PlacementStatus {
+    /// Build an `Unclassifiable` status prefixing the message with the reason
+    pub fn unclassifiable(reason: UnclassifiableReason, msg: &str) -> Self {
+        Unclassifiable(format!("{reason}: {msg}"))
+    }
+
+    /// Get the reason of `Unclassifiable` statuses
+    pub fn unclassifiable_reason(&self) -> Option<UnclassifiableReason> {
+        match self {
+            Unclassifiable(msg) => UnclassifiableReason::ALL
+                .into_iter()
+                .find(|reason| msg.starts_with(&format!("{reason}:"))),
+            _ => None,
+        }
+    }
+
+    /// Get a canonical copy of the status
+    ///
+    /// Proposals are collected in parallel, then the tests of `Inconclusive`
+    /// statuses are sorted by the number of matches (descending), the clade ID
+    /// and the rest, so equal placements compare and serialize identically.
+    pub fn normalized(&self) -> Self {
+        match self {
+            Inconclusive(tests, msg) => {
+                let mut tests = tests.to_owned();
+
+                tests.sort_by(|a, b| {
+                    b.one
+                        .cmp(&a.one)
+                        .then(a.clade_id().cmp(&b.clade_id()))
+                        .then(a.rest.cmp(&b.rest))
+                });
+
+                Inconclusive(tests, msg.to_owned())
+            }
+            other => other.to_owned(),
+        }
+    }
+}
+
+/// The reason of an `Unclassifiable` placement
+///
+/// Queries foreign to the reference tree and low quality or short queries are
+/// both unclassifiable, but users may treat them differently.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnclassifiableReason {
+    /// The query shares no kmers with the reference tree
+    NoOverlap,
+
+    /// The query shares fewer kmers with the reference tree than the minimum
+    /// match coverage
+    LowCoverage,
+
+    /// No clade of the first tree level wins the one-vs-rest test
+    NoProposals,
+
+    /// The tree introspection exceeded the per-sequence timeout
+    Timeout,
+}
+
+impl UnclassifiableReason {
+    const ALL: [UnclassifiableReason; 4] = [
+        UnclassifiableReason::NoOverlap,
+        UnclassifiableReason::LowCoverage,
+        UnclassifiableReason::NoProposals,
+        UnclassifiableReason::Timeout,
+    ];
+}
+
+impl std::fmt::Display for UnclassifiableReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnclassifiableReason::NoOverlap => write!(f, "NoOverlap"),
+            UnclassifiableReason::LowCoverage => write!(f, "LowCoverage"),
+            UnclassifiableReason::NoProposals => write!(f, "NoProposals"),
+            UnclassifiableReason::Timeout => write!(f, "Timeout"),
+        }
+    }
+}
+
 impl ToString for PlacementStatus {
     fn to_string(&self) -> String {
         match self {
@@ -66,8 +149,70 @@ pub struct PlacementResponse<T> {
     #[serde(skip_serializing_if = "Option::is_none")]
     annotations: Option<Vec<Annotation>>,
 
+    /// The taxonomic lineage of the placed clade, built from the scientific
+    /// names of the annotated ancestors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lineage: Option<String>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     placement: Option<T>,
+
+    /// The minimum decision margin along the accepted placement path
+    ///
+    /// Placements which traversed a weak decision (e.g. a clade selected by
+    /// 51 vs 50 kmers) should be considered less confident.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    weakest_margin: Option<i32>,
+
+    /// The number of tree levels introspected before the placement
+    ///
+    /// Deeper placements are usually more specific, then the depth could be
+    /// used as a proxy of the taxonomic resolution.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    depth: Option<usize>,
+
+    /// The names of the reference leaves under the placed clade
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    leaves: Option<Vec<String>>,
+
+    /// The query kmers discriminating the placed clade from its siblings
+    ///
+    /// Only available for databases built retaining kmer strings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    discriminating_kmers: Option<Vec<String>>,
+
+    /// The one-vs-rest counts of all candidate clades of the level deciding
+    /// the placement, including the rejected ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    evidence: Option<Vec<AdherenceTest>>,
+
+    /// The query orientation inferred from the overlapping kmers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    strand: Option<Strand>,
+
+    /// The branch supports of the clades from the root to the placed clade
+    ///
+    /// Queries descending through weakly supported branches should be
+    /// considered less confident.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    path_support: Option<Vec<CladeSupport>>,
+
+    /// True if a previous query of the same input shares the query header
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    duplicate: Option<bool>,
+
+    /// The reading frame placed for translated queries
+    ///
+    /// Frames `1` to `3` are read from the query, and `-1` to `-3` from its
+    /// reverse complement.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    frame: Option<i8>,
+
+    /// The human-readable trace of the placement decisions
+    ///
+    /// Not serialized, since it is written to a dedicated text file.
+    #[serde(skip)]
+    explanation: Option<String>,
 }
 
 impl<T> PlacementResponse<T> {
@@ -76,7 +221,18 @@ impl<T> PlacementResponse<T> {
             query,
             code,
             annotations: None,
+            lineage: None,
             placement,
+            weakest_margin: None,
+            depth: None,
+            leaves: None,
+            discriminating_kmers: None,
+            evidence: None,
+            strand: None,
+            path_support: None,
+            duplicate: None,
+            frame: None,
+            explanation: None,
         }
     }
 
@@ -88,7 +244,179 @@ impl<T> PlacementResponse<T> {
         self
     }
 
+    pub fn with_lineage(mut self, lineage: Option<String>) -> Self {
+        self.lineage = lineage;
+        self
+    }
+
+    pub fn with_weakest_margin(mut self, margin: Option<i32>) -> Self {
+        self.weakest_margin = margin;
+        self
+    }
+
+    pub fn with_depth(mut self, depth: Option<usize>) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_leaves(mut self, leaves: Option<Vec<String>>) -> Self {
+        self.leaves = leaves;
+        self
+    }
+
+    pub fn with_discriminating_kmers(
+        mut self,
+        kmers: Option<Vec<String>>,
+    ) -> Self {
+        self.discriminating_kmers = kmers;
+        self
+    }
+
+    pub fn with_evidence(
+        mut self,
+        evidence: Option<Vec<AdherenceTest>>,
+    ) -> Self {
+        self.evidence = evidence;
+        self
+    }
+
+    pub fn with_strand(mut self, strand: Option<Strand>) -> Self {
+        self.strand = strand;
+        self
+    }
+
+    pub fn with_frame(mut self, frame: Option<i8>) -> Self {
+        self.frame = frame;
+        self
+    }
+
+    pub fn with_path_support(
+        mut self,
+        path_support: Option<Vec<CladeSupport>>,
+    ) -> Self {
+        self.path_support = path_support;
+        self
+    }
+
+    pub fn with_explanation(mut self, explanation: Option<String>) -> Self {
+        self.explanation = explanation;
+        self
+    }
+
+    /// Remove the placement from the response
+    ///
+    /// Used to slim the output when the placement is not selected.
+    pub fn without_placement(mut self) -> Self {
+        self.placement = None;
+        self
+    }
+
+    /// Flag the query as duplicated
+    ///
+    /// The flag is omitted from the serialized response when `false`.
+    pub fn with_duplicate(mut self, duplicate: bool) -> Self {
+        self.duplicate = duplicate.then_some(true);
+        self
+    }
+
+    pub fn is_duplicate(&self) -> bool {
+        self.duplicate.unwrap_or(false)
+    }
+
+    pub fn discriminating_kmers(&self) -> Option<&Vec<String>> {
+        self.discriminating_kmers.as_ref()
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn annotations(&self) -> Option<&Vec<Annotation>> {
+        self.annotations.as_ref()
+    }
+
+    pub fn lineage(&self) -> Option<&str> {
+        self.lineage.as_deref()
+    }
+
+    pub fn weakest_margin(&self) -> Option<i32> {
+        self.weakest_margin
+    }
+
+    pub fn depth(&self) -> Option<usize> {
+        self.depth
+    }
+
+    pub fn frame(&self) -> Option<i8> {
+        self.frame
+    }
+
+    pub fn leaves(&self) -> Option<&Vec<String>> {
+        self.leaves.as_ref()
+    }
+
+    pub fn evidence(&self) -> Option<&Vec<AdherenceTest>> {
+        self.evidence.as_ref()
+    }
+
+    pub fn path_support(&self) -> Option<&Vec<CladeSupport>> {
+        self.path_support.as_ref()
+    }
+
+    pub fn strand(&self) -> Option<&Strand> {
+        self.strand.as_ref()
+    }
+
+    pub fn explanation(&self) -> Option<&str> {
+        self.explanation.as_deref()
+    }
+
     pub fn placement(&self) -> Option<&T> {
         self.placement.as_ref()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mycelium_base::dtos::UntaggedParent;
+
+    #[test]
+    fn test_normalized_inconclusive_statuses_are_equal() {
+        let test = |clade: u64, one: i32, rest: i32| AdherenceTest {
+            clade: UntaggedParent::Id(clade),
+            one,
+            rest,
+        };
+
+        let first = Inconclusive(
+            vec![test(3, 10, 2), test(1, 10, 2), test(2, 12, 1)],
+            "Multiple proposals".to_string(),
+        );
+
+        let second = Inconclusive(
+            vec![test(2, 12, 1), test(3, 10, 2), test(1, 10, 2)],
+            "Multiple proposals".to_string(),
+        );
+
+        assert_ne!(first, second);
+        assert_eq!(first.normalized(), second.normalized());
+
+        match first.normalized() {
+            Inconclusive(tests, _) => assert_eq!(
+                tests.iter().map(|i| i.clade_id()).collect::<Vec<u64>>(),
+                vec![2, 1, 3]
+            ),
+            _ => panic!("Expected an inconclusive status"),
+        }
+
+        assert_eq!(
+            serde_json::to_string(&first.normalized()).unwrap(),
+            serde_json::to_string(&second.normalized()).unwrap()
+        );
+    }
+}
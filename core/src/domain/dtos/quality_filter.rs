@@ -0,0 +1,95 @@
+use super::sequence::SequenceBody;
+
+use serde::{Deserialize, Serialize};
+
+/// The offset of Phred scores encoded in FASTQ quality strings (Sanger and
+/// Illumina 1.8+).
+const PHRED_OFFSET: u8 = 33;
+
+/// Quality thresholds applied to FASTQ reads before the kmers building.
+///
+/// Filters are ignored for FASTA inputs, since no quality is available.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityFilter {
+    /// Bases with Phred score below this value are masked. Masked bases are
+    /// dropped, as done with non-IUPAC characters.
+    pub min_base_quality: Option<u8>,
+
+    /// Reads with mean Phred score below this value are skipped.
+    pub min_read_quality: Option<f64>,
+}
+
+impl QualityFilter {
+    pub fn new(
+        min_base_quality: Option<u8>,
+        min_read_quality: Option<f64>,
+    ) -> Self {
+        QualityFilter {
+            min_base_quality,
+            min_read_quality,
+        }
+    }
+
+    /// Apply the quality thresholds to a read.
+    ///
+    /// Returns `None` if the read should be skipped. Otherwise, returns the
+    /// sequence without masked and non-IUPAC bases.
+    pub fn apply(&self, sequence: &str, quality: &str) -> Option<String> {
+        let scores = quality
+            .bytes()
+            .map(|score| score.saturating_sub(PHRED_OFFSET))
+            .collect::<Vec<_>>();
+
+        if let Some(min_read_quality) = self.min_read_quality {
+            let mean = match scores.len() {
+                0 => 0.0,
+                len => {
+                    scores.iter().map(|i| *i as f64).sum::<f64>() / len as f64
+                }
+            };
+
+            if mean < min_read_quality {
+                return None;
+            }
+        }
+
+        let masked = match self.min_base_quality {
+            None => sequence.to_string(),
+            Some(min_base_quality) => sequence
+                .chars()
+                .zip(scores.iter())
+                .filter(|(_, score)| **score >= min_base_quality)
+                .map(|(base, _)| base)
+                .collect(),
+        };
+
+        Some(SequenceBody::remove_non_iupac_from_sequence(&masked))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quality_filter_apply() {
+        let sequence = "ACGTNACGTA";
+        let quality = "IIIIIII###";
+
+        assert_eq!(
+            QualityFilter::default().apply(sequence, quality),
+            Some("ACGTNACGTA".to_string())
+        );
+
+        assert_eq!(
+            QualityFilter::new(Some(20), None).apply(sequence, quality),
+            Some("ACGTNAC".to_string())
+        );
+
+        assert_eq!(
+            QualityFilter::new(None, Some(35.0)).apply(sequence, quality),
+            None
+        );
+    }
+}
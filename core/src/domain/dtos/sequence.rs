@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
+use tracing::warn;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -40,26 +41,165 @@ impl SequenceBody {
         &self.0
     }
 
+    /// The number of bases of the sequence
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The fraction of `G` and `C` bases of the sequence
+    ///
+    /// Only the unambiguous bases are counted, then ambiguity codes (e.g. `N`)
+    /// do not bias the content. Returns zero for sequences without unambiguous
+    /// bases.
+    pub fn gc_content(&self) -> f64 {
+        let (gc, acgt) = self.0.chars().map(|c| c.to_ascii_uppercase()).fold(
+            (0usize, 0usize),
+            |(gc, acgt), c| match c {
+                'G' | 'C' => (gc + 1, acgt + 1),
+                'A' | 'T' => (gc, acgt + 1),
+                _ => (gc, acgt),
+            },
+        );
+
+        match acgt {
+            0 => 0.0,
+            _ => gc as f64 / acgt as f64,
+        }
+    }
+
     /// Remove non-IUPAC characters from a sequence
     ///
-    /// Returns a string with only IUPAC characters. This method is used to
-    /// remove non-IUPAC characters from a given sequence.
+    /// Returns an uppercase string with only IUPAC nucleotide characters,
+    /// including the ambiguity codes (e.g. `N`). Kmers containing ambiguity
+    /// codes are skipped while building kmers.
     pub fn remove_non_iupac_from_sequence(sequence: &str) -> String {
+        Self::remove_non_iupac_keeping_case(&sequence.to_uppercase())
+    }
+
+    /// Remove non-IUPAC characters from a sequence keeping the bases case
+    ///
+    /// Lowercase bases are kept, then soft-masked regions can be identified
+    /// while building kmers.
+    pub fn remove_non_iupac_keeping_case(sequence: &str) -> String {
+        sequence
+            .chars()
+            .filter(|c| Self::is_iupac(c.to_ascii_uppercase()))
+            .collect()
+    }
+
+    /// Remove gaps and non-residue characters from a protein sequence
+    ///
+    /// All letters are kept, including the ambiguity codes (e.g. `X`), then
+    /// kmers containing them are skipped while building kmers.
+    pub fn remove_non_amino_acids_keeping_case(sequence: &str) -> String {
+        sequence.chars().filter(char::is_ascii_alphabetic).collect()
+    }
+
+    /// Keep only the unambiguous bases of a sequence
+    ///
+    /// Returns an uppercase string containing only `A`, `C`, `G`, and `T`.
+    pub fn to_acgt_only(sequence: &str) -> String {
         sequence
-            .to_uppercase()
             .chars()
-            .filter(|c| match c {
-                'A' | 'C' | 'G' | 'T' => true,
-                _ => false,
-            })
+            .map(|c| c.to_ascii_uppercase())
+            .filter(|c| matches!(c, 'A' | 'C' | 'G' | 'T'))
             .collect()
     }
+
+    fn is_iupac(c: char) -> bool {
+        matches!(
+            c,
+            'A' | 'C'
+                | 'G'
+                | 'T'
+                | 'U'
+                | 'R'
+                | 'Y'
+                | 'S'
+                | 'W'
+                | 'K'
+                | 'M'
+                | 'B'
+                | 'D'
+                | 'H'
+                | 'V'
+                | 'N'
+        )
+    }
+}
+
+/// The characters dropped from a raw sequence before building kmers
+///
+/// Gaps (`-` and `.`) are expected in MSA records, then they are counted
+/// separately and not considered as dropped.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReport {
+    /// The number of non-gap characters
+    pub total: usize,
+
+    /// The number of gap characters
+    pub gaps: usize,
+
+    /// The number of IUPAC ambiguity codes (e.g. `N`)
+    pub ambiguous: usize,
+
+    /// The number of non-IUPAC characters (e.g. amino acids)
+    pub invalid: usize,
+}
+
+impl ValidationReport {
+    /// The dropped fraction above which the sequence is reported
+    pub const MAX_DROPPED_FRACTION: f64 = 0.1;
+
+    /// Build the report of a raw sequence
+    pub fn from_sequence(sequence: &str) -> Self {
+        let mut report = Self::default();
+        report.update(sequence);
+        report
+    }
+
+    /// Count the characters of a raw sequence chunk (e.g. a FASTA line)
+    pub fn update(&mut self, sequence: &str) {
+        for c in sequence.chars() {
+            match c.to_ascii_uppercase() {
+                '-' | '.' => self.gaps += 1,
+                c if c.is_whitespace() => (),
+                'A' | 'C' | 'G' | 'T' => self.total += 1,
+                'R' | 'Y' | 'S' | 'W' | 'K' | 'M' | 'B' | 'D' | 'H' | 'V'
+                | 'N' => {
+                    self.total += 1;
+                    self.ambiguous += 1;
+                }
+                _ => {
+                    self.total += 1;
+                    self.invalid += 1;
+                }
+            }
+        }
+    }
+
+    pub fn dropped(&self) -> usize {
+        self.ambiguous + self.invalid
+    }
+
+    pub fn dropped_fraction(&self) -> f64 {
+        match self.total {
+            0 => 0.0,
+            total => self.dropped() as f64 / total as f64,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Sequence {
     header: SequenceHeader,
     sequence: SequenceBody,
+    validation: ValidationReport,
 }
 
 impl Sequence {
@@ -67,12 +207,51 @@ impl Sequence {
     where
         T: Into<String>,
     {
+        let sequence = SequenceBody::new(sequence);
+
         Self {
             header: SequenceHeader::new(header),
-            sequence: SequenceBody::new(sequence),
+            validation: ValidationReport::from_sequence(sequence.seq()),
+            sequence,
         }
     }
 
+    /// Set the report of the raw sequence
+    ///
+    /// Used by readers which filter the sequence content before building the
+    /// sequence.
+    pub fn with_validation_report(mut self, report: ValidationReport) -> Self {
+        self.validation = report;
+        self
+    }
+
+    /// The characters dropped from the raw sequence
+    pub fn validation_report(&self) -> &ValidationReport {
+        &self.validation
+    }
+
+    /// Log a warning if the dropped fraction exceeds the report threshold
+    ///
+    /// Returns true if the warning was emitted.
+    pub fn warn_if_heavily_filtered(&self) -> bool {
+        let fraction = self.validation.dropped_fraction();
+
+        if fraction <= ValidationReport::MAX_DROPPED_FRACTION {
+            return false;
+        }
+
+        warn!(
+            "{dropped} of {total} characters ({percent:.1}%) were dropped from \
+            sequence {header}",
+            dropped = self.validation.dropped(),
+            total = self.validation.total,
+            percent = fraction * 100.0,
+            header = self.header_content(),
+        );
+
+        true
+    }
+
     pub fn header(&self) -> &SequenceHeader {
         &self.header
     }
@@ -93,3 +272,76 @@ impl Sequence {
         format!(">{}\n{}\n", self.header.header(), self.sequence.seq())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::dtos::{
+        file_or_stdin::FileOrStdin, quality_filter::QualityFilter,
+    };
+
+    #[test]
+    fn test_validation_report_of_half_filtered_sequence() {
+        let raw = "ACGTNNXX--acgtnnxx";
+
+        let sequence = Sequence::new(
+            "half_filtered",
+            SequenceBody::remove_non_iupac_from_sequence(raw).as_str(),
+        )
+        .with_validation_report(ValidationReport::from_sequence(raw));
+
+        let report = sequence.validation_report();
+
+        assert_eq!(sequence.sequence_content(), "ACGTNNACGTNN");
+        assert_eq!(report.total, 16);
+        assert_eq!(report.gaps, 2);
+        assert_eq!(report.ambiguous, 4);
+        assert_eq!(report.invalid, 4);
+        assert_eq!(report.dropped_fraction(), 0.5);
+        assert!(sequence.warn_if_heavily_filtered());
+
+        //
+        // Readers report the raw content of each record
+        //
+        let (sender, receiver) =
+            std::sync::mpsc::sync_channel(FileOrStdin::CHANNEL_BOUND);
+
+        FileOrStdin::sequence_content_from_reader(
+            format!(">half_filtered\n{raw}\n>clean\nACGT\n").as_bytes(),
+            sender,
+            &QualityFilter::default(),
+        )
+        .unwrap();
+
+        let reports = receiver
+            .into_iter()
+            .map(|sequence| sequence.validation_report().to_owned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(reports[0], *report);
+        assert_eq!(reports[1].dropped(), 0);
+
+        //
+        // Sequences built from clean content are not reported
+        //
+        assert!(!Sequence::new("clean", "ACGT").warn_if_heavily_filtered());
+    }
+
+    #[test]
+    fn test_ambiguity_codes_survive_cleaning() {
+        let cleaned = SequenceBody::remove_non_iupac_from_sequence("acgNr-X");
+
+        assert_eq!(cleaned, "ACGNR");
+        assert_eq!(SequenceBody::to_acgt_only(&cleaned), "ACG");
+    }
+
+    #[test]
+    fn test_sequence_body_gc_content() {
+        let body = SequenceBody::new("ACGTGGCCnN");
+
+        assert_eq!(body.len(), 10);
+        assert!((body.gc_content() - 0.75).abs() < f64::EPSILON);
+        assert_eq!(SequenceBody::new("NNN").gc_content(), 0.0);
+        assert!(SequenceBody::new("").is_empty());
+    }
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+/// The query orientation inferred from the overlapping kmers
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Strand {
+    /// The query kmers overlap the database more than the reverse complement
+    Forward,
+
+    /// The reverse complement kmers overlap the database more than the query
+    Reverse,
+
+    /// Both orientations overlap the database equally, as expected for
+    /// databases indexing both strands
+    Unknown,
+}
@@ -97,6 +97,35 @@ pub(crate) enum TelemetryCode {
     /// triggered
     ///
     UCPLACE0019,
+    //
+    /// If the accepted clade is annotated with the target rank, the
+    /// `MaxResolutionReached` state is triggered
+    ///
+    UCPLACE0021,
+    // ? -----------------------------------------------------------------------
+
+    // ? -----------------------------------------------------------------------
+    // ? Reserved codes to the tree initialization
+    //
+    /// The tree file extension is missing or not supported
+    ///
+    DTTREE0001,
+    //
+    /// The tree file could not be read
+    ///
+    DTTREE0002,
+    //
+    /// The tree content could not be parsed
+    ///
+    DTTREE0003,
+    //
+    /// The tree is not rooted
+    ///
+    DTTREE0004,
+    //
+    /// The tree root could not be resolved
+    ///
+    DTTREE0005,
     // ? -----------------------------------------------------------------------
 }
 
@@ -1,9 +1,22 @@
-use super::{annotation::Annotation, clade::Clade, kmers_map::KmersMap};
-
-use mycelium_base::utils::errors::MappedErrors;
+use super::{
+    annotation::Annotation,
+    clade::{Clade, CladeSupport},
+    kmers_map::KmersMap,
+    nexus::nexus_to_newick,
+    telemetry_code::TelemetryCode,
+};
+
+use mycelium_base::utils::errors::{creation_err, MappedErrors};
 use phylotree::tree::Tree as PhyloTree;
 use serde::{Deserialize, Serialize};
-use std::{ffi::OsStr, fs::read_to_string, mem::size_of_val, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs::read_to_string,
+    mem::size_of_val,
+    path::Path,
+    sync::OnceLock,
+};
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -49,6 +62,13 @@ pub struct Tree {
 
     //#[serde(skip_serializing_if = "Option::is_none")]
     pub kmers_map: Option<KmersMap>,
+
+    /// The number of kmers indexed for each clade and for its siblings.
+    ///
+    /// Counted once from the kmers map at the first placement normalized by
+    /// clade sizes, then shared by the following queries.
+    #[serde(skip)]
+    clade_sizes: OnceLock<HashMap<u64, (usize, usize)>>,
 }
 
 impl Tree {
@@ -71,7 +91,167 @@ impl Tree {
             root,
             annotations: None,
             kmers_map: None,
+            clade_sizes: OnceLock::new(),
+        }
+    }
+
+    /// Get the maximum number of edges between the root and a leaf.
+    ///
+    /// The tree is traversed iteratively, avoiding recursion limits on deep
+    /// trees.
+    pub fn depth(&self) -> usize {
+        let mut stack = vec![(&self.root, 0)];
+        let mut depth = 0;
+
+        while let Some((clade, level)) = stack.pop() {
+            depth = depth.max(level);
+
+            if let Some(children) = &clade.children {
+                stack.extend(children.iter().map(|child| (child, level + 1)));
+            }
+        }
+
+        depth
+    }
+
+    /// List the names of the leaves descending from a clade.
+    ///
+    /// Used to interpret placements at internal clades, where the leaves are
+    /// the candidate taxa. Returns an empty list if the clade does not exist.
+    pub fn leaves_under(&self, clade_id: u64) -> Vec<String> {
+        match self.root.get_node_by_id(clade_id) {
+            None => vec![],
+            Some(clade) => clade
+                .iter()
+                .filter(|node| node.is_leaf())
+                .filter_map(|leaf| leaf.name.to_owned())
+                .collect(),
+        }
+    }
+
+    /// List the branch supports of the clades from the root to a clade.
+    ///
+    /// Clades are visited in pre-order, then ancestors come before their
+    /// descendants. Returns `None` if the clade does not exist.
+    pub fn path_support(&self, clade_id: u64) -> Option<Vec<CladeSupport>> {
+        let path = self
+            .root
+            .get_node_by_id(clade_id)?
+            .get_path_to_root(&self.root);
+
+        Some(
+            self.root
+                .iter()
+                .filter(|node| path.contains(&node.id))
+                .map(|node| CladeSupport {
+                    clade: node.id,
+                    support: node.support,
+                })
+                .collect(),
+        )
+    }
+
+    /// Get the number of kmers indexed for each clade and for its siblings.
+    ///
+    /// Returns a map of the clade id to the `(one, rest)` sizes, where the rest
+    /// size is the number of distinct kmers indexed for the sibling clades.
+    /// Leaves are not tested during the placement, then they are not counted.
+    /// Sizes are counted at the first call, then trees whose kmers map is
+    /// modified afterwards should clear them (see `clear_clade_sizes`).
+    pub fn clade_sizes(&self) -> &HashMap<u64, (usize, usize)> {
+        self.clade_sizes.get_or_init(|| {
+            let kmers_map = match &self.kmers_map {
+                Some(kmers_map) => kmers_map,
+                None => return HashMap::new(),
+            };
+
+            let parents = self
+                .root
+                .iter()
+                .flat_map(|clade| {
+                    clade
+                        .children
+                        .iter()
+                        .flatten()
+                        .filter(|child| !child.is_leaf())
+                        .map(|child| (child.id, clade.id))
+                })
+                .collect::<HashMap<u64, u64>>();
+
+            kmers_map.count_sibling_kmers(&parents)
+        })
+    }
+
+    /// Clear the clade sizes counted from a previous kmers map.
+    pub(crate) fn clear_clade_sizes(&mut self) {
+        self.clade_sizes = OnceLock::new();
+    }
+
+    /// Rename the leaves found in the mapping from old to new names.
+    ///
+    /// Leaves not included in the mapping keep their names. Kmers and
+    /// annotations are indexed by clade ids, then the database remains valid
+    /// after renaming. Returns the sorted names of the mapping not found among
+    /// the tree leaves.
+    pub fn rename_leaves(
+        &mut self,
+        mapping: &HashMap<String, String>,
+    ) -> Vec<String> {
+        let mut renamed = HashSet::<&str>::new();
+        let mut stack = vec![&mut self.root];
+
+        while let Some(clade) = stack.pop() {
+            if clade.is_leaf() {
+                let new_name = clade
+                    .name
+                    .as_ref()
+                    .and_then(|name| mapping.get_key_value(name));
+
+                if let Some((old_name, new_name)) = new_name {
+                    renamed.insert(old_name.as_str());
+                    clade.name = Some(new_name.to_owned());
+                }
+            }
+
+            if let Some(children) = clade.children.as_mut() {
+                stack.extend(children.iter_mut());
+            }
         }
+
+        let mut missing = mapping
+            .keys()
+            .filter(|name| !renamed.contains(name.as_str()))
+            .cloned()
+            .collect::<Vec<String>>();
+
+        missing.sort();
+        missing
+    }
+
+    /// Export the tree in Newick format with the kmers count of each clade.
+    ///
+    /// Internal nodes are labeled as `'clade_id:kmer_count'`. Labels are
+    /// quoted, since the colon separates branch lengths in Newick. Used to
+    /// spot clades with few or no indexed kmers.
+    pub fn to_newick_with_kmers(&self) -> Result<String, MappedErrors> {
+        let kmers_map = match &self.kmers_map {
+            Some(kmers_map) => kmers_map,
+            None => {
+                return creation_err("The tree does not have a kmers map.")
+                    .as_error()
+            }
+        };
+
+        let newick = self.root.to_newick(&|clade| {
+            let count = kmers_map
+                .get_hashed_kmers_with_node(clade.id)
+                .map(|kmers| kmers.len())
+                .unwrap_or_default();
+
+            Some(format!("'{id}:{count}'", id = clade.id))
+        });
+
+        Ok(format!("{newick};"))
     }
 
     pub fn get_in_memory_size(&self) -> Option<String> {
@@ -113,7 +293,7 @@ impl Tree {
     pub fn pretty_print(&self) {
         println!("R: {}", self.name);
 
-        for child in self.root.children.to_owned().unwrap() {
+        for child in self.root.children.to_owned().unwrap_or_default() {
             self.pretty_print_clade(&child, 0);
         }
     }
@@ -165,44 +345,102 @@ impl Tree {
         tree_path: &Path,
         min_branch_support: f64,
     ) -> Result<Tree, MappedErrors> {
-        assert!(
-            vec!["nwk", "newick", "tree"].contains(
-                &tree_path
-                    .extension()
-                    .and_then(OsStr::to_str)
-                    .expect("Could not get extension")
-            ),
-            "Tree file format is not supported"
-        );
+        Self::init_from_file_with_index(tree_path, min_branch_support, None)
+    }
+
+    /// Create a new Tree from a Newick or Nexus file.
+    ///
+    /// Nexus files (`.nex`, `.nexus`, `.nxs` and `.trees`) may contain
+    /// multiple trees. The tree at `tree_index` (zero based) is used, and the
+    /// index is required if more than one tree is found. The index is ignored
+    /// for Newick files.
+    pub fn init_from_file_with_index(
+        tree_path: &Path,
+        min_branch_support: f64,
+        tree_index: Option<usize>,
+    ) -> Result<Tree, MappedErrors> {
+        let extension = tree_path.extension().and_then(OsStr::to_str);
+
+        let is_newick = matches!(extension, Some("nwk" | "newick" | "tree"));
+        let is_nexus =
+            matches!(extension, Some("nex" | "nexus" | "nxs" | "trees"));
+
+        if !is_newick && !is_nexus {
+            return creation_err(format!(
+                "Tree file format is not supported: {tree_path:?}. Expected a \
+                Newick (.nwk, .newick, .tree) or Nexus (.nex, .nexus, .nxs, \
+                .trees) file."
+            ))
+            .with_code(TelemetryCode::DTTREE0001.to_string().as_str())
+            .as_error();
+        }
 
-        let newick_content =
-            read_to_string(tree_path).expect("Could not read file");
+        let content = match read_to_string(tree_path) {
+            Ok(content) => content,
+            Err(err) => {
+                return creation_err(format!(
+                    "Could not read the tree file {tree_path:?}: {err}"
+                ))
+                .with_code(TelemetryCode::DTTREE0002.to_string().as_str())
+                .as_error()
+            }
+        };
 
-        let phylo_tree = PhyloTree::from_newick(&newick_content.as_str())
-            .expect("Could not parse tree");
+        let newick_content = match is_nexus {
+            true => nexus_to_newick(&content, tree_index)?,
+            false => content,
+        };
 
-        let root_name = (if let Some(name) = tree_path.file_name() {
-            Some(
-                name.to_str()
-                    .expect("Could not convert path to string")
-                    .to_string(),
-            )
-        } else {
-            None
-        })
-        .unwrap_or("UnnamedTree".to_string());
+        let phylo_tree = match PhyloTree::from_newick(newick_content.as_str()) {
+            Ok(tree) => tree,
+            Err(err) => {
+                return creation_err(format!(
+                    "Could not parse the tree file {tree_path:?}: {err:?}"
+                ))
+                .with_code(TelemetryCode::DTTREE0003.to_string().as_str())
+                .as_error()
+            }
+        };
+
+        let root_name = tree_path
+            .file_name()
+            .and_then(OsStr::to_str)
+            .map(|name| name.to_string())
+            .unwrap_or("UnnamedTree".to_string());
 
         if !phylo_tree.is_rooted().unwrap_or(false) {
-            panic!("Tree is not rooted");
+            return creation_err(format!(
+                "The tree is not rooted: {tree_path:?}. Root the tree before \
+                building the database."
+            ))
+            .with_code(TelemetryCode::DTTREE0004.to_string().as_str())
+            .as_error();
         }
 
         let root_tree = match phylo_tree.get_root() {
-            Err(err) => panic!("Could not get root: {err}"),
-            Ok(root) => phylo_tree.get(&root).expect("Could not get root"),
+            Err(err) => {
+                return creation_err(format!(
+                    "Could not get the tree root: {err}"
+                ))
+                .with_code(TelemetryCode::DTTREE0005.to_string().as_str())
+                .as_error()
+            }
+            Ok(root) => match phylo_tree.get(&root) {
+                Ok(root) => root,
+                Err(err) => {
+                    return creation_err(format!(
+                        "Could not get the tree root: {err:?}"
+                    ))
+                    .with_code(TelemetryCode::DTTREE0005.to_string().as_str())
+                    .as_error()
+                }
+            },
         };
 
         if !root_tree.is_root() {
-            panic!("Root node is not a root");
+            return creation_err("The tree root node is not a root")
+                .with_code(TelemetryCode::DTTREE0005.to_string().as_str())
+                .as_error();
         }
 
         let children = Self::get_children_nodes(&phylo_tree, &root_tree.id);
@@ -367,6 +605,12 @@ impl Tree {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        domain::dtos::{
+            build_options::BuildOptions, file_or_stdin::FileOrStdin,
+        },
+        use_cases::map_kmers_to_tree,
+    };
     use std::path::PathBuf;
 
     #[test]
@@ -379,4 +623,172 @@ mod tests {
 
         tree.unwrap().pretty_print();
     }
+
+    #[test]
+    fn test_tree_from_nexus_file() {
+        let newick_path = PathBuf::from("/tmp/cls-nexus-equivalent.nwk");
+        let nexus_path = PathBuf::from("/tmp/cls-nexus-tree.nex");
+
+        std::fs::write(
+            &newick_path,
+            "((Seq_a:0.1,Seq_b:0.2)95:0.3,(Seq_c:0.1,Seq_d:0.2)90:0.4);",
+        )
+        .unwrap();
+
+        std::fs::write(
+            &nexus_path,
+            "#NEXUS\n\
+            BEGIN TREES;\n\
+            \tTRANSLATE\n\
+            \t\t1 Seq_a,\n\
+            \t\t2 Seq_b,\n\
+            \t\t3 Seq_c,\n\
+            \t\t4 Seq_d\n\
+            \t;\n\
+            \tTREE tree_1 = [&R] ((1:0.1,2:0.2)95:0.3,(3:0.1,4:0.2)90:0.4);\n\
+            END;\n",
+        )
+        .unwrap();
+
+        let newick_tree = Tree::init_from_file(&newick_path, 70.0).unwrap();
+        let nexus_tree = Tree::init_from_file(&nexus_path, 70.0).unwrap();
+
+        assert_eq!(nexus_tree.root, newick_tree.root);
+    }
+
+    #[test]
+    fn test_tree_node_counts() {
+        let path = PathBuf::from("/tmp/cls-node-counts.nwk");
+
+        std::fs::write(
+            &path,
+            "((A:0.1,B:0.2)95:0.3,(C:0.1,(D:0.1,E:0.1)90:0.2)90:0.4);",
+        )
+        .unwrap();
+
+        let tree = Tree::init_from_file(&path, 70.0).unwrap();
+
+        assert_eq!(tree.root.count_leaves(), 5);
+        assert_eq!(tree.root.count_internal(), 4);
+        assert_eq!(tree.depth(), 3);
+
+        //
+        // The sample tree contains 171 leaves and 169 internal nodes (including
+        // the root), all of them kept when no branch support is required.
+        //
+        let path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let tree = Tree::init_from_file(&path, 0.0).unwrap();
+
+        assert_eq!(tree.root.count_leaves(), 171);
+        assert_eq!(tree.root.count_internal(), 169);
+        assert!(tree.depth() > 1);
+    }
+
+    #[test]
+    fn test_tree_leaves_under() {
+        let path = PathBuf::from("/tmp/cls-leaves-under.nwk");
+
+        std::fs::write(
+            &path,
+            "((A:0.1,B:0.2)95:0.3,(C:0.1,(D:0.1,E:0.1)90:0.2)90:0.4);",
+        )
+        .unwrap();
+
+        let tree = Tree::init_from_file(&path, 70.0).unwrap();
+
+        let leaves_of = |names: &[&str]| {
+            tree.root
+                .children
+                .as_ref()
+                .unwrap()
+                .iter()
+                .flat_map(|child| child.children.iter().flatten())
+                .chain(tree.root.children.iter().flatten())
+                .filter(|clade| clade.is_internal())
+                .map(|clade| {
+                    let mut leaves = tree.leaves_under(clade.id);
+                    leaves.sort();
+                    leaves
+                })
+                .find(|leaves| leaves == names)
+        };
+
+        assert!(leaves_of(&["A", "B"]).is_some());
+        assert!(leaves_of(&["C", "D", "E"]).is_some());
+        assert!(leaves_of(&["D", "E"]).is_some());
+        assert_eq!(tree.leaves_under(tree.root.id).len(), 5);
+        assert!(tree.leaves_under(tree.root.max_id() + 1).is_empty());
+    }
+
+    #[test]
+    fn test_tree_clade_sizes() {
+        let tree = map_kmers_to_tree(
+            PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk"),
+            FileOrStdin::from_file("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta"),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let kmers_map = tree.kmers_map.as_ref().unwrap();
+        let sizes = tree.clade_sizes();
+
+        //
+        // Sizes counted in a single pass should match the kmer sets of each
+        // clade and of the union of its siblings
+        //
+        for parent in tree.root.iter() {
+            let children = parent
+                .children
+                .iter()
+                .flatten()
+                .filter(|child| !child.is_leaf())
+                .map(|child| {
+                    (
+                        child.id,
+                        kmers_map
+                            .get_hashed_kmers_with_node(child.id)
+                            .unwrap_or_default(),
+                    )
+                })
+                .collect::<Vec<(u64, HashSet<u64>)>>();
+
+            for (id, kmers) in children.iter() {
+                let rest = children
+                    .iter()
+                    .filter(|(rest_id, _)| rest_id != id)
+                    .flat_map(|(_, rest_kmers)| rest_kmers.iter())
+                    .collect::<HashSet<&u64>>();
+
+                assert_eq!(sizes.get(id), Some(&(kmers.len(), rest.len())));
+            }
+        }
+
+        assert!(sizes.values().any(|(one, rest)| *one > 0 && *rest > 0));
+        assert!(!sizes.contains_key(&tree.root.id));
+    }
+
+    #[test]
+    fn test_tree_from_invalid_files_returns_errors() {
+        let init = |file_name: &str, content: &str| {
+            let path = PathBuf::from(format!("/tmp/{file_name}"));
+            std::fs::write(&path, content).unwrap();
+
+            Tree::init_from_file(&path, 70.0).unwrap_err().to_string()
+        };
+
+        assert!(init("cls-invalid-tree.txt", "(A:0.1,B:0.2);")
+            .contains("Tree file format is not supported"));
+
+        assert!(init("cls-malformed-tree.nwk", "((A:0.1,B:0.2);")
+            .contains("Could not parse the tree file"));
+
+        assert!(init("cls-unrooted-tree.nwk", "(A:0.1,B:0.2,C:0.3);")
+            .contains("The tree is not rooted"));
+
+        assert!(Tree::init_from_file(
+            &PathBuf::from("/tmp/cls-missing-tree.nwk"),
+            70.0
+        )
+        .is_err());
+    }
 }
@@ -0,0 +1,225 @@
+use crate::domain::dtos::kmers_map::{KmersMap, MinimizerKey, MinimizerValue};
+
+use mycelium_base::utils::errors::{execution_err, MappedErrors};
+use std::{
+    fs::{create_dir_all, remove_dir_all, remove_file, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use uuid::Uuid;
+
+/// Partial kmers indexes spilled to disk during the build
+///
+/// Minimizer buckets are appended to partition files by their minimizer key,
+/// then all buckets of a minimizer are found in the same partition. Spilled
+/// buckets are merged one partition at a time, then only the final index and
+/// the buckets of a single partition are held in memory during the merge.
+///
+/// Spill files are written to a unique directory, removed when the spill is
+/// dropped.
+pub(super) struct KmersSpill {
+    dir: PathBuf,
+    runs: usize,
+}
+
+impl KmersSpill {
+    /// The number of partition files
+    const PARTITIONS: u64 = 16;
+
+    /// Create the spill directory inside `parent`
+    pub(super) fn create(parent: &Path) -> Result<Self, MappedErrors> {
+        let dir = parent.join(format!("cls-build-{}", Uuid::new_v4()));
+
+        if let Err(err) = create_dir_all(&dir) {
+            return execution_err(format!(
+                "Unable to create the spill directory {dir:?}: {err}"
+            ))
+            .as_error();
+        }
+
+        Ok(KmersSpill { dir, runs: 0 })
+    }
+
+    /// The number of partial indexes spilled
+    pub(super) fn runs(&self) -> usize {
+        self.runs
+    }
+
+    fn partition_path(&self, partition: u64) -> PathBuf {
+        self.dir.join(format!("partition-{partition}.jsonl"))
+    }
+
+    /// Move the buckets of the map to the partition files
+    ///
+    /// The map is left empty, keeping its settings and kmer strings.
+    pub(super) fn spill(
+        &mut self,
+        map: &mut KmersMap,
+    ) -> Result<(), MappedErrors> {
+        let mut writers = Vec::<Option<BufWriter<File>>>::new();
+        writers.resize_with(Self::PARTITIONS as usize, || None);
+
+        for (key, bucket) in map.take_buckets() {
+            let partition = key.0 % Self::PARTITIONS;
+            let writer = match &mut writers[partition as usize] {
+                Some(writer) => writer,
+                slot => {
+                    let path = self.partition_path(partition);
+
+                    match OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&path)
+                    {
+                        Ok(file) => slot.insert(BufWriter::new(file)),
+                        Err(err) => {
+                            return execution_err(format!(
+                                "Unable to open the spill file {path:?}: {err}"
+                            ))
+                            .as_error()
+                        }
+                    }
+                }
+            };
+
+            let line = match serde_json::to_string(&(key, bucket)) {
+                Ok(line) => line,
+                Err(err) => {
+                    return execution_err(format!(
+                        "Unable to serialize the spilled kmers: {err}"
+                    ))
+                    .as_error()
+                }
+            };
+
+            if let Err(err) = writeln!(writer, "{line}") {
+                return execution_err(format!(
+                    "Unable to write the spilled kmers: {err}"
+                ))
+                .as_error();
+            }
+        }
+
+        for writer in writers.iter_mut().flatten() {
+            if let Err(err) = writer.flush() {
+                return execution_err(format!(
+                    "Unable to write the spilled kmers: {err}"
+                ))
+                .as_error();
+            }
+        }
+
+        self.runs += 1;
+
+        Ok(())
+    }
+
+    /// Merge all spilled buckets into the map
+    ///
+    /// Partition files are removed once merged.
+    pub(super) fn merge_into(
+        self,
+        map: &mut KmersMap,
+    ) -> Result<(), MappedErrors> {
+        for partition in 0..Self::PARTITIONS {
+            let path = self.partition_path(partition);
+
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                    continue
+                }
+                Err(err) => {
+                    return execution_err(format!(
+                        "Unable to open the spill file {path:?}: {err}"
+                    ))
+                    .as_error()
+                }
+            };
+
+            for line in BufReader::new(file).lines() {
+                let entry: Result<(MinimizerKey, MinimizerValue), String> =
+                    line.map_err(|err| err.to_string()).and_then(|line| {
+                        serde_json::from_str(&line)
+                            .map_err(|err| err.to_string())
+                    });
+
+                match entry {
+                    Ok((key, bucket)) => {
+                        map.insert_or_append_bucket(key, bucket)
+                    }
+                    Err(err) => {
+                        return execution_err(format!(
+                            "Unable to read the spill file {path:?}: {err}"
+                        ))
+                        .as_error()
+                    }
+                }
+            }
+
+            let _ = remove_file(&path);
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for KmersSpill {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_spilled_buckets_are_merged() -> Result<(), MappedErrors> {
+        let parent = PathBuf::from("/tmp/cls-kmers-spill");
+        let _ = remove_dir_all(&parent);
+
+        let mut expected = KmersMap::new(5, 2);
+        let mut map = KmersMap::new(5, 2);
+        let mut spill = KmersSpill::create(&parent)?;
+
+        //
+        // The same kmers are indexed to distinct nodes in each run, then node
+        // sets should be combined by the merge
+        //
+        for node in 0..3 {
+            for kmer in ["ACGTA", "CGTAC", "TTGCA"] {
+                let hash = KmersMap::hash_kmer_full(kmer) as u64;
+                let nodes = HashSet::from([node]);
+
+                expected.insert_or_append_kmer_hash(
+                    kmer.to_string(),
+                    hash,
+                    nodes.to_owned(),
+                );
+                map.insert_or_append_kmer_hash(kmer.to_string(), hash, nodes);
+            }
+
+            spill.spill(&mut map)?;
+            assert!(map.get_map().is_empty());
+        }
+
+        assert_eq!(spill.runs(), 3);
+
+        let dir = spill.dir.to_owned();
+        spill.merge_into(&mut map)?;
+
+        let buckets = |map: &KmersMap| {
+            map.get_map()
+                .iter()
+                .map(|(key, value)| (key.0, value.0.to_owned()))
+                .collect::<HashMap<u64, HashMap<u64, HashSet<u64>>>>()
+        };
+
+        assert_eq!(buckets(&map), buckets(&expected));
+        assert!(!dir.exists());
+
+        Ok(())
+    }
+}
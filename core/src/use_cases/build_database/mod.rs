@@ -1,23 +1,56 @@
+mod kmers_spill;
+
+use self::kmers_spill::KmersSpill;
 use crate::domain::dtos::{
-    kmers_map::KmersMap, sequence::SequenceBody, tree::Tree,
+    build_options::BuildOptions,
+    file_or_stdin::{FileOrStdin, Source},
+    kmers_map::KmersMap,
+    msa_columns::MsaColumns,
+    tree::Tree,
 };
 
-use mycelium_base::utils::errors::MappedErrors;
+use mycelium_base::utils::errors::{use_case_err, MappedErrors};
 use rayon::iter::{ParallelBridge, ParallelIterator};
 use std::{
     collections::HashSet,
-    io::{BufRead, Write},
+    io::Write,
     path::PathBuf,
-    sync::mpsc::channel,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::sync_channel,
+    },
     thread,
 };
-use tracing::debug;
+use tracing::{debug, info, warn};
+
+/// The number of mapped kmers buffered before the indexing
+///
+/// Mapping workers pause while the buffer is full, then the kmers of the whole
+/// MSA are never held in memory besides the index itself.
+const KMERS_CHANNEL_BOUND: usize = 65_536;
 
 /// Map kmers to nodes in a phylogenetic tree
 ///
 /// Each kmer is mapped to a set of nodes in the tree. The set of nodes is the
 /// path from the root to the leaf node that contains the kmer.
 ///
+/// The MSA is read from a file or from stdin, allowing the reference MSA to be
+/// piped into the database build.
+///
+/// # Memory and threads
+///
+/// Records are streamed from a single reader thread through a bounded
+/// channel, mapped to kmers by the rayon thread pool, and indexed as they are
+/// mapped by the calling thread. The number of threads does not depend on the
+/// number of MSA records, and the memory usage is dominated by the kmers index
+/// itself.
+///
+/// If `max_resident_kmers` is set, the partial index is spilled to disk
+/// whenever it exceeds the limit, then the MSA reading holds at most that
+/// number of kmers in memory. Spilled partial indexes are merged one
+/// partition at a time after the MSA reading. The final index is assembled in
+/// memory, since it is serialized and loaded as a whole for the placement.
+///
 /// # Returns
 /// A tree with the kmers map attached to it. A kmer map is a KmersMap struct
 /// that contains a mapping of kmers to a set of nodes along the tree.
@@ -25,27 +58,89 @@ use tracing::debug;
 #[tracing::instrument(name = "Building Classeq database")]
 pub fn map_kmers_to_tree(
     tree_path: PathBuf,
-    msa_path: PathBuf,
-    k_size: Option<u64>,
-    m_size: Option<u64>,
-    min_branch_support: Option<f64>,
+    msa: FileOrStdin,
+    options: &BuildOptions,
 ) -> Result<Tree, MappedErrors> {
     // ? -----------------------------------------------------------------------
     // ? Initialize and Validate arguments
     // ? -----------------------------------------------------------------------
 
-    let k_size = k_size.unwrap_or(35);
+    let min_branch_support = options.min_branch_support;
 
-    let m_size = m_size.unwrap_or(4);
+    if !tree_path.exists() {
+        return use_case_err(format!(
+            "The tree file does not exist: {tree_path:?}"
+        ))
+        .as_error();
+    }
 
-    let min_branch_support = min_branch_support.unwrap_or(70.0);
+    if let Source::Arg(msa_path) = &msa.source {
+        if !PathBuf::from(msa_path).exists() {
+            return use_case_err(format!(
+                "The MSA file does not exist: {msa_path:?}"
+            ))
+            .as_error();
+        }
+    }
 
-    if !tree_path.exists() {
-        panic!("The tree file does not exist.");
+    if let Some(window) = &options.msa_window {
+        if !window.is_valid() || window.width() < options.k_size as usize {
+            return use_case_err(format!(
+                "The MSA window {start}-{end} should contain at least the \
+                kmer size ({k_size}) columns.",
+                start = window.start,
+                end = window.end,
+                k_size = options.k_size,
+            ))
+            .as_error();
+        }
     }
 
-    if !msa_path.exists() {
-        panic!("The MSA file does not exist.");
+    if let Some(fraction) = options.max_gap_fraction {
+        if !(0.0..=1.0).contains(&fraction) {
+            return use_case_err(format!(
+                "The maximum gap fraction should be between 0 and 1, but \
+                {fraction} was given."
+            ))
+            .as_error();
+        }
+
+        if let Source::Stdin = msa.source {
+            return use_case_err(
+                "The maximum gap fraction requires the MSA to be read from a \
+                file, since columns are evaluated before the indexing.",
+            )
+            .as_error();
+        }
+    }
+
+    if options.protein
+        && (options.homopolymer_compress
+            || options.max_ambiguity_expansions.is_some()
+            || options.max_gap_fraction.is_some())
+    {
+        return use_case_err(
+            "Homopolymer compression, ambiguity expansions and gap fractions \
+            are only supported for nucleotide MSAs.",
+        )
+        .as_error();
+    }
+
+    if options.max_resident_kmers == Some(0) {
+        return use_case_err(
+            "The maximum number of resident kmers should be greater than zero.",
+        )
+        .as_error();
+    }
+
+    if options.aligned_kmers {
+        if let Source::Stdin = msa.source {
+            return use_case_err(
+                "Aligned kmers require the MSA to be read from a file, since \
+                columns are evaluated before the indexing.",
+            )
+            .as_error();
+        }
     }
 
     // ? -----------------------------------------------------------------------
@@ -53,13 +148,43 @@ pub fn map_kmers_to_tree(
     // ? -----------------------------------------------------------------------
 
     debug!("Reading the phylogenetic tree");
-    let mut tree = Tree::init_from_file(&tree_path, min_branch_support)?;
+    let mut tree = Tree::init_from_file_with_index(
+        &tree_path,
+        min_branch_support,
+        options.tree_index,
+    )?;
+
+    //
+    // Placement starts from the root children. A root with less than two
+    // children after the low supported branches removal results in a database
+    // unable to discriminate clades, then the build should be aborted here.
+    //
+    match tree.root.children.as_ref() {
+        Some(children) if children.len() >= 2 => (),
+        children => {
+            return use_case_err(format!(
+                "The tree root should contain at least two children after \
+                sanitization, but {count} was found. Try to reduce the \
+                minimum branch support (current {min_branch_support}).",
+                count = children.map(|i| i.len()).unwrap_or(0),
+            ))
+            .as_error();
+        }
+    };
 
     // ? -----------------------------------------------------------------------
     // ? Initialize mappings
     // ? -----------------------------------------------------------------------
 
-    let mut map = KmersMap::new(k_size, m_size);
+    let mut map = KmersMap::new(options.k_size, options.m_size)
+        .with_homopolymer_compression(options.homopolymer_compress)
+        .with_soft_masked_exclusion(options.exclude_soft_masked)
+        .with_forward_only(options.forward_only)
+        .with_protein(options.protein)
+        .with_msa_window(options.msa_window.to_owned())
+        .with_retained_kmer_strings(
+            options.retain_kmer_strings || options.detect_collisions,
+        );
     let tree_leaves = tree.root.get_leaves_with_paths(None);
 
     // ? -----------------------------------------------------------------------
@@ -70,105 +195,347 @@ pub fn map_kmers_to_tree(
     // ? -----------------------------------------------------------------------
 
     debug!("Reading the MSA file");
-    let mut headers = Vec::<String>::new();
-    let mut header = String::new();
-    let mut sequence = String::new();
+    let (sequence_sender, sequence_receiver) =
+        sync_channel(FileOrStdin::CHANNEL_BOUND);
+    let (kmer_sender, kmer_receiver) = sync_channel(KMERS_CHANNEL_BOUND);
 
-    let reader = match std::fs::File::open(msa_path) {
-        Err(err) => panic!("The MSA file could not be opened: {err}"),
-        Ok(file) => std::io::BufReader::new(file),
-    };
+    //
+    // The MSA records are read by the FASTA channel reader, allowing the MSA
+    // to be piped through stdin.
+    //
+    let msa_columns = match (&options.msa_window, options.max_gap_fraction) {
+        (None, None) if !options.aligned_kmers => None,
+        (window, max_gap_fraction) => {
+            let mut columns =
+                MsaColumns::default().with_window(window.to_owned());
 
-    let (sequence_sender, sequence_receiver) = channel();
-    let (kmer_sender, kmer_receiver) = channel();
+            //
+            // Gap fractions are evaluated in a first pass over the MSA, then
+            // gap-rich columns are dropped before the kmers building
+            //
+            if let Some(max_gap_fraction) = max_gap_fraction {
+                let fractions = match msa.msa_gap_fractions() {
+                    Ok(fractions) => fractions,
+                    Err(err) => {
+                        return use_case_err(format!(
+                            "The MSA could not be read: {err}"
+                        ))
+                        .as_error()
+                    }
+                };
 
-    let mut i = 0;
-    for line in reader.lines() {
-        let line = line.unwrap();
+                columns =
+                    columns.with_max_gap_fraction(&fractions, max_gap_fraction);
 
-        if line.is_empty() {
-            continue;
-        }
+                info!(
+                    "{skipped} of {total} MSA columns skipped with gap \
+                    fraction above {max_gap_fraction}",
+                    skipped = columns.skipped_count(),
+                    total = fractions.len(),
+                );
+            }
+
+            //
+            // Columns with a gap in any record are dropped from all records,
+            // then homologous kmers are built from the same columns despite
+            // the indels of each record
+            //
+            if options.aligned_kmers {
+                let gaps = match msa.msa_gap_columns() {
+                    Ok(gaps) => gaps,
+                    Err(err) => {
+                        return use_case_err(format!(
+                            "The MSA could not be read: {err}"
+                        ))
+                        .as_error()
+                    }
+                };
 
-        if line.starts_with('>') {
-            if !header.is_empty() {
-                headers.push(header.clone());
-                header.clear();
+                let gap_rich = columns.skipped_count();
+                columns = columns.with_gap_columns(&gaps);
+
+                info!(
+                    "{skipped} of {total} MSA columns skipped with gaps",
+                    skipped = columns.skipped_count() - gap_rich,
+                    total = gaps.len(),
+                );
             }
 
-            header.push_str(&line.replace(">", ""));
+            Some(columns)
+        }
+    };
 
-            i += 1;
-            print!("Build kmer for sequence {i}\r");
-            std::io::stdout().flush().unwrap();
+    let protein = options.protein;
 
-            let own_sender = sequence_sender.to_owned();
-            let cloned_header = header.clone();
-            let kmers = map.build_kmer_from_string(sequence.clone(), None);
+    //
+    // A single reader thread sends all records through the bounded channel.
+    // Threads should never be spawned per record, since large MSAs would
+    // exhaust the system thread limits. The indexing memory is bounded
+    // separately, by spilling the partial index (see `max_resident_kmers`).
+    //
+    let msa_reader = thread::spawn(move || match (msa_columns, protein) {
+        (columns, true) => msa
+            .protein_msa_content_by_channel(sequence_sender, columns.as_ref()),
+        (None, false) => msa.sequence_content_by_channel(sequence_sender),
+        (Some(columns), false) => {
+            msa.msa_content_by_channel(sequence_sender, &columns)
+        }
+    });
 
-            let _ = thread::spawn(move || {
-                match own_sender.send((cloned_header.clone(), kmers.clone())) {
-                    Err(err) => panic!("Error: {err}"),
-                    Ok(_) => (),
-                }
-            });
+    //
+    // Records sharing a header would map their kmers to the same leaf, then
+    // duplicated headers are collected and the build is rejected after the
+    // MSA reading.
+    //
+    let mut msa_headers = HashSet::<String>::new();
+    let mut duplicated_headers = Vec::<String>::new();
 
-            sequence.clear();
-        } else {
-            sequence.push_str(
-                SequenceBody::remove_non_iupac_from_sequence(&line).as_str(),
+    //
+    // The number of ambiguous kmers exceeding the expansions cap
+    //
+    let skipped_ambiguous_kmers = AtomicUsize::new(0);
+
+    //
+    // Kmers are built from a copy of the empty map, since the map itself is
+    // borrowed by the indexing while the records are mapped
+    //
+    let builder = map.to_owned();
+
+    //
+    // Partial indexes are spilled once the resident kmers exceed the limit.
+    // Counting the indexed kmers walks all buckets, then it is checked once
+    // per batch of insertions.
+    //
+    let mut spill = match options.max_resident_kmers {
+        None => None,
+        Some(_) => Some(KmersSpill::create(
+            &options
+                .spill_directory
+                .to_owned()
+                .unwrap_or_else(std::env::temp_dir),
+        )?),
+    };
+
+    let spill_check_interval = options
+        .max_resident_kmers
+        .unwrap_or(KMERS_CHANNEL_BOUND)
+        .min(KMERS_CHANNEL_BOUND);
+
+    let mut spilling = Ok(());
+
+    let (mapping, msa_reading) = thread::scope(|scope| {
+        let mapper = scope.spawn(|| {
+            let mapping = sequence_receiver
+                .into_iter()
+                .filter(|sequence| {
+                    let header = sequence.header_content();
+
+                    if msa_headers.insert(header.to_string()) {
+                        return true;
+                    }
+
+                    duplicated_headers.push(header.to_string());
+                    false
+                })
+                .enumerate()
+                .par_bridge()
+                .try_for_each_with(
+                    kmer_sender,
+                    |kmer_sender, (i, sequence)| {
+                        print!(
+                            "Mapping kmers to nodes {index}\r",
+                            index = i + 1
+                        );
+                        let _ = std::io::stdout().flush();
+
+                        let header = sequence.header_content();
+
+                        sequence.warn_if_heavily_filtered();
+
+                        let leaf_path =
+                            match tree_leaves.iter().find(|(clade, _)| {
+                                clade.name.as_deref() == Some(header)
+                            }) {
+                                None => {
+                                    return Err(format!(
+                                "The sequence header does not match any tree \
+                                leaf: {header}"
+                            ))
+                                }
+                                Some((_, path)) => path,
+                            };
+
+                        let kmers = match options.max_ambiguity_expansions {
+                            None => builder.build_kmer_from_string(
+                                sequence.sequence_content().to_string(),
+                                None,
+                            ),
+                            Some(max_expansions) => {
+                                let (kmers, skipped) = builder
+                                    .build_expanded_kmer_from_string(
+                                        sequence.sequence_content().to_string(),
+                                        max_expansions,
+                                    );
+
+                                skipped_ambiguous_kmers
+                                    .fetch_add(skipped, Ordering::Relaxed);
+
+                                kmers
+                            }
+                        };
+
+                        for (kmer, hash) in kmers {
+                            if let Err(err) =
+                                kmer_sender.send((leaf_path, kmer, hash))
+                            {
+                                return Err(format!(
+                                    "Error sending kmer to the receiver: {err}"
+                                ));
+                            }
+                        }
+
+                        Ok(())
+                    },
+                );
+
+            //
+            // The reader stops once the mapping fails, since the records
+            // receiver is dropped with the mapping
+            //
+            (mapping, msa_reader.join())
+        });
+
+        //
+        // Senders are dropped once all records are mapped, ending the indexing
+        //
+        for (i, (leaf_path, kmer, hash)) in kmer_receiver.iter().enumerate() {
+            map.insert_or_append_kmer_hash(
+                kmer,
+                hash,
+                HashSet::from_iter(leaf_path.iter().cloned()),
             );
-        }
-    }
 
-    // Push the last line preventing losing the print value from the kmers map
-    // loop which prints the sequence index
-    println!();
+            if let (Some(spill), Some(max_resident_kmers)) =
+                (spill.as_mut(), options.max_resident_kmers)
+            {
+                if (i + 1) % spill_check_interval == 0
+                    && map.total_kmers() > max_resident_kmers
+                {
+                    //
+                    // The mapping fails once the receiver is dropped, then
+                    // the spilling error is reported first
+                    //
+                    spilling = spill.spill(&mut map);
 
-    // Drop to allow the receiver to finish
-    drop(sequence_sender);
-
-    sequence_receiver
-        .into_iter()
-        .enumerate()
-        .par_bridge()
-        .for_each(|(i, (header, kmers))| {
-            print!("Mapping kmers to nodes {index}\r", index = i + 1);
-            std::io::stdout().flush().unwrap();
-
-            let leaf_path = match tree_leaves.iter().find(|(clade, _)| {
-                clade.name.as_ref().expect("The clade name is empty").to_owned() == header
-            }) {
-                None => {
-                    panic!("The sequence header does not match any tree leaf: {header}")
+                    if spilling.is_err() {
+                        break;
+                    }
                 }
-                Some((_, path)) => path,
-            };
-
-            for (kmer, hash) in kmers {
-                kmer_sender
-                    .send((leaf_path.clone(), kmer, hash))
-                    .expect("Error sending kmer to the receiver");
             }
-        });
+        }
+
+        drop(kmer_receiver);
 
-    // Drop to allow the receiver to finish
-    drop(kmer_sender);
+        mapper.join()
+    })
+    .unwrap_or_else(|_| {
+        (
+            Err("The kmers mapping thread panicked".to_string()),
+            Ok(Ok(())),
+        )
+    });
 
     println!();
 
-    for (i, (leaf_path, kmer, hash)) in kmer_receiver.into_iter().enumerate() {
-        print!("Indexing kmer {index}\r", index = i + 1);
-        std::io::stdout().flush().unwrap();
+    spilling?;
+
+    //
+    // The mapping error is reported first
+    //
+    if let Err(err) = mapping {
+        return use_case_err(err).as_error();
+    }
+
+    match msa_reading {
+        Ok(Ok(_)) => (),
+        Ok(Err(err)) => {
+            return use_case_err(format!("The MSA could not be read: {err}"))
+                .as_error()
+        }
+        Err(_) => {
+            return use_case_err("The MSA reader thread panicked").as_error()
+        }
+    };
+
+    if !duplicated_headers.is_empty() {
+        return use_case_err(format!(
+            "The MSA contains duplicated headers: {headers}",
+            headers = duplicated_headers.join(", ")
+        ))
+        .as_error();
+    }
+
+    // ? -----------------------------------------------------------------------
+    // ? Merge the spilled partial indexes
+    // ? -----------------------------------------------------------------------
+
+    if let Some(spill) = spill {
+        info!(
+            "Merging {runs} partial indexes spilled to disk",
+            runs = spill.runs()
+        );
 
-        map.insert_or_append_kmer_hash(
-            kmer,
-            hash,
-            HashSet::from_iter(leaf_path.iter().cloned()),
+        spill.merge_into(&mut map)?;
+    }
+
+    // ? -----------------------------------------------------------------------
+    // ? Report skipped ambiguous kmers
+    // ? -----------------------------------------------------------------------
+
+    if let Some(max_expansions) = options.max_ambiguity_expansions {
+        let skipped = skipped_ambiguous_kmers.into_inner();
+
+        let message = format!(
+            "{skipped} ambiguous kmers skipped with more than \
+            {max_expansions} ACGT expansions"
         );
+
+        if skipped > 0 {
+            warn!("{message}");
+        } else {
+            info!("{message}");
+        }
     }
 
-    println!();
+    // ? -----------------------------------------------------------------------
+    // ? Report hash collisions
+    // ? -----------------------------------------------------------------------
+
+    if options.detect_collisions {
+        if let Some(collisions) = map.get_hash_collisions() {
+            let message = format!(
+                "{colliding} of {distinct} distinct kmers share a 64-bit hash \
+                with another kmer ({hashes} hashes, rate {rate:.2e})",
+                colliding = collisions.colliding_kmers,
+                distinct = collisions.distinct_kmers,
+                hashes = collisions.colliding_hashes,
+                rate = collisions.rate(),
+            );
+
+            if collisions.colliding_hashes > 0 {
+                warn!("{message}");
+            } else {
+                info!("{message}");
+            }
+        }
+
+        //
+        // Strings were kept only to detect collisions
+        //
+        if !options.retain_kmer_strings {
+            map = map.with_retained_kmer_strings(false);
+        }
+    }
 
     // ? -----------------------------------------------------------------------
     // ? Return a positive response
@@ -182,16 +549,35 @@ pub fn map_kmers_to_tree(
 
 #[cfg(test)]
 mod tests {
-    use crate::use_cases::map_kmers_to_tree;
+    use crate::{
+        domain::dtos::{
+            build_options::BuildOptions, file_or_stdin::FileOrStdin,
+            kmers_map::KmersMap, msa_window::MsaWindow, tree::Tree,
+        },
+        use_cases::map_kmers_to_tree,
+    };
     use mycelium_base::utils::errors::MappedErrors;
-    use std::path::PathBuf;
+    use std::{
+        collections::{HashMap, HashSet},
+        path::PathBuf,
+        sync::atomic::{AtomicBool, Ordering},
+        thread,
+        time::Duration,
+    };
+
+    /// Set in the child process building with a single reader thread
+    const SINGLE_READER_CHILD: &str = "CLS_SINGLE_READER_CHILD";
 
     #[test]
     fn test_map_kmers_to_tree() -> Result<(), MappedErrors> {
         let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
         let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
 
-        let tree = map_kmers_to_tree(tree_path, msa_path, None, None, None)?;
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )?;
 
         let content = match serde_yaml::to_string(&tree) {
             Err(err) => panic!("Error: {err}"),
@@ -206,4 +592,648 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_map_kmers_to_two_leaves_tree() -> Result<(), MappedErrors> {
+        let tree_path = PathBuf::from("/tmp/cls-two-leaves.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-two-leaves.fasta");
+
+        std::fs::write(&tree_path, "(Seq_a:0.1,Seq_b:0.2);").unwrap();
+        std::fs::write(
+            &msa_path,
+            ">Seq_a\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCATCGAT\n\
+            >Seq_b\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCTTTTTT\n",
+        )
+        .unwrap();
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )?;
+
+        assert_eq!(tree.root.children.map(|i| i.len()), Some(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_kmers_to_single_child_root_tree() {
+        let tree_path = PathBuf::from("/tmp/cls-single-child-root.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-single-child-root.fasta");
+
+        std::fs::write(&tree_path, "((Seq_a:0.1,Seq_b:0.2):0.1);").unwrap();
+        std::fs::write(
+            &msa_path,
+            ">Seq_a\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCATCGAT\n\
+            >Seq_b\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCTTTTTT\n",
+        )
+        .unwrap();
+
+        let err = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("root should contain at least two children"));
+    }
+
+    /// Write a star tree and an MSA of pseudo-random records
+    fn write_many_records(records: usize) -> (PathBuf, PathBuf) {
+        let tree_path =
+            PathBuf::from(format!("/tmp/cls-{records}-records.nwk"));
+        let msa_path =
+            PathBuf::from(format!("/tmp/cls-{records}-records.fasta"));
+
+        let leaves = (0..records)
+            .map(|i| format!("Seq_{i}:0.1"))
+            .collect::<Vec<String>>()
+            .join(",");
+
+        std::fs::write(&tree_path, format!("({leaves});")).unwrap();
+
+        //
+        // Pseudo-random sequences, then records share only a few kmers
+        //
+        let mut state = 42u64;
+        let mut msa = String::new();
+
+        for i in 0..records {
+            let sequence = (0..80)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+
+                    b"ACGT"[(state >> 62) as usize] as char
+                })
+                .collect::<String>();
+
+            msa.push_str(&format!(">Seq_{i}\n{sequence}\n"));
+        }
+
+        std::fs::write(&msa_path, msa).unwrap();
+
+        (tree_path, msa_path)
+    }
+
+    /// Count the threads of the current process
+    fn count_threads() -> Option<usize> {
+        std::fs::read_to_string("/proc/self/status")
+            .ok()?
+            .lines()
+            .find(|line| line.starts_with("Threads:"))?
+            .split_whitespace()
+            .nth(1)?
+            .parse::<usize>()
+            .ok()
+    }
+
+    /// Build a database sampling the maximum number of process threads
+    fn build_sampling_threads(
+        tree_path: PathBuf,
+        msa_path: PathBuf,
+    ) -> (Result<Tree, MappedErrors>, usize) {
+        let done = AtomicBool::new(false);
+
+        thread::scope(|scope| {
+            let sampler = scope.spawn(|| {
+                let mut max_threads = 0;
+
+                while !done.load(Ordering::SeqCst) {
+                    max_threads = max_threads.max(count_threads().unwrap_or(0));
+                    thread::sleep(Duration::from_millis(1));
+                }
+
+                max_threads
+            });
+
+            let tree = map_kmers_to_tree(
+                tree_path,
+                FileOrStdin::from_file(msa_path.to_str().unwrap()),
+                &BuildOptions::default(),
+            );
+
+            done.store(true, Ordering::SeqCst);
+            (tree, sampler.join().unwrap())
+        })
+    }
+
+    #[test]
+    fn test_map_kmers_to_tree_with_bounded_threads() -> Result<(), MappedErrors>
+    {
+        let records = 2_000;
+        let (tree_path, msa_path) = write_many_records(records);
+
+        //
+        // Threads are sampled during the build. Other tests may run at the
+        // same time, then the ceiling is loose, but far below one thread per
+        // record.
+        //
+        let (tree, max_threads) = build_sampling_threads(tree_path, msa_path);
+        let tree = tree?;
+
+        assert_eq!(tree.root.children.map(|i| i.len()), Some(records));
+        assert!(!tree.kmers_map.unwrap().get_map().is_empty());
+        assert!(
+            max_threads < rayon::current_num_threads() + 64,
+            "{max_threads} threads running during the build"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_kmers_to_tree_with_a_single_reader_thread() {
+        //
+        // Threads of concurrent tests would be counted as well, then the build
+        // is repeated alone in a child process of the test binary
+        //
+        if std::env::var_os(SINGLE_READER_CHILD).is_none() {
+            let test_binary = std::env::current_exe().unwrap();
+
+            let output = std::process::Command::new(test_binary)
+                .args([
+                    "test_map_kmers_to_tree_with_a_single_reader_thread",
+                    "--nocapture",
+                    "--test-threads=1",
+                ])
+                .env(SINGLE_READER_CHILD, "1")
+                .output()
+                .unwrap();
+
+            let stdout = String::from_utf8_lossy(&output.stdout);
+
+            assert!(
+                output.status.success() && stdout.contains(" 1 passed"),
+                "{stdout}"
+            );
+
+            return;
+        }
+
+        //
+        // The mapping pool is started before the baseline, then besides the
+        // sampler the build may only add the MSA reader thread and the thread
+        // feeding the pool, whatever the number of records
+        //
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build_global()
+            .unwrap();
+
+        let baseline = match count_threads() {
+            Some(threads) => threads,
+            None => return,
+        };
+
+        let (tree_path, msa_path) = write_many_records(4_000);
+        let (tree, max_threads) = build_sampling_threads(tree_path, msa_path);
+
+        assert!(tree.is_ok());
+        assert!(
+            max_threads <= baseline + 3,
+            "{max_threads} threads running during the build ({baseline} before)"
+        );
+    }
+
+    #[test]
+    fn test_map_kmers_to_tree_spilling_partial_indexes(
+    ) -> Result<(), MappedErrors> {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = "src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta";
+        let spill_directory = PathBuf::from("/tmp/cls-build-spill");
+
+        let _ = std::fs::remove_dir_all(&spill_directory);
+
+        let buckets = |tree: Tree| {
+            tree.kmers_map
+                .unwrap()
+                .get_map()
+                .iter()
+                .map(|(key, value)| (key.0, value.0.to_owned()))
+                .collect::<HashMap<u64, HashMap<u64, HashSet<u64>>>>()
+        };
+
+        let resident = map_kmers_to_tree(
+            tree_path.to_owned(),
+            FileOrStdin::from_file(msa_path),
+            &BuildOptions::default(),
+        )?;
+
+        //
+        // The partial index is spilled multiple times, then the merged index
+        // should match the one built in memory
+        //
+        let spilled = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path),
+            &BuildOptions::default()
+                .with_max_resident_kmers(Some(1_000))
+                .with_spill_directory(Some(spill_directory.to_owned())),
+        )?;
+
+        assert_eq!(buckets(spilled), buckets(resident));
+
+        //
+        // Spill files are removed after the merge
+        //
+        assert_eq!(std::fs::read_dir(&spill_directory).unwrap().count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_kmers_to_tree_detecting_collisions() -> Result<(), MappedErrors>
+    {
+        let tree_path = PathBuf::from("/tmp/cls-collisions.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-collisions.fasta");
+
+        std::fs::write(&tree_path, "(Seq_a:0.1,Seq_b:0.2);").unwrap();
+        std::fs::write(
+            &msa_path,
+            ">Seq_a\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCATCGAT\n\
+            >Seq_b\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCTTTTTT\n",
+        )
+        .unwrap();
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default().with_detect_collisions(true),
+        )?;
+
+        //
+        // Strings used to detect collisions are not stored unless requested
+        //
+        assert!(!tree.kmers_map.unwrap().retains_kmer_strings());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_kmers_to_tree_with_crlf_msa() -> Result<(), MappedErrors> {
+        let tree_path = PathBuf::from("/tmp/cls-crlf.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-crlf.fasta");
+
+        std::fs::write(&tree_path, "(Seq_a:0.1,Seq_b:0.2);").unwrap();
+
+        //
+        // Windows line endings and multi-line sequences. Headers should match
+        // the tree leaves without the trailing carriage return.
+        //
+        std::fs::write(
+            &msa_path,
+            ">Seq_a \r\nACGTACGTTTGACCATGACGATCG\r\nATCGGGCTAGCTAGCATCGAT\r\n\
+            \r\n\
+            >Seq_b\r\nACGTACGTTTGACCATGACGATCG\r\nATCGGGCTAGCTAGCTTTTTT\r\n",
+        )
+        .unwrap();
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )?;
+
+        assert_eq!(tree.root.children.map(|i| i.len()), Some(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_kmers_to_tree_with_duplicated_msa_headers() {
+        let tree_path = PathBuf::from("/tmp/cls-duplicated-headers.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-duplicated-headers.fasta");
+
+        std::fs::write(&tree_path, "(Seq_a:0.1,Seq_b:0.2);").unwrap();
+        std::fs::write(
+            &msa_path,
+            ">Seq_a\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCATCGAT\n\
+            >Seq_b\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCTTTTTT\n\
+            >Seq_a\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCAAAAAA\n",
+        )
+        .unwrap();
+
+        let err = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("duplicated headers: Seq_a"));
+    }
+
+    #[test]
+    fn test_map_kmers_to_tree_excluding_soft_masked_kmers(
+    ) -> Result<(), MappedErrors> {
+        let tree_path = PathBuf::from("/tmp/cls-soft-masked.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-soft-masked.fasta");
+
+        std::fs::write(&tree_path, "(Seq_a:0.1,Seq_b:0.2);").unwrap();
+
+        //
+        // The repeat is soft-masked in the middle of both sequences
+        //
+        let repeat = "atatatatatgcgcgcgcgc";
+        let left = "ACGTACGTTTGACCATGACG";
+        let right_a = "ATCGGGCTAGCTAGCATCGA";
+        let right_b = "ATCGGGCTAGCTAGCTTTTT";
+
+        std::fs::write(
+            &msa_path,
+            format!(
+                ">Seq_a\n{left}{repeat}{right_a}\n\
+                >Seq_b\n{left}{repeat}{right_b}\n"
+            ),
+        )
+        .unwrap();
+
+        let options = BuildOptions::default().with_k_size(Some(10));
+
+        let build = |options: &BuildOptions| {
+            map_kmers_to_tree(
+                tree_path.to_owned(),
+                FileOrStdin::from_file(msa_path.to_str().unwrap()),
+                options,
+            )
+            .map(|tree| tree.kmers_map.unwrap())
+        };
+
+        let masked = build(&options.to_owned().with_exclude_soft_masked(true))?;
+        let unmasked = build(&options)?;
+
+        assert!(masked.get_soft_masked_exclusion());
+        assert!(!unmasked.get_soft_masked_exclusion());
+
+        //
+        // Kmers inside or overlapping the repeat are only indexed without the
+        // soft-masked exclusion
+        //
+        let repeat_kmer = "ATATATATAT".to_string();
+        let overlapping_kmer = "CCATGACGAT".to_string();
+        let flanking_kmer = "ACGTACGTTT".to_string();
+
+        let contains = |map: &KmersMap, kmer: &String| {
+            !map.get_overlapping_hashed_kmers(
+                map.build_kmer_from_string(kmer.to_owned(), None),
+            )
+            .get_map()
+            .is_empty()
+        };
+
+        for kmer in [&repeat_kmer, &overlapping_kmer] {
+            assert!(contains(&unmasked, kmer));
+            assert!(!contains(&masked, kmer));
+        }
+
+        assert!(contains(&masked, &flanking_kmer));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_kmers_to_tree_within_msa_window() -> Result<(), MappedErrors> {
+        let tree_path = PathBuf::from("/tmp/cls-msa-window.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-msa-window.fasta");
+
+        std::fs::write(&tree_path, "(Seq_a:0.1,Seq_b:0.2);").unwrap();
+
+        //
+        // The window covers the columns 11 to 40. The Seq_b record is gapped
+        // and split in multiple lines, then columns differ from positions.
+        //
+        let prefix = "ACGTTGCAAC";
+        let inner_a = "ATCGGGCTAGCTAGCATCGATTGACCATGA";
+        let inner_b = "ATCGGGCTAGCTAGCTTTTTTTGACCATGA";
+        let suffix = "GGCATTACGA";
+
+        std::fs::write(
+            &msa_path,
+            format!(
+                ">Seq_a\n{prefix}{inner_a}{suffix}\n\
+                >Seq_b\n---{}\n{inner_b}\n{suffix}\n",
+                &prefix[3..]
+            ),
+        )
+        .unwrap();
+
+        let tree = map_kmers_to_tree(
+            tree_path.to_owned(),
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default()
+                .with_k_size(Some(10))
+                .with_msa_window(Some(MsaWindow::new(11, 40))),
+        )?;
+
+        let map = tree.kmers_map.unwrap();
+
+        assert_eq!(map.get_msa_window(), Some(&MsaWindow::new(11, 40)));
+
+        let contains = |kmer: &str| {
+            !map.get_overlapping_hashed_kmers(
+                map.build_kmer_from_string(kmer.to_string(), None),
+            )
+            .get_map()
+            .is_empty()
+        };
+
+        //
+        // Only kmers fully inside the window are indexed
+        //
+        assert!(contains(&inner_a[..10]));
+        assert!(contains(&inner_b[10..20]));
+        assert!(!contains(prefix));
+        assert!(!contains(suffix));
+        assert!(!contains(&format!("{}{}", &prefix[5..], &inner_a[..5])));
+
+        //
+        // Windows narrower than the kmer size are rejected
+        //
+        assert!(map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default()
+                .with_k_size(Some(10))
+                .with_msa_window(Some(MsaWindow::new(11, 15))),
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_kmers_to_tree_skipping_gap_rich_columns(
+    ) -> Result<(), MappedErrors> {
+        let tree_path = PathBuf::from("/tmp/cls-gap-rich-columns.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-gap-rich-columns.fasta");
+
+        std::fs::write(&tree_path, "(Seq_a:0.1,Seq_b:0.2);").unwrap();
+
+        //
+        // The insertion of Seq_a is aligned to gaps and undetermined bases in
+        // Seq_b, then its columns contain half gaps
+        //
+        let prefix = "ACGTTGCAACGGCATTACGA";
+        let insertion = "TTTGACCATGACGAT";
+        let suffix = "ATCGGGCTAGCTAGCATCGA";
+
+        std::fs::write(
+            &msa_path,
+            format!(
+                ">Seq_a\n{prefix}{insertion}{suffix}\n\
+                >Seq_b\n{prefix}\n-------NNNNNNNN\n{suffix}\n",
+            ),
+        )
+        .unwrap();
+
+        let build = |max_gap_fraction: Option<f64>| {
+            map_kmers_to_tree(
+                tree_path.to_owned(),
+                FileOrStdin::from_file(msa_path.to_str().unwrap()),
+                &BuildOptions::default()
+                    .with_k_size(Some(10))
+                    .with_max_gap_fraction(max_gap_fraction),
+            )
+            .map(|tree| tree.kmers_map.unwrap())
+        };
+
+        let contains = |map: &KmersMap, kmer: &str| {
+            !map.get_overlapping_hashed_kmers(
+                map.build_kmer_from_string(kmer.to_string(), None),
+            )
+            .get_map()
+            .is_empty()
+        };
+
+        let unfiltered = build(None)?;
+        let filtered = build(Some(0.4))?;
+
+        assert!(contains(&unfiltered, &insertion[..10]));
+        assert!(!contains(&filtered, &insertion[..10]));
+        assert!(!contains(&filtered, &insertion[5..]));
+
+        //
+        // Columns out of the gap-rich region are kept
+        //
+        assert!(contains(&filtered, &prefix[..10]));
+        assert!(contains(&filtered, &suffix[10..]));
+
+        //
+        // Invalid fractions are rejected
+        //
+        assert!(build(Some(1.5)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_kmers_to_tree_with_aligned_kmers() -> Result<(), MappedErrors> {
+        let tree_path = PathBuf::from("/tmp/cls-aligned-kmers.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-aligned-kmers.fasta");
+
+        std::fs::write(&tree_path, "(Seq_a:0.1,Seq_b:0.2);").unwrap();
+
+        //
+        // Sibling records differing by a short indel only
+        //
+        let prefix = "ACGTTGCAACGGCATTACGA";
+        let suffix = "ATCGGGCTAGCTAGCATCGA";
+
+        std::fs::write(
+            &msa_path,
+            format!(
+                ">Seq_a\n{prefix}TGA{suffix}\n>Seq_b\n{prefix}-*-\n{suffix}\n"
+            ),
+        )
+        .unwrap();
+
+        let count_shared_kmers = |aligned_kmers: bool| {
+            let tree = map_kmers_to_tree(
+                tree_path.to_owned(),
+                FileOrStdin::from_file(msa_path.to_str().unwrap()),
+                &BuildOptions::default()
+                    .with_k_size(Some(10))
+                    .with_aligned_kmers(aligned_kmers),
+            )?;
+
+            let leaves = tree
+                .root
+                .children
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|leaf| leaf.id)
+                .collect::<Vec<u64>>();
+
+            let shared = tree
+                .kmers_map
+                .unwrap()
+                .get_map()
+                .values()
+                .flat_map(|value| value.0.values())
+                .filter(|nodes| leaves.iter().all(|leaf| nodes.contains(leaf)))
+                .count();
+
+            Ok::<usize, MappedErrors>(shared)
+        };
+
+        let ungapped = count_shared_kmers(false)?;
+        let aligned = count_shared_kmers(true)?;
+
+        //
+        // Kmers spanning the indel are shared only if the gap columns are
+        // skipped in both records
+        //
+        assert!(aligned > ungapped);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_kmers_to_tree_returns_errors_on_invalid_inputs() {
+        let tree_path = PathBuf::from("/tmp/cls-invalid-inputs.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-invalid-inputs.fasta");
+
+        std::fs::write(&tree_path, "(Seq_a:0.1,Seq_b:0.2);").unwrap();
+        std::fs::write(
+            &msa_path,
+            ">Seq_a\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCATCGAT\n\
+            >Seq_c\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCTTTTTT\n",
+        )
+        .unwrap();
+
+        let build = |tree_path: &str, msa_path: &str| {
+            map_kmers_to_tree(
+                PathBuf::from(tree_path),
+                FileOrStdin::from_file(msa_path),
+                &BuildOptions::default(),
+            )
+        };
+
+        let err =
+            build(tree_path.to_str().unwrap(), "/tmp/cls-missing-msa.fasta")
+                .unwrap_err();
+
+        assert!(err.to_string().contains("The MSA file does not exist"));
+
+        let err =
+            build("/tmp/cls-missing-tree.nwk", msa_path.to_str().unwrap())
+                .unwrap_err();
+
+        assert!(err.to_string().contains("The tree file does not exist"));
+
+        let err =
+            build(tree_path.to_str().unwrap(), msa_path.to_str().unwrap())
+                .unwrap_err();
+
+        assert!(err
+            .to_string()
+            .contains("does not match any tree leaf: Seq_c"));
+    }
 }
@@ -0,0 +1,273 @@
+use crate::domain::dtos::{clade::Clade, tree::Tree};
+
+use mycelium_base::utils::errors::{use_case_err, MappedErrors};
+use std::collections::HashSet;
+use tracing::debug;
+use uuid::Uuid;
+
+/// Merge two databases built from disjoint leaf sets
+///
+/// The trees are grafted as sister clades under a new shared root, then the
+/// merge assumes that the leaves of each database form a monophyletic group
+/// of the combined phylogeny (e.g. both trees are rooted subtrees of a shared
+/// super-tree). The relationships between leaves of distinct databases are
+/// not inferred, then placements resolve first which database a query belongs
+/// to and then proceed inside it.
+///
+/// Clade ids are renumbered to avoid collisions: the first tree ids are
+/// shifted by one and the second tree ids are shifted after the first tree
+/// ids. Annotations are renumbered accordingly.
+///
+/// # Errors
+///
+/// Merges are rejected if any database does not contain a kmers map, if kmers
+/// were built with distinct parameters (kmer and minimizer sizes, homopolymer
+/// compression or soft-masking), or if the leaf sets share any name.
+///
+#[tracing::instrument(name = "Merging Classeq databases", skip_all)]
+pub fn merge_databases(
+    first: Tree,
+    second: Tree,
+    name: Option<String>,
+) -> Result<Tree, MappedErrors> {
+    // ? -----------------------------------------------------------------------
+    // ? Validate the databases compatibility
+    // ? -----------------------------------------------------------------------
+
+    let (first_map, second_map) = match (first.kmers_map, second.kmers_map) {
+        (Some(first_map), Some(second_map)) => (first_map, second_map),
+        _ => {
+            return use_case_err("Both databases should contain a kmers map")
+                .as_error()
+        }
+    };
+
+    if !first_map.is_compatible_with(&second_map) {
+        return use_case_err(
+            "Databases were built with distinct kmer parameters (kmer size, \
+            minimizer size, homopolymer compression or soft-masking)",
+        )
+        .as_error();
+    }
+
+    let leaf_names = |root: &Clade| {
+        root.get_leaves_with_paths(None)
+            .into_iter()
+            .filter_map(|(leaf, _)| leaf.name)
+            .collect::<HashSet<String>>()
+    };
+
+    let mut shared_leaves = leaf_names(&first.root)
+        .intersection(&leaf_names(&second.root))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if !shared_leaves.is_empty() {
+        shared_leaves.sort();
+
+        return use_case_err(format!(
+            "Databases should be built from disjoint leaf sets, but {count} \
+            leaves are shared: {leaves}",
+            count = shared_leaves.len(),
+            leaves = shared_leaves.join(", ")
+        ))
+        .as_error();
+    }
+
+    // ? -----------------------------------------------------------------------
+    // ? Renumber and graft the trees
+    // ? -----------------------------------------------------------------------
+
+    let first_offset = 1;
+    let second_offset = first.root.max_id() + first_offset + 1;
+
+    debug!(
+        "Renumbering clades with offsets {first_offset} and {second_offset}"
+    );
+
+    let mut first_root = first.root;
+    let mut second_root = second.root;
+
+    first_root.renumber(first_offset);
+    second_root.renumber(second_offset);
+
+    let root = Clade::graft(vec![first_root, second_root]);
+
+    let kmers_map = first_map
+        .offset_nodes(first_offset)
+        .merge(second_map.offset_nodes(second_offset), root.id);
+
+    let annotations = [
+        (first.annotations, first_offset),
+        (second.annotations, second_offset),
+    ]
+    .into_iter()
+    .filter_map(|(annotations, offset)| annotations.map(|i| (i, offset)))
+    .flat_map(|(annotations, offset)| {
+        annotations.into_iter().map(move |mut annotation| {
+            annotation.clade += offset as u32;
+            annotation
+        })
+    })
+    .collect::<Vec<_>>();
+
+    // ? -----------------------------------------------------------------------
+    // ? Build the merged tree
+    // ? -----------------------------------------------------------------------
+
+    //
+    // The lower support threshold is kept, since branches of both trees were
+    // sanitized with their own thresholds
+    //
+    let mut tree = Tree::new(
+        Uuid::new_v4(),
+        name.unwrap_or(format!("{}+{}", first.name, second.name)),
+        first.min_branch_support.min(second.min_branch_support),
+        root,
+    );
+
+    if !annotations.is_empty() {
+        tree.annotations = Some(annotations);
+    }
+
+    tree.kmers_map = Some(kmers_map);
+    tree.update_in_memory_size();
+
+    Ok(tree)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::dtos::{
+            build_options::BuildOptions, file_or_stdin::FileOrStdin,
+            placement_options::PlacementOptions,
+            placement_response::PlacementStatus,
+        },
+        use_cases::{map_kmers_to_tree, place_one},
+    };
+    use std::path::PathBuf;
+
+    /// Build a pseudo-random sequence from a seed
+    fn random_sequence(seed: u64, length: usize) -> String {
+        let mut state = seed;
+
+        (0..length)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                ['A', 'C', 'G', 'T'][(state % 4) as usize]
+            })
+            .collect()
+    }
+
+    fn build_two_leaves_tree(
+        prefix: &str,
+        names: [&str; 2],
+        sequences: [&str; 2],
+    ) -> Tree {
+        let tree_path = PathBuf::from(format!("/tmp/cls-merge-{prefix}.nwk"));
+        let msa_path = PathBuf::from(format!("/tmp/cls-merge-{prefix}.fasta"));
+
+        std::fs::write(
+            &tree_path,
+            format!("({}:0.1,{}:0.2);", names[0], names[1]),
+        )
+        .unwrap();
+
+        std::fs::write(
+            &msa_path,
+            format!(
+                ">{}\n{}\n>{}\n{}\n",
+                names[0], sequences[0], names[1], sequences[1]
+            ),
+        )
+        .unwrap();
+
+        map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_merge_two_leaves_databases() {
+        let sequences = (1..=4)
+            .map(|seed| random_sequence(seed * 7919, 300))
+            .collect::<Vec<_>>();
+
+        let first = build_two_leaves_tree(
+            "first",
+            ["Seq_a", "Seq_b"],
+            [&sequences[0], &sequences[1]],
+        );
+
+        let second = build_two_leaves_tree(
+            "second",
+            ["Seq_c", "Seq_d"],
+            [&sequences[2], &sequences[3]],
+        );
+
+        let tree = merge_databases(first, second, None).unwrap();
+
+        assert_eq!(tree.root.count_leaves(), 4);
+        assert_eq!(tree.root.children.as_ref().map(|i| i.len()), Some(2));
+
+        //
+        // Clade ids should be unique after the renumbering
+        //
+        let leaves = tree.root.get_leaves_with_paths(None);
+        let ids = leaves
+            .iter()
+            .flat_map(|(_, path)| path.iter().cloned())
+            .collect::<HashSet<_>>();
+
+        assert_eq!(ids.len(), 7);
+
+        //
+        // A query from the second database is placed on its leaf
+        //
+        let response = place_one(
+            "query",
+            &sequences[2],
+            &tree,
+            &PlacementOptions::default(),
+            &None,
+        )
+        .unwrap();
+
+        let placed_id = match response.placement() {
+            Some(PlacementStatus::IdentityFound(test)) => test.clade_id(),
+            Some(PlacementStatus::MaxResolutionReached(id, _)) => *id,
+            other => panic!("Unexpected placement: {other:?}"),
+        };
+
+        assert_eq!(
+            tree.root
+                .get_node_by_id(placed_id)
+                .and_then(|i| i.name.to_owned()),
+            Some("Seq_c".to_string())
+        );
+
+        //
+        // Databases sharing leaves are rejected
+        //
+        let first = build_two_leaves_tree(
+            "shared-first",
+            ["Seq_a", "Seq_b"],
+            [&sequences[0], &sequences[1]],
+        );
+
+        let second = build_two_leaves_tree(
+            "shared-second",
+            ["Seq_a", "Seq_d"],
+            [&sequences[2], &sequences[3]],
+        );
+
+        assert!(merge_databases(first, second, None).is_err());
+    }
+}
@@ -2,12 +2,27 @@
 /// fasta file to a phylogenetic tree.
 mod build_database;
 
+/// This module contains the use case to merge databases built from disjoint
+/// leaf sets.
+mod merge_databases;
+
+/// This module contains the use case to place the sequences of several genes
+/// of a specimen and vote a consensus lineage.
+mod place_multigene;
+
 /// This module contains the use case to place sequences on a model generated
 /// from a phylogenetic tree.
 mod place_sequences;
 
+/// This module contains the use case to add new reference sequences to an
+/// existing database.
+mod update_database;
+
 /// Elements of shared module are restricted to be used only in this crate.
 mod shared;
 
 pub use build_database::*;
+pub use merge_databases::*;
+pub use place_multigene::*;
 pub use place_sequences::*;
+pub use update_database::*;
@@ -0,0 +1,271 @@
+use crate::{
+    domain::dtos::{
+        multigene_response::{GenePlacement, MultigeneResponse},
+        output_field::OutputField,
+        placement_options::PlacementOptions,
+        tree::Tree,
+    },
+    use_cases::place_one,
+};
+
+use mycelium_base::utils::errors::{use_case_err, MappedErrors};
+use std::collections::{HashMap, HashSet};
+use tracing::debug;
+
+/// The sequence of a single gene of the specimen and its reference database
+pub struct GeneQuery<'a> {
+    pub gene: String,
+    pub tree: &'a Tree,
+    pub sequence: String,
+}
+
+/// Place the sequences of several genes of a specimen and vote a consensus
+///
+/// Each gene sequence is placed on its own database, then the resulting
+/// lineages are compared name by name from the root. A lineage name is
+/// accepted if it is shared by a strict majority of the genes with a lineage,
+/// and the voting stops at the first level without a majority. Genes without
+/// a lineage (e.g. unclassifiable queries or databases without annotations)
+/// do not vote.
+///
+/// Lineages are always built, even if not selected as an output field, and
+/// split by the lineage delimiter of the placement options.
+///
+/// # Errors
+///
+/// Fails if no gene is given, if gene names are repeated, or if any single
+/// gene placement fails.
+///
+#[tracing::instrument(name = "Placing multi-gene specimen", skip_all)]
+pub fn place_multigene(
+    specimen: &str,
+    queries: &[GeneQuery<'_>],
+    opts: PlacementOptions,
+) -> Result<MultigeneResponse, MappedErrors> {
+    if queries.is_empty() {
+        return use_case_err("At least one gene query should be provided")
+            .as_error();
+    }
+
+    let mut genes = HashSet::<&str>::new();
+
+    for query in queries {
+        if !genes.insert(query.gene.as_str()) {
+            return use_case_err(format!(
+                "Gene {gene} is repeated for specimen {specimen}",
+                gene = query.gene
+            ))
+            .as_error();
+        }
+    }
+
+    let mut opts = opts;
+
+    if let Some(fields) = opts.output_fields.as_mut() {
+        if !fields.contains(&OutputField::Lineage) {
+            fields.push(OutputField::Lineage);
+        }
+    }
+
+    // ? -----------------------------------------------------------------------
+    // ? Place each gene on its own database
+    // ? -----------------------------------------------------------------------
+
+    let mut responses = Vec::with_capacity(queries.len());
+
+    for query in queries {
+        let response =
+            place_one(specimen, &query.sequence, query.tree, &opts, &None)?;
+
+        debug!(
+            "Gene {gene} of {specimen} placed as {code}",
+            gene = query.gene,
+            code = response.code()
+        );
+
+        responses.push(response);
+    }
+
+    // ? -----------------------------------------------------------------------
+    // ? Vote the consensus lineage
+    // ? -----------------------------------------------------------------------
+
+    let lineages = responses
+        .iter()
+        .map(|response| {
+            response.lineage().map(|lineage| {
+                lineage
+                    .split(opts.lineage_delimiter.as_str())
+                    .collect::<Vec<&str>>()
+            })
+        })
+        .collect::<Vec<Option<Vec<&str>>>>();
+
+    let consensus = vote_consensus(&lineages);
+
+    let genes = queries
+        .iter()
+        .zip(responses.iter())
+        .zip(lineages.iter())
+        .map(|((query, response), lineage)| {
+            GenePlacement::new(
+                query.gene.to_owned(),
+                lineage.as_ref().is_some_and(|lineage| {
+                    lineage.starts_with(consensus.as_slice())
+                }),
+                response.to_owned(),
+            )
+        })
+        .collect();
+
+    Ok(MultigeneResponse::new(
+        specimen.to_string(),
+        match consensus.is_empty() {
+            true => None,
+            false => Some(consensus.join(opts.lineage_delimiter.as_str())),
+        },
+        consensus.len(),
+        genes,
+    ))
+}
+
+/// Get the longest lineage prefix supported by a strict majority of voters
+fn vote_consensus<'a>(lineages: &[Option<Vec<&'a str>>]) -> Vec<&'a str> {
+    let voters = lineages.iter().flatten().collect::<Vec<_>>();
+    let mut consensus = Vec::<&str>::new();
+
+    loop {
+        let level = consensus.len();
+        let mut votes = HashMap::<&str, usize>::new();
+
+        for lineage in voters.iter() {
+            if lineage.starts_with(consensus.as_slice()) {
+                if let Some(name) = lineage.get(level) {
+                    *votes.entry(*name).or_default() += 1;
+                }
+            }
+        }
+
+        match votes
+            .into_iter()
+            .find(|(_, count)| count * 2 > voters.len())
+        {
+            Some((name, _)) => consensus.push(name),
+            None => return consensus,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::dtos::{
+            annotation::{Annotation, Tag},
+            build_options::BuildOptions,
+            file_or_stdin::FileOrStdin,
+        },
+        use_cases::{
+            map_kmers_to_tree, place_sequences::clade_from_placement_status,
+        },
+    };
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_vote_consensus() {
+        let lineages = vec![
+            Some(vec!["Colletotrichum", "C. acutatum"]),
+            Some(vec!["Colletotrichum", "C. fioriniae"]),
+            Some(vec!["Colletotrichum", "C. acutatum", "strain"]),
+            None,
+        ];
+
+        //
+        // Genes without a lineage do not vote, while ties stop the consensus
+        //
+        assert_eq!(
+            vote_consensus(&lineages),
+            vec!["Colletotrichum", "C. acutatum"]
+        );
+        assert_eq!(vote_consensus(&lineages[..2]), vec!["Colletotrichum"]);
+        assert!(vote_consensus(&[None]).is_empty());
+    }
+
+    #[test]
+    fn test_place_multigene_votes_the_genus_consensus() {
+        let header = "Col_orchidophilum";
+        let sequence = "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA";
+
+        //
+        // Both databases share the genus annotation at the root, but the
+        // placed clade is annotated with distinct species
+        //
+        let annotated_tree = |species: &str| {
+            let mut tree = map_kmers_to_tree(
+                PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk"),
+                FileOrStdin::from_file("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta"),
+                &BuildOptions::default(),
+            )
+            .unwrap();
+
+            let placed = clade_from_placement_status(
+                place_one(
+                    header,
+                    sequence,
+                    &tree,
+                    &PlacementOptions::default(),
+                    &None,
+                )
+                .unwrap()
+                .placement(),
+            )
+            .unwrap();
+
+            let annotation = |clade: u64, name: &str| Annotation {
+                clade: clade as u32,
+                meta: Some(vec![Tag::SciName(name.to_string())]),
+                source: None,
+                version: None,
+            };
+
+            tree.annotations = Some(vec![
+                annotation(tree.root.id, "Colletotrichum"),
+                annotation(placed, species),
+            ]);
+
+            tree
+        };
+
+        let first = annotated_tree("C. orchidophilum");
+        let second = annotated_tree("C. acutatum");
+
+        let query = |gene: &str, tree| GeneQuery {
+            gene: gene.to_string(),
+            tree,
+            sequence: sequence.to_string(),
+        };
+
+        let response = place_multigene(
+            header,
+            &[query("gapdh", &first), query("tub2", &second)],
+            PlacementOptions::default()
+                .with_output_fields(Some(vec![OutputField::Depth])),
+        )
+        .unwrap();
+
+        assert_eq!(response.consensus(), Some("Colletotrichum"));
+        assert_eq!(response.consensus_depth(), 1);
+        assert!(response.genes().iter().all(|gene| gene.agrees()));
+        assert_eq!(
+            response.genes()[0].response().lineage(),
+            Some("Colletotrichum;C. orchidophilum")
+        );
+
+        assert!(place_multigene(
+            header,
+            &[query("gapdh", &first), query("gapdh", &second)],
+            PlacementOptions::default(),
+        )
+        .is_err());
+    }
+}
@@ -1,6 +1,91 @@
-use crate::domain::dtos::{clade::Clade, placement_response::PlacementStatus};
+use crate::domain::dtos::{
+    adherence_test::AdherenceTest, clade::Clade,
+    placement_response::PlacementStatus,
+};
 
-pub(super) enum IntrospectionUpdateResponse {
-    Continue(Clade, Vec<Clade>),
+pub(super) enum IntrospectionUpdateResponse<'a> {
+    Continue(&'a Clade, Vec<&'a Clade>),
     Return(PlacementStatus),
 }
+
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct PlacementOutcome {
+    pub(super) status: PlacementStatus,
+
+    /// The minimum `one - rest` margin of the decisions taken along the
+    /// accepted path. It is `None` if no decision was taken.
+    pub(super) weakest_margin: Option<i32>,
+
+    /// The number of tree levels introspected before the placement. It is
+    /// zero if the search loop was not started.
+    pub(super) depth: usize,
+
+    /// The one-vs-rest tests of all candidate clades of the last introspected
+    /// level. Only collected if requested by the placement options.
+    pub(super) evidence: Option<Vec<AdherenceTest>>,
+
+    /// The tests of all introspected levels. Only collected if the placement
+    /// should be explained.
+    pub(super) trace: Option<PlacementTrace>,
+}
+
+/// The decisions taken along the tree introspection of a query
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct PlacementTrace {
+    /// The number of query kmers overlapping the database kmers
+    pub(super) overlapping_kmers: usize,
+
+    pub(super) steps: Vec<IntrospectionStep>,
+}
+
+/// The one-vs-rest tests of a single introspection level
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct IntrospectionStep {
+    /// The clade whose children were tested
+    pub(super) parent: u64,
+
+    /// The tests of the candidate children, sorted by decreasing matches
+    pub(super) tests: Vec<AdherenceTest>,
+}
+
+impl PlacementOutcome {
+    pub(super) fn new(
+        status: PlacementStatus,
+        weakest_margin: Option<i32>,
+        depth: usize,
+    ) -> Self {
+        PlacementOutcome {
+            status,
+            weakest_margin,
+            depth,
+            evidence: None,
+            trace: None,
+        }
+    }
+
+    pub(super) fn with_evidence(
+        mut self,
+        evidence: Option<Vec<AdherenceTest>>,
+    ) -> Self {
+        self.evidence = evidence;
+        self
+    }
+
+    pub(super) fn with_trace(mut self, trace: Option<PlacementTrace>) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// The score ranking alternative placements of the same query
+    ///
+    /// Classified placements rank above unclassifiable ones, then deeper
+    /// placements rank first, and placements taking stronger decisions break
+    /// the ties.
+    pub(super) fn score(&self) -> (bool, usize, i32) {
+        (
+            !matches!(self.status, PlacementStatus::Unclassifiable(_)),
+            self.depth,
+            self.weakest_margin.unwrap_or(i32::MIN),
+        )
+    }
+}
@@ -0,0 +1,232 @@
+use crate::domain::dtos::{
+    annotation::{Annotation, Tag},
+    tree::Tree,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        RwLock,
+    },
+};
+
+/// Tree annotations indexed by clade
+///
+/// Annotations are grouped by clade once, and the paths from placed clades to
+/// the root are lazily cached. Then, gathering the annotations of a placement
+/// costs the path length, instead of a tree walk followed by a scan of all
+/// annotations.
+pub(super) struct AnnotationIndex<'a> {
+    tree: &'a Tree,
+    annotations: HashMap<u64, Vec<Annotation>>,
+    paths: RwLock<HashMap<u64, Vec<u64>>>,
+    computed_paths: AtomicUsize,
+}
+
+impl<'a> AnnotationIndex<'a> {
+    pub(super) fn new(tree: &'a Tree) -> Self {
+        let mut annotations = HashMap::<u64, Vec<Annotation>>::new();
+
+        for annotation in tree.annotations.iter().flatten() {
+            annotations
+                .entry(annotation.clade as u64)
+                .or_default()
+                .push(annotation.to_owned());
+        }
+
+        AnnotationIndex {
+            tree,
+            annotations,
+            paths: RwLock::new(HashMap::new()),
+            computed_paths: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get the number of paths to root computed since the index creation
+    #[cfg(test)]
+    pub(super) fn computed_paths_count(&self) -> usize {
+        self.computed_paths.load(Ordering::SeqCst)
+    }
+
+    /// Get the annotations of a clade and its ancestors sorted by clade
+    pub(super) fn get_clade_annotations(
+        &self,
+        clade: u64,
+    ) -> Option<Vec<Annotation>> {
+        let cached = match self.paths.read() {
+            Ok(paths) => paths.get(&clade).cloned(),
+            Err(_) => None,
+        };
+
+        let path = match cached {
+            Some(path) => path,
+            None => {
+                let path = match self.tree.root.get_node_by_id(clade) {
+                    Some(node) => node
+                        .get_path_to_root(&self.tree.root)
+                        .into_iter()
+                        .collect::<Vec<_>>(),
+                    None => vec![],
+                };
+
+                self.computed_paths.fetch_add(1, Ordering::SeqCst);
+
+                if let Ok(mut paths) = self.paths.write() {
+                    paths.insert(clade, path.to_owned());
+                }
+
+                path
+            }
+        };
+
+        let mut records = path
+            .iter()
+            .filter_map(|id| self.annotations.get(id))
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if records.is_empty() {
+            return None;
+        }
+
+        records.sort_by_key(|record| record.clade);
+
+        Some(records)
+    }
+
+    /// Check if the clade itself is annotated with the given rank
+    ///
+    /// Ranks are compared case-insensitively. Ancestors annotations are not
+    /// considered.
+    pub(super) fn has_rank(&self, clade: u64, rank: &str) -> bool {
+        self.annotations
+            .get(&clade)
+            .into_iter()
+            .flatten()
+            .filter_map(|annotation| annotation.meta.as_ref())
+            .flatten()
+            .any(|tag| match tag {
+                Tag::Rank(value) => value.eq_ignore_ascii_case(rank),
+                _ => false,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        clade_from_placement_status::clade_from_placement_status,
+        place_one::{place_one, place_one_with_index},
+        placement_metrics::PlacementMetrics,
+    };
+    use super::*;
+    use crate::{
+        domain::dtos::{
+            build_options::BuildOptions, file_or_stdin::FileOrStdin,
+            placement_options::PlacementOptions,
+        },
+        use_cases::map_kmers_to_tree,
+    };
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_annotation_index_caches_paths_to_root() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let mut tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let header = "Col_orchidophilum";
+        let sequence = "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA";
+
+        let placed_clade = clade_from_placement_status(
+            place_one(
+                header,
+                sequence,
+                &tree,
+                &PlacementOptions::default(),
+                &None,
+            )
+            .unwrap()
+            .placement(),
+        )
+        .unwrap();
+
+        //
+        // Annotate the placed clade and the root, and add many annotations
+        // outside the placement path.
+        //
+        let mut annotations = vec![
+            Annotation {
+                clade: placed_clade as u32,
+                meta: None,
+                source: None,
+                version: None,
+            },
+            Annotation {
+                clade: tree.root.id as u32,
+                meta: None,
+                source: None,
+                version: None,
+            },
+        ];
+
+        annotations.extend((0..10_000).map(|_| Annotation {
+            clade: u32::MAX,
+            meta: None,
+            source: None,
+            version: None,
+        }));
+
+        tree.annotations = Some(annotations);
+
+        let expected = place_one(
+            header,
+            sequence,
+            &tree,
+            &PlacementOptions::default(),
+            &None,
+        )
+        .unwrap();
+
+        let index = AnnotationIndex::new(&tree);
+        let rounds = 20;
+        let time = std::time::Instant::now();
+
+        for _ in 0..rounds {
+            let response = place_one_with_index(
+                header,
+                sequence,
+                &tree,
+                &PlacementOptions::default(),
+                &index,
+                &PlacementMetrics::from_global(),
+                &None,
+            )
+            .unwrap();
+
+            assert_eq!(response, expected);
+        }
+
+        println!(
+            "Average placement time with indexed annotations: {:?}",
+            time.elapsed() / rounds as u32
+        );
+
+        //
+        // The path to root of the placed clade is computed only once
+        //
+        assert_eq!(index.computed_paths_count(), 1);
+        assert_eq!(
+            index.get_clade_annotations(placed_clade).map(|i| i.len()),
+            Some(2)
+        );
+    }
+}
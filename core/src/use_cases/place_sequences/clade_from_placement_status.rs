@@ -2,7 +2,7 @@ use crate::domain::dtos::placement_response::PlacementStatus::{self, *};
 
 use mycelium_base::dtos::UntaggedParent;
 
-pub(super) fn clade_from_placement_status(
+pub(crate) fn clade_from_placement_status(
     placement: Option<&PlacementStatus>,
 ) -> Option<u64> {
     match placement {
@@ -0,0 +1,135 @@
+use crate::domain::dtos::tree::Tree;
+
+use std::collections::HashSet;
+
+/// Get the query kmers discriminating a clade from its siblings
+///
+/// Discriminating kmers are query kmers indexed in the clade and absent from
+/// the sibling clades. Kmers shared by more nodes of the clade are reported
+/// first, since they better represent the clade as a whole. Returns `None` if
+/// the database does not retain kmer strings.
+pub(super) fn get_discriminating_kmers(
+    sequence: &str,
+    tree: &Tree,
+    clade: u64,
+    limit: usize,
+) -> Option<Vec<String>> {
+    let kmers_map = tree.kmers_map.as_ref()?;
+
+    if !kmers_map.retains_kmer_strings() {
+        return None;
+    }
+
+    let siblings = tree
+        .root
+        .get_node_by_id(clade)
+        .and_then(|node| node.parent)
+        .and_then(|parent| tree.root.get_node_by_id(parent))
+        .and_then(|parent| parent.children.as_ref())
+        .map(|children| {
+            children
+                .iter()
+                .map(|child| child.id)
+                .filter(|id| *id != clade)
+                .collect::<HashSet<_>>()
+        })
+        .unwrap_or_default();
+
+    let overlapping_kmers = kmers_map.get_overlapping_hashed_kmers(
+        kmers_map.build_kmer_from_string(sequence.to_string(), None),
+    );
+
+    let mut kmers = overlapping_kmers
+        .get_map()
+        .values()
+        .flat_map(|value| value.0.iter())
+        .filter(|(_, nodes)| {
+            nodes.contains(&clade) && nodes.is_disjoint(&siblings)
+        })
+        .filter_map(|(hash, nodes)| {
+            kmers_map
+                .get_kmer_string(*hash)
+                .map(|kmer| (nodes.len(), kmer.to_string()))
+        })
+        .collect::<Vec<_>>();
+
+    kmers.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    kmers.dedup();
+
+    Some(
+        kmers
+            .into_iter()
+            .take(limit)
+            .map(|(_, kmer)| kmer)
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::dtos::{
+            build_options::BuildOptions, file_or_stdin::FileOrStdin,
+        },
+        use_cases::map_kmers_to_tree,
+    };
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_get_discriminating_kmers() {
+        let tree_path = PathBuf::from("/tmp/cls-discriminating-kmers.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-discriminating-kmers.fasta");
+
+        std::fs::write(&tree_path, "(Seq_a:0.1,Seq_b:0.2);").unwrap();
+        std::fs::write(
+            &msa_path,
+            ">Seq_a\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCATCGAT\n\
+            >Seq_b\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCTTTTTT\n",
+        )
+        .unwrap();
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default().with_retain_kmer_strings(true),
+        )
+        .unwrap();
+
+        //
+        // The kmer strings table should survive the database serialization
+        //
+        let tree: Tree =
+            serde_yaml::from_str(&serde_yaml::to_string(&tree).unwrap())
+                .unwrap();
+
+        assert!(tree.kmers_map.as_ref().unwrap().retains_kmer_strings());
+
+        let seq_a = tree
+            .root
+            .get_leaves_with_paths(None)
+            .into_iter()
+            .find(|(clade, _)| clade.name == Some("Seq_a".to_string()))
+            .map(|(clade, _)| clade.id)
+            .unwrap();
+
+        let kmers = get_discriminating_kmers(
+            "ACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCATCGAT",
+            &tree,
+            seq_a,
+            100,
+        )
+        .unwrap();
+
+        //
+        // The last kmer of Seq_a covers the region that differs from Seq_b,
+        // while the first kmer is shared by both sequences.
+        //
+        assert!(
+            kmers.contains(&"GACCATGACGATCGATCGGGCTAGCTAGCATCGAT".to_string())
+        );
+        assert!(
+            !kmers.contains(&"ACGTACGTTTGACCATGACGATCGATCGGGCTAGC".to_string())
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use super::_dtos::PlacementTrace;
+use crate::domain::dtos::{
+    adherence_test::AdherenceTest, placement_response::PlacementStatus,
+};
+
+/// The maximum number of candidate clades described at each level
+const MAX_CANDIDATES: usize = 3;
+
+/// Describe the decisions of a placement in human-readable form
+///
+/// Each introspected level is described by its best candidates, ordered by
+/// the one-vs-rest counts, followed by the final placement status.
+pub(super) fn explain_placement(
+    header: &str,
+    trace: Option<&PlacementTrace>,
+    status: &PlacementStatus,
+) -> String {
+    let mut lines = Vec::<String>::new();
+
+    if let Some(trace) = trace {
+        lines.push(format!(
+            "Query {header} had {kmers} overlapping kmers.",
+            kmers = trace.overlapping_kmers
+        ));
+
+        for step in trace.steps.iter() {
+            lines.push(match step.tests.split_first() {
+                None => format!(
+                    "At clade {parent}, no candidate clade was tested.",
+                    parent = step.parent
+                ),
+                Some((best, [])) => format!(
+                    "At clade {parent}, {best} was the only candidate.",
+                    parent = step.parent,
+                    best = describe_test(best)
+                ),
+                Some((best, others)) => format!(
+                    "At clade {parent}, {best} beat {others}.",
+                    parent = step.parent,
+                    best = describe_test(best),
+                    others = describe_others(others)
+                ),
+            });
+        }
+    }
+
+    lines.push(match status {
+        PlacementStatus::IdentityFound(test) => {
+            format!("Identity found at {}.", describe_test(test))
+        }
+        PlacementStatus::MaxResolutionReached(id, _) => {
+            format!("Placed at clade {id} (MaxResolutionReached).")
+        }
+        PlacementStatus::Inconclusive(tests, _) => format!(
+            "Inconclusive between {}.",
+            describe_others(tests.as_slice())
+        ),
+        PlacementStatus::Unclassifiable(msg) => {
+            format!("Unclassifiable ({msg}).")
+        }
+    });
+
+    lines.join("\n") + "\n"
+}
+
+fn describe_test(test: &AdherenceTest) -> String {
+    format!(
+        "clade {id} ({one} vs {rest})",
+        id = test.clade_id(),
+        one = test.one,
+        rest = test.rest
+    )
+}
+
+fn describe_others(tests: &[AdherenceTest]) -> String {
+    let mut described = tests
+        .iter()
+        .take(MAX_CANDIDATES)
+        .map(describe_test)
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    if tests.len() > MAX_CANDIDATES {
+        described
+            .push_str(&format!(" and {} more", tests.len() - MAX_CANDIDATES));
+    }
+
+    described
+}
@@ -0,0 +1,74 @@
+use crate::domain::dtos::{kmers_map::KmersMap, strand::Strand};
+
+use std::cmp::Ordering;
+
+/// Infer the query strand from the database kmers
+///
+/// The forward kmers and the reverse complement kmers of the query are
+/// overlapped with the database separately, then the orientation with more
+/// overlapping kmers is reported. Databases indexing both strands match both
+/// orientations equally, then the strand is only informative for databases
+/// built with forward kmers only.
+pub(super) fn infer_strand(sequence: &str, kmers_map: &KmersMap) -> Strand {
+    let count_overlaps = |sequence: String| {
+        kmers_map
+            .get_overlapping_hashed_kmers(
+                kmers_map.build_stranded_kmer_from_string(sequence, None, true),
+            )
+            .total_kmers()
+    };
+
+    //
+    // Uracil is replaced before complementing, since it is indexed as thymine
+    //
+    let sequence = sequence.replace('U', "T").replace('u', "t");
+
+    let forward = count_overlaps(sequence.to_owned());
+    let reverse = count_overlaps(KmersMap::reverse_complement(sequence));
+
+    match forward.cmp(&reverse) {
+        Ordering::Greater => Strand::Forward,
+        Ordering::Less => Strand::Reverse,
+        Ordering::Equal => Strand::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::dtos::{
+            build_options::BuildOptions, file_or_stdin::FileOrStdin,
+        },
+        use_cases::map_kmers_to_tree,
+    };
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_infer_strand() {
+        let build = |forward_only: bool| {
+            map_kmers_to_tree(
+                PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk"),
+                FileOrStdin::from_file("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta"),
+                &BuildOptions::default().with_forward_only(forward_only),
+            )
+            .unwrap()
+            .kmers_map
+            .unwrap()
+        };
+
+        let forward = "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA";
+        let reverse = KmersMap::reverse_complement(forward.to_string());
+
+        let kmers_map = build(true);
+
+        assert_eq!(infer_strand(forward, &kmers_map), Strand::Forward);
+        assert_eq!(infer_strand(&reverse, &kmers_map), Strand::Reverse);
+        assert_eq!(infer_strand(&"A".repeat(100), &kmers_map), Strand::Unknown);
+
+        //
+        // Both orientations match databases indexing both strands
+        //
+        assert_eq!(infer_strand(&reverse, &build(false)), Strand::Unknown);
+    }
+}
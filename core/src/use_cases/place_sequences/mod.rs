@@ -1,16 +1,42 @@
 mod _dtos;
+mod annotation_index;
 mod clade_from_placement_status;
+mod discriminating_kmers;
+mod explain;
+mod infer_strand;
+mod place_one;
 mod place_sequence;
+mod placement_metrics;
+mod query_trace;
+mod translate_query;
 mod update_introspection_node;
 
-use clade_from_placement_status::*;
-use place_sequence::*;
+pub use place_one::*;
 
-use super::shared::write_or_append_to_file::write_or_append_to_file;
+pub(crate) use self::clade_from_placement_status::clade_from_placement_status;
+
+use self::{
+    annotation_index::AnnotationIndex, placement_metrics::PlacementMetrics,
+    query_trace::with_query_trace,
+    translate_query::check_query_translation,
+};
+#[cfg(feature = "sqlite")]
+use super::shared::sqlite_writer::SqliteWriter;
+use super::shared::{
+    compressed_writer::{CompressedWriter, OutputCompression},
+    lazy_file_writer::LazyFileWriter,
+    partitioned_writer::PartitionedWriter,
+    write_or_append_to_file::write_or_append_to_file,
+};
 use crate::domain::dtos::{
+    chunk_by::ChunkBy,
     file_or_stdin::FileOrStdin,
+    output_field::OutputField,
     output_format::OutputFormat,
+    output_order::OutputOrder,
+    placement_config::PlacementConfig,
     placement_response::{PlacementResponse, PlacementStatus},
+    sequence::Sequence,
     telemetry_code::TelemetryCode,
     tree::Tree,
 };
@@ -19,9 +45,13 @@ use mycelium_base::utils::errors::{use_case_err, MappedErrors};
 use rayon::iter::{ParallelBridge, ParallelIterator};
 use serde::{Deserialize, Serialize};
 use std::{
-    fs::{create_dir, remove_file},
-    path::PathBuf,
-    sync::mpsc::channel,
+    collections::{HashMap, HashSet},
+    fs::{
+        create_dir, read_dir, read_to_string, remove_file, rename, OpenOptions,
+    },
+    path::{Path, PathBuf},
+    sync::{mpsc::sync_channel, Mutex},
+    thread,
     time::Duration,
 };
 use tracing::{debug, trace_span, warn};
@@ -44,11 +74,7 @@ pub fn place_sequences(
     query_sequence: FileOrStdin,
     tree: &Tree,
     out_file: &PathBuf,
-    max_iterations: &Option<i32>,
-    min_match_coverage: &Option<f64>,
-    overwrite: &bool,
-    output_format: &OutputFormat,
-    remove_intersection: &Option<bool>,
+    config: &PlacementConfig,
     parent_span: &Option<&tracing::Span>,
 ) -> Result<Vec<PlacementTime>, MappedErrors> {
     // ? -----------------------------------------------------------------------
@@ -70,60 +96,351 @@ pub fn place_sequences(
     // ? Build the output paths
     // ? -----------------------------------------------------------------------
 
-    let mut out_file_path = out_file.to_owned();
-    let mut err_file_path = out_file.to_owned();
+    //
+    // Outputs with a `.gz` or `.zst` suffix are compressed. The suffix is
+    // kept after the output format extension (e.g. `results.jsonl.gz`).
+    //
+    let compression = OutputCompression::from_path(out_file);
+    let base_file_path = compression.strip_extension(out_file);
 
-    out_file_path.set_extension(match output_format {
-        OutputFormat::Yaml => "yaml",
-        OutputFormat::Jsonl => "jsonl",
-    });
+    let mut out_file_path = base_file_path.to_owned();
+    let mut err_file_path = base_file_path.to_owned();
+
+    out_file_path.set_extension(config.output_format.extension());
+
+    let out_file_path = compression.append_extension(&out_file_path);
 
     err_file_path.set_extension("error");
 
+    let mut trace_file_path = base_file_path.to_owned();
+    trace_file_path.set_extension("trace.jsonl");
+
+    let mut explain_file_path = base_file_path.to_owned();
+    explain_file_path.set_extension("explain.txt");
+
     let out_dir = out_file_path.parent().unwrap();
 
     if !out_dir.exists() {
         let _ = create_dir(out_dir);
     }
 
-    if out_file_path.exists() {
-        if !overwrite {
+    check_query_translation(tree, &config.options)?;
+
+    if config.chunk_by.is_some() && config.resume {
+        return use_case_err("Resume is not supported for chunked outputs.")
+            .as_error();
+    }
+
+    if config.chunk_by.is_some() && config.append {
+        return use_case_err("Append is not supported for chunked outputs.")
+            .as_error();
+    }
+
+    //
+    // Staged runs interrupted before finishing leave their records in the
+    // temporary file only, then resumed runs continue from it
+    //
+    let staged_path = CompressedWriter::staged_path(&out_file_path);
+    let resume_staged = config.resume && staged_path.exists();
+
+    if resume_staged && out_file_path.exists() {
+        return use_case_err(format!(
+            "Could not resume: both the output file {out_file_path:?} and the \
+            interrupted run output {staged_path:?} exist. Remove one of them \
+            to resume from the other."
+        ))
+        .as_error();
+    }
+
+    let mut placed_queries =
+        if config.resume && (out_file_path.exists() || resume_staged) {
+            if config.output_format != OutputFormat::Jsonl {
+                return use_case_err(
+                    "Resume is only supported for the JSONL output format.",
+                )
+                .as_error();
+            }
+
+            if compression != OutputCompression::None {
+                return use_case_err(
+                    "Resume is not supported for compressed outputs.",
+                )
+                .as_error();
+            }
+
+            if resume_staged {
+                warn!("Resuming the interrupted run output {staged_path:?}");
+
+                if let Err(err) = rename(&staged_path, &out_file_path) {
+                    return use_case_err(format!(
+                        "Could not move the interrupted run output: {err}"
+                    ))
+                    .as_error();
+                }
+            }
+
+            get_placed_queries(&out_file_path)?
+        } else {
+            HashSet::new()
+        };
+
+    //
+    // Existing outputs are only replaced when the new output is complete, then
+    // they are kept if the run fails.
+    //
+    if out_file_path.exists() && !config.resume && !config.append {
+        if !config.overwrite {
             return use_case_err(format!(
                 "Could not overwrite existing file {:?} when overwrite option is `false`.", 
                 out_file_path
             )).as_error();
         }
 
-        match remove_file(out_file_path.clone()) {
-            Err(err) => {
-                return use_case_err(format!(
-                    "Could not remove file given {err}"
-                ))
-                .as_error()
+        warn!("Output file will be overwritten!");
+    };
+
+    let unclassifiable_file = match config.unclassifiable_fasta.as_ref() {
+        None => None,
+        Some(path) => {
+            if path.exists() {
+                if config.resume {
+                    //
+                    // Unclassifiable queries may be absent from the results
+                    // file when dropped, then they should be skipped as well.
+                    //
+                    placed_queries.extend(get_fasta_headers(path)?);
+                } else if !config.overwrite {
+                    return use_case_err(format!(
+                        "Could not overwrite existing file {:?} when overwrite option is `false`.",
+                        path
+                    ))
+                    .as_error();
+                } else if let Err(err) = remove_file(path) {
+                    return use_case_err(format!(
+                        "Could not remove file given {err}"
+                    ))
+                    .as_error();
+                }
             }
-            Ok(_) => warn!("Output file overwritten!"),
-        };
+
+            Some(write_or_append_to_file(path.as_path()))
+        }
+    };
+
+    let qc_report_file = match config.qc_report.as_ref() {
+        None => None,
+        Some(path) => {
+            if path.exists() && !config.resume {
+                if !config.overwrite {
+                    return use_case_err(format!(
+                        "Could not overwrite existing file {:?} when overwrite option is `false`.",
+                        path
+                    ))
+                    .as_error();
+                } else if let Err(err) = remove_file(path) {
+                    return use_case_err(format!(
+                        "Could not remove file given {err}"
+                    ))
+                    .as_error();
+                }
+            }
+
+            //
+            // The header is only written to new files, then resumed reports
+            // are appended without repeating it.
+            //
+            let is_new = !path.exists();
+            let (qc_writer, qc_file) = write_or_append_to_file(path.as_path());
+
+            if is_new {
+                qc_writer(
+                    "query\tlength\tgc_content\tstatus\n".to_string(),
+                    qc_file
+                        .try_clone()
+                        .expect("Unexpected error detected on write QC report"),
+                )?;
+            }
+
+            Some((qc_writer, qc_file))
+        }
+    };
+
+    let explain_file = match config.options.explain {
+        false => None,
+        true => {
+            if explain_file_path.exists() && !config.resume {
+                if !config.overwrite {
+                    return use_case_err(format!(
+                        "Could not overwrite existing file {:?} when overwrite option is `false`.",
+                        explain_file_path
+                    ))
+                    .as_error();
+                } else if let Err(err) = remove_file(&explain_file_path) {
+                    return use_case_err(format!(
+                        "Could not remove file given {err}"
+                    ))
+                    .as_error();
+                }
+            }
+
+            Some(write_or_append_to_file(explain_file_path.as_path()))
+        }
+    };
+
+    #[cfg(not(feature = "sqlite"))]
+    if config.sqlite_out.is_some() {
+        return use_case_err(
+            "The SQLite output requires the `sqlite` feature to be enabled",
+        )
+        .as_error();
+    }
+
+    #[cfg(feature = "sqlite")]
+    let sqlite_writer = match config.sqlite_out.as_ref() {
+        None => None,
+        Some(path) => {
+            if path.exists() && !config.resume {
+                if !config.overwrite {
+                    return use_case_err(format!(
+                        "Could not overwrite existing file {:?} when overwrite option is `false`.",
+                        path
+                    ))
+                    .as_error();
+                } else if let Err(err) = remove_file(path) {
+                    return use_case_err(format!(
+                        "Could not remove file given {err}"
+                    ))
+                    .as_error();
+                }
+            }
+
+            Some(SqliteWriter::open(path.as_path())?)
+        }
     };
 
     // ? -----------------------------------------------------------------------
     // ? Run the placement
     // ? -----------------------------------------------------------------------
 
-    let (result_writer, result_file) =
-        write_or_append_to_file(out_file_path.as_path());
+    let result_writer = match config.chunk_by {
+        //
+        // Resumed runs append to the output as well, since they depend on
+        // the records written before the interruption.
+        //
+        None => ResultWriter::Single(match config.resume || config.append {
+            true => {
+                CompressedWriter::open(out_file_path.as_path(), &compression)?
+            }
+            false => CompressedWriter::open_staged(
+                out_file_path.as_path(),
+                &compression,
+            )?,
+        }),
+        //
+        // Chunked results are written to a directory named as the output
+        // file without extensions (e.g. `results/IdentityFound.jsonl`).
+        //
+        Some(_) => {
+            let extension = compression
+                .append_extension(Path::new(config.output_format.extension()));
+
+            let extension = extension.to_string_lossy();
+
+            remove_partition_files(&base_file_path, &extension, config)?;
+
+            ResultWriter::Partitioned(PartitionedWriter::open(
+                base_file_path.as_path(),
+                &extension,
+                &compression,
+            )?)
+        }
+    };
+
+    //
+    // The error file is only created on the first failed placement, then
+    // successful runs do not leave an empty error file behind.
+    //
+    let error_writer = LazyFileWriter::new(err_file_path.as_path());
+
+    //
+    // Queries are read while placed. The channel is bounded, then the reader
+    // waits for the placement instead of buffering the whole query file.
+    //
+    let (sender, receiver) = sync_channel(
+        config
+            .query_buffer_size
+            .unwrap_or(FileOrStdin::CHANNEL_BOUND),
+    );
+
+    let quality_filter = config.quality_filter.to_owned();
+    let query_reader = thread::spawn(move || {
+        query_sequence
+            .sequence_content_by_channel_with_quality(sender, &quality_filter)
+    });
+
+    if !placed_queries.is_empty() {
+        warn!(
+            "Resuming placement. {count} queries already placed will be skipped.",
+            count = placed_queries.len()
+        );
+    }
 
-    let (error_writer, error_file) =
-        write_or_append_to_file(err_file_path.as_path());
+    //
+    // Annotations are indexed once and shared by all queries
+    //
+    let annotation_index = AnnotationIndex::new(tree);
+    let metrics = PlacementMetrics::from_global();
 
-    let (sender, receiver) = channel();
-    let _ = query_sequence.sequence_content_by_channel(sender);
+    //
+    // Repeated headers are detected before the placement, since queries are
+    // placed in parallel. Renamed headers are also used to filter queries
+    // placed before resuming.
+    //
+    let mut header_occurrences = HashMap::<String, usize>::new();
 
-    let annotations = tree.annotations.to_owned();
+    //
+    // Results are buffered with the input index and the query header when a
+    // sorted output is requested, then written after all placements.
+    //
+    let buffered_results =
+        Mutex::new(Vec::<(usize, String, Option<String>, String)>::new());
 
     let responses = receiver
         .into_iter()
-        .par_bridge()
         .map(|sequence| {
+            sequence.warn_if_heavily_filtered();
+
+            let occurrence = *header_occurrences
+                .entry(sequence.header_content().to_string())
+                .and_modify(|count| *count += 1)
+                .or_insert(1);
+
+            if occurrence == 1 {
+                return (sequence, false);
+            }
+
+            warn!(
+                "Duplicated query header: {header} (occurrence {occurrence})",
+                header = sequence.header_content()
+            );
+
+            match config.rename_duplicates {
+                true => (
+                    Sequence::new(
+                        format!("{}_{occurrence}", sequence.header_content()),
+                        sequence.sequence_content().to_string(),
+                    ),
+                    false,
+                ),
+                false => (sequence, true),
+            }
+        })
+        .filter(|(sequence, _)| {
+            !placed_queries.contains(sequence.header_content())
+        })
+        .enumerate()
+        .par_bridge()
+        .map(|(index, (sequence, duplicate))| {
             let header = sequence.header_content();
 
             let span = trace_span!(
@@ -148,104 +465,145 @@ pub fn place_sequences(
 
             let time = std::time::Instant::now();
 
-            match place_sequence(
-                &sequence.header().to_owned(),
-                &sequence.sequence().to_owned(),
-                &tree,
-                &max_iterations,
-                &min_match_coverage,
-                &remove_intersection,
-                parent_span,
-            ) {
+            let placement = || {
+                place_one_with_index(
+                    header,
+                    sequence.sequence_content(),
+                    &tree,
+                    &config.options,
+                    &annotation_index,
+                    &metrics,
+                    &Some(&span),
+                )
+            };
+
+            //
+            // Only the traced query is placed collecting its full trace
+            //
+            let response = match config.trace_query.as_deref() == Some(header)
+            {
+                true => with_query_trace(header, &trace_file_path, placement)
+                    .and_then(|response| response),
+                false => placement(),
+            };
+
+            //
+            // Failed placements are reported with the `Error` status
+            //
+            if let Some((qc_writer, qc_file)) = qc_report_file.as_ref() {
+                let status = match &response {
+                    Err(_) => "Error",
+                    Ok(output) => {
+                        output.code().split(':').next().unwrap_or_default()
+                    }
+                };
+
+                if let Err(err) = qc_writer(
+                    format!(
+                        "{header}\t{length}\t{gc:.4}\t{status}\n",
+                        length = sequence.sequence().len(),
+                        gc = sequence.sequence().gc_content(),
+                    ),
+                    qc_file.try_clone().expect(
+                        "Unexpected error detected on write QC report",
+                    ),
+                ) {
+                    panic!("Error writing to file: {err}")
+                };
+            }
+
+            match response {
                 Err(err) => {
-                    if let Err(err) = error_writer(
-                        err.to_string(),
-                        error_file.try_clone().expect(
-                            "Unexpected error detected on write blast result",
-                        ),
-                    ) {
+                    if let Err(err) = error_writer.write(err.to_string()) {
                         panic!("Error writing to file: {err}")
                     };
                 }
-                Ok(placement) => {
-                    let mut output = PlacementResponse::new(
-                        sequence.header_content().to_string(),
-                        placement.to_string(),
-                        match placement {
-                            PlacementStatus::Unclassifiable(_) => None,
-                            other => Some(other),
-                        },
-                    );
-
-                    if let Some(annotations) = annotations.to_owned() {
-                        debug!(
-                            code = TelemetryCode::UCPLACE00020.to_string(),
-                            "Annotating sequence: {header}",
-                            header = header
-                        );
-
-                        let optional_clade =
-                            clade_from_placement_status(output.placement());
-
-                        let node_annotations =
-                            if let Some(clade) = optional_clade {
-                                let tree_node = if let Some(node) =
-                                    tree.root.get_node_by_id(clade)
-                                {
-                                    node.get_path_to_root(&tree.root)
-                                        .into_iter()
-                                        .collect::<Vec<_>>()
-                                } else {
-                                    vec![]
-                                };
-
-                                let mut records = annotations
-                                    .to_owned()
-                                    .iter()
-                                    .filter(|item| {
-                                        tree_node.contains(&(item.clade as u64))
-                                    })
-                                    .cloned()
-                                    .collect::<Vec<_>>();
-
-                                if records.len() > 0 {
-                                    records.sort_by(|a, b| {
-                                        a.clade.partial_cmp(&b.clade).unwrap()
-                                    });
-                                    Some(records)
-                                } else {
-                                    None
-                                }
-                            } else {
-                                None
-                            };
+                Ok(output) => {
+                    let output = output.with_duplicate(duplicate);
 
-                        output = output.with_annotation(node_annotations);
+                    if let (Some((explain_writer, explain_file)), Some(text)) =
+                        (explain_file.as_ref(), output.explanation())
+                    {
+                        if let Err(err) = explain_writer(
+                            format!("{text}\n"),
+                            explain_file.try_clone().expect(
+                                "Unexpected error detected on write explanation",
+                            ),
+                        ) {
+                            panic!("Error writing to file: {err}")
+                        };
                     }
 
-                    let output_content = match output_format {
-                        OutputFormat::Yaml => {
-                            let content = serde_yaml::to_string(&output)
-                                .expect("Error serializing YAML response");
+                    //
+                    // The placement is only omitted from responses of
+                    // unclassifiable queries.
+                    //
+                    let unclassifiable = output.placement().is_none();
 
-                            format!("---\n{content}")
+                    if unclassifiable {
+                        if let Some((fasta_writer, fasta_file)) =
+                            unclassifiable_file.as_ref()
+                        {
+                            if let Err(err) = fasta_writer(
+                                sequence.to_fasta(),
+                                fasta_file.try_clone().expect(
+                                    "Unexpected error detected on write unclassifiable query",
+                                ),
+                            ) {
+                                panic!("Error writing to file: {err}")
+                            };
                         }
-                        OutputFormat::Jsonl => {
-                            let content = serde_json::to_string(&output)
-                                .expect("Error serializing JSON response");
+                    }
+
+                    if !(unclassifiable && config.drop_unclassifiable) {
+                        let partition = config
+                            .chunk_by
+                            .as_ref()
+                            .map(|chunk_by| get_partition(chunk_by, &output));
 
-                            format!("{content}\n")
+                        //
+                        // The SQLite database is normalized, then it receives
+                        // the placement regardless of the output fields.
+                        //
+                        #[cfg(feature = "sqlite")]
+                        if let Some(writer) = sqlite_writer.as_ref() {
+                            if let Err(err) = writer.write(output.clone()) {
+                                panic!("Error writing to SQLite: {err}")
+                            };
                         }
-                    };
 
-                    if let Err(err) = result_writer(
-                        output_content,
-                        result_file.try_clone().expect(
-                            "Unexpected error detected on write blast result",
-                        ),
-                    ) {
-                        panic!("Error writing to file: {err}")
-                    };
+                        let output = match config
+                            .options
+                            .includes_field(&OutputField::Placement)
+                        {
+                                true => output,
+                                false => output.without_placement(),
+                            };
+
+                        let output_content = config
+                            .output_format
+                            .format_record(&output)
+                            .expect("Error serializing placement response");
+
+                        match config.output_order {
+                            OutputOrder::Arrival => {
+                                if let Err(err) = result_writer
+                                    .write(partition.as_deref(), output_content)
+                                {
+                                    panic!("Error writing to file: {err}")
+                                };
+                            }
+                            _ => buffered_results
+                                .lock()
+                                .expect("Error buffering placement result")
+                                .push((
+                                    index,
+                                    header.to_string(),
+                                    partition,
+                                    output_content,
+                                )),
+                        }
+                    }
                 }
             }
 
@@ -261,6 +619,53 @@ pub fn place_sequences(
         })
         .collect();
 
+    // ? -----------------------------------------------------------------------
+    // ? Write the buffered results
+    // ? -----------------------------------------------------------------------
+
+    if config.output_order != OutputOrder::Arrival {
+        let mut results = match buffered_results.into_inner() {
+            Ok(results) => results,
+            Err(err) => {
+                return use_case_err(format!(
+                    "Could not collect buffered results given {err}"
+                ))
+                .as_error()
+            }
+        };
+
+        match config.output_order {
+            OutputOrder::Header => results
+                .sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0))),
+            _ => results.sort_by_key(|(index, _, _, _)| *index),
+        };
+
+        for (_, _, partition, content) in results {
+            result_writer.write(partition.as_deref(), content)?;
+        }
+    }
+
+    result_writer.finish()?;
+    error_writer.finish()?;
+
+    #[cfg(feature = "sqlite")]
+    if let Some(sqlite_writer) = sqlite_writer {
+        sqlite_writer.finish()?;
+    }
+
+    match query_reader.join() {
+        Ok(Ok(_)) => (),
+        Ok(Err(err)) => {
+            return use_case_err(format!(
+                "The query sequences could not be read: {err}"
+            ))
+            .as_error()
+        }
+        Err(_) => {
+            return use_case_err("The query reader thread panicked").as_error()
+        }
+    };
+
     debug!(
         code = TelemetryCode::UCPLACE0002.to_string(),
         "End multiple sequences placement"
@@ -268,3 +673,1310 @@ pub fn place_sequences(
 
     Ok(responses)
 }
+
+/// The writer of the placement results
+enum ResultWriter {
+    Single(CompressedWriter),
+    Partitioned(PartitionedWriter),
+}
+
+impl ResultWriter {
+    /// Write the content to the single output file, or to the partition file
+    /// when the results are chunked
+    fn write(
+        &self,
+        partition: Option<&str>,
+        content: String,
+    ) -> Result<(), MappedErrors> {
+        match self {
+            ResultWriter::Single(writer) => writer.write(content),
+            ResultWriter::Partitioned(writer) => {
+                writer.write(partition.unwrap_or_default(), content)
+            }
+        }
+    }
+
+    fn finish(self) -> Result<(), MappedErrors> {
+        match self {
+            ResultWriter::Single(writer) => writer.finish(),
+            ResultWriter::Partitioned(writer) => writer.finish(),
+        }
+    }
+}
+
+/// The partition of a placement response when chunking the results
+fn get_partition(
+    chunk_by: &ChunkBy,
+    output: &PlacementResponse<PlacementStatus>,
+) -> String {
+    match chunk_by {
+        ChunkBy::Status => output
+            .code()
+            .split(':')
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        ChunkBy::Clade => match clade_from_placement_status(output.placement())
+        {
+            Some(clade) => format!("clade-{clade}"),
+            None => "unplaced".to_string(),
+        },
+    }
+}
+
+/// Remove the partition files of a previous run from the output directory
+///
+/// Only files with the output extension are removed, then other files of the
+/// directory are kept.
+fn remove_partition_files(
+    dir: &Path,
+    extension: &str,
+    config: &PlacementConfig,
+) -> Result<(), MappedErrors> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+
+    let entries = match read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            return use_case_err(format!(
+                "Could not read the output directory given {err}"
+            ))
+            .as_error()
+        }
+    };
+
+    let suffix = format!(".{extension}");
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if !path.is_file() || !path.to_string_lossy().ends_with(&suffix) {
+            continue;
+        }
+
+        if !config.overwrite {
+            return use_case_err(format!(
+                "Could not overwrite existing file {:?} when overwrite option is `false`.",
+                path
+            ))
+            .as_error();
+        }
+
+        if let Err(err) = remove_file(&path) {
+            return use_case_err(format!("Could not remove file given {err}"))
+                .as_error();
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect the headers of the records of a FASTA file
+fn get_fasta_headers(path: &PathBuf) -> Result<HashSet<String>, MappedErrors> {
+    match read_to_string(path) {
+        Ok(content) => Ok(content
+            .lines()
+            .filter_map(|line| line.strip_prefix('>'))
+            .map(String::from)
+            .collect()),
+        Err(err) => use_case_err(format!(
+            "Could not read the unclassifiable queries file to resume: {err}"
+        ))
+        .as_error(),
+    }
+}
+
+/// Collect the queries already placed in a JSONL output file
+///
+/// A partially written last line (e.g. from an interrupted run) is discarded
+/// and the file is truncated to the last complete record, then new records can
+/// be safely appended.
+fn get_placed_queries(
+    out_file_path: &PathBuf,
+) -> Result<HashSet<String>, MappedErrors> {
+    let content = match read_to_string(out_file_path) {
+        Ok(content) => content,
+        Err(err) => {
+            return use_case_err(format!(
+                "Could not read the output file to resume: {err}"
+            ))
+            .as_error()
+        }
+    };
+
+    let mut placed = HashSet::new();
+    let mut valid_length = 0;
+
+    for line in content.split_inclusive('\n') {
+        if !line.ends_with('\n') {
+            break;
+        }
+
+        let query = serde_json::from_str::<serde_json::Value>(line.trim_end())
+            .ok()
+            .and_then(|record| {
+                record
+                    .get("query")
+                    .and_then(|i| i.as_str())
+                    .map(String::from)
+            });
+
+        match query {
+            Some(query) => {
+                placed.insert(query);
+                valid_length += line.len();
+            }
+            None => break,
+        }
+    }
+
+    if valid_length < content.len() {
+        warn!("Discarding the partially written records of the output file");
+
+        if let Err(err) = OpenOptions::new()
+            .write(true)
+            .open(out_file_path)
+            .and_then(|file| file.set_len(valid_length as u64))
+        {
+            return use_case_err(format!(
+                "Could not truncate the output file to resume: {err}"
+            ))
+            .as_error();
+        }
+    }
+
+    Ok(placed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::dtos::{
+            annotation::Annotation, build_options::BuildOptions,
+            placement_options::PlacementOptions,
+            placement_response::UnclassifiableReason,
+            quality_filter::QualityFilter,
+        },
+        use_cases::map_kmers_to_tree,
+    };
+
+    #[test]
+    fn test_place_sequences_with_yaml_outputs() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let query_path = PathBuf::from("/tmp/cls-yaml-query.fasta");
+
+        std::fs::write(
+            &query_path,
+            std::fs::read_to_string(&msa_path)
+                .unwrap()
+                .split_inclusive('>')
+                .take(5)
+                .collect::<String>()
+                .trim_end_matches('>'),
+        )
+        .unwrap();
+
+        let place = |format: OutputFormat| {
+            place_sequences(
+                FileOrStdin::from_file(query_path.to_str().unwrap()),
+                &tree,
+                &PathBuf::from("/tmp/cls-yaml-output"),
+                &PlacementConfig::default()
+                    .with_overwrite(true)
+                    .with_output_format(format),
+                &None,
+            )
+            .unwrap();
+
+            read_to_string("/tmp/cls-yaml-output.yaml").unwrap()
+        };
+
+        let content = place(OutputFormat::Yaml);
+
+        assert!(content.starts_with("---\n"));
+        assert!(content.ends_with('\n'));
+
+        let documents = serde_yaml::Deserializer::from_str(&content)
+            .map(serde_yaml::Value::deserialize)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(documents.len(), 4);
+        assert!(documents
+            .iter()
+            .all(|document| document["query"].is_string()));
+
+        let records = serde_yaml::from_str::<Vec<serde_yaml::Value>>(&place(
+            OutputFormat::YamlArray,
+        ))
+        .unwrap();
+
+        assert_eq!(records.len(), 4);
+    }
+
+    #[test]
+    fn test_place_sequences_with_per_sequence_timeout() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let query_path = PathBuf::from("/tmp/cls-timeout-query.fasta");
+
+        std::fs::write(
+            &query_path,
+            std::fs::read_to_string(&msa_path)
+                .unwrap()
+                .split_inclusive('>')
+                .take(5)
+                .collect::<String>()
+                .trim_end_matches('>'),
+        )
+        .unwrap();
+
+        //
+        // The timeout is exceeded before the first introspection level, then
+        // all queries are aborted but the run completes
+        //
+        let placed = place_sequences(
+            FileOrStdin::from_file(query_path.to_str().unwrap()),
+            &tree,
+            &PathBuf::from("/tmp/cls-timeout-output"),
+            &PlacementConfig::default()
+                .with_options(
+                    PlacementOptions::default().with_per_sequence_timeout(
+                        Some(Duration::from_nanos(1)),
+                    ),
+                )
+                .with_overwrite(true)
+                .with_output_format(OutputFormat::Jsonl),
+            &None,
+        )
+        .unwrap();
+
+        assert_eq!(placed.len(), 4);
+
+        let records = read_to_string("/tmp/cls-timeout-output.jsonl")
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(records.len(), 4);
+
+        for record in records {
+            assert!(record["code"].as_str().unwrap().starts_with(&format!(
+                "Unclassifiable: {}:",
+                UnclassifiableReason::Timeout
+            )));
+        }
+    }
+
+    #[test]
+    fn test_place_sequences_with_options_builder() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let query_path = PathBuf::from("/tmp/cls-options-query.fasta");
+
+        std::fs::write(
+            &query_path,
+            ">Col_orchidophilum\nCCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA\n",
+        )
+        .unwrap();
+
+        let config = PlacementConfig::default()
+            .with_options(
+                PlacementOptions::default()
+                    .with_max_iterations(Some(100))
+                    .with_remove_intersection(Some(true)),
+            )
+            .with_overwrite(true)
+            .with_output_format(OutputFormat::Jsonl);
+
+        let responses = place_sequences(
+            FileOrStdin::from_file(query_path.to_str().unwrap()),
+            &tree,
+            &PathBuf::from("/tmp/cls-options-output"),
+            &config,
+            &None,
+        )
+        .unwrap();
+
+        assert_eq!(responses.len(), 1);
+        assert!(PathBuf::from("/tmp/cls-options-output.jsonl").exists());
+    }
+
+    #[test]
+    fn test_place_sequences_with_resume() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        //
+        // Use the first sequences of the MSA as queries
+        //
+        let query_path = PathBuf::from("/tmp/cls-resume-query.fasta");
+        let query_content = std::fs::read_to_string(&msa_path)
+            .unwrap()
+            .split_inclusive('>')
+            .take(7)
+            .collect::<String>()
+            .trim_end_matches('>')
+            .to_string();
+
+        std::fs::write(&query_path, &query_content).unwrap();
+
+        let headers = query_content
+            .lines()
+            .filter(|line| line.starts_with('>'))
+            .map(|line| line.replace(">", ""))
+            .collect::<Vec<String>>();
+
+        let out_file = PathBuf::from("/tmp/cls-resume-output");
+        let out_file_path = PathBuf::from("/tmp/cls-resume-output.jsonl");
+
+        let config = PlacementConfig::default()
+            .with_overwrite(true)
+            .with_output_format(OutputFormat::Jsonl);
+
+        place_sequences(
+            FileOrStdin::from_file(query_path.to_str().unwrap()),
+            &tree,
+            &out_file,
+            &config,
+            &None,
+        )
+        .unwrap();
+
+        //
+        // Simulate an interrupted run keeping half of the records and a
+        // partially written one
+        //
+        let content = std::fs::read_to_string(&out_file_path).unwrap();
+        let lines = content.lines().collect::<Vec<&str>>();
+        let half = lines.len() / 2;
+
+        std::fs::write(
+            &out_file_path,
+            format!(
+                "{}\n{}",
+                lines[..half].join("\n"),
+                &lines[half][..lines[half].len() / 2]
+            ),
+        )
+        .unwrap();
+
+        let responses = place_sequences(
+            FileOrStdin::from_file(query_path.to_str().unwrap()),
+            &tree,
+            &out_file,
+            &config.with_resume(true),
+            &None,
+        )
+        .unwrap();
+
+        assert_eq!(responses.len(), headers.len() - half);
+
+        let queries = std::fs::read_to_string(&out_file_path)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()
+                    ["query"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<String>>();
+
+        assert_eq!(queries.len(), headers.len());
+
+        for header in headers {
+            assert_eq!(queries.iter().filter(|i| **i == header).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_place_sequences_resuming_an_interrupted_staged_run() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let query_path = PathBuf::from("/tmp/cls-resume-staged-query.fasta");
+        let query_content = std::fs::read_to_string(&msa_path)
+            .unwrap()
+            .split_inclusive('>')
+            .take(7)
+            .collect::<String>()
+            .trim_end_matches('>')
+            .to_string();
+
+        std::fs::write(&query_path, &query_content).unwrap();
+
+        let headers = query_content
+            .lines()
+            .filter(|line| line.starts_with('>'))
+            .map(|line| line.replace(">", ""))
+            .collect::<Vec<String>>();
+
+        let out_file = PathBuf::from("/tmp/cls-resume-staged-output");
+        let out_file_path =
+            PathBuf::from("/tmp/cls-resume-staged-output.jsonl");
+        let staged_path = CompressedWriter::staged_path(&out_file_path);
+
+        let _ = remove_file(&staged_path);
+
+        let config = PlacementConfig::default()
+            .with_overwrite(true)
+            .with_output_format(OutputFormat::Jsonl);
+
+        place_sequences(
+            FileOrStdin::from_file(query_path.to_str().unwrap()),
+            &tree,
+            &out_file,
+            &config,
+            &None,
+        )
+        .unwrap();
+
+        //
+        // Simulate a staged run crashed before finishing, leaving half of the
+        // records and a partially written one in the temporary file only
+        //
+        let content = std::fs::read_to_string(&out_file_path).unwrap();
+        let lines = content.lines().collect::<Vec<&str>>();
+        let half = lines.len() / 2;
+
+        std::fs::write(
+            &staged_path,
+            format!(
+                "{}\n{}",
+                lines[..half].join("\n"),
+                &lines[half][..lines[half].len() / 2]
+            ),
+        )
+        .unwrap();
+
+        //
+        // The interrupted run output is not resumed over a complete output
+        //
+        assert!(place_sequences(
+            FileOrStdin::from_file(query_path.to_str().unwrap()),
+            &tree,
+            &out_file,
+            &config.to_owned().with_resume(true),
+            &None,
+        )
+        .is_err());
+
+        remove_file(&out_file_path).unwrap();
+
+        let responses = place_sequences(
+            FileOrStdin::from_file(query_path.to_str().unwrap()),
+            &tree,
+            &out_file,
+            &config.with_resume(true),
+            &None,
+        )
+        .unwrap();
+
+        assert_eq!(responses.len(), headers.len() - half);
+        assert!(!staged_path.exists());
+
+        let queries = std::fs::read_to_string(&out_file_path)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()
+                    ["query"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<String>>();
+
+        assert_eq!(queries.len(), headers.len());
+
+        for header in headers {
+            assert_eq!(queries.iter().filter(|i| **i == header).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_place_sequences_with_unclassifiable_fasta() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let query_path = PathBuf::from("/tmp/cls-unclassifiable-query.fasta");
+        let fasta_path = PathBuf::from("/tmp/cls-unclassifiable-output.fasta");
+
+        //
+        // A poly-A sequence shares no kmers with the reference sequences
+        //
+        std::fs::write(
+            &query_path,
+            format!(
+                ">Col_orchidophilum\nCCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA\n\
+                >Poly_a\n{}\n",
+                "A".repeat(100)
+            ),
+        )
+        .unwrap();
+
+        let config = PlacementConfig::default()
+            .with_overwrite(true)
+            .with_unclassifiable_fasta(Some(fasta_path.to_owned()))
+            .with_drop_unclassifiable(true)
+            .with_output_format(OutputFormat::Jsonl);
+
+        place_sequences(
+            FileOrStdin::from_file(query_path.to_str().unwrap()),
+            &tree,
+            &PathBuf::from("/tmp/cls-unclassifiable-output"),
+            &config,
+            &None,
+        )
+        .unwrap();
+
+        let unclassifiable = std::fs::read_to_string(&fasta_path).unwrap();
+        let results =
+            std::fs::read_to_string("/tmp/cls-unclassifiable-output.jsonl")
+                .unwrap();
+
+        assert!(unclassifiable.contains(">Poly_a\n"));
+        assert!(!unclassifiable.contains(">Col_orchidophilum\n"));
+        assert!(results.contains("\"Col_orchidophilum\""));
+        assert!(!results.contains("\"Poly_a\""));
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_place_sequences_with_sqlite_out() {
+        use crate::domain::dtos::annotation::Tag;
+
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let mut tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        tree.annotations = Some(vec![Annotation {
+            clade: tree.root.id as u32,
+            meta: Some(vec![
+                Tag::SciName("Colletotrichum".to_string()),
+                Tag::Rank("genus".to_string()),
+            ]),
+            source: None,
+            version: None,
+        }]);
+
+        let query_path = PathBuf::from("/tmp/cls-sqlite-query.fasta");
+        let sqlite_path = PathBuf::from("/tmp/cls-sqlite-output.db");
+
+        std::fs::write(
+            &query_path,
+            format!(
+                ">Col_orchidophilum\nCCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA\n\
+                >Poly_a\n{}\n",
+                "A".repeat(100)
+            ),
+        )
+        .unwrap();
+
+        let config = PlacementConfig::default()
+            .with_overwrite(true)
+            .with_sqlite_out(Some(sqlite_path.to_owned()))
+            .with_output_format(OutputFormat::Jsonl);
+
+        place_sequences(
+            FileOrStdin::from_file(query_path.to_str().unwrap()),
+            &tree,
+            &PathBuf::from("/tmp/cls-sqlite-output"),
+            &config,
+            &None,
+        )
+        .unwrap();
+
+        let connection = rusqlite::Connection::open(&sqlite_path).unwrap();
+
+        let count = |sql: &str| {
+            connection
+                .query_row(sql, [], |row| row.get::<_, i64>(0))
+                .unwrap()
+        };
+
+        assert_eq!(count("SELECT COUNT(*) FROM queries"), 2);
+
+        assert_eq!(
+            count(
+                "SELECT COUNT(*) FROM queries
+                WHERE query = 'Poly_a' AND status = 'Unclassifiable'"
+            ),
+            1
+        );
+
+        //
+        // Unclassifiable queries have no placed clades
+        //
+        assert_eq!(
+            count(
+                "SELECT COUNT(DISTINCT q.query) FROM placements p
+                JOIN queries q ON q.id = p.query_id"
+            ),
+            1
+        );
+
+        //
+        // Count the placed queries per genus
+        //
+        let genera = connection
+            .prepare(
+                "SELECT sci_name, COUNT(DISTINCT query_id) FROM annotations
+                WHERE rank = 'genus' GROUP BY sci_name",
+            )
+            .unwrap()
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(genera, vec![("Colletotrichum".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_place_sequences_with_chunk_by() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let query_path = PathBuf::from("/tmp/cls-chunk-query.fasta");
+        let out_dir = PathBuf::from("/tmp/cls-chunk-output");
+
+        std::fs::write(
+            &query_path,
+            format!(
+                ">Col_orchidophilum\nCCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA\n\
+                >Poly_a\n{}\n",
+                "A".repeat(100)
+            ),
+        )
+        .unwrap();
+
+        //
+        // Collect the queries of each partition file
+        //
+        let place = |chunk_by: ChunkBy| {
+            place_sequences(
+                FileOrStdin::from_file(query_path.to_str().unwrap()),
+                &tree,
+                &out_dir,
+                &PlacementConfig::default()
+                    .with_overwrite(true)
+                    .with_chunk_by(Some(chunk_by))
+                    .with_output_format(OutputFormat::Jsonl),
+                &None,
+            )
+            .unwrap();
+
+            let mut partitions = read_dir(&out_dir)
+                .unwrap()
+                .map(|entry| {
+                    let path = entry.unwrap().path();
+
+                    let records = read_to_string(&path)
+                        .unwrap()
+                        .lines()
+                        .map(|line| {
+                            serde_json::from_str::<serde_json::Value>(line)
+                                .unwrap()
+                        })
+                        .collect::<Vec<_>>();
+
+                    (
+                        path.file_name().unwrap().to_str().unwrap().to_string(),
+                        records,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            partitions.sort_by(|a, b| a.0.cmp(&b.0));
+            partitions
+        };
+
+        let partitions = place(ChunkBy::Status);
+
+        assert_eq!(partitions.len(), 2);
+
+        for (file_name, records) in partitions.iter() {
+            assert_eq!(records.len(), 1);
+
+            let status = records[0]["code"]
+                .as_str()
+                .unwrap()
+                .split(':')
+                .next()
+                .unwrap();
+
+            assert_eq!(file_name, &format!("{status}.jsonl"));
+        }
+
+        assert!(partitions.iter().any(|(file_name, records)| {
+            file_name == "Unclassifiable.jsonl"
+                && records[0]["query"] == "Poly_a"
+        }));
+
+        //
+        // Partition files of the previous run are overwritten
+        //
+        let partitions = place(ChunkBy::Clade);
+
+        assert_eq!(partitions.len(), 2);
+        assert_eq!(partitions[1].0, "unplaced.jsonl");
+        assert_eq!(partitions[1].1[0]["query"], "Poly_a");
+        assert!(partitions[0].0.starts_with("clade-"));
+        assert_eq!(partitions[0].1[0]["query"], "Col_orchidophilum");
+    }
+
+    #[test]
+    fn test_place_sequences_with_qc_report() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let query_path = PathBuf::from("/tmp/cls-qc-report-query.fasta");
+        let report_path = PathBuf::from("/tmp/cls-qc-report.tsv");
+
+        std::fs::write(
+            &query_path,
+            format!(
+                ">Col_orchidophilum\nCCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA\n\
+                >Poly_a\n{}\n",
+                "A".repeat(100)
+            ),
+        )
+        .unwrap();
+
+        let config = PlacementConfig::default()
+            .with_overwrite(true)
+            .with_qc_report(Some(report_path.to_owned()))
+            .with_output_format(OutputFormat::Jsonl);
+
+        place_sequences(
+            FileOrStdin::from_file(query_path.to_str().unwrap()),
+            &tree,
+            &PathBuf::from("/tmp/cls-qc-report-output"),
+            &config,
+            &None,
+        )
+        .unwrap();
+
+        let report = std::fs::read_to_string(&report_path).unwrap();
+        let mut lines = report.lines();
+
+        assert_eq!(lines.next(), Some("query\tlength\tgc_content\tstatus"));
+
+        let mut rows = lines
+            .map(|line| line.split('\t').collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        rows.sort();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][0], "Col_orchidophilum");
+        assert_eq!(rows[1], ["Poly_a", "100", "0.0000", "Unclassifiable"]);
+    }
+
+    #[test]
+    fn test_place_sequences_trims_low_quality_fastq_bases() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let kmers_map = tree.kmers_map.as_ref().unwrap();
+
+        //
+        // The read tail is a reference segment sequenced with low quality, then
+        // its kmers overlap the tree only if the tail is kept.
+        //
+        let sequence = "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA";
+        let quality =
+            format!("{}{}", "I".repeat(100), "#".repeat(sequence.len() - 100));
+
+        let query_path = PathBuf::from("/tmp/cls-quality-query.fastq");
+
+        std::fs::write(
+            &query_path,
+            format!("@Col_orchidophilum\n{sequence}\n+\n{quality}\n"),
+        )
+        .unwrap();
+
+        let read_query = |filter: QualityFilter| {
+            let (sender, receiver) =
+                std::sync::mpsc::sync_channel(FileOrStdin::CHANNEL_BOUND);
+
+            FileOrStdin::from_file(query_path.to_str().unwrap())
+                .sequence_content_by_channel_with_quality(sender, &filter)
+                .unwrap();
+
+            receiver.into_iter().collect::<Vec<_>>()
+        };
+
+        let overlapping_kmers = |sequence: &str| {
+            kmers_map
+                .get_overlapping_hashed_kmers(
+                    kmers_map
+                        .build_kmer_from_string(sequence.to_string(), None),
+                )
+                .get_map()
+                .values()
+                .map(|i| i.0.len())
+                .sum::<usize>()
+        };
+
+        let untrimmed = read_query(QualityFilter::default());
+        let trimmed = read_query(QualityFilter::new(Some(20), None));
+
+        assert_eq!(untrimmed[0].sequence_content().len(), sequence.len());
+        assert_eq!(trimmed[0].sequence_content().len(), 100);
+
+        let untrimmed_overlaps =
+            overlapping_kmers(untrimmed[0].sequence_content());
+        let trimmed_overlaps = overlapping_kmers(trimmed[0].sequence_content());
+
+        assert!(trimmed_overlaps > 0);
+        assert!(trimmed_overlaps < untrimmed_overlaps);
+
+        //
+        // Reads with low mean quality are skipped
+        //
+        assert!(read_query(QualityFilter::new(None, Some(30.0))).is_empty());
+
+        let config = PlacementConfig::default()
+            .with_overwrite(true)
+            .with_min_base_quality(Some(20))
+            .with_output_format(OutputFormat::Jsonl);
+
+        let responses = place_sequences(
+            FileOrStdin::from_file(query_path.to_str().unwrap()),
+            &tree,
+            &PathBuf::from("/tmp/cls-quality-output"),
+            &config,
+            &None,
+        )
+        .unwrap();
+
+        assert_eq!(responses.len(), 1);
+    }
+
+    #[test]
+    fn test_place_sequences_with_duplicate_headers() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let sequence = "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA";
+        let query_path = PathBuf::from("/tmp/cls-duplicate-query.fasta");
+
+        std::fs::write(
+            &query_path,
+            format!(">Col_orchidophilum\n{sequence}\n>Col_orchidophilum\n{sequence}\n"),
+        )
+        .unwrap();
+
+        let place = |config: PlacementConfig| {
+            let out_file = PathBuf::from("/tmp/cls-duplicate-output");
+
+            place_sequences(
+                FileOrStdin::from_file(query_path.to_str().unwrap()),
+                &tree,
+                &out_file,
+                &config
+                    .with_overwrite(true)
+                    .with_output_format(OutputFormat::Jsonl),
+                &None,
+            )
+            .unwrap();
+
+            std::fs::read_to_string("/tmp/cls-duplicate-output.jsonl").unwrap()
+        };
+
+        //
+        // Repeated queries are flagged by default
+        //
+        let results = place(PlacementConfig::default());
+
+        assert_eq!(results.lines().count(), 2);
+        assert_eq!(results.matches("\"duplicate\":true").count(), 1);
+
+        //
+        // Or renamed with the occurrence index
+        //
+        let results =
+            place(PlacementConfig::default().with_rename_duplicates(true));
+
+        assert_eq!(results.lines().count(), 2);
+        assert!(results.contains("\"query\":\"Col_orchidophilum\""));
+        assert!(results.contains("\"query\":\"Col_orchidophilum_2\""));
+        assert!(!results.contains("\"duplicate\""));
+    }
+
+    #[test]
+    fn test_place_sequences_with_output_fields() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let mut tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        //
+        // Annotate the root, then all placed queries have annotations
+        //
+        tree.annotations = Some(vec![Annotation {
+            clade: tree.root.id as u32,
+            meta: None,
+            source: Some("curator".to_string()),
+            version: Some("1.0".to_string()),
+        }]);
+
+        let query_path = PathBuf::from("/tmp/cls-output-fields-query.fasta");
+
+        std::fs::write(
+            &query_path,
+            ">Col_orchidophilum\nCCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA\n",
+        )
+        .unwrap();
+
+        let place = |options: PlacementOptions| {
+            place_sequences(
+                FileOrStdin::from_file(query_path.to_str().unwrap()),
+                &tree,
+                &PathBuf::from("/tmp/cls-output-fields-output"),
+                &PlacementConfig::default()
+                    .with_options(options)
+                    .with_overwrite(true)
+                    .with_output_format(OutputFormat::Jsonl),
+                &None,
+            )
+            .unwrap();
+
+            std::fs::read_to_string("/tmp/cls-output-fields-output.jsonl")
+                .unwrap()
+        };
+
+        //
+        // All fields are included by default
+        //
+        let results = place(PlacementOptions::default());
+
+        assert!(results.contains("\"annotations\""));
+        assert!(results.contains("\"source\":\"curator\""));
+        assert!(results.contains("\"version\":\"1.0\""));
+        assert!(results.contains("\"placement\""));
+
+        let results =
+            place(PlacementOptions::default().with_output_fields(Some(vec![
+                OutputField::Query,
+                OutputField::Code,
+            ])));
+
+        assert!(results.contains("\"query\":\"Col_orchidophilum\""));
+        assert!(results.contains("\"code\""));
+        assert!(!results.contains("\"annotations\""));
+        assert!(!results.contains("\"placement\""));
+    }
+
+    #[test]
+    fn test_place_sequences_with_sorted_output() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let place = |out_file: &str| {
+            place_sequences(
+                FileOrStdin::from_file(msa_path.to_str().unwrap()),
+                &tree,
+                &PathBuf::from(out_file),
+                &PlacementConfig::default()
+                    .with_overwrite(true)
+                    .with_output_format(OutputFormat::Jsonl)
+                    .with_output_order(OutputOrder::Header),
+                &None,
+            )
+            .unwrap();
+
+            std::fs::read(format!("{out_file}.jsonl")).unwrap()
+        };
+
+        let first = place("/tmp/cls-sorted-output-1");
+        let second = place("/tmp/cls-sorted-output-2");
+
+        assert!(!first.is_empty());
+        assert_eq!(first, second);
+
+        let headers = String::from_utf8(first)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()
+                    ["query"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+
+        let mut sorted_headers = headers.to_owned();
+        sorted_headers.sort();
+
+        assert_eq!(headers, sorted_headers);
+    }
+
+    #[test]
+    fn test_place_sequences_with_compressed_output() {
+        use std::io::Read;
+
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        //
+        // Results are sorted, then compressed and plain outputs are comparable
+        //
+        let place = |out_file: &str| {
+            place_sequences(
+                FileOrStdin::from_file(msa_path.to_str().unwrap()),
+                &tree,
+                &PathBuf::from(out_file),
+                &PlacementConfig::default()
+                    .with_overwrite(true)
+                    .with_output_format(OutputFormat::Jsonl)
+                    .with_output_order(OutputOrder::Header),
+                &None,
+            )
+            .unwrap();
+        };
+
+        place("/tmp/cls-compressed-output");
+        place("/tmp/cls-compressed-output.gz");
+        place("/tmp/cls-compressed-output.zst");
+
+        let expected =
+            std::fs::read_to_string("/tmp/cls-compressed-output.jsonl")
+                .unwrap();
+
+        let mut gzip_content = String::new();
+        flate2::read::GzDecoder::new(
+            std::fs::File::open("/tmp/cls-compressed-output.jsonl.gz").unwrap(),
+        )
+        .read_to_string(&mut gzip_content)
+        .unwrap();
+
+        let zstd_content = String::from_utf8(
+            zstd::decode_all(
+                std::fs::File::open("/tmp/cls-compressed-output.jsonl.zst")
+                    .unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert!(!expected.is_empty());
+        assert_eq!(gzip_content, expected);
+        assert_eq!(zstd_content, expected);
+    }
+
+    #[test]
+    fn test_place_sequences_with_query_trace() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let query_path = PathBuf::from("/tmp/cls-trace-query.fasta");
+        let sequence = "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA";
+
+        std::fs::write(
+            &query_path,
+            format!(">Traced_query\n{sequence}\n>Other_query\n{sequence}\n"),
+        )
+        .unwrap();
+
+        let responses = place_sequences(
+            FileOrStdin::from_file(query_path.to_str().unwrap()),
+            &tree,
+            &PathBuf::from("/tmp/cls-trace-output"),
+            &PlacementConfig::default()
+                .with_overwrite(true)
+                .with_output_format(OutputFormat::Jsonl)
+                .with_trace_query(Some("Traced_query".to_string())),
+            &None,
+        )
+        .unwrap();
+
+        assert_eq!(responses.len(), 2);
+
+        let trace =
+            std::fs::read_to_string("/tmp/cls-trace-output.trace.jsonl")
+                .unwrap();
+
+        //
+        // Levels introspected for the traced query are reported
+        //
+        assert!(trace.contains(&TelemetryCode::UCPLACE0012.to_string()));
+
+        assert!(trace
+            .lines()
+            .all(|line| line.contains("\"query\":\"Traced_query\"")));
+
+        assert!(!trace.contains("Other_query"));
+    }
+
+    #[test]
+    fn test_place_sequences_creates_the_error_file_on_failures_only() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let query_path = PathBuf::from("/tmp/cls-error-file-query.fasta");
+        let err_file_path = PathBuf::from("/tmp/cls-error-file-output.error");
+
+        let queries = std::fs::read_to_string(&msa_path)
+            .unwrap()
+            .split_inclusive('>')
+            .take(5)
+            .collect::<String>()
+            .trim_end_matches('>')
+            .to_string();
+
+        let place = |content: &str| {
+            std::fs::write(&query_path, content).unwrap();
+
+            place_sequences(
+                FileOrStdin::from_file(query_path.to_str().unwrap()),
+                &tree,
+                &PathBuf::from("/tmp/cls-error-file-output"),
+                &PlacementConfig::default().with_overwrite(true),
+                &None,
+            )
+            .unwrap();
+        };
+
+        //
+        // Empty error files left by previous runs are removed as well
+        //
+        std::fs::write(&err_file_path, "").unwrap();
+        place(&queries);
+
+        assert!(!err_file_path.exists());
+
+        //
+        // Queries shorter than the kmer size fail to be placed
+        //
+        place(&format!("{queries}>Short_query\nACGT\n"));
+
+        assert!(!std::fs::read_to_string(&err_file_path).unwrap().is_empty());
+    }
+}
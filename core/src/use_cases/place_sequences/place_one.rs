@@ -0,0 +1,767 @@
+use super::{
+    _dtos::PlacementOutcome,
+    annotation_index::AnnotationIndex,
+    clade_from_placement_status::clade_from_placement_status,
+    discriminating_kmers::get_discriminating_kmers,
+    explain::explain_placement,
+    infer_strand::infer_strand,
+    place_sequence::{place_kmer_hashes, place_sequence},
+    placement_metrics::PlacementMetrics,
+    translate_query::{check_query_translation, place_translated_frames},
+};
+use crate::domain::dtos::{
+    annotation::Annotation,
+    output_field::OutputField,
+    placement_options::PlacementOptions,
+    placement_response::{PlacementResponse, PlacementStatus},
+    sequence::{SequenceBody, SequenceHeader},
+    telemetry_code::TelemetryCode,
+    tree::Tree,
+};
+
+use mycelium_base::utils::errors::{use_case_err, MappedErrors};
+use tracing::debug;
+
+/// Place a single in-memory sequence in the tree.
+///
+/// This function does not perform any file I/O. The placement status is
+/// wrapped into a `PlacementResponse` and annotated with the tree annotations,
+/// if available.
+///
+/// # Example
+///
+/// ```
+/// use classeq_core::{
+///     domain::dtos::{
+///         build_options::BuildOptions, file_or_stdin::FileOrStdin,
+///         placement_options::PlacementOptions,
+///     },
+///     use_cases::{map_kmers_to_tree, place_one},
+/// };
+/// use std::path::PathBuf;
+///
+/// let tree = map_kmers_to_tree(
+///     PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk"),
+///     FileOrStdin::from_file("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta"),
+///     &BuildOptions::default(),
+/// )
+/// .unwrap();
+///
+/// let response = place_one(
+///     "Col_orchidophilum",
+///     "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA",
+///     &tree,
+///     &PlacementOptions::default(),
+///     &None,
+/// )
+/// .unwrap();
+///
+/// println!("{}", serde_json::to_string(&response).unwrap());
+/// ```
+///
+pub fn place_one(
+    header: &str,
+    seq: &str,
+    tree: &Tree,
+    opts: &PlacementOptions,
+    parent_span: &Option<&tracing::Span>,
+) -> Result<PlacementResponse<PlacementStatus>, MappedErrors> {
+    place_one_with_index(
+        header,
+        seq,
+        tree,
+        &opts,
+        &AnnotationIndex::new(tree),
+        &PlacementMetrics::from_global(),
+        parent_span,
+    )
+}
+
+/// Place a single sequence annotating it from a shared annotation index.
+///
+/// Used when placing multiple sequences, then the annotations index and the
+/// metrics instruments are built once for all queries.
+pub(super) fn place_one_with_index(
+    header: &str,
+    seq: &str,
+    tree: &Tree,
+    opts: &PlacementOptions,
+    annotation_index: &AnnotationIndex,
+    metrics: &PlacementMetrics,
+    parent_span: &Option<&tracing::Span>,
+) -> Result<PlacementResponse<PlacementStatus>, MappedErrors> {
+    check_query_translation(tree, opts)?;
+
+    let time = std::time::Instant::now();
+
+    //
+    // Translated queries are placed in the six frames, keeping the best
+    // scoring placement
+    //
+    let (sequence, frame, outcome) = match opts.translate {
+        Some(code) => {
+            let (frame, protein, outcome) = place_translated_frames(
+                &SequenceHeader::new(header),
+                seq,
+                tree,
+                opts,
+                &code,
+                Some(annotation_index),
+                parent_span,
+            )?;
+
+            (protein, Some(frame), outcome)
+        }
+        None => {
+            let sequence = SequenceBody::remove_non_iupac_from_sequence(seq);
+
+            let outcome = place_sequence(
+                &SequenceHeader::new(header),
+                &SequenceBody::new(sequence.as_str()),
+                tree,
+                opts,
+                Some(annotation_index),
+                parent_span,
+            )?;
+
+            (sequence, None, outcome)
+        }
+    };
+
+    metrics.record(&outcome.status, outcome.depth, time.elapsed());
+
+    let response = build_response(
+        header,
+        outcome,
+        Some(sequence.as_str()),
+        tree,
+        opts,
+        annotation_index,
+    )?;
+
+    Ok(match opts.includes_field(&OutputField::Frame) {
+        true => response.with_frame(frame),
+        false => response,
+    })
+}
+
+/// Place a single query given as pre-extracted kmers.
+///
+/// Each kmer is given as a nucleotide string of the database kmer size, or as
+/// its hash. The query sequence is not available, then discriminating kmers
+/// are not reported.
+pub fn place_kmers(
+    header: &str,
+    kmers: &[String],
+    tree: &Tree,
+    opts: &PlacementOptions,
+) -> Result<PlacementResponse<PlacementStatus>, MappedErrors> {
+    let kmers_map = match tree.kmers_map.as_ref() {
+        Some(kmers_map) => kmers_map,
+        None => {
+            return use_case_err("The tree does not have a kmers map.")
+                .as_error()
+        }
+    };
+
+    let (hashes, kmers): (Vec<_>, Vec<_>) =
+        kmers.iter().partition(|kmer| kmer.parse::<u64>().is_ok());
+
+    let mut query_hashes = kmers_map.hash_kmers(
+        &kmers.into_iter().cloned().collect::<Vec<String>>(),
+        opts.query_forward_only
+            .unwrap_or(kmers_map.get_forward_only()),
+    );
+
+    query_hashes.extend(hashes.iter().filter_map(|hash| hash.parse().ok()));
+
+    let annotation_index = AnnotationIndex::new(tree);
+    let metrics = PlacementMetrics::from_global();
+    let time = std::time::Instant::now();
+
+    let outcome = place_kmer_hashes(
+        &SequenceHeader::new(header),
+        &query_hashes,
+        tree,
+        opts,
+        Some(&annotation_index),
+    )?;
+
+    metrics.record(&outcome.status, outcome.depth, time.elapsed());
+
+    build_response(header, outcome, None, tree, opts, &annotation_index)
+}
+
+/// Wrap a placement outcome into a response with the selected fields.
+fn build_response(
+    header: &str,
+    outcome: PlacementOutcome,
+    sequence: Option<&str>,
+    tree: &Tree,
+    opts: &PlacementOptions,
+    annotation_index: &AnnotationIndex,
+) -> Result<PlacementResponse<PlacementStatus>, MappedErrors> {
+    //
+    // Placements are normalized before output, so equal placements are always
+    // serialized identically
+    //
+    let placement = outcome.status.normalized();
+
+    let explanation = opts
+        .explain
+        .then(|| explain_placement(header, outcome.trace.as_ref(), &placement));
+
+    let mut output = PlacementResponse::new(
+        header.to_string(),
+        placement.to_string(),
+        match placement {
+            PlacementStatus::Unclassifiable(_) => None,
+            other => Some(other),
+        },
+    )
+    .with_explanation(explanation);
+
+    //
+    // Optional fields are only built if selected for output
+    //
+    if opts.report_margin && opts.includes_field(&OutputField::Margin) {
+        output = output.with_weakest_margin(outcome.weakest_margin);
+    }
+
+    if opts.includes_field(&OutputField::Depth) {
+        output = output.with_depth(Some(outcome.depth));
+    }
+
+    if opts.report_evidence && opts.includes_field(&OutputField::Evidence) {
+        output = output.with_evidence(outcome.evidence);
+    }
+
+    if opts.report_leaves && opts.includes_field(&OutputField::Leaves) {
+        output = output.with_leaves(
+            clade_from_placement_status(output.placement())
+                .map(|clade| tree.leaves_under(clade)),
+        );
+    }
+
+    if opts.report_path_support
+        && opts.includes_field(&OutputField::PathSupport)
+    {
+        output = output.with_path_support(
+            clade_from_placement_status(output.placement())
+                .and_then(|clade| tree.path_support(clade)),
+        );
+    }
+
+    //
+    // The frame of translated queries already gives the strand
+    //
+    if opts.report_strand
+        && opts.translate.is_none()
+        && opts.includes_field(&OutputField::Strand)
+    {
+        if let (Some(kmers_map), Some(sequence)) =
+            (tree.kmers_map.as_ref(), sequence)
+        {
+            output =
+                output.with_strand(Some(infer_strand(sequence, kmers_map)));
+        }
+    }
+
+    if let Some(limit) = opts
+        .explain_kmers
+        .filter(|_| opts.includes_field(&OutputField::Kmers))
+    {
+        if let (Some(clade), Some(sequence)) =
+            (clade_from_placement_status(output.placement()), sequence)
+        {
+            output = output.with_discriminating_kmers(
+                get_discriminating_kmers(sequence, tree, clade, limit),
+            );
+        }
+    }
+
+    let with_annotations = opts.includes_field(&OutputField::Annotations);
+    let with_lineage = opts.includes_field(&OutputField::Lineage);
+
+    if tree.annotations.is_some() && (with_annotations || with_lineage) {
+        debug!(
+            code = TelemetryCode::UCPLACE00020.to_string(),
+            "Annotating sequence: {header}",
+            header = header
+        );
+
+        let node_annotations = clade_from_placement_status(output.placement())
+            .and_then(|clade| annotation_index.get_clade_annotations(clade));
+
+        //
+        // Annotations are sorted by clade, then from root to tip
+        //
+        if with_lineage {
+            output = output.with_lineage(node_annotations.as_ref().and_then(
+                |annotations| {
+                    Annotation::lineage(annotations, &opts.lineage_delimiter)
+                },
+            ));
+        }
+
+        if with_annotations {
+            output = output.with_annotation(node_annotations);
+        }
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::dtos::{
+            annotation::Tag, build_options::BuildOptions,
+            file_or_stdin::FileOrStdin, genetic_code::GeneticCode,
+            kmers_map::KmersMap, strand::Strand,
+        },
+        use_cases::map_kmers_to_tree,
+    };
+    use std::{collections::HashMap, path::PathBuf};
+
+    #[test]
+    fn test_place_kmers_matches_the_sequence_placement() {
+        let tree = map_kmers_to_tree(
+            PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk"),
+            FileOrStdin::from_file("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta"),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let header = "Col_orchidophilum";
+        let sequence = "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA";
+
+        let expected = place_one(
+            header,
+            sequence,
+            &tree,
+            &PlacementOptions::default(),
+            &None,
+        )
+        .unwrap();
+
+        //
+        // Forward kmers are given as an external kmer counter would output
+        // them, the reverse complements being derived during the placement
+        //
+        let kmers = tree
+            .kmers_map
+            .as_ref()
+            .unwrap()
+            .build_stranded_kmer_from_string(sequence.to_string(), None, true);
+
+        let from_strings = place_kmers(
+            header,
+            &kmers
+                .iter()
+                .map(|(kmer, _)| kmer.to_owned())
+                .collect::<Vec<String>>(),
+            &tree,
+            &PlacementOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(from_strings, expected);
+
+        //
+        // Kmers given as hashes are not derived, then both strands are given
+        //
+        let from_hashes = place_kmers(
+            header,
+            &tree
+                .kmers_map
+                .as_ref()
+                .unwrap()
+                .build_kmer_from_string(sequence.to_string(), None)
+                .iter()
+                .map(|(_, hash)| hash.to_string())
+                .collect::<Vec<String>>(),
+            &tree,
+            &PlacementOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(from_hashes, expected);
+    }
+
+    #[test]
+    fn test_place_one_selects_the_translated_frame() {
+        let tree_path = PathBuf::from("/tmp/cls-translated-frame.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-translated-frame.fasta");
+        let code = GeneticCode::default();
+
+        let coding = [
+            "AGGCGAGCGCATATGTGTTGGAACTACGTTAAAAGAGATAGCCAGAGATACGCAGCCTTT\
+            TTCCCTACCCTAGCTTCATCTAGGAGAAAACGGCGACTCCCCTTCCCATCACCGTCACAG\
+            ATATGGACAGACATCGTTTCACCGCGATTGGCGTCCTGCGGTTATTCCCAGGTCATAAAA",
+            "GAAGACCGTGAGCTTGCGGGGGGCTTTCCTTGCGGGCCGGCGAATCATTTGCAAAATCGA\
+            GGTATGTCAAGGGATAATGTTGGGGGAGAGTCGAGGACGAATATGCTATTATCGAGCCTC\
+            TGCAAGTATAGTCTGCGGTCCCCTTCACACGCCGAGAGCTTGGGGACGCCCATATTGCGC",
+            "TTTCAGGCCGTTCTAGGCCAGATAAAGCGAATCATTACGAAAAATAGCCAACCCCGTAGA\
+            TACCCAGGGGGCGTACCCCTGGCGCACTTAATTTCCAGAGCAACCCAGGAAAGCACTTGT\
+            GAACGACCCACAGTCCAACTTAAGATCCTGGAGCCTCTCTTCTTTATATGGTCGCCCCAT",
+            "GAGAGACGGCCGAAACGCACGTTATTTCTCGTTTATACGATCATAGCCGTGGAAGGGTGG\
+            TATACCTTTTTTACGCTACGCCGGGCGCACTCACGGGGGGAGAATCCGGCTGTGGAGTAC\
+            AATGGCTGCTATAAGGATGAGTCTTCACCAACAAGACCCCGAATATTAGTGGGTTTAGCA",
+        ];
+
+        std::fs::write(
+            &tree_path,
+            "((Seq_a:0.1,Seq_b:0.1)100:0.1,(Seq_c:0.1,Seq_d:0.1)100:0.1);",
+        )
+        .unwrap();
+        std::fs::write(
+            &msa_path,
+            ["Seq_a", "Seq_b", "Seq_c", "Seq_d"]
+                .iter()
+                .zip(coding)
+                .map(|(name, dna)| {
+                    format!(">{name}\n{}\n", code.translate(dna))
+                })
+                .collect::<String>(),
+        )
+        .unwrap();
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default()
+                .with_k_size(Some(12))
+                .with_protein(true),
+        )
+        .unwrap();
+
+        //
+        // The coding sequence of Seq_b is read from the second base of the
+        // reverse strand, followed by a stop codon and a partial codon
+        //
+        let query =
+            KmersMap::reverse_complement(format!("C{}TAAGA", coding[1]));
+
+        let response = place_one(
+            "Seq_b",
+            &query,
+            &tree,
+            &PlacementOptions::default()
+                .with_translate(Some(code))
+                .with_report_leaves(true),
+            &None,
+        )
+        .unwrap();
+
+        assert_eq!(response.frame(), Some(-2));
+        assert_eq!(response.leaves(), Some(&vec!["Seq_b".to_string()]));
+
+        //
+        // The selected frame wins on the placement score, each other frame
+        // being placed as a protein query by itself
+        //
+        let scores = code
+            .six_frames(&query)
+            .into_iter()
+            .filter_map(|(frame, protein)| {
+                place_sequence(
+                    &SequenceHeader::new("Seq_b"),
+                    &SequenceBody::new(protein),
+                    &tree,
+                    &PlacementOptions::default(),
+                    None,
+                    &None,
+                )
+                .ok()
+                .map(|outcome| (frame, outcome.score()))
+            })
+            .collect::<Vec<(i8, (bool, usize, i32))>>();
+
+        let (_, selected) =
+            *scores.iter().find(|(frame, _)| *frame == -2).unwrap();
+
+        assert!(selected.0);
+        assert!(scores
+            .iter()
+            .filter(|(frame, _)| *frame != -2)
+            .all(|(_, score)| *score < selected));
+
+        //
+        // Protein databases do not place untranslated queries
+        //
+        assert!(place_one(
+            "Seq_b",
+            &query,
+            &tree,
+            &PlacementOptions::default(),
+            &None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_place_one_reports_the_annotated_lineage() {
+        let mut tree = map_kmers_to_tree(
+            PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk"),
+            FileOrStdin::from_file("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta"),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let header = "Col_orchidophilum";
+        let sequence = "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA";
+
+        let placed = clade_from_placement_status(
+            place_one(
+                header,
+                sequence,
+                &tree,
+                &PlacementOptions::default(),
+                &None,
+            )
+            .unwrap()
+            .placement(),
+        )
+        .unwrap();
+
+        let path = tree
+            .root
+            .get_node_by_id(placed)
+            .unwrap()
+            .get_path_to_root(&tree.root);
+
+        let off_path = tree
+            .root
+            .iter()
+            .find(|clade| !path.contains(&clade.id))
+            .unwrap()
+            .id;
+
+        let annotation = |clade: u64, tags: Vec<Tag>| Annotation {
+            clade: clade as u32,
+            meta: Some(tags),
+            source: None,
+            version: None,
+        };
+
+        //
+        // Clades without scientific names are omitted from the lineage
+        //
+        tree.annotations = Some(vec![
+            annotation(placed, vec![Tag::SciName("C. orchidophilum".into())]),
+            annotation(off_path, vec![Tag::SciName("Off path".into())]),
+            annotation(
+                tree.root.id,
+                vec![
+                    Tag::Rank("genus".into()),
+                    Tag::SciName("Colletotrichum".into()),
+                ],
+            ),
+            annotation(tree.root.id, vec![Tag::Note("no name".into())]),
+        ]);
+
+        let lineage = |delimiter: Option<&str>| {
+            place_one(
+                header,
+                sequence,
+                &tree,
+                &PlacementOptions::default()
+                    .with_lineage_delimiter(delimiter.map(String::from)),
+                &None,
+            )
+            .unwrap()
+            .lineage()
+            .map(String::from)
+        };
+
+        assert_eq!(
+            lineage(None).as_deref(),
+            Some("Colletotrichum;C. orchidophilum")
+        );
+        assert_eq!(
+            lineage(Some(" | ")).as_deref(),
+            Some("Colletotrichum | C. orchidophilum")
+        );
+    }
+
+    #[test]
+    fn test_place_one_reports_the_reverse_strand() {
+        let tree = map_kmers_to_tree(
+            PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk"),
+            FileOrStdin::from_file("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta"),
+            &BuildOptions::default().with_forward_only(true),
+        )
+        .unwrap();
+
+        let reverse = KmersMap::reverse_complement("CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA".to_string());
+
+        let place = |report_strand: bool| {
+            place_one(
+                "Col_orchidophilum",
+                &reverse,
+                &tree,
+                &PlacementOptions::default()
+                    .with_query_forward_only(Some(false))
+                    .with_report_strand(report_strand),
+                &None,
+            )
+            .unwrap()
+        };
+
+        let response = place(true);
+
+        assert!(response.placement().is_some());
+        assert_eq!(response.strand(), Some(&Strand::Reverse));
+        assert_eq!(place(false).strand(), None);
+    }
+
+    #[test]
+    fn test_place_one_reports_renamed_leaves() {
+        let mut tree = map_kmers_to_tree(
+            PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk"),
+            FileOrStdin::from_file("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta"),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let header = "Col_orchidophilum";
+        let sequence = "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA";
+
+        let leaves = |tree: &Tree| {
+            place_one(
+                header,
+                sequence,
+                tree,
+                &PlacementOptions::default().with_report_leaves(true),
+                &None,
+            )
+            .unwrap()
+            .leaves()
+            .cloned()
+            .unwrap()
+        };
+
+        let before = leaves(&tree);
+        let old_name = before[0].to_owned();
+
+        let missing = tree.rename_leaves(&HashMap::from([
+            (old_name.to_owned(), "Renamed leaf".to_string()),
+            ("Missing leaf".to_string(), "Unused".to_string()),
+        ]));
+
+        assert_eq!(missing, vec!["Missing leaf".to_string()]);
+
+        let after = leaves(&tree);
+
+        assert_eq!(after.len(), before.len());
+        assert!(after.contains(&"Renamed leaf".to_string()));
+        assert!(!after.contains(&old_name));
+    }
+
+    #[test]
+    fn test_place_one_explains_the_placement() {
+        let tree = map_kmers_to_tree(
+            PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk"),
+            FileOrStdin::from_file("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta"),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let header = "Col_orchidophilum";
+        let sequence = "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA";
+
+        let response = place_one(
+            header,
+            sequence,
+            &tree,
+            &PlacementOptions::default().with_explain(true),
+            &None,
+        )
+        .unwrap();
+
+        let explanation = response.explanation().unwrap();
+        let placed = clade_from_placement_status(response.placement()).unwrap();
+        let lines = explanation.lines().collect::<Vec<&str>>();
+
+        assert!(lines[0].starts_with(&format!("Query {header} had ")));
+        assert!(lines.last().unwrap().contains(&format!("clade {placed}")));
+
+        //
+        // The placed clade won the one-vs-rest test of some introspected
+        // level, then its decisive counts are reported
+        //
+        let (one, rest) = lines[..lines.len() - 1]
+            .iter()
+            .find_map(|line| {
+                let counts = line.split(&format!("clade {placed} (")).nth(1)?;
+                let counts = counts.split(')').next()?;
+                let (one, rest) = counts.split_once(" vs ")?;
+
+                Some((one.parse::<i32>().ok()?, rest.parse::<i32>().ok()?))
+            })
+            .unwrap();
+
+        assert!(one > rest);
+
+        assert!(place_one(
+            header,
+            sequence,
+            &tree,
+            &PlacementOptions::default(),
+            &None
+        )
+        .unwrap()
+        .explanation()
+        .is_none());
+    }
+
+    #[test]
+    fn test_place_one_reports_the_path_support() {
+        let tree = map_kmers_to_tree(
+            PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk"),
+            FileOrStdin::from_file("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta"),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let header = "Col_orchidophilum";
+        let sequence = "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA";
+
+        let place = |report_path_support: bool| {
+            place_one(
+                header,
+                sequence,
+                &tree,
+                &PlacementOptions::default()
+                    .with_report_path_support(report_path_support),
+                &None,
+            )
+            .unwrap()
+        };
+
+        let response = place(true);
+        let path = response.path_support().unwrap();
+        let placed = clade_from_placement_status(response.placement()).unwrap();
+
+        //
+        // The path goes from the root to the placed clade, reporting the
+        // supports of the tree clades
+        //
+        assert_eq!(path.first().unwrap().clade, tree.root.id);
+        assert_eq!(path.last().unwrap().clade, placed);
+
+        for step in path.iter() {
+            let clade = tree.root.get_node_by_id(step.clade).unwrap();
+            assert_eq!(step.support, clade.support);
+        }
+
+        for pair in path.windows(2) {
+            let child = tree.root.get_node_by_id(pair[1].clade).unwrap();
+            assert_eq!(child.parent, Some(pair[0].clade));
+        }
+
+        assert!(place(false).path_support().is_none());
+    }
+}
@@ -1,11 +1,20 @@
 use super::{
-    _dtos::IntrospectionUpdateResponse::*,
+    _dtos::{
+        IntrospectionStep, IntrospectionUpdateResponse::*, PlacementOutcome,
+        PlacementTrace,
+    },
+    annotation_index::AnnotationIndex,
     update_introspection_node::update_introspection_node,
 };
 use crate::domain::dtos::{
     adherence_test::AdherenceTest,
     clade::Clade,
-    placement_response::PlacementStatus::{self, *},
+    kmers_map::KmersMap,
+    placement_options::PlacementOptions,
+    placement_response::{
+        PlacementStatus::{self, *},
+        UnclassifiableReason,
+    },
     sequence::{SequenceBody, SequenceHeader},
     telemetry_code::TelemetryCode,
     tree::Tree,
@@ -20,13 +29,16 @@ use rayon::iter::{
     ParallelIterator,
 };
 use std::collections::{HashMap, HashSet};
-use tracing::{debug_span, info, trace, trace_span, Span};
+use tracing::{debug_span, info, trace, trace_span, warn, Span};
 
 /// Place a sequence in the tree.
 ///
 /// This function tries to place a sequence in the tree using the overlapping
 /// kmers. The function uses a recursive strategy to traverse the tree and
 /// evaluate the adherence of the query sequence to the clades.
+///
+/// The annotation index is only consulted when the placement should stop at a
+/// taxonomic rank.
 #[tracing::instrument(
     name = "PlaceSingleSequence",
     skip_all,
@@ -43,11 +55,10 @@ pub(super) fn place_sequence(
     header: &SequenceHeader,
     sequence: &SequenceBody,
     tree: &Tree,
-    max_iterations: &Option<i32>,
-    min_match_coverage: &Option<f64>,
-    remove_intersection: &Option<bool>,
+    options: &PlacementOptions,
+    annotation_index: Option<&AnnotationIndex>,
     parent_span: &Option<&tracing::Span>,
-) -> Result<PlacementStatus, MappedErrors> {
+) -> Result<PlacementOutcome, MappedErrors> {
     // ? -----------------------------------------------------------------------
     // ? Configure the logging span
     // ? -----------------------------------------------------------------------
@@ -61,22 +72,9 @@ pub(super) fn place_sequence(
     // ? Start placement
     // ? -----------------------------------------------------------------------
 
-    let remove_intersection = remove_intersection.unwrap_or(false);
-    let max_iterations = max_iterations.unwrap_or(1000);
-
-    let min_match_coverage = if let Some(value) = min_match_coverage {
-        match value.to_owned() {
-            value if value > 1.0 => 1.0,
-            value if value < 0.0 => 0.0,
-            value => value,
-        }
-    } else {
-        0.7
-    };
-
-    let mut kmers_map = tree
+    let kmers_map = tree
         .kmers_map
-        .to_owned()
+        .as_ref()
         .expect("The tree does not have a kmers map.");
 
     // ? -----------------------------------------------------------------------
@@ -84,8 +82,16 @@ pub(super) fn place_sequence(
     // ? -----------------------------------------------------------------------
 
     let time = std::time::Instant::now();
-    let query_kmers =
-        kmers_map.build_kmer_from_string(sequence.seq().to_string(), None);
+    //
+    // Query kmers follow the database strand mode unless overridden
+    //
+    let query_kmers = kmers_map.build_stranded_kmer_from_string(
+        sequence.seq().to_string(),
+        None,
+        options
+            .query_forward_only
+            .unwrap_or(kmers_map.get_forward_only()),
+    );
 
     Span::current()
         .record("query.kmers.count", &Some(query_kmers.len() as i32));
@@ -117,6 +123,85 @@ pub(super) fn place_sequence(
 
     let query_kmers_map = kmers_map.get_overlapping_hashed_kmers(query_kmers);
 
+    place_query_kmers_map(
+        header,
+        query_kmers_map,
+        tree,
+        options,
+        annotation_index,
+    )
+}
+
+/// Place a query given as pre-extracted kmer hashes in the tree.
+///
+/// Works as `place_sequence`, but the query kmers are not built from a
+/// sequence. Hashes are expected to be built as the database kmers.
+#[tracing::instrument(
+    name = "PlaceSingleKmersQuery",
+    skip_all,
+    fields(
+        query.kmers.count = tracing::field::Empty,
+        query.kmers.treeMatches = tracing::field::Empty,
+        subject.kmers.queryMatches = tracing::field::Empty,
+        subject.kmers.buildTime = tracing::field::Empty,
+        subject.kmers.children = tracing::field::Empty,
+    )
+)]
+pub(super) fn place_kmer_hashes(
+    header: &SequenceHeader,
+    hashes: &HashSet<u64>,
+    tree: &Tree,
+    options: &PlacementOptions,
+    annotation_index: Option<&AnnotationIndex>,
+) -> Result<PlacementOutcome, MappedErrors> {
+    let kmers_map = tree
+        .kmers_map
+        .as_ref()
+        .expect("The tree does not have a kmers map.");
+
+    Span::current().record("query.kmers.count", &Some(hashes.len() as i32));
+
+    if hashes.len() < 2 {
+        return use_case_err("The query does not contain enough kmers.")
+            .with_code(TelemetryCode::UCPLACE0005.to_string().as_str())
+            .as_error();
+    }
+
+    //
+    // Hashes carry no minimizer, then all minimizer buckets are filtered
+    //
+    let query_kmers_map = kmers_map.get_overlapping_hashes(hashes);
+
+    place_query_kmers_map(
+        header,
+        query_kmers_map,
+        tree,
+        options,
+        annotation_index,
+    )
+}
+
+/// Place a query from the database kmers overlapping the query kmers.
+fn place_query_kmers_map(
+    header: &SequenceHeader,
+    query_kmers_map: KmersMap,
+    tree: &Tree,
+    options: &PlacementOptions,
+    annotation_index: Option<&AnnotationIndex>,
+) -> Result<PlacementOutcome, MappedErrors> {
+    let remove_intersection = options.remove_intersection;
+    let min_adherence_margin = options.min_adherence_margin;
+    let min_adherence_ratio = options.min_adherence_ratio;
+    let normalize_by_clade_size = options.normalize_by_clade_size;
+    let max_iterations = options.max_iterations;
+    let per_sequence_timeout = options.per_sequence_timeout;
+    let stop_at_rank = options.stop_at_rank.as_deref();
+    let report_evidence = options.report_evidence;
+
+    let min_match_coverage = &options.min_match_coverage;
+
+    let started = std::time::Instant::now();
+
     let query_kmers_len = query_kmers_map
         .get_map()
         .values()
@@ -127,6 +212,14 @@ pub(super) fn place_sequence(
     Span::current()
         .record("query.kmers.treeMatches", &Some(query_kmers_len as i32));
 
+    //
+    // The tests of each level are only kept to explain the placement
+    //
+    let mut trace = options.explain.then(|| PlacementTrace {
+        overlapping_kmers: query_kmers_len,
+        steps: vec![],
+    });
+
     if query_kmers_len == 0 {
         let msg = format!(
             "Query sequence {query:?} may not be related to the phylogeny",
@@ -135,7 +228,15 @@ pub(super) fn place_sequence(
 
         info!(code = TelemetryCode::UCPLACE0006.to_string(), msg);
 
-        return Ok(Unclassifiable(msg));
+        return Ok(PlacementOutcome::new(
+            PlacementStatus::unclassifiable(
+                UnclassifiableReason::NoOverlap,
+                &msg,
+            ),
+            None,
+            0,
+        )
+        .with_trace(trace));
     }
 
     trace!(
@@ -160,7 +261,15 @@ pub(super) fn place_sequence(
             let msg = "Query sequence has no overlapping kmers with the \
                 reference tree";
             trace!(code = TelemetryCode::UCPLACE0007.to_string(), msg);
-            return Ok(Unclassifiable(msg.to_string()));
+            return Ok(PlacementOutcome::new(
+                PlacementStatus::unclassifiable(
+                    UnclassifiableReason::NoOverlap,
+                    msg,
+                ),
+                None,
+                0,
+            )
+            .with_trace(trace));
         }
         Some(kmers) => query_kmers_map.get_overlapping_minimized_hashes(kmers),
     };
@@ -196,8 +305,12 @@ pub(super) fn place_sequence(
     //
     // ? -----------------------------------------------------------------------
 
-    let mut children = if let Some(children) = tree.root.to_owned().children {
-        children
+    //
+    // Clades are borrowed from the tree along the search loop, then the tree
+    // subtrees are never cloned per query.
+    //
+    let mut children = if let Some(children) = &tree.root.children {
+        children.iter().collect::<Vec<&Clade>>()
     } else {
         return use_case_err(
             "The root node does not have children. This is unexpected.",
@@ -207,6 +320,18 @@ pub(super) fn place_sequence(
 
     let mut iteration = 0;
 
+    //
+    // The weakest decision margin along the accepted path. It is updated on
+    // each decision taken during the tree introspection.
+    //
+    let mut weakest_margin: Option<i32> = None;
+
+    //
+    // The one-vs-rest tests of all candidate clades of the current level. It
+    // is only updated if the evidence is requested.
+    //
+    let mut evidence: Option<Vec<AdherenceTest>> = None;
+
     // ? -----------------------------------------------------------------------
     // ? Set the initial parent
     //
@@ -217,7 +342,7 @@ pub(super) fn place_sequence(
     //
     // ? -----------------------------------------------------------------------
 
-    let mut parent = tree.root.to_owned();
+    let mut parent = &tree.root;
 
     Span::current()
         .record("subject.kmers.children", &Some(children.len() as i32));
@@ -229,11 +354,11 @@ pub(super) fn place_sequence(
     // return with `Unclassifiable` status.
     //
     let expected_min_clade_coverage =
-        (query_kmers_len as f64 * min_match_coverage).round();
+        min_match_coverage.expected_min_coverage(query_kmers_len);
 
     trace!(
         code = TelemetryCode::UCPLACE0008.to_string(),
-        "Expected min clade coverage (base {base}): {expected}",
+        "Expected min clade coverage (base {base:?}): {expected}",
         base = min_match_coverage,
         expected = expected_min_clade_coverage
     );
@@ -244,13 +369,28 @@ pub(super) fn place_sequence(
         .map(|i| i.1 .0.len())
         .sum::<usize>();
 
-    if introspection_coverage < expected_min_clade_coverage as usize {
+    //
+    // Low coverage queries are only placed on partial mode, then they are
+    // placed at the first accepted clade.
+    //
+    let partial_overlap =
+        introspection_coverage < expected_min_clade_coverage as usize;
+
+    if partial_overlap && !options.classify_low_coverage_as_partial {
         let msg =
             format!("Insufficient kmers coverage: {introspection_coverage}");
 
         trace!(code = TelemetryCode::UCPLACE0008.to_string(), msg);
 
-        return Ok(Unclassifiable(msg));
+        return Ok(PlacementOutcome::new(
+            PlacementStatus::unclassifiable(
+                UnclassifiableReason::LowCoverage,
+                &msg,
+            ),
+            None,
+            0,
+        )
+        .with_trace(trace));
     }
 
     // ? -----------------------------------------------------------------------
@@ -276,7 +416,7 @@ pub(super) fn place_sequence(
         "Starting tree introspection"
     );
 
-    loop {
+    let outcome = loop {
         iteration += 1;
 
         let iteration_span = debug_span!(
@@ -300,6 +440,29 @@ pub(super) fn place_sequence(
             .as_error();
         }
 
+        //
+        // A single level can not be interrupted, then the timeout is checked
+        // before each level introspection
+        //
+        if let Some(timeout) =
+            per_sequence_timeout.filter(|timeout| started.elapsed() > *timeout)
+        {
+            let msg = format!(
+                "Introspection aborted at level {iteration} after {timeout:?}"
+            );
+
+            warn!(code = TelemetryCode::UCPLACE0010.to_string(), msg);
+
+            break PlacementOutcome::new(
+                PlacementStatus::unclassifiable(
+                    UnclassifiableReason::Timeout,
+                    &msg,
+                ),
+                weakest_margin,
+                (iteration - 1) as usize,
+            );
+        }
+
         // ? -------------------------------------------------------------------
         // ? PHASE 1: Generate clade proposals for the current level
         //
@@ -308,7 +471,7 @@ pub(super) fn place_sequence(
         // comparing the query sequence to the sibling clades.
         //
         // ? -------------------------------------------------------------------
-        let clade_proposals = {
+        let (adherence_tests, clade_proposals) = {
             let children_lengths_time = std::time::Instant::now();
 
             //
@@ -327,7 +490,7 @@ pub(super) fn place_sequence(
                         .get_hashed_kmers_with_node(record.id)
                     {
                         None => None,
-                        Some(kmers) => Some((kmers, record)),
+                        Some(kmers) => Some((kmers, *record)),
                     }
                 })
                 .collect::<Vec<(HashSet<u64>, &Clade)>>();
@@ -339,7 +502,6 @@ pub(super) fn place_sequence(
                 "Level clades (runtime {time}): {lengths}",
                 time = format!("{:?}", children_lengths_time.elapsed()),
                 lengths = children_kmers
-                    .to_owned()
                     .iter()
                     .map(|(kmers, clade)| {
                         format!("{} ({})", clade.id, kmers.len())
@@ -350,10 +512,30 @@ pub(super) fn place_sequence(
 
             let clade_proposals_time = std::time::Instant::now();
 
-            let clade_proposals = children_kmers
-                .to_owned()
-                .into_iter()
-                .par_bridge()
+            //
+            // The number of kmers indexed for each clade and for its siblings,
+            // used to normalize the one-vs-rest test. Sizes are counted once
+            // per tree.
+            //
+            let clade_sizes =
+                normalize_by_clade_size.then(|| tree.clade_sizes());
+
+            let adherence_counts =
+                |id: u64, one: usize, rest: usize| match clade_sizes {
+                    None => (one as i32, rest as i32),
+                    Some(sizes) => {
+                        let (one_size, rest_size) =
+                            sizes.get(&id).copied().unwrap_or_default();
+
+                        (
+                            normalize_count(one, one_size),
+                            normalize_count(rest, rest_size),
+                        )
+                    }
+                };
+
+            let adherence_tests = children_kmers
+                .par_iter()
                 .filter_map(|(kmers, clade)| {
                     let rest: Vec<_> = children_kmers
                         .par_iter()
@@ -367,10 +549,13 @@ pub(super) fn place_sequence(
                         .collect();
 
                     if rest.is_empty() {
+                        let (one, rest) =
+                            adherence_counts(clade.id, kmers.len(), 0);
+
                         return Some(AdherenceTest {
-                            clade: UntaggedParent::Record(clade.to_owned()),
-                            one: kmers.len() as i32,
-                            rest: 0,
+                            clade: UntaggedParent::Id(clade.id),
+                            one,
+                            rest,
                         });
                     }
 
@@ -402,21 +587,42 @@ pub(super) fn place_sequence(
                         rest_kmers = rest_kmers.len(),
                     );
 
+                    let (one, rest) = adherence_counts(
+                        clade.id,
+                        one_kmers.len(),
+                        rest_kmers.len(),
+                    );
+
                     Some(AdherenceTest {
-                        clade: UntaggedParent::Record(clade.to_owned()),
-                        one: one_kmers.len() as i32,
-                        rest: rest_kmers.len() as i32,
+                        clade: UntaggedParent::Id(clade.id),
+                        one,
+                        rest,
                     })
                 })
-                .filter_map(|adherence| {
-                    if adherence.one > adherence.rest as i32 {
-                        Some(adherence.to_owned())
-                    } else {
-                        None
-                    }
+                .collect::<Vec<AdherenceTest>>();
+
+            let clade_proposals = adherence_tests
+                .iter()
+                .filter(|adherence| {
+                    adherence.wins(min_adherence_margin, min_adherence_ratio)
                 })
+                .cloned()
                 .collect::<Vec<AdherenceTest>>();
 
+            //
+            // The tests of the rejected clades are kept as evidence
+            //
+            if report_evidence {
+                evidence = Some(sort_proposals(&adherence_tests));
+            }
+
+            if let Some(trace) = trace.as_mut() {
+                trace.steps.push(IntrospectionStep {
+                    parent: parent.id,
+                    tests: sort_proposals(&adherence_tests),
+                });
+            }
+
             trace!(
                 code = TelemetryCode::UCPLACE0014.to_string(),
                 "Available proposals (runtime {time}): {proposals}",
@@ -424,7 +630,7 @@ pub(super) fn place_sequence(
                 proposals = clade_proposals.len()
             );
 
-            clade_proposals
+            (adherence_tests, clade_proposals)
         };
 
         // ? -------------------------------------------------------------------
@@ -445,11 +651,47 @@ pub(super) fn place_sequence(
             // ? ---------------------------------------------------------------
             if clade_proposals.is_empty() {
                 if iteration == 1 {
+                    //
+                    // Clades winning the one-vs-rest test but rejected by the
+                    // minimum margin (or ratio) are related to the query, then
+                    // the placement is inconclusive at the root
+                    //
+                    let rejected = adherence_tests
+                        .iter()
+                        .filter(|adherence| adherence.wins(0, None))
+                        .cloned()
+                        .collect::<Vec<AdherenceTest>>();
+
+                    if !rejected.is_empty() {
+                        trace!(
+                            code = TelemetryCode::UCPLACE0019.to_string(),
+                            "Proposals rejected at the root clade {clade_id}",
+                            clade_id = parent.id
+                        );
+
+                        break PlacementOutcome::new(
+                            Inconclusive(
+                                sort_proposals(&rejected),
+                                "Proposals below the minimum adherence"
+                                    .to_string(),
+                            ),
+                            weakest_margin,
+                            iteration as usize,
+                        );
+                    }
+
                     let msg = "Tree introspection not possible. Query \
                         sequence has no overlapping kmers with the reference \
                         tree";
                     trace!(code = TelemetryCode::UCPLACE0011.to_string(), msg);
-                    return Ok(Unclassifiable(msg.to_string()));
+                    break PlacementOutcome::new(
+                        PlacementStatus::unclassifiable(
+                            UnclassifiableReason::NoProposals,
+                            msg,
+                        ),
+                        weakest_margin,
+                        iteration as usize,
+                    );
                 }
 
                 trace!(
@@ -458,10 +700,11 @@ pub(super) fn place_sequence(
                     clade_id = parent.id
                 );
 
-                return Ok(MaxResolutionReached(
-                    parent.id,
-                    "LCA Accepted".to_string(),
-                ));
+                break PlacementOutcome::new(
+                    MaxResolutionReached(parent.id, "LCA Accepted".to_string()),
+                    weakest_margin,
+                    iteration as usize,
+                );
             }
 
             // ? ---------------------------------------------------------------
@@ -484,25 +727,61 @@ pub(super) fn place_sequence(
                     }
                 };
 
+                update_weakest_margin(
+                    &mut weakest_margin,
+                    adherence.one - adherence.rest,
+                );
+
+                //
+                // ✅ Stop the search loop at the first accepted clade if the
+                // query has a partial overlap
+                //
+                if partial_overlap {
+                    break get_partial_stop(
+                        &adherence,
+                        weakest_margin,
+                        iteration as usize,
+                    );
+                }
+
                 //
                 // 🍁 1st clade update
                 // 🌿 1st children update
                 //
-                (parent, children) =
-                    match update_introspection_node(&adherence)? {
-                        //
-                        // ✅ Return a status if the search loop is finished with
-                        // a conclusive identity.
-                        //
-                        Return(status) => {
-                            return Ok(status);
-                        }
-                        //
-                        // 🟢 Continue the search loop with the new parent and
-                        // children
-                        //
-                        Continue(a, b) => (a, b),
-                    };
+                (parent, children) = match update_introspection_node(
+                    &adherence,
+                    get_child(&children, adherence.clade_id())?,
+                )? {
+                    //
+                    // ✅ Return a status if the search loop is finished with
+                    // a conclusive identity.
+                    //
+                    Return(status) => {
+                        break PlacementOutcome::new(
+                            status,
+                            weakest_margin,
+                            iteration as usize,
+                        );
+                    }
+                    //
+                    // 🟢 Continue the search loop with the new parent and
+                    // children
+                    //
+                    Continue(a, b) => (a, b),
+                };
+
+                //
+                // ✅ Stop the search loop if the target rank is reached
+                //
+                if let Some(status) =
+                    get_rank_stop(parent, stop_at_rank, annotation_index)
+                {
+                    break PlacementOutcome::new(
+                        status,
+                        weakest_margin,
+                        iteration as usize,
+                    );
+                }
 
                 continue;
             }
@@ -549,25 +828,58 @@ pub(super) fn place_sequence(
                         }
                     };
 
+                    update_weakest_margin(&mut weakest_margin, *max_diff_key);
+
+                    //
+                    // ✅ Stop the search loop at the first accepted clade if
+                    // the query has a partial overlap
+                    //
+                    if partial_overlap {
+                        break get_partial_stop(
+                            adherence,
+                            weakest_margin,
+                            iteration as usize,
+                        );
+                    }
+
                     //
                     // 🍁 2nd clade update
                     // 🌿 2nd children update
                     //
-                    (parent, children) =
-                        match update_introspection_node(&adherence)? {
-                            //
-                            // ✅ Return a status if the search loop is finished
-                            // with a conclusive identity.
-                            //
-                            Return(status) => {
-                                return Ok(status);
-                            }
-                            //
-                            // 🟢 Continue the search loop with the new parent
-                            // and children
-                            //
-                            Continue(a, b) => (a, b),
-                        };
+                    (parent, children) = match update_introspection_node(
+                        &adherence,
+                        get_child(&children, adherence.clade_id())?,
+                    )? {
+                        //
+                        // ✅ Return a status if the search loop is finished
+                        // with a conclusive identity.
+                        //
+                        Return(status) => {
+                            break PlacementOutcome::new(
+                                status,
+                                weakest_margin,
+                                iteration as usize,
+                            );
+                        }
+                        //
+                        // 🟢 Continue the search loop with the new parent
+                        // and children
+                        //
+                        Continue(a, b) => (a, b),
+                    };
+
+                    //
+                    // ✅ Stop the search loop if the target rank is reached
+                    //
+                    if let Some(status) =
+                        get_rank_stop(parent, stop_at_rank, annotation_index)
+                    {
+                        break PlacementOutcome::new(
+                            status,
+                            weakest_margin,
+                            iteration as usize,
+                        );
+                    }
 
                     continue;
                 }
@@ -581,30 +893,138 @@ pub(super) fn place_sequence(
                     "Inconclusive identity found at clade",
                 );
 
-                return Ok(Inconclusive(
-                    clade_proposals
-                        .iter()
-                        .map(|item| AdherenceTest {
-                            clade: match &item.clade {
-                                UntaggedParent::Record(clade) => {
-                                    UntaggedParent::Id(clade.id.to_owned())
-                                }
-                                id => id.to_owned(),
-                            },
-                            ..item.to_owned()
-                        })
-                        .collect(),
-                    "Multiple proposals".to_string(),
-                ));
+                break PlacementOutcome::new(
+                    Inconclusive(
+                        sort_proposals(&clade_proposals),
+                        "Multiple proposals".to_string(),
+                    ),
+                    weakest_margin,
+                    iteration as usize,
+                );
             }
         }
+    };
+
+    Ok(outcome.with_evidence(evidence).with_trace(trace))
+}
+
+/// Get the current level child with the given id.
+fn get_child<'a>(
+    children: &[&'a Clade],
+    id: u64,
+) -> Result<&'a Clade, MappedErrors> {
+    match children.iter().find(|child| child.id == id) {
+        Some(child) => Ok(*child),
+        None => use_case_err(format!(
+            "The proposed clade {id} is not a child of the current level."
+        ))
+        .as_error(),
     }
 }
 
+/// Get the status of a placement stopped at the target rank.
+///
+/// Returns `None` if no rank was requested, or if the clade is not annotated
+/// with the target rank. Clades without rank annotations are traversed.
+fn get_rank_stop(
+    clade: &Clade,
+    rank: Option<&str>,
+    annotation_index: Option<&AnnotationIndex>,
+) -> Option<PlacementStatus> {
+    let rank = rank?;
+
+    if !annotation_index?.has_rank(clade.id, rank) {
+        return None;
+    }
+
+    trace!(
+        code = TelemetryCode::UCPLACE0021.to_string(),
+        "Rank {rank} reached at clade {clade_id}",
+        clade_id = clade.id
+    );
+
+    Some(MaxResolutionReached(
+        clade.id,
+        format!("Rank {rank} reached"),
+    ))
+}
+
+/// Get the outcome of a low coverage query placed on partial mode.
+fn get_partial_stop(
+    adherence: &AdherenceTest,
+    weakest_margin: Option<i32>,
+    depth: usize,
+) -> PlacementOutcome {
+    trace!(
+        code = TelemetryCode::UCPLACE0008.to_string(),
+        "Partial overlap placed at clade {clade_id}",
+        clade_id = adherence.clade_id()
+    );
+
+    PlacementOutcome::new(
+        MaxResolutionReached(
+            adherence.clade_id(),
+            "Partial overlap accepted".to_string(),
+        ),
+        weakest_margin,
+        depth,
+    )
+}
+
+/// The scale of normalized adherence counts (parts per million).
+const NORMALIZED_SCALE: f64 = 1_000_000.0;
+
+/// Normalize a kmers count by the number of kmers indexed for the clade.
+fn normalize_count(count: usize, size: usize) -> i32 {
+    if size == 0 {
+        return 0;
+    }
+
+    (count as f64 / size as f64 * NORMALIZED_SCALE).round() as i32
+}
+
+/// Update the weakest margin with the margin of a new decision.
+fn update_weakest_margin(weakest_margin: &mut Option<i32>, margin: i32) {
+    *weakest_margin = Some(match weakest_margin {
+        Some(current) => margin.min(*current),
+        None => margin,
+    });
+}
+
+/// Build the sorted list of proposals.
+///
+/// Clade proposals are collected in parallel, then the order is not
+/// deterministic. Proposals are sorted by the number of matches (descending)
+/// and then by the clade id, so the same input always yields the same output.
+fn sort_proposals(proposals: &[AdherenceTest]) -> Vec<AdherenceTest> {
+    let mut proposals = proposals
+        .iter()
+        .map(|item| AdherenceTest {
+            clade: UntaggedParent::Id(item.clade_id()),
+            ..item.to_owned()
+        })
+        .collect::<Vec<AdherenceTest>>();
+
+    proposals.sort_by(|a, b| {
+        b.one.cmp(&a.one).then(a.clade_id().cmp(&b.clade_id()))
+    });
+
+    proposals
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::dtos::sequence::Sequence;
+    use crate::{
+        domain::dtos::{
+            annotation::{Annotation, Tag},
+            build_options::BuildOptions,
+            file_or_stdin::FileOrStdin,
+            match_coverage::MatchCoverage,
+            sequence::Sequence,
+        },
+        use_cases::map_kmers_to_tree,
+    };
     use std::path::PathBuf;
 
     #[test]
@@ -637,15 +1057,850 @@ mod tests {
             &query_sequence.header().to_owned(),
             &query_sequence.sequence().to_owned(),
             &tree,
-            &None,
-            &None,
-            &None,
+            &PlacementOptions::default(),
+            None,
             &None,
         ) {
             Err(err) => panic!("Error: {err}"),
             Ok(response) => {
-                println!("{:?}", serde_json::to_string(&response).unwrap());
+                println!(
+                    "{:?}",
+                    serde_json::to_string(&response.status).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_place_sequence_reuses_tree_kmers_map() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let options = PlacementOptions::default();
+        let content = std::fs::read_to_string(&msa_path).unwrap();
+
+        for record in content.split('>').filter(|i| !i.is_empty()) {
+            let (header, sequence) = record.split_once('\n').unwrap();
+            let header = SequenceHeader::new(header);
+            let sequence = SequenceBody::remove_non_iupac_from_sequence(
+                &sequence.replace('\n', ""),
+            );
+
+            //
+            // The kmers map was cloned from the tree for each query before
+            // being borrowed, then both paths should place queries the same
+            //
+            let kmers_map = tree.kmers_map.to_owned().unwrap();
+
+            let query_kmers = kmers_map.build_stranded_kmer_from_string(
+                sequence.to_owned(),
+                None,
+                kmers_map.get_forward_only(),
+            );
+
+            let cloned = place_query_kmers_map(
+                &header,
+                kmers_map.get_overlapping_hashed_kmers(query_kmers),
+                &tree,
+                &options,
+                None,
+            )
+            .unwrap();
+
+            let reused = place_sequence(
+                &header,
+                &SequenceBody::new(sequence.as_str()),
+                &tree,
+                &options,
+                None,
+                &None,
+            )
+            .unwrap();
+
+            assert_eq!(cloned, reused);
+        }
+    }
+
+    #[test]
+    fn test_place_sequence_with_homopolymer_compression() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default().with_homopolymer_compress(true),
+        )
+        .unwrap();
+
+        let reference = Sequence::new(
+            "Col_orchidophilum",
+            "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA",
+        );
+
+        //
+        // The same sequence with expanded homopolymers, simulating long reads
+        // sequencing errors.
+        //
+        let expanded = Sequence::new(
+            "Col_orchidophilum_expanded",
+            "CCCTTTCATTTGAGACCCAAGTACGCTGTGAGTATCACCCCCCACTTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA",
+        );
+
+        let place = |sequence: &Sequence| {
+            place_sequence(
+                &sequence.header().to_owned(),
+                &sequence.sequence().to_owned(),
+                &tree,
+                &PlacementOptions::default(),
+                None,
+                &None,
+            )
+            .unwrap()
+        };
+
+        assert_eq!(place(&reference), place(&expanded));
+    }
+
+    #[test]
+    fn test_place_sequence_with_match_coverage_modes() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        //
+        // A short query containing only a few kmers
+        //
+        let query_sequence = Sequence::new(
+            "Col_orchidophilum_short",
+            "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCC",
+        );
+
+        let kmers_map = tree.kmers_map.as_ref().unwrap();
+
+        let query_kmers_len = kmers_map
+            .get_overlapping_hashed_kmers(kmers_map.build_kmer_from_string(
+                query_sequence.sequence().seq().to_string(),
+                None,
+            ))
+            .get_map()
+            .values()
+            .map(|i| i.0.len())
+            .sum::<usize>();
+
+        let place = |coverage: MatchCoverage| {
+            place_sequence(
+                &query_sequence.header().to_owned(),
+                &query_sequence.sequence().to_owned(),
+                &tree,
+                &PlacementOptions::default().with_match_coverage(coverage),
+                None,
+                &None,
+            )
+            .unwrap()
+            .status
+        };
+
+        let is_insufficient = |status: PlacementStatus| {
+            status.unclassifiable_reason()
+                == Some(UnclassifiableReason::LowCoverage)
+        };
+
+        assert!(!is_insufficient(place(MatchCoverage::Fraction(1.0))));
+        assert!(!is_insufficient(place(MatchCoverage::Absolute(
+            query_kmers_len
+        ))));
+        assert!(is_insufficient(place(MatchCoverage::Absolute(
+            query_kmers_len + 1
+        ))));
+    }
+
+    #[test]
+    fn test_place_sequence_splits_no_overlap_from_low_coverage() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let place = |sequence: &str, partial: bool| {
+            place_sequence(
+                &SequenceHeader::new("query"),
+                &SequenceBody::new(sequence),
+                &tree,
+                &PlacementOptions::default()
+                    .with_match_coverage(MatchCoverage::Absolute(usize::MAX))
+                    .with_classify_low_coverage_as_partial(partial),
+                None,
+                &None,
+            )
+            .unwrap()
+        };
+
+        //
+        // A poly-A sequence shares no kmers with the reference tree, then it
+        // stays unclassifiable on partial mode
+        //
+        let foreign = "A".repeat(120);
+
+        for partial in [false, true] {
+            assert_eq!(
+                place(&foreign, partial).status.unclassifiable_reason(),
+                Some(UnclassifiableReason::NoOverlap)
+            );
+        }
+
+        //
+        // A short fragment of a reference sequence never reaches the required
+        // coverage
+        //
+        let fragment = "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAG";
+
+        assert_eq!(
+            place(fragment, false).status.unclassifiable_reason(),
+            Some(UnclassifiableReason::LowCoverage)
+        );
+
+        let outcome = place(fragment, true);
+
+        match outcome.status {
+            MaxResolutionReached(id, msg) => {
+                assert!(tree
+                    .root
+                    .children_of(tree.root.id)
+                    .iter()
+                    .any(|clade| clade.id == id));
+                assert_eq!(msg, "Partial overlap accepted");
+            }
+            status => panic!("Unexpected status: {status:?}"),
+        }
+
+        assert_eq!(outcome.depth, 1);
+    }
+
+    #[test]
+    fn test_sort_proposals_is_deterministic() {
+        let proposal = |id: u64, one: i32, rest: i32| AdherenceTest {
+            clade: UntaggedParent::Id(id),
+            one,
+            rest,
+        };
+
+        let proposals = vec![
+            proposal(12, 10, 5),
+            proposal(4, 10, 5),
+            proposal(7, 15, 10),
+            proposal(9, 8, 3),
+        ];
+
+        let expected = sort_proposals(&proposals);
+
+        assert_eq!(
+            expected.iter().map(|i| i.clade_id()).collect::<Vec<_>>(),
+            vec![7, 4, 12, 9]
+        );
+
+        //
+        // Simulate the nondeterministic order of the parallel collection
+        //
+        for rotation in 0..proposals.len() {
+            let mut shuffled = proposals.to_owned();
+            shuffled.rotate_left(rotation);
+            shuffled.swap(0, proposals.len() - 1);
+
+            assert_eq!(sort_proposals(&shuffled), expected);
+        }
+    }
+
+    #[test]
+    fn test_place_sequence_weakest_margin() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let clear = Sequence::new(
+            "Col_orchidophilum",
+            "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA",
+        );
+
+        //
+        // The same query truncated to less than a half, resulting in weaker
+        // decisions along the tree introspection.
+        //
+        let borderline = Sequence::new(
+            "Col_orchidophilum_truncated",
+            "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCAC",
+        );
+
+        let place = |sequence: &Sequence| {
+            place_sequence(
+                &sequence.header().to_owned(),
+                &sequence.sequence().to_owned(),
+                &tree,
+                &PlacementOptions::default(),
+                None,
+                &None,
+            )
+            .unwrap()
+        };
+
+        let clear_margin = place(&clear).weakest_margin.unwrap();
+        let borderline_margin = place(&borderline).weakest_margin.unwrap_or(0);
+
+        assert!(clear_margin > 0);
+        assert!(clear_margin > borderline_margin);
+    }
+
+    #[test]
+    fn test_place_sequence_depth() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let resolved = Sequence::new(
+            "Col_orchidophilum",
+            "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA",
+        );
+
+        //
+        // The conserved start of the query does not discriminate the deeper
+        // clades, then the introspection stops earlier.
+        //
+        let shallow = Sequence::new(
+            "Col_orchidophilum_truncated",
+            "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCAC",
+        );
+
+        let place = |sequence: &Sequence| {
+            place_sequence(
+                &sequence.header().to_owned(),
+                &sequence.sequence().to_owned(),
+                &tree,
+                &PlacementOptions::default(),
+                None,
+                &None,
+            )
+            .unwrap()
+        };
+
+        let resolved = place(&resolved);
+        let shallow = place(&shallow);
+
+        assert!(matches!(shallow.status, MaxResolutionReached(_, _)));
+        assert!(resolved.depth > shallow.depth);
+    }
+
+    #[test]
+    fn test_place_sequence_with_min_adherence_margin() {
+        let tree_path = PathBuf::from("/tmp/cls-adherence-margin.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-adherence-margin.fasta");
+
+        std::fs::write(
+            &tree_path,
+            "((A1:0.1,A2:0.1)100:0.1,(B1:0.1,B2:0.1)100:0.1);",
+        )
+        .unwrap();
+
+        let mut state: u64 = 2089;
+        let mut random_sequence = |length: usize| {
+            (0..length)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+
+                    ['A', 'C', 'G', 'T'][(state >> 62) as usize]
+                })
+                .collect::<String>()
+        };
+
+        let references = ["A1", "A2", "B1", "B2"]
+            .map(|header| (header, random_sequence(300)));
+
+        std::fs::write(
+            &msa_path,
+            references
+                .iter()
+                .map(|(header, sequence)| format!(">{header}\n{sequence}\n"))
+                .collect::<String>(),
+        )
+        .unwrap();
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let clade_a = tree
+            .root
+            .children_of(tree.root.id)
+            .iter()
+            .find(|clade| tree.leaves_under(clade.id).contains(&"A1".into()))
+            .unwrap()
+            .id;
+
+        //
+        // The query shares three more bases (then a few more kmers) with the
+        // A clade than with the B clade, then the A clade wins the root level
+        // by a small margin
+        //
+        let query =
+            format!("{}{}", &references[0].1[..100], &references[2].1[..97]);
+
+        let place = |margin: i32| {
+            place_sequence(
+                &SequenceHeader::new("Marginal"),
+                &SequenceBody::new(query.as_str()),
+                &tree,
+                &PlacementOptions::default()
+                    .with_min_adherence_margin(Some(margin)),
+                None,
+                &None,
+            )
+            .unwrap()
+        };
+
+        let accepted = place(0);
+
+        assert!(matches!(
+            accepted.status,
+            IdentityFound(_) | MaxResolutionReached(_, _)
+        ));
+        assert!(accepted
+            .weakest_margin
+            .is_some_and(|margin| (1..10).contains(&margin)));
+
+        //
+        // The same win is rejected with a larger margin, then the placement is
+        // inconclusive at the root instead of unclassifiable
+        //
+        let rejected = place(10);
+
+        match rejected.status {
+            Inconclusive(tests, _) => {
+                assert_eq!(tests[0].clade_id(), clade_a);
+                assert!(tests[0].one - tests[0].rest < 10);
+            }
+            status => panic!("Unexpected status: {status:?}"),
+        }
+
+        assert_eq!(rejected.weakest_margin, None);
+        assert_eq!(rejected.depth, 1);
+    }
+
+    #[test]
+    fn test_place_sequence_with_forward_only_database() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default().with_forward_only(true),
+        )
+        .unwrap();
+
+        let forward = "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA";
+
+        let reverse = forward
+            .chars()
+            .rev()
+            .map(|c| match c {
+                'A' => 'T',
+                'T' => 'A',
+                'C' => 'G',
+                _ => 'C',
+            })
+            .collect::<String>();
+
+        let place = |sequence: &str, query_forward_only: Option<bool>| {
+            let query = Sequence::new("Col_orchidophilum", sequence);
+
+            place_sequence(
+                &query.header().to_owned(),
+                &query.sequence().to_owned(),
+                &tree,
+                &PlacementOptions::default()
+                    .with_query_forward_only(query_forward_only),
+                None,
+                &None,
+            )
+            .unwrap()
+            .status
+        };
+
+        assert!(!matches!(place(forward, None), Unclassifiable(_)));
+
+        //
+        // The reverse complemented query is ignored when following the
+        // database strand mode, but placed if both strands are queried
+        //
+        assert!(matches!(place(&reverse, None), Unclassifiable(_)));
+        assert!(!matches!(place(&reverse, Some(false)), Unclassifiable(_)));
+    }
+
+    #[test]
+    fn test_place_sequence_borrowing_clades_keeps_records() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let content = std::fs::read_to_string(&msa_path).unwrap();
+        let mut identities = 0;
+
+        for record in content.split('>').filter(|i| !i.is_empty()) {
+            let (header, sequence) = record.split_once('\n').unwrap();
+
+            let outcome = place_sequence(
+                &SequenceHeader::new(header),
+                &SequenceBody::new(
+                    SequenceBody::remove_non_iupac_from_sequence(
+                        &sequence.replace('\n', ""),
+                    ),
+                ),
+                &tree,
+                &PlacementOptions::default(),
+                None,
+                &None,
+            )
+            .unwrap();
+
+            //
+            // Clades are borrowed during the search, then the clade record of
+            // conclusive identities should still be the full tree node, as
+            // reported when subtrees were cloned on each level.
+            //
+            if let IdentityFound(adherence) = outcome.status {
+                let node = tree.root.get_node_by_id(adherence.clade_id());
+
+                assert_eq!(
+                    adherence.clade,
+                    UntaggedParent::Record(node.unwrap().to_owned())
+                );
+
+                identities += 1;
             }
         }
+
+        assert!(identities > 0);
+    }
+
+    #[test]
+    fn test_place_sequence_normalized_by_clade_size() {
+        let tree_path = PathBuf::from("/tmp/cls-clade-size.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-clade-size.fasta");
+
+        //
+        // The large clade indexes twice the kmers of the small clade
+        //
+        std::fs::write(
+            &tree_path,
+            "((L1:0.1,L2:0.1,L3:0.1,L4:0.1)100:0.1,(S1:0.1,S2:0.1)100:0.1);",
+        )
+        .unwrap();
+
+        let mut state: u64 = 7;
+        let mut random_sequence = |length: usize| {
+            (0..length)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+
+                    ['A', 'C', 'G', 'T'][(state >> 62) as usize]
+                })
+                .collect::<String>()
+        };
+
+        let references = ["L1", "L2", "L3", "L4", "S1", "S2"]
+            .map(|header| (header, random_sequence(300)));
+
+        std::fs::write(
+            &msa_path,
+            references
+                .iter()
+                .map(|(header, sequence)| format!(">{header}\n{sequence}\n"))
+                .collect::<String>(),
+        )
+        .unwrap();
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let clade_with_leaves = |count: usize| {
+            tree.root
+                .children_of(tree.root.id)
+                .iter()
+                .find(|clade| clade.count_leaves() == count)
+                .unwrap()
+                .id
+        };
+
+        let large_id = clade_with_leaves(4);
+        let small_id = clade_with_leaves(2);
+
+        //
+        // The query shares more kmers with the large clade, but covers a
+        // larger fraction of the small clade
+        //
+        let query = format!(
+            "{}{}{}{}{}",
+            &references[0].1[..80],
+            &references[1].1[..80],
+            &references[2].1[..80],
+            &references[3].1[..80],
+            &references[4].1[..150],
+        );
+
+        let place = |normalize: bool| match place_sequence(
+            &SequenceHeader::new("Mixed"),
+            &SequenceBody::new(query.as_str()),
+            &tree,
+            &PlacementOptions::default()
+                .with_normalize_by_clade_size(normalize),
+            None,
+            &None,
+        )
+        .unwrap()
+        .status
+        {
+            IdentityFound(adherence) => adherence.clade_id(),
+            status => panic!("Unexpected status: {status:?}"),
+        };
+
+        assert_eq!(place(false), large_id);
+        assert_eq!(place(true), small_id);
+    }
+
+    #[test]
+    fn test_place_sequence_stops_at_rank() {
+        let tree_path = PathBuf::from("/tmp/cls-stop-at-rank.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-stop-at-rank.fasta");
+
+        //
+        // Two genera with two species each
+        //
+        std::fs::write(
+            &tree_path,
+            "(((A1:0.1,A2:0.1)100:0.1,(A3:0.1,A4:0.1)100:0.1)100:0.1,\
+            ((B1:0.1,B2:0.1)100:0.1,(B3:0.1,B4:0.1)100:0.1)100:0.1);",
+        )
+        .unwrap();
+
+        let mut state: u64 = 11;
+        let mut random_sequence = |length: usize| {
+            (0..length)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+
+                    ['A', 'C', 'G', 'T'][(state >> 62) as usize]
+                })
+                .collect::<String>()
+        };
+
+        let references = ["A1", "A2", "A3", "A4", "B1", "B2", "B3", "B4"]
+            .map(|header| (header, random_sequence(300)));
+
+        std::fs::write(
+            &msa_path,
+            references
+                .iter()
+                .map(|(header, sequence)| format!(">{header}\n{sequence}\n"))
+                .collect::<String>(),
+        )
+        .unwrap();
+
+        let mut tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let genera = tree
+            .root
+            .children_of(tree.root.id)
+            .iter()
+            .map(|clade| clade.id)
+            .collect::<Vec<u64>>();
+
+        let species = genera
+            .iter()
+            .flat_map(|id| tree.root.children_of(*id))
+            .map(|clade| clade.id)
+            .collect::<Vec<u64>>();
+
+        let rank_annotation = |clade: u64, rank: &str| Annotation {
+            clade: clade as u32,
+            meta: Some(vec![Tag::Rank(rank.to_string())]),
+            source: None,
+            version: None,
+        };
+
+        tree.annotations = Some(
+            genera
+                .iter()
+                .map(|id| rank_annotation(*id, "genus"))
+                .chain(species.iter().map(|id| rank_annotation(*id, "species")))
+                .collect(),
+        );
+
+        let index = AnnotationIndex::new(&tree);
+
+        let place = |rank: Option<&str>| {
+            place_sequence(
+                &SequenceHeader::new("A1"),
+                &SequenceBody::new(references[0].1.as_str()),
+                &tree,
+                &PlacementOptions::default()
+                    .with_stop_at_rank(rank.map(|i| i.to_string())),
+                Some(&index),
+                &None,
+            )
+            .unwrap()
+        };
+
+        //
+        // Without a target rank the query is placed at its leaf
+        //
+        assert!(matches!(place(None).status, IdentityFound(_)));
+
+        //
+        // With the genus rank the search never reaches the species level
+        //
+        let outcome = place(Some("Genus"));
+
+        match outcome.status {
+            MaxResolutionReached(id, _) => {
+                assert!(genera.contains(&id));
+                assert!(!species.contains(&id));
+            }
+            status => panic!("Unexpected status: {status:?}"),
+        }
+
+        assert_eq!(outcome.depth, 1);
+    }
+
+    #[test]
+    fn test_place_sequence_reports_deciding_level_evidence() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let content = std::fs::read_to_string(&msa_path).unwrap();
+        let mut identities = 0;
+
+        for record in content.split('>').filter(|i| !i.is_empty()) {
+            let (header, sequence) = record.split_once('\n').unwrap();
+
+            let place = |report_evidence: bool| {
+                place_sequence(
+                    &SequenceHeader::new(header),
+                    &SequenceBody::new(
+                        SequenceBody::remove_non_iupac_from_sequence(
+                            &sequence.replace('\n', ""),
+                        ),
+                    ),
+                    &tree,
+                    &PlacementOptions::default()
+                        .with_report_evidence(report_evidence),
+                    None,
+                    &None,
+                )
+                .unwrap()
+            };
+
+            assert_eq!(place(false).evidence, None);
+
+            let outcome = place(true);
+
+            let winner = match outcome.status {
+                IdentityFound(adherence) => adherence,
+                _ => continue,
+            };
+
+            let evidence = outcome.evidence.unwrap();
+
+            //
+            // The table includes the winner with the same counts
+            //
+            let reported = evidence
+                .iter()
+                .find(|test| test.clade_id() == winner.clade_id())
+                .unwrap();
+
+            assert_eq!(
+                (reported.one, reported.rest),
+                (winner.one, winner.rest)
+            );
+
+            //
+            // Without removing the intersection, the rest of each clade is the
+            // union of the sibling kmers, then it never exceeds the sum of the
+            // sibling counts. The winner holds the largest margin.
+            //
+            let total = evidence.iter().map(|test| test.one).sum::<i32>();
+
+            for test in evidence.iter() {
+                assert!(test.rest <= total - test.one);
+
+                if test.clade_id() != winner.clade_id() {
+                    assert!(test.one - test.rest < winner.one - winner.rest);
+                }
+            }
+
+            identities += 1;
+        }
+
+        assert!(identities > 0);
     }
 }
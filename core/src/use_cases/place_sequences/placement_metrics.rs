@@ -0,0 +1,197 @@
+use crate::domain::dtos::placement_response::PlacementStatus;
+
+use std::time::Duration;
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{
+    global,
+    metrics::{Counter, Histogram, Meter},
+    KeyValue,
+};
+
+/// The name of the meter used to record placement metrics
+#[cfg(feature = "telemetry")]
+const METER_NAME: &str = "classeq";
+
+/// Metrics recorded for each placed sequence.
+///
+/// Metrics are exported with OpenTelemetry when the `telemetry` feature is
+/// enabled. Otherwise, recording is a no-op.
+pub(super) struct PlacementMetrics {
+    #[cfg(feature = "telemetry")]
+    outcomes: Counter<u64>,
+
+    #[cfg(feature = "telemetry")]
+    placement_time: Histogram<f64>,
+
+    #[cfg(feature = "telemetry")]
+    depth: Histogram<u64>,
+}
+
+impl PlacementMetrics {
+    /// Build the metrics from the globally registered meter provider.
+    #[cfg(feature = "telemetry")]
+    pub(super) fn from_global() -> Self {
+        Self::new(&global::meter(METER_NAME))
+    }
+
+    #[cfg(not(feature = "telemetry"))]
+    pub(super) fn from_global() -> Self {
+        PlacementMetrics {}
+    }
+
+    #[cfg(feature = "telemetry")]
+    pub(super) fn new(meter: &Meter) -> Self {
+        PlacementMetrics {
+            outcomes: meter
+                .u64_counter("classeq.placement.outcomes")
+                .with_description("Placed sequences by placement status")
+                .init(),
+            placement_time: meter
+                .f64_histogram("classeq.placement.time")
+                .with_description("Time spent placing a single sequence")
+                .with_unit(opentelemetry::metrics::Unit::new("ms"))
+                .init(),
+            depth: meter
+                .u64_histogram("classeq.placement.depth")
+                .with_description("Tree levels introspected per sequence")
+                .init(),
+        }
+    }
+
+    /// Record the placement of a single sequence.
+    #[cfg(feature = "telemetry")]
+    pub(super) fn record(
+        &self,
+        status: &PlacementStatus,
+        depth: usize,
+        elapsed: Duration,
+    ) {
+        let attributes = [KeyValue::new("status", status_label(status))];
+
+        self.outcomes.add(1, &attributes);
+        self.placement_time
+            .record(elapsed.as_secs_f64() * 1000.0, &attributes);
+        self.depth.record(depth as u64, &attributes);
+    }
+
+    #[cfg(not(feature = "telemetry"))]
+    pub(super) fn record(
+        &self,
+        _status: &PlacementStatus,
+        _depth: usize,
+        _elapsed: Duration,
+    ) {
+    }
+}
+
+/// The status name used as metric attribute, without the status message.
+#[cfg(feature = "telemetry")]
+fn status_label(status: &PlacementStatus) -> &'static str {
+    match status {
+        PlacementStatus::Unclassifiable(_) => "Unclassifiable",
+        PlacementStatus::IdentityFound(_) => "IdentityFound",
+        PlacementStatus::MaxResolutionReached(_, _) => "MaxResolutionReached",
+        PlacementStatus::Inconclusive(_, _) => "Inconclusive",
+    }
+}
+
+#[cfg(all(test, feature = "telemetry"))]
+mod tests {
+    use super::super::{
+        annotation_index::AnnotationIndex, place_one::place_one_with_index,
+    };
+    use super::*;
+    use crate::{
+        domain::dtos::{
+            build_options::BuildOptions, file_or_stdin::FileOrStdin,
+            placement_options::PlacementOptions,
+        },
+        use_cases::map_kmers_to_tree,
+    };
+    use opentelemetry::metrics::{
+        InstrumentProvider, Result as MetricsResult, SyncCounter, Unit,
+    };
+    use std::{
+        borrow::Cow,
+        collections::HashMap,
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
+
+    type Counts = Arc<Mutex<HashMap<String, u64>>>;
+
+    struct StubCounter(Counts);
+
+    impl SyncCounter<u64> for StubCounter {
+        fn add(&self, value: u64, attributes: &[KeyValue]) {
+            let status = attributes
+                .iter()
+                .find(|i| i.key.as_str() == "status")
+                .map(|i| i.value.to_string())
+                .unwrap_or_default();
+
+            *self.0.lock().unwrap().entry(status).or_insert(0) += value;
+        }
+    }
+
+    /// A meter counting outcomes in memory. Histograms are no-op.
+    struct StubMeter(Counts);
+
+    impl InstrumentProvider for StubMeter {
+        fn u64_counter(
+            &self,
+            _name: Cow<'static, str>,
+            _description: Option<Cow<'static, str>>,
+            _unit: Option<Unit>,
+        ) -> MetricsResult<Counter<u64>> {
+            Ok(Counter::new(Arc::new(StubCounter(self.0.to_owned()))))
+        }
+    }
+
+    #[test]
+    fn test_placement_metrics_count_outcomes() {
+        let tree = map_kmers_to_tree(
+            PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk"),
+            FileOrStdin::from_file("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta"),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let counts = Counts::default();
+        let metrics = PlacementMetrics::new(&Meter::new(Arc::new(StubMeter(
+            counts.to_owned(),
+        ))));
+
+        let index = AnnotationIndex::new(&tree);
+
+        let place = |header: &str, sequence: &str| {
+            place_one_with_index(
+                header,
+                sequence,
+                &tree,
+                &PlacementOptions::default(),
+                &index,
+                &metrics,
+                &None,
+            )
+            .unwrap()
+        };
+
+        let placed = place(
+            "Col_orchidophilum",
+            "CCTTCATTGAGACCAAGTACGCTGTGAGTATCACCCCACTTTACCCCTCCATGATGATATCACATCTGTCACGACAATACCAGCCTCATCGGCCACTGGGAAAGAAATGAGCTAGCACTCTCGATCCTGTGACCCAGGATACTGAAGCGGCTCGTCCCAATGGCATGATGTGA",
+        );
+
+        let placed_label = status_label(placed.placement().unwrap());
+
+        place("Unrelated_1", &"A".repeat(200));
+        place("Unrelated_2", &"C".repeat(200));
+
+        let counts = counts.lock().unwrap();
+
+        assert_eq!(counts.get(placed_label), Some(&1));
+        assert_eq!(counts.get("Unclassifiable"), Some(&2));
+        assert_eq!(counts.values().sum::<u64>(), 3);
+    }
+}
@@ -0,0 +1,71 @@
+use super::super::shared::vector_writer::VectorWriter;
+
+use mycelium_base::utils::errors::{execution_err, MappedErrors};
+use rayon::ThreadPoolBuilder;
+use std::path::Path;
+use tracing::{dispatcher, trace_span, Dispatch, Level};
+use tracing_subscriber::fmt;
+
+/// Run the placement of a single query collecting its full trace
+///
+/// All events emitted by `placement` are captured despite the global log
+/// level and written to `trace_file` in JSON lines format.
+pub(super) fn with_query_trace<T: Send>(
+    header: &str,
+    trace_file: &Path,
+    placement: impl FnOnce() -> T + Send,
+) -> Result<T, MappedErrors> {
+    let writer = VectorWriter::new();
+    let make_writer = writer.to_owned();
+
+    let dispatch = Dispatch::new(
+        fmt::Subscriber::builder()
+            .with_max_level(Level::TRACE)
+            .with_writer(move || make_writer.to_owned())
+            .json()
+            .finish(),
+    );
+
+    //
+    // The default dispatcher is thread local. Then, the placement runs in a
+    // single thread pool to capture events emitted by nested parallel
+    // iterators as well.
+    //
+    let pool = match ThreadPoolBuilder::new().num_threads(1).build() {
+        Ok(pool) => pool,
+        Err(err) => {
+            return execution_err(format!(
+                "Unable to build the query trace thread pool: {err}"
+            ))
+            .as_error()
+        }
+    };
+
+    let response = pool.install(|| {
+        dispatcher::with_default(&dispatch, || {
+            let span = trace_span!("TracingQuery", query = header);
+            let _span_guard = span.enter();
+
+            placement()
+        })
+    });
+
+    let content = match writer.get_buffer().lock() {
+        Ok(buffer) => buffer.to_owned(),
+        Err(err) => {
+            return execution_err(format!(
+                "Unable to lock the query trace buffer: {err}"
+            ))
+            .as_error()
+        }
+    };
+
+    if let Err(err) = std::fs::write(trace_file, content) {
+        return execution_err(format!(
+            "Unable to write the query trace file {trace_file:?}: {err}"
+        ))
+        .as_error();
+    }
+
+    Ok(response)
+}
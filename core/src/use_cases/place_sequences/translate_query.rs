@@ -0,0 +1,91 @@
+use super::{
+    _dtos::PlacementOutcome, annotation_index::AnnotationIndex,
+    place_sequence::place_sequence,
+};
+use crate::domain::dtos::{
+    genetic_code::GeneticCode,
+    kmers_map::KmersMap,
+    placement_options::PlacementOptions,
+    sequence::{SequenceBody, SequenceHeader},
+    tree::Tree,
+};
+
+use mycelium_base::utils::errors::{use_case_err, MappedErrors};
+
+/// Check if the query translation matches the database alphabet
+///
+/// Protein databases only match translated queries, and nucleotide databases
+/// only match untranslated ones.
+pub(super) fn check_query_translation(
+    tree: &Tree,
+    options: &PlacementOptions,
+) -> Result<(), MappedErrors> {
+    let protein = tree.kmers_map.as_ref().is_some_and(KmersMap::is_protein);
+
+    match (protein, options.translate.is_some()) {
+        (true, false) => use_case_err(
+            "Nucleotide queries should be translated to be placed in protein \
+            databases.",
+        )
+        .as_error(),
+        (false, true) => {
+            use_case_err("Translated queries require a protein database.")
+                .as_error()
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Place a nucleotide query translated in the six reading frames
+///
+/// Each frame is placed as a protein query, and the placement with the best
+/// score is kept (see `PlacementOutcome::score`). Ties are resolved by the
+/// frames order (`1`, `2`, `3`, `-1`, `-2`, `-3`). Frames without enough
+/// kmers are not placed, then the query fails only if no frame is placed.
+///
+/// Returns the placed frame, the translated query and the placement outcome.
+pub(super) fn place_translated_frames(
+    header: &SequenceHeader,
+    sequence: &str,
+    tree: &Tree,
+    options: &PlacementOptions,
+    code: &GeneticCode,
+    annotation_index: Option<&AnnotationIndex>,
+    parent_span: &Option<&tracing::Span>,
+) -> Result<(i8, String, PlacementOutcome), MappedErrors> {
+    let sequence = SequenceBody::remove_non_iupac_from_sequence(sequence);
+    let mut best: Option<(i8, String, PlacementOutcome)> = None;
+    let mut first_err = None;
+
+    for (frame, protein) in code.six_frames(&sequence) {
+        let outcome = match place_sequence(
+            header,
+            &SequenceBody::new(protein.as_str()),
+            tree,
+            options,
+            annotation_index,
+            parent_span,
+        ) {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                first_err.get_or_insert(err);
+                continue;
+            }
+        };
+
+        if best
+            .as_ref()
+            .map_or(true, |(_, _, best)| outcome.score() > best.score())
+        {
+            best = Some((frame, protein, outcome));
+        }
+    }
+
+    match (best, first_err) {
+        (Some(best), _) => Ok(best),
+        (None, Some(err)) => Err(err),
+        (None, None) => {
+            use_case_err("The query has no translated frames.").as_error()
+        }
+    }
+}
@@ -4,88 +4,63 @@ use crate::domain::dtos::{
     placement_response::PlacementStatus::*, telemetry_code::TelemetryCode,
 };
 
-use mycelium_base::{
-    dtos::UntaggedParent,
-    utils::errors::{use_case_err, MappedErrors},
-};
+use mycelium_base::{dtos::UntaggedParent, utils::errors::MappedErrors};
 use tracing::trace;
 
-pub(super) fn update_introspection_node(
+/// Update the introspection node with the accepted clade proposal.
+///
+/// The accepted clade and its children are borrowed from the tree. The clade
+/// is only cloned into the adherence test when a conclusive identity is
+/// found.
+pub(super) fn update_introspection_node<'a>(
     adherence: &AdherenceTest,
-) -> Result<IntrospectionUpdateResponse, MappedErrors> {
+    clade: &'a Clade,
+) -> Result<IntrospectionUpdateResponse<'a>, MappedErrors> {
     //
     // 🍁 clade update
     //
-    let parent = match adherence.clade.to_owned() {
-        UntaggedParent::Record(record) => record,
-        UntaggedParent::Id(_) => {
-            return use_case_err(
-                "The adherence test does not contain a clade record.",
-            )
-            .as_error();
-        }
-    };
+    let parent = clade;
 
     //
     // 🌿 1st children update
     //
-    let children = match parent.to_owned().children {
-        Some(children) => {
-            let non_leaf_children = children
-                .iter()
-                .filter_map(|record| {
-                    if record.is_leaf() {
-                        return None;
-                    }
-
-                    Some(record.to_owned())
-                })
-                .collect::<Vec<Clade>>();
-
-            //
-            // ✅ Case no children clades exits, the search loop is
-            // finished with a conclusive identity.
-            //
-            if non_leaf_children.is_empty() {
-                trace!(
-                    code = TelemetryCode::UCPLACE0016.to_string(),
-                    "Conclusive identity found at clade {clade_id}",
-                    clade_id = parent.id
-                );
-
-                return Ok(IntrospectionUpdateResponse::Return(IdentityFound(
-                    adherence.to_owned(),
-                )));
-            }
+    let non_leaf_children = parent
+        .children
+        .iter()
+        .flatten()
+        .filter(|record| !record.is_leaf())
+        .collect::<Vec<&Clade>>();
 
-            //
-            // 🟢 Case the clade contain children ones, the search loop
-            // continues.
-            //
-            trace!(
-                code = TelemetryCode::UCPLACE0017.to_string(),
-                "One proposal found. Clade {parent} selected",
-                parent = parent.id
-            );
+    //
+    // ✅ Case no children clades exits, the search loop is finished with a
+    // conclusive identity.
+    //
+    if non_leaf_children.is_empty() {
+        trace!(
+            code = TelemetryCode::UCPLACE0016.to_string(),
+            "Conclusive identity found at clade {clade_id}",
+            clade_id = parent.id
+        );
 
-            non_leaf_children
-        }
-        None => {
-            //
-            // ✅ Case no children clades exits, the search loop is
-            // finished with a conclusive identity.
-            //
-            trace!(
-                code = TelemetryCode::UCPLACE0016.to_string(),
-                "Conclusive identity found at clade {clade_id}",
-                clade_id = parent.id
-            );
+        return Ok(IntrospectionUpdateResponse::Return(IdentityFound(
+            AdherenceTest {
+                clade: UntaggedParent::Record(parent.to_owned()),
+                ..adherence.to_owned()
+            },
+        )));
+    }
 
-            return Ok(IntrospectionUpdateResponse::Return(IdentityFound(
-                adherence.to_owned(),
-            )));
-        }
-    };
+    //
+    // 🟢 Case the clade contain children ones, the search loop continues.
+    //
+    trace!(
+        code = TelemetryCode::UCPLACE0017.to_string(),
+        "One proposal found. Clade {parent} selected",
+        parent = parent.id
+    );
 
-    Ok(IntrospectionUpdateResponse::Continue(parent, children))
+    Ok(IntrospectionUpdateResponse::Continue(
+        parent,
+        non_leaf_children,
+    ))
 }
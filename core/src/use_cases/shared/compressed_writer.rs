@@ -0,0 +1,255 @@
+use flate2::{write::GzEncoder, Compression};
+use mycelium_base::utils::errors::{execution_err, MappedErrors};
+use std::{
+    fs::{rename, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// The compression of an output file, detected from the file suffix
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum OutputCompression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl OutputCompression {
+    pub(crate) fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|i| i.to_str()) {
+            Some("gz") => OutputCompression::Gzip,
+            Some("zst") => OutputCompression::Zstd,
+            _ => OutputCompression::None,
+        }
+    }
+
+    pub(crate) fn extension(&self) -> Option<&'static str> {
+        match self {
+            OutputCompression::None => None,
+            OutputCompression::Gzip => Some("gz"),
+            OutputCompression::Zstd => Some("zst"),
+        }
+    }
+
+    /// Remove the compression suffix from the path, if any
+    pub(crate) fn strip_extension(&self, path: &Path) -> PathBuf {
+        let mut path = path.to_path_buf();
+
+        if self.extension().is_some() {
+            path.set_extension("");
+        }
+
+        path
+    }
+
+    /// Append the compression suffix to the path, if any
+    pub(crate) fn append_extension(&self, path: &Path) -> PathBuf {
+        match self.extension() {
+            None => path.to_path_buf(),
+            Some(extension) => {
+                let mut path = path.as_os_str().to_owned();
+                path.push(format!(".{extension}"));
+                PathBuf::from(path)
+            }
+        }
+    }
+}
+
+enum Sink {
+    Plain(File),
+    Gzip(GzEncoder<File>),
+    Zstd(zstd::Encoder<'static, File>),
+}
+
+/// A writer shared by the placement threads
+///
+/// Compressed streams could not be reopened per record, then all records are
+/// written through a single persistent encoder. Call `finish` to flush the
+/// encoder after the last record.
+pub(crate) struct CompressedWriter {
+    sink: Mutex<Sink>,
+
+    /// The temporary and the final paths of staged outputs
+    staged: Option<(PathBuf, PathBuf)>,
+}
+
+impl CompressedWriter {
+    /// Open the file appending records to it
+    pub(crate) fn open(
+        path: &Path,
+        compression: &OutputCompression,
+    ) -> Result<Self, MappedErrors> {
+        Self::from_file(
+            OpenOptions::new().create(true).append(true).open(path),
+            path,
+            compression,
+            None,
+        )
+    }
+
+    /// Open a temporary file renamed to `path` on `finish`
+    ///
+    /// The output only appears once all records are written, then interrupted
+    /// runs never leave a partial file looking complete. Temporary files of
+    /// previous interrupted runs are truncated.
+    pub(crate) fn open_staged(
+        path: &Path,
+        compression: &OutputCompression,
+    ) -> Result<Self, MappedErrors> {
+        let temp_path = Self::staged_path(path);
+
+        Self::from_file(
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&temp_path),
+            &temp_path,
+            compression,
+            Some((temp_path.to_owned(), path.to_path_buf())),
+        )
+    }
+
+    /// The temporary file of outputs staged at `path`
+    ///
+    /// The file is left behind by staged runs interrupted before finishing.
+    pub(crate) fn staged_path(path: &Path) -> PathBuf {
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".partial");
+        PathBuf::from(temp_path)
+    }
+
+    fn from_file(
+        file: std::io::Result<File>,
+        path: &Path,
+        compression: &OutputCompression,
+        staged: Option<(PathBuf, PathBuf)>,
+    ) -> Result<Self, MappedErrors> {
+        let file = match file {
+            Ok(file) => file,
+            Err(err) => {
+                return execution_err(format!(
+                    "Unable to open file {path:?}: {err}"
+                ))
+                .as_error()
+            }
+        };
+
+        let sink = match compression {
+            OutputCompression::None => Sink::Plain(file),
+            OutputCompression::Gzip => {
+                Sink::Gzip(GzEncoder::new(file, Compression::default()))
+            }
+            OutputCompression::Zstd => match zstd::Encoder::new(file, 0) {
+                Ok(encoder) => Sink::Zstd(encoder),
+                Err(err) => {
+                    return execution_err(format!(
+                        "Unable to create the zstd encoder: {err}"
+                    ))
+                    .as_error()
+                }
+            },
+        };
+
+        Ok(CompressedWriter {
+            sink: Mutex::new(sink),
+            staged,
+        })
+    }
+
+    pub(crate) fn write(&self, content: String) -> Result<(), MappedErrors> {
+        let mut sink = match self.sink.lock() {
+            Ok(sink) => sink,
+            Err(err) => {
+                return execution_err(format!("Unable to lock writer: {err}"))
+                    .as_error()
+            }
+        };
+
+        let result = match &mut *sink {
+            Sink::Plain(file) => file.write_all(content.as_bytes()),
+            Sink::Gzip(encoder) => encoder.write_all(content.as_bytes()),
+            Sink::Zstd(encoder) => encoder.write_all(content.as_bytes()),
+        };
+
+        if let Err(err) = result {
+            return execution_err(format!(
+                "Unexpected error detected on write file: {err}"
+            ))
+            .as_error();
+        }
+
+        Ok(())
+    }
+
+    /// Flush the encoder, writing the compressed stream trailer
+    ///
+    /// Staged outputs are moved to the final path, replacing existing files.
+    pub(crate) fn finish(self) -> Result<(), MappedErrors> {
+        let sink = match self.sink.into_inner() {
+            Ok(sink) => sink,
+            Err(err) => {
+                return execution_err(format!("Unable to lock writer: {err}"))
+                    .as_error()
+            }
+        };
+
+        let result = match sink {
+            Sink::Plain(mut file) => file.flush(),
+            Sink::Gzip(encoder) => encoder.finish().map(|_| ()),
+            Sink::Zstd(encoder) => encoder.finish().map(|_| ()),
+        };
+
+        if let Err(err) = result {
+            return execution_err(format!(
+                "Unexpected error detected on finish file: {err}"
+            ))
+            .as_error();
+        }
+
+        if let Some((temp_path, path)) = self.staged {
+            if let Err(err) = rename(&temp_path, &path) {
+                return execution_err(format!(
+                    "Unable to move {temp_path:?} to {path:?}: {err}"
+                ))
+                .as_error();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staged_writer_only_produces_finished_outputs() {
+        let path = PathBuf::from("/tmp/cls-staged-writer.jsonl");
+        let _ = std::fs::remove_file(&path);
+
+        //
+        // Dropping the writer before finishing simulates an interrupted run
+        //
+        let writer =
+            CompressedWriter::open_staged(&path, &OutputCompression::None)
+                .unwrap();
+
+        writer.write("{}\n".to_string()).unwrap();
+        drop(writer);
+
+        assert!(!path.exists());
+
+        let writer =
+            CompressedWriter::open_staged(&path, &OutputCompression::None)
+                .unwrap();
+
+        writer.write("{}\n".to_string()).unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}\n");
+        assert!(!CompressedWriter::staged_path(&path).exists());
+    }
+}
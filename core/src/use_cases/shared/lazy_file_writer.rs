@@ -0,0 +1,83 @@
+use mycelium_base::utils::errors::{execution_err, MappedErrors};
+use std::{
+    fs::{remove_file, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// A writer appending to a file opened on the first write
+///
+/// The file is not created if nothing is written. Call `finish` after the
+/// last write to remove the file if it is empty (e.g. left by a previous run).
+pub(crate) struct LazyFileWriter {
+    path: PathBuf,
+    file: Mutex<Option<File>>,
+}
+
+impl LazyFileWriter {
+    pub(crate) fn new(path: &Path) -> Self {
+        LazyFileWriter {
+            path: path.to_path_buf(),
+            file: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn write(&self, content: String) -> Result<(), MappedErrors> {
+        let mut file = match self.file.lock() {
+            Ok(file) => file,
+            Err(err) => {
+                return execution_err(format!("Unable to lock writer: {err}"))
+                    .as_error()
+            }
+        };
+
+        if file.is_none() {
+            match OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+            {
+                Ok(opened) => *file = Some(opened),
+                Err(err) => {
+                    return execution_err(format!(
+                        "Unable to open file {:?}: {err}",
+                        self.path
+                    ))
+                    .as_error()
+                }
+            }
+        }
+
+        if let Some(Err(err)) =
+            file.as_mut().map(|file| file.write_all(content.as_bytes()))
+        {
+            return execution_err(format!(
+                "Unexpected error detected on write file: {err}"
+            ))
+            .as_error();
+        }
+
+        Ok(())
+    }
+
+    /// Close the file, removing it if empty
+    pub(crate) fn finish(self) -> Result<(), MappedErrors> {
+        drop(self.file);
+
+        match self.path.metadata() {
+            Ok(metadata) if metadata.len() == 0 => {
+                if let Err(err) = remove_file(&self.path) {
+                    return execution_err(format!(
+                        "Unable to remove empty file {:?}: {err}",
+                        self.path
+                    ))
+                    .as_error();
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
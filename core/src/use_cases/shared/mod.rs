@@ -1 +1,7 @@
+pub(in crate::use_cases) mod compressed_writer;
+pub(in crate::use_cases) mod lazy_file_writer;
+pub(in crate::use_cases) mod partitioned_writer;
+#[cfg(feature = "sqlite")]
+pub(in crate::use_cases) mod sqlite_writer;
+pub(in crate::use_cases) mod vector_writer;
 pub(in crate::use_cases) mod write_or_append_to_file;
@@ -0,0 +1,153 @@
+use super::compressed_writer::{CompressedWriter, OutputCompression};
+
+use mycelium_base::utils::errors::{execution_err, MappedErrors};
+use std::{
+    collections::HashMap,
+    fs::create_dir_all,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// A writer partitioning records into files of an output directory
+///
+/// Partition files are opened when the first record of the partition is
+/// written, then only non-empty partitions are created. Call `finish` to flush
+/// all partition files after the last record.
+pub(crate) struct PartitionedWriter {
+    dir: PathBuf,
+    extension: String,
+    compression: OutputCompression,
+    writers: Mutex<HashMap<String, Arc<CompressedWriter>>>,
+}
+
+impl PartitionedWriter {
+    /// Create the output directory of the partition files
+    ///
+    /// The `extension` is appended to the partition names, including the
+    /// compression suffix (e.g. `jsonl.gz`).
+    pub(crate) fn open(
+        dir: &Path,
+        extension: &str,
+        compression: &OutputCompression,
+    ) -> Result<Self, MappedErrors> {
+        if let Err(err) = create_dir_all(dir) {
+            return execution_err(format!(
+                "Unable to create the output directory {dir:?}: {err}"
+            ))
+            .as_error();
+        }
+
+        Ok(PartitionedWriter {
+            dir: dir.to_path_buf(),
+            extension: extension.to_string(),
+            compression: compression.to_owned(),
+            writers: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The path of the file containing the records of the partition
+    pub(crate) fn partition_path(&self, partition: &str) -> PathBuf {
+        self.dir.join(format!(
+            "{name}.{extension}",
+            name = sanitize_partition_name(partition),
+            extension = self.extension
+        ))
+    }
+
+    pub(crate) fn write(
+        &self,
+        partition: &str,
+        content: String,
+    ) -> Result<(), MappedErrors> {
+        //
+        // The map is locked only to get the partition writer, then records of
+        // distinct partitions are written concurrently.
+        //
+        let writer = {
+            let mut writers = match self.writers.lock() {
+                Ok(writers) => writers,
+                Err(err) => {
+                    return execution_err(format!(
+                        "Unable to lock writer: {err}"
+                    ))
+                    .as_error()
+                }
+            };
+
+            match writers.get(partition) {
+                Some(writer) => writer.clone(),
+                None => {
+                    let writer = Arc::new(CompressedWriter::open(
+                        self.partition_path(partition).as_path(),
+                        &self.compression,
+                    )?);
+
+                    writers.insert(partition.to_string(), writer.clone());
+                    writer
+                }
+            }
+        };
+
+        writer.write(content)
+    }
+
+    /// Flush the encoders of all partition files
+    pub(crate) fn finish(self) -> Result<(), MappedErrors> {
+        let writers = match self.writers.into_inner() {
+            Ok(writers) => writers,
+            Err(err) => {
+                return execution_err(format!("Unable to lock writer: {err}"))
+                    .as_error()
+            }
+        };
+
+        for (partition, writer) in writers {
+            match Arc::try_unwrap(writer) {
+                Ok(writer) => writer.finish()?,
+                Err(_) => {
+                    return execution_err(format!(
+                        "The writer of partition {partition} is still in use"
+                    ))
+                    .as_error()
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Replace the characters not allowed in file names
+///
+/// Only ASCII alphanumeric characters, dashes, underscores and dots are kept.
+/// Leading dots are also replaced, avoiding hidden files and relative paths.
+pub(crate) fn sanitize_partition_name(partition: &str) -> String {
+    let name = partition
+        .chars()
+        .enumerate()
+        .map(|(index, char)| match char {
+            '.' if index == 0 => '_',
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => char,
+            _ => '_',
+        })
+        .collect::<String>();
+
+    match name.is_empty() {
+        true => "_".to_string(),
+        false => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_partition_name() {
+        assert_eq!(sanitize_partition_name("IdentityFound"), "IdentityFound");
+        assert_eq!(sanitize_partition_name("clade-42"), "clade-42");
+        assert_eq!(sanitize_partition_name("../etc/passwd"), "_._etc_passwd");
+        assert_eq!(sanitize_partition_name("a b:c"), "a_b_c");
+        assert_eq!(sanitize_partition_name(""), "_");
+    }
+}
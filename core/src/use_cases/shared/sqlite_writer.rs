@@ -0,0 +1,229 @@
+use crate::domain::dtos::{
+    annotation::Tag,
+    file_or_stdin::FileOrStdin,
+    placement_response::{PlacementResponse, PlacementStatus},
+};
+
+use mycelium_base::utils::errors::{execution_err, MappedErrors};
+use rusqlite::{params, Connection, Transaction};
+use std::{
+    path::Path,
+    sync::mpsc::{sync_channel, SyncSender},
+    thread::{self, JoinHandle},
+};
+
+/// The normalized schema of the placement database
+///
+/// Each query is a row of `queries`, referenced by the placed clades (one row
+/// per clade of inconclusive placements) and by the annotations of the placed
+/// clade ancestors.
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS queries (
+        id INTEGER PRIMARY KEY,
+        query TEXT NOT NULL,
+        status TEXT NOT NULL,
+        code TEXT NOT NULL,
+        lineage TEXT,
+        depth INTEGER,
+        duplicate INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS placements (
+        query_id INTEGER NOT NULL REFERENCES queries (id),
+        clade_id INTEGER NOT NULL,
+        one INTEGER,
+        rest INTEGER
+    );
+
+    CREATE TABLE IF NOT EXISTS annotations (
+        query_id INTEGER NOT NULL REFERENCES queries (id),
+        clade_id INTEGER NOT NULL,
+        taxid INTEGER,
+        sci_name TEXT,
+        rank TEXT
+    );
+
+    CREATE INDEX IF NOT EXISTS annotations_rank
+        ON annotations (rank, sci_name);
+";
+
+type Response = PlacementResponse<PlacementStatus>;
+
+/// A SQLite writer shared by the placement threads
+///
+/// SQLite connections do not support concurrent writes, then responses are
+/// sent through a channel to a single writer thread. Call `finish` to commit
+/// the last responses.
+pub(crate) struct SqliteWriter {
+    sender: SyncSender<Response>,
+    handle: JoinHandle<Result<(), MappedErrors>>,
+}
+
+impl SqliteWriter {
+    /// The number of responses committed per transaction
+    const BATCH_SIZE: usize = 1000;
+
+    pub(crate) fn open(path: &Path) -> Result<Self, MappedErrors> {
+        let mut connection = match Connection::open(path) {
+            Ok(connection) => connection,
+            Err(err) => {
+                return execution_err(format!(
+                    "Unable to open the SQLite database {path:?}: {err}"
+                ))
+                .as_error()
+            }
+        };
+
+        if let Err(err) = connection.execute_batch(SCHEMA) {
+            return execution_err(format!(
+                "Unable to create the SQLite schema: {err}"
+            ))
+            .as_error();
+        }
+
+        let (sender, receiver) =
+            sync_channel::<Response>(FileOrStdin::CHANNEL_BOUND);
+
+        let handle = thread::spawn(move || {
+            let mut batch = Vec::with_capacity(Self::BATCH_SIZE);
+
+            for response in receiver {
+                batch.push(response);
+
+                if batch.len() == Self::BATCH_SIZE {
+                    write_batch(&mut connection, batch.drain(..))?;
+                }
+            }
+
+            write_batch(&mut connection, batch.drain(..))
+        });
+
+        Ok(SqliteWriter { sender, handle })
+    }
+
+    pub(crate) fn write(&self, response: Response) -> Result<(), MappedErrors> {
+        if let Err(err) = self.sender.send(response) {
+            return execution_err(format!(
+                "The SQLite writer stopped before the last response: {err}"
+            ))
+            .as_error();
+        }
+
+        Ok(())
+    }
+
+    /// Wait for the writer thread to commit the pending responses
+    pub(crate) fn finish(self) -> Result<(), MappedErrors> {
+        drop(self.sender);
+
+        match self.handle.join() {
+            Ok(result) => result,
+            Err(_) => {
+                execution_err("The SQLite writer thread panicked").as_error()
+            }
+        }
+    }
+}
+
+/// Write a batch of responses in a single transaction
+fn write_batch(
+    connection: &mut Connection,
+    responses: impl Iterator<Item = Response>,
+) -> Result<(), MappedErrors> {
+    let transaction = match connection.transaction() {
+        Ok(transaction) => transaction,
+        Err(err) => {
+            return execution_err(format!(
+                "Unable to start a SQLite transaction: {err}"
+            ))
+            .as_error()
+        }
+    };
+
+    for response in responses {
+        if let Err(err) = insert_response(&transaction, &response) {
+            return execution_err(format!(
+                "Unable to write the query {query} to SQLite: {err}",
+                query = response.query()
+            ))
+            .as_error();
+        }
+    }
+
+    if let Err(err) = transaction.commit() {
+        return execution_err(format!(
+            "Unable to commit the SQLite transaction: {err}"
+        ))
+        .as_error();
+    }
+
+    Ok(())
+}
+
+/// Insert the rows of a single response
+fn insert_response(
+    transaction: &Transaction,
+    response: &Response,
+) -> rusqlite::Result<()> {
+    transaction.execute(
+        "INSERT INTO queries (query, status, code, lineage, depth, duplicate)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            response.query(),
+            response.code().split(':').next().unwrap_or_default(),
+            response.code(),
+            response.lineage(),
+            response.depth().map(|depth| depth as i64),
+            response.is_duplicate(),
+        ],
+    )?;
+
+    let query_id = transaction.last_insert_rowid();
+
+    let placements = match response.placement() {
+        None | Some(PlacementStatus::Unclassifiable(_)) => vec![],
+        Some(PlacementStatus::IdentityFound(test)) => {
+            vec![(test.clade_id(), Some(test.one), Some(test.rest))]
+        }
+        Some(PlacementStatus::MaxResolutionReached(clade, _)) => {
+            vec![(*clade, None, None)]
+        }
+        Some(PlacementStatus::Inconclusive(tests, _)) => tests
+            .iter()
+            .map(|test| (test.clade_id(), Some(test.one), Some(test.rest)))
+            .collect(),
+    };
+
+    for (clade, one, rest) in placements {
+        transaction.execute(
+            "INSERT INTO placements (query_id, clade_id, one, rest)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![query_id, clade as i64, one, rest],
+        )?;
+    }
+
+    for annotation in response.annotations().into_iter().flatten() {
+        let tags = annotation.meta.iter().flatten();
+
+        transaction.execute(
+            "INSERT INTO annotations
+                (query_id, clade_id, taxid, sci_name, rank)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                query_id,
+                annotation.clade,
+                tags.clone().find_map(|tag| match tag {
+                    Tag::Taxid(taxid) => Some(*taxid),
+                    _ => None,
+                }),
+                annotation.sci_name(),
+                tags.clone().find_map(|tag| match tag {
+                    Tag::Rank(rank) => Some(rank.to_lowercase()),
+                    _ => None,
+                }),
+            ],
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,526 @@
+use crate::domain::dtos::{
+    clade::{Clade, NodeType},
+    file_or_stdin::FileOrStdin,
+    kmers_map::KmersMap,
+    msa_columns::MsaColumns,
+    tree::Tree,
+};
+
+use mycelium_base::utils::errors::{use_case_err, MappedErrors};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::mpsc::sync_channel,
+    thread,
+};
+use tracing::{debug, info};
+
+/// Add new reference sequences to an existing database
+///
+/// The updated tree should contain all leaves of the database and the added
+/// ones, while the MSA should contain the rows of the added leaves only,
+/// aligned to the columns of the original MSA. Each added leaf is attached to
+/// the most recent common ancestor of its sister leaves already present in
+/// the database:
+///
+/// - If the sister leaves form a single clade of the updated tree, a new
+///   internal node is inserted above their common ancestor, grouping the
+///   ancestor and the added leaf.
+/// - Otherwise (e.g. a polytomy), the added leaf becomes a new child of the
+///   common ancestor.
+///
+/// New clades receive ids after the greatest id of the database, then the ids
+/// of existing clades, their kmers and annotations are kept. Topology changes
+/// of existing clades are not applied, then a full rebuild is recommended
+/// after major tree updates. Columns skipped by the maximum gap fraction
+/// during the original build are not known, then the full rows are indexed.
+///
+/// # Errors
+///
+/// Updates are rejected if the database does not contain a kmers map, if the
+/// updated tree lacks any database leaf or adds no leaves, or if the MSA rows
+/// do not match the added leaves.
+///
+#[tracing::instrument(name = "Updating Classeq database", skip(tree, msa))]
+pub fn update_database(
+    mut tree: Tree,
+    tree_path: PathBuf,
+    msa: FileOrStdin,
+) -> Result<Tree, MappedErrors> {
+    // ? -----------------------------------------------------------------------
+    // ? Validate the updated tree
+    // ? -----------------------------------------------------------------------
+
+    let mut map = match tree.kmers_map.take() {
+        Some(map) => map,
+        None => {
+            return use_case_err("The database does not contain a kmers map")
+                .as_error()
+        }
+    };
+
+    if !tree_path.exists() {
+        return use_case_err(format!(
+            "The tree file does not exist: {tree_path:?}"
+        ))
+        .as_error();
+    }
+
+    let updated_tree =
+        Tree::init_from_file(&tree_path, tree.min_branch_support)?;
+
+    let mut present = tree
+        .root
+        .iter()
+        .filter(|clade| clade.is_leaf())
+        .filter_map(|leaf| leaf.name.to_owned())
+        .collect::<HashSet<String>>();
+
+    let updated_leaves = updated_tree.root.get_leaves_with_paths(None);
+
+    let updated_names = updated_leaves
+        .iter()
+        .filter_map(|(leaf, _)| leaf.name.as_deref())
+        .collect::<HashSet<&str>>();
+
+    let mut missing = present
+        .iter()
+        .filter(|name| !updated_names.contains(name.as_str()))
+        .cloned()
+        .collect::<Vec<String>>();
+
+    if !missing.is_empty() {
+        missing.sort();
+
+        return use_case_err(format!(
+            "The updated tree should contain all database leaves, but \
+            {count} are missing: {leaves}",
+            count = missing.len(),
+            leaves = missing.join(", ")
+        ))
+        .as_error();
+    }
+
+    let added = updated_leaves
+        .iter()
+        .filter_map(|(leaf, path)| {
+            leaf.name
+                .as_ref()
+                .filter(|name| !present.contains(*name))
+                .map(|name| (name.to_owned(), leaf.length, path))
+        })
+        .collect::<Vec<_>>();
+
+    if added.is_empty() {
+        return use_case_err("The updated tree does not add any leaf")
+            .as_error();
+    }
+
+    // ? -----------------------------------------------------------------------
+    // ? Read the MSA rows of the added leaves
+    // ? -----------------------------------------------------------------------
+
+    let sequences = read_added_rows(msa, &map)?;
+
+    let mut unexpected = sequences
+        .keys()
+        .filter(|header| !added.iter().any(|(name, _, _)| name == *header))
+        .cloned()
+        .collect::<Vec<String>>();
+
+    if !unexpected.is_empty() {
+        unexpected.sort();
+
+        return use_case_err(format!(
+            "The MSA headers should match the added leaves, but {count} \
+            records do not: {headers}",
+            count = unexpected.len(),
+            headers = unexpected.join(", ")
+        ))
+        .as_error();
+    }
+
+    if let Some((name, _, _)) = added
+        .iter()
+        .find(|(name, _, _)| !sequences.contains_key(name))
+    {
+        return use_case_err(format!(
+            "The MSA does not contain the added leaf: {name}"
+        ))
+        .as_error();
+    }
+
+    // ? -----------------------------------------------------------------------
+    // ? Insert the added leaves and index their kmers
+    // ? -----------------------------------------------------------------------
+
+    let mut next_id = tree.root.max_id() + 1;
+
+    for (name, length, path) in added.iter() {
+        let (sisters, is_sister_clade) =
+            get_sister_leaves(&updated_tree, path, &present);
+
+        let leaf_id = insert_leaf(
+            &mut tree.root,
+            &mut map,
+            &sisters,
+            is_sister_clade,
+            Clade {
+                id: 0,
+                parent: None,
+                kind: NodeType::Leaf,
+                name: Some(name.to_owned()),
+                support: None,
+                length: *length,
+                children: None,
+            },
+            &mut next_id,
+        )?;
+
+        let leaf_path = match tree.root.get_node_by_id(leaf_id) {
+            Some(leaf) => leaf.get_path_to_root(&tree.root),
+            None => {
+                return use_case_err(format!(
+                    "The added leaf {name} was not inserted"
+                ))
+                .as_error()
+            }
+        };
+
+        debug!("Leaf {name} inserted as clade {leaf_id}");
+
+        for (kmer, hash) in
+            map.build_kmer_from_string(sequences[name].to_owned(), None)
+        {
+            map.insert_or_append_kmer_hash(kmer, hash, leaf_path.to_owned());
+        }
+
+        present.insert(name.to_owned());
+    }
+
+    info!("{count} leaves added to the database", count = added.len());
+
+    // ? -----------------------------------------------------------------------
+    // ? Return a positive response
+    // ? -----------------------------------------------------------------------
+
+    tree.kmers_map = Some(map);
+    tree.clear_clade_sizes();
+    tree.update_in_memory_size();
+
+    Ok(tree)
+}
+
+/// Read the MSA rows, restricted to the window of the database
+fn read_added_rows(
+    msa: FileOrStdin,
+    map: &KmersMap,
+) -> Result<HashMap<String, String>, MappedErrors> {
+    let (sender, receiver) = sync_channel(FileOrStdin::CHANNEL_BOUND);
+
+    let window = map.get_msa_window().cloned();
+
+    let reader = thread::spawn(move || match window {
+        None => msa.sequence_content_by_channel(sender),
+        Some(window) => msa.msa_content_by_channel(
+            sender,
+            &MsaColumns::default().with_window(Some(window)),
+        ),
+    });
+
+    let mut sequences = HashMap::<String, String>::new();
+    let mut duplicated_headers = Vec::<String>::new();
+
+    for sequence in receiver {
+        let header = sequence.header_content().to_string();
+
+        if sequences.contains_key(&header) {
+            duplicated_headers.push(header);
+            continue;
+        }
+
+        sequences.insert(header, sequence.sequence_content().to_string());
+    }
+
+    match reader.join() {
+        Ok(Ok(_)) => (),
+        Ok(Err(err)) => {
+            return use_case_err(format!("The MSA could not be read: {err}"))
+                .as_error()
+        }
+        Err(_) => {
+            return use_case_err("The MSA reader thread panicked").as_error()
+        }
+    };
+
+    if !duplicated_headers.is_empty() {
+        return use_case_err(format!(
+            "The MSA contains duplicated headers: {headers}",
+            headers = duplicated_headers.join(", ")
+        ))
+        .as_error();
+    }
+
+    Ok(sequences)
+}
+
+/// Get the present leaves closest to an added leaf of the updated tree
+///
+/// Ancestors of the added leaf are visited from the leaf to the root, and the
+/// present leaves under the first ancestor containing any are returned. The
+/// flag is true if all of them descend from a single child of the ancestor,
+/// then they form the sister clade of the added leaf.
+fn get_sister_leaves(
+    updated_tree: &Tree,
+    path: &[u64],
+    present: &HashSet<String>,
+) -> (HashSet<String>, bool) {
+    let present_under = |clade: u64| {
+        updated_tree
+            .leaves_under(clade)
+            .into_iter()
+            .filter(|name| present.contains(name))
+            .collect::<HashSet<String>>()
+    };
+
+    for ancestor in path.iter().rev().skip(1) {
+        let sisters = present_under(*ancestor);
+
+        if sisters.is_empty() {
+            continue;
+        }
+
+        let is_sister_clade = updated_tree
+            .root
+            .children_of(*ancestor)
+            .iter()
+            .any(|child| present_under(child.id) == sisters);
+
+        return (sisters, is_sister_clade);
+    }
+
+    (HashSet::new(), false)
+}
+
+/// Insert a leaf next to the common ancestor of the sister leaves
+///
+/// Returns the id given to the inserted leaf.
+fn insert_leaf(
+    root: &mut Clade,
+    map: &mut KmersMap,
+    sisters: &HashSet<String>,
+    is_sister_clade: bool,
+    mut leaf: Clade,
+    next_id: &mut u64,
+) -> Result<u64, MappedErrors> {
+    //
+    // The common ancestor is the last clade shared by the root paths of all
+    // sister leaves
+    //
+    let mut paths = root
+        .get_leaves_with_paths(None)
+        .into_iter()
+        .filter(|(clade, _)| {
+            clade
+                .name
+                .as_ref()
+                .is_some_and(|name| sisters.contains(name))
+        })
+        .map(|(_, path)| path);
+
+    let ancestor = match paths.next() {
+        None => root.id,
+        Some(first) => {
+            let shared = paths.fold(first.len(), |shared, path| {
+                first
+                    .iter()
+                    .zip(path.iter())
+                    .take(shared)
+                    .take_while(|(a, b)| a == b)
+                    .count()
+            });
+
+            first[shared - 1]
+        }
+    };
+
+    leaf.id = *next_id;
+    *next_id += 1;
+
+    let leaf_id = leaf.id;
+
+    //
+    // A new internal node replaces the ancestor in its parent children,
+    // grouping the ancestor and the added leaf
+    //
+    if let Some(parent_id) = root
+        .get_node_by_id(ancestor)
+        .and_then(|clade| clade.parent)
+        .filter(|_| is_sister_clade)
+    {
+        let node_id = *next_id;
+        *next_id += 1;
+
+        let parent = match root.get_node_by_id_mut(parent_id) {
+            Some(parent) => parent,
+            None => {
+                return use_case_err(format!(
+                    "The parent clade {parent_id} was not found"
+                ))
+                .as_error()
+            }
+        };
+
+        let children = parent.children.get_or_insert_with(Vec::new);
+
+        let index = match children.iter().position(|i| i.id == ancestor) {
+            Some(index) => index,
+            None => {
+                return use_case_err(format!(
+                    "The clade {ancestor} is not a child of {parent_id}"
+                ))
+                .as_error()
+            }
+        };
+
+        let mut sister = children.remove(index);
+        sister.parent = Some(node_id);
+        leaf.parent = Some(node_id);
+
+        children.insert(
+            index,
+            Clade {
+                id: node_id,
+                parent: Some(parent_id),
+                kind: NodeType::Node,
+                name: None,
+                support: None,
+                length: None,
+                children: Some(vec![sister, leaf]),
+            },
+        );
+
+        map.add_ancestor(ancestor, node_id);
+
+        return Ok(leaf_id);
+    }
+
+    let clade = match root.get_node_by_id_mut(ancestor) {
+        Some(clade) => clade,
+        None => {
+            return use_case_err(format!("The clade {ancestor} was not found"))
+                .as_error()
+        }
+    };
+
+    leaf.parent = Some(clade.id);
+    clade.children.get_or_insert_with(Vec::new).push(leaf);
+
+    Ok(leaf_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::dtos::{
+            build_options::BuildOptions, placement_options::PlacementOptions,
+        },
+        use_cases::{map_kmers_to_tree, place_one},
+    };
+
+    #[test]
+    fn test_update_database_recovers_the_added_leaf() {
+        let tree_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk");
+        let msa_path = PathBuf::from("src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta");
+
+        let added = "Col_orchidophilum_BJ103_2";
+
+        //
+        // The database is built without the added leaf, which is the first
+        // record of the MSA
+        //
+        let base_tree_path = PathBuf::from("/tmp/cls-update-base.nwk");
+        let base_msa_path = PathBuf::from("/tmp/cls-update-base.fasta");
+        let added_msa_path = PathBuf::from("/tmp/cls-update-added.fasta");
+
+        let newick = std::fs::read_to_string(&tree_path).unwrap();
+        let msa = std::fs::read_to_string(&msa_path).unwrap();
+
+        let leaf_label = format!("{added}:0.0213821,");
+        assert!(newick.contains(&leaf_label));
+        std::fs::write(&base_tree_path, newick.replace(&leaf_label, ""))
+            .unwrap();
+
+        let records = msa.split_inclusive('>').collect::<Vec<_>>();
+        assert!(records[1].starts_with(added));
+
+        std::fs::write(
+            &added_msa_path,
+            format!(">{}", records[1].trim_end_matches('>')),
+        )
+        .unwrap();
+        std::fs::write(&base_msa_path, format!(">{}", records[2..].concat()))
+            .unwrap();
+
+        let base = map_kmers_to_tree(
+            base_tree_path,
+            FileOrStdin::from_file(base_msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let base_max_id = base.root.max_id();
+        let base_leaves = base.root.count_leaves();
+
+        let updated = update_database(
+            base,
+            tree_path.to_owned(),
+            FileOrStdin::from_file(added_msa_path.to_str().unwrap()),
+        )
+        .unwrap();
+
+        assert_eq!(updated.root.count_leaves(), base_leaves + 1);
+
+        let leaf = updated
+            .root
+            .iter()
+            .find(|clade| clade.name.as_deref() == Some(added))
+            .unwrap();
+
+        assert!(leaf.id > base_max_id);
+
+        //
+        // The added sequence is placed back to the added leaf
+        //
+        let sequence = records[1]
+            .lines()
+            .skip(1)
+            .collect::<String>()
+            .replace(['-', '>'], "")
+            .to_uppercase();
+
+        let response = place_one(
+            added,
+            &sequence,
+            &updated,
+            &PlacementOptions::default().with_report_leaves(true),
+            &None,
+        )
+        .unwrap();
+
+        assert!(response
+            .leaves()
+            .is_some_and(|leaves| leaves.contains(&added.to_string())));
+
+        //
+        // Updates without added leaves are rejected
+        //
+        assert!(update_database(
+            updated,
+            tree_path,
+            FileOrStdin::from_file(added_msa_path.to_str().unwrap()),
+        )
+        .is_err());
+    }
+}
@@ -1,21 +1,30 @@
-use crate::models::node::Node;
+use crate::models::{
+    api_error::{ApiError, ApiErrorCode},
+    node::{Node, NodesPage},
+};
 
 use actix_files::NamedFile;
 use actix_multipart::Multipart;
-use actix_web::{web, HttpRequest, HttpResponse, Result};
+use actix_web::{http::StatusCode, web, HttpRequest, HttpResponse, Result};
 use classeq_ports_lib::{
-    get_file_by_inode, PlacementConfig, FileSystemConfig, ModelsConfig,
+    get_file_by_inode, FileIdentity, FileSystemConfig, ModelsConfig,
+    PlacementConfig,
 };
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::PathBuf, sync::Mutex};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
 use tokio::io::AsyncWriteExt;
 use tracing::{error, instrument};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 use walkdir::WalkDir;
 
-#[derive(Deserialize, Serialize, Debug)]
-struct DirResponse {
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub(crate) struct DirResponse {
     status: u32,
     msg: Option<String>,
 }
@@ -28,19 +37,7 @@ fn check_directory_existence(
     let ignore_empty_input_dir = ignore_empty_input_dir.unwrap_or(false);
 
     let data = match fs_config.lock() {
-        Err(err) => {
-            error!("{err}");
-
-            return Err(HttpResponse::InternalServerError().json(
-                DirResponse {
-                    status: 404,
-                    msg: Some(
-                        "Unexpected error on try to process work directory"
-                            .to_string(),
-                    ),
-                },
-            ));
-        }
+        Err(err) => return Err(ApiError::internal(err).into()),
         Ok(res) => res,
     };
 
@@ -49,10 +46,11 @@ fn check_directory_existence(
         .join(work_dir_id.to_owned());
 
     if !base_dir.exists() {
-        return Err(HttpResponse::NotFound().json(DirResponse {
-            status: 404,
-            msg: Some("Work directory not exists".to_string()),
-        }));
+        return Err(ApiError::not_found(
+            ApiErrorCode::WorkDirNotFound,
+            "Work directory not exists",
+        )
+        .into());
     }
 
     let target_dir = base_dir.join(data.input_directory.clone());
@@ -66,11 +64,24 @@ fn check_directory_existence(
 
 /// Initialize the work directory
 ///
+#[utoipa::path(
+    post,
+    path = "/wd",
+    tag = "work-directory",
+    responses(
+        (status = 201, description = "The ID of the created work directory"),
+        (status = 500, description = "Internal error", body = ApiError),
+    )
+)]
 #[instrument(name = "Initializing work directory", skip(config))]
 pub(crate) async fn init_wd(
     config: web::Data<Mutex<FileSystemConfig>>,
 ) -> HttpResponse {
-    let data = config.lock().unwrap();
+    let data = match config.lock() {
+        Ok(res) => res,
+        Err(err) => return ApiError::internal(err).into(),
+    };
+
     let path: PathBuf = PathBuf::from(&data.serve_directory);
 
     // TODO:
@@ -82,17 +93,120 @@ pub(crate) async fn init_wd(
     let target_dir = path.join(target_prefix).join(directory_id.to_owned());
 
     if let Err(err) = std::fs::create_dir_all(&target_dir) {
-        return HttpResponse::InternalServerError().body(err.to_string());
+        return ApiError::from(err).into();
     };
 
     HttpResponse::Created()
         .json(HashMap::from([("workDirId".to_string(), directory_id)]))
 }
 
+/// Delete the work directory
+///
+/// Only directories inside the public directory can be deleted. The paths are
+/// canonicalized before the removal, then symbolic links and parent directory
+/// references can not be used to delete anything outside it.
+#[utoipa::path(
+    delete,
+    path = "/wd/{work_dir_id}",
+    tag = "work-directory",
+    params(("work_dir_id" = String, Path, description = "Work directory ID")),
+    responses(
+        (status = 204, description = "The work directory was deleted"),
+        (status = 400, description = "Invalid work directory", body = ApiError),
+        (
+            status = 404,
+            description = "Work directory not found",
+            body = ApiError
+        ),
+    )
+)]
+#[instrument(name = "Deleting work directory", skip(config))]
+pub(crate) async fn delete_wd(
+    work_dir_id: web::Path<String>,
+    config: web::Data<Mutex<FileSystemConfig>>,
+) -> HttpResponse {
+    let public_dir = match config.lock() {
+        Ok(res) => PathBuf::from(&res.serve_directory)
+            .join(res.public_directory.clone()),
+        Err(err) => return ApiError::internal(err).into(),
+    };
+
+    let target_dir = public_dir.join(work_dir_id.into_inner());
+
+    if !target_dir.exists() {
+        return ApiError::not_found(
+            ApiErrorCode::WorkDirNotFound,
+            "Work directory not exists",
+        )
+        .into();
+    }
+
+    let (public_dir, target_dir) =
+        match (public_dir.canonicalize(), target_dir.canonicalize()) {
+            (Ok(public_dir), Ok(target_dir)) => (public_dir, target_dir),
+            (Err(err), _) | (_, Err(err)) => {
+                return ApiError::internal(err).into()
+            }
+        };
+
+    if target_dir == public_dir
+        || !target_dir.starts_with(&public_dir)
+        || !target_dir.is_dir()
+    {
+        return ApiError::bad_request(
+            ApiErrorCode::InvalidWorkDir,
+            "Invalid work directory",
+        )
+        .into();
+    }
+
+    if let Err(err) = std::fs::remove_dir_all(&target_dir) {
+        return ApiError::from(err).into();
+    };
+
+    HttpResponse::NoContent().finish()
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListWdContentArgs {
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    pub name_contains: Option<String>,
+}
+
+/// List the work directory files
+///
+/// Files are sorted by name and optionally filtered by a name substring. The
+/// `limit` and `offset` query parameters select the returned window, and the
+/// total count of matching files is returned together.
+#[utoipa::path(
+    get,
+    path = "/wd/{work_dir_id}",
+    tag = "work-directory",
+    params(
+        ("work_dir_id" = String, Path, description = "Work directory ID"),
+        ListWdContentArgs,
+    ),
+    responses(
+        (
+            status = 200,
+            description = "The work directory files",
+            body = NodesPage
+        ),
+        (status = 204, description = "The work directory has no input files"),
+        (
+            status = 404,
+            description = "Work directory not found",
+            body = ApiError
+        ),
+    )
+)]
 #[instrument(name = "List work dir content", skip(config))]
 pub(crate) async fn list_wd_content(
     work_dir_id: web::Path<String>,
     config: web::Data<Mutex<FileSystemConfig>>,
+    query: web::Query<ListWdContentArgs>,
 ) -> HttpResponse {
     let work_dir_id = work_dir_id.into_inner();
 
@@ -102,34 +216,75 @@ pub(crate) async fn list_wd_content(
             Ok(path) => path,
         };
 
-    let directory_content: Vec<Node> =
-        WalkDir::new(&target_dir.parent().unwrap_or(&target_dir))
-            .contents_first(true)
-            .sort_by_file_name()
-            .into_iter()
-            .filter_map(|entry| entry.ok())
-            .filter(|entry| {
-                entry.path().exists()
-                    && (entry.path().is_file() || entry.path().is_symlink())
-            })
-            .filter_map(|entry| {
-                match Node::new(entry.path().into(), work_dir_id.to_owned()) {
-                    Ok(node) => Some(node),
-                    Err(err) => {
-                        error!("{:?}", err);
-                        None
-                    }
+    let work_dir = target_dir.parent().unwrap_or(&target_dir);
+
+    //
+    // Only paths are collected here. Nodes are built for the requested window
+    // only, avoiding to read metadata of all files.
+    //
+    let paths: Vec<PathBuf> = WalkDir::new(work_dir)
+        .contents_first(true)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry.path().exists()
+                && (entry.path().is_file() || entry.path().is_symlink())
+        })
+        .filter(|entry| match &query.name_contains {
+            None => true,
+            Some(pattern) => entry
+                .path()
+                .strip_prefix(work_dir)
+                .map(|name| name.to_string_lossy().contains(pattern.as_str()))
+                .unwrap_or(false),
+        })
+        .map(|entry| entry.path().to_path_buf())
+        .collect();
+
+    let offset = query.offset.unwrap_or(0);
+
+    let records: Vec<Node> = paths
+        .iter()
+        .skip(offset)
+        .take(query.limit.unwrap_or(usize::MAX))
+        .filter_map(|path| {
+            match Node::new(path.to_owned(), work_dir_id.to_owned()) {
+                Ok(node) => Some(node),
+                Err(err) => {
+                    error!("{:?}", err);
+                    None
                 }
-            })
-            .filter(|node| vec![""].contains(&node.name.as_str()) == false)
-            .collect();
+            }
+        })
+        .filter(|node| vec![""].contains(&node.name.as_str()) == false)
+        .collect();
 
-    HttpResponse::Ok().json(directory_content)
+    HttpResponse::Ok().json(NodesPage {
+        total: paths.len(),
+        offset,
+        limit: query.limit,
+        records,
+    })
 }
 
+/// Download a work directory file by its ID
+#[utoipa::path(
+    get,
+    path = "/wd/{work_dir_id}/{file_id}",
+    tag = "work-directory",
+    params(
+        ("work_dir_id" = String, Path, description = "Work directory ID"),
+        ("file_id" = u32, Path, description = "File ID, as listed"),
+    ),
+    responses(
+        (status = 200, description = "The file content"),
+        (status = 404, description = "File not found", body = ApiError),
+    )
+)]
 #[instrument(name = "Get file content", skip(config))]
 pub(crate) async fn get_file_content_by_id(
-    info: web::Path<(String, i32)>,
+    info: web::Path<(String, u32)>,
     config: web::Data<Mutex<FileSystemConfig>>,
     req: HttpRequest,
 ) -> HttpResponse {
@@ -147,28 +302,75 @@ pub(crate) async fn get_file_content_by_id(
     let parent = match target_dir.parent() {
         Some(parent) => parent,
         None => {
-            return HttpResponse::InternalServerError().finish();
+            return ApiError::internal("Work directory without parent").into()
         }
     };
 
-    match get_file_by_inode(parent.to_owned(), file_id as u32) {
-        None => HttpResponse::NoContent().finish(),
+    match get_file_by_inode(parent.to_owned(), file_id) {
+        None => ApiError::not_found(
+            ApiErrorCode::FileNotFound,
+            format!("File {file_id} not found in the work directory"),
+        )
+        .into(),
         Some(file) => match NamedFile::open(file) {
             Ok(file) => file.into_response(&req),
-            Err(err) => {
-                error!("{:?}", err);
-                HttpResponse::InternalServerError().finish()
-            }
+            Err(err) => ApiError::from(err).into(),
         },
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
 #[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
 pub struct UploadAnalysisFileArgs {
     pub force: Option<bool>,
 }
 
+/// Check if the content starts as a FASTA or FASTQ file
+///
+/// Returns `None` if the content contains only whitespaces, then more bytes
+/// are needed to decide.
+fn is_sequence_file_start(content: &[u8]) -> Option<bool> {
+    content
+        .iter()
+        .find(|byte| !byte.is_ascii_whitespace())
+        .map(|byte| [b'>', b'@'].contains(byte))
+}
+
+/// Remove a partially written file and build the error response
+pub(super) fn reject_upload(target_file: &Path, err: ApiError) -> HttpResponse {
+    if let Err(err) = std::fs::remove_file(target_file) {
+        error!("{:?}", err);
+    }
+
+    err.into()
+}
+
+/// Upload a query file to the work directory
+///
+/// Uploaded files should be FASTA or FASTQ files with size below the
+/// `maxUploadBytes` limit, if configured. File names containing path
+/// separators or parent directory references are rejected.
+#[utoipa::path(
+    post,
+    path = "/wd/{work_dir_id}",
+    tag = "work-directory",
+    params(
+        ("work_dir_id" = String, Path, description = "Work directory ID"),
+        UploadAnalysisFileArgs,
+    ),
+    request_body(
+        content = String,
+        description = "FASTA or FASTQ file",
+        content_type = "multipart/form-data",
+    ),
+    responses(
+        (status = 201, description = "The file was saved", body = DirResponse),
+        (status = 400, description = "Invalid file", body = ApiError),
+        (status = 409, description = "File already exists", body = ApiError),
+        (status = 413, description = "File too large", body = ApiError),
+    )
+)]
 #[instrument(name = "Upload analysis file", skip(config, query, payload))]
 pub(crate) async fn upload_analysis_file(
     work_dir_id: web::Path<String>,
@@ -177,6 +379,11 @@ pub(crate) async fn upload_analysis_file(
     request: HttpRequest,
     mut payload: Multipart,
 ) -> HttpResponse {
+    let max_upload_bytes = match config.lock() {
+        Ok(res) => res.max_upload_bytes,
+        Err(err) => return ApiError::internal(err).into(),
+    };
+
     let target_dir = match check_directory_existence(
         config,
         work_dir_id.into_inner(),
@@ -187,31 +394,47 @@ pub(crate) async fn upload_analysis_file(
     };
 
     if let Err(err) = std::fs::create_dir_all(&target_dir) {
-        error!("{:?}", err);
-        return HttpResponse::InternalServerError().finish();
+        return ApiError::internal(err).into();
     };
 
     while let Some(field) = payload.next().await {
         let mut field = match field {
             Ok(field) => field,
             Err(err) => {
-                error!("{:?}", err);
-                return HttpResponse::BadRequest().body("Invalid request");
+                return ApiError::bad_request(
+                    ApiErrorCode::InvalidRequest,
+                    format!("Invalid multipart content: {err}"),
+                )
+                .into()
             }
         };
 
         let file_name = match field.content_disposition().get_filename() {
-            Some(name) => name,
-            None => return HttpResponse::BadRequest().body("Invalid request"),
+            Some(name) => name.to_owned(),
+            None => {
+                return ApiError::bad_request(
+                    ApiErrorCode::InvalidFileName,
+                    "File name not provided",
+                )
+                .into()
+            }
         };
 
+        if file_name.contains("..") || file_name.contains('/') {
+            return ApiError::bad_request(
+                ApiErrorCode::InvalidFileName,
+                format!("Invalid file name: {file_name}"),
+            )
+            .into();
+        }
+
         let target_file = target_dir.join(file_name);
 
         if target_file.exists() {
             if !query.force.unwrap_or(false) {
-                return HttpResponse::Conflict().json(DirResponse {
-                    status: 409,
-                    msg: Some(format!(
+                return ApiError::conflict(
+                    ApiErrorCode::FileAlreadyExists,
+                    format!(
                         "\
     File already exists ({f}). If you want to overwrite it, use the `force` query \
     parameter.",
@@ -220,39 +443,85 @@ pub(crate) async fn upload_analysis_file(
                             .unwrap()
                             .to_str()
                             .unwrap_or("unnamed")
-                    )),
-                });
+                    ),
+                )
+                .into();
             } else {
                 if let Err(err) = std::fs::remove_file(&target_file) {
-                    error!("{:?}", err);
-                    return HttpResponse::InternalServerError().finish();
+                    return ApiError::from(err).into();
                 };
             }
         }
 
-        let mut file = match tokio::fs::File::create(target_file).await {
+        let mut file = match tokio::fs::File::create(&target_file).await {
             Ok(file) => file,
-            Err(err) => {
-                error!("{:?}", err);
-                return HttpResponse::InternalServerError().finish();
-            }
+            Err(err) => return ApiError::internal(err).into(),
         };
 
         if field.name() == "file" {
+            let mut written_bytes: u64 = 0;
+            let mut is_sequence_file: Option<bool> = None;
+
             while let Some(chunk) = field.next().await {
                 let chunk = match chunk {
                     Ok(chunk) => chunk,
                     Err(err) => {
-                        error!("{:?}", err);
-                        return HttpResponse::InternalServerError().finish();
+                        return reject_upload(
+                            &target_file,
+                            ApiError::bad_request(
+                                ApiErrorCode::InvalidRequest,
+                                format!("Invalid multipart content: {err}"),
+                            ),
+                        );
                     }
                 };
 
+                //
+                // The file format is checked from the first non-blank bytes
+                //
+                if is_sequence_file.is_none() {
+                    is_sequence_file = is_sequence_file_start(&chunk);
+
+                    if is_sequence_file == Some(false) {
+                        break;
+                    }
+                }
+
+                written_bytes += chunk.len() as u64;
+
+                if let Some(max_bytes) = max_upload_bytes {
+                    if written_bytes > max_bytes {
+                        return reject_upload(
+                            &target_file,
+                            ApiError::new(
+                                StatusCode::PAYLOAD_TOO_LARGE,
+                                ApiErrorCode::PayloadTooLarge,
+                                format!(
+                                    "File exceeds the upload limit of \
+                                    {max_bytes} bytes"
+                                ),
+                            ),
+                        );
+                    }
+                }
+
                 if let Err(err) = file.write_all(&chunk).await {
-                    error!("{:?}", err);
-                    return HttpResponse::InternalServerError().finish();
+                    return reject_upload(
+                        &target_file,
+                        ApiError::internal(err),
+                    );
                 };
             }
+
+            if is_sequence_file != Some(true) {
+                return reject_upload(
+                    &target_file,
+                    ApiError::bad_request(
+                        ApiErrorCode::InvalidFileFormat,
+                        "Uploaded file should be in FASTA or FASTQ format",
+                    ),
+                );
+            }
         }
     }
 
@@ -262,6 +531,27 @@ pub(crate) async fn upload_analysis_file(
     })
 }
 
+/// Save the placement analysis configuration to the work directory
+#[utoipa::path(
+    post,
+    path = "/wd/{work_dir_id}/config",
+    tag = "work-directory",
+    params(("work_dir_id" = String, Path, description = "Work directory ID")),
+    request_body = PlacementConfig,
+    responses(
+        (
+            status = 201,
+            description = "The configuration was saved",
+            body = DirResponse
+        ),
+        (status = 400, description = "Invalid configuration", body = ApiError),
+        (
+            status = 404,
+            description = "Work directory not found",
+            body = ApiError
+        ),
+    )
+)]
 #[instrument(name = "Configure Placement Analysis")]
 pub(crate) async fn configure_placement_analysis(
     work_dir_id: web::Path<String>,
@@ -281,32 +571,77 @@ pub(crate) async fn configure_placement_analysis(
 
     let fs_config = match fs_config.lock() {
         Ok(res) => res,
-        Err(err) => {
-            error!("{:?}", err);
-            return HttpResponse::InternalServerError().finish();
+        Err(err) => return ApiError::internal(err).into(),
+    };
+
+    let mut analysis_config = body.into_inner();
+
+    //
+    // Validate the configuration before writing it. Invalid configurations
+    // would only fail later in the watcher.
+    //
+    let model_ids = match trees_config.lock() {
+        Ok(res) => res
+            .get_models()
+            .into_iter()
+            .map(|model| model.id)
+            .collect::<Vec<_>>(),
+        Err(err) => return ApiError::internal(err).into(),
+    };
+
+    if !model_ids.contains(&analysis_config.model_id) {
+        return ApiError::bad_request(
+            ApiErrorCode::UnknownModel,
+            format!(
+                "Unknown model ID {model_id}. Valid model IDs are: {valid}",
+                model_id = analysis_config.model_id,
+                valid = model_ids
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        )
+        .into();
+    }
+
+    let query_file = match get_file_by_inode(
+        target_dir.to_owned(),
+        analysis_config.query_file_id,
+    ) {
+        Some(file) => file,
+        None => {
+            return ApiError::bad_request(
+                ApiErrorCode::FileNotFound,
+                format!(
+                    "Query file {inode} not found in the work directory",
+                    inode = analysis_config.query_file_id,
+                ),
+            )
+            .into()
         }
     };
 
-    let analysis_config = body.into_inner();
+    //
+    // The query file identity is recorded with the inode, then files
+    // inheriting the inode after the configuration are not placed.
+    //
+    analysis_config.query_file_identity = FileIdentity::from_path(&query_file);
 
     let config_file_path = (if let Some(path) = target_dir.parent() {
         path
     } else {
-        return HttpResponse::InternalServerError().finish();
+        return ApiError::internal("Work directory without parent").into();
     })
     .join(fs_config.to_owned().config_file_name);
 
     let config_file = match std::fs::File::create(config_file_path) {
         Ok(file) => file,
-        Err(err) => {
-            error!("{:?}", err);
-            return HttpResponse::InternalServerError().finish();
-        }
+        Err(err) => return ApiError::internal(err).into(),
     };
 
     if let Err(err) = serde_yaml::to_writer(config_file, &analysis_config) {
-        error!("{:?}", err);
-        return HttpResponse::InternalServerError().finish();
+        return ApiError::internal(err).into();
     };
 
     HttpResponse::Created().json(DirResponse {
@@ -314,3 +649,415 @@ pub(crate) async fn configure_placement_analysis(
         msg: Some("Analysis configuration saved successfully".to_string()),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use actix_web::{body::MessageBody, dev::ServiceResponse, test, App};
+    use std::os::unix::fs::MetadataExt;
+
+    const MODEL_ID: &str = "ce47d8bc-2885-3d2c-8247-5b8c8b28fefe";
+
+    const WORK_DIR_ID: &str = "019051d9-4c7a-7b2d-9dd1-66ef92236fe0";
+
+    fn build_fs_config(
+        serve_directory: &str,
+        max_upload_bytes: Option<u64>,
+    ) -> FileSystemConfig {
+        FileSystemConfig {
+            public_directory: "public".to_string(),
+            serve_directory: serve_directory.to_string(),
+            input_directory: "input".to_string(),
+            output_directory: "output".to_string(),
+            config_file_name: "config.yaml".to_string(),
+            results_file_name: "result.yaml".to_string(),
+            success_file_name: "success.yaml".to_string(),
+            running_file_name: "running.yaml".to_string(),
+            error_file_name: "error.yaml".to_string(),
+            logging_file_name: "logging.jsonl".to_string(),
+            max_upload_bytes,
+        }
+    }
+
+    /// Read the response status and the error body, if any
+    async fn read_error<B: MessageBody>(
+        response: ServiceResponse<B>,
+    ) -> (u16, Option<ApiError>) {
+        let status = response.status().as_u16();
+
+        match status >= 400 {
+            true => (status, Some(test::read_body_json(response).await)),
+            false => (status, None),
+        }
+    }
+
+    async fn post_config(
+        serve_directory: &str,
+        model_id: &str,
+        query_file_id: u64,
+    ) -> (u16, Option<ApiError>) {
+        let work_dir_id = WORK_DIR_ID;
+        let fs_config = build_fs_config(serve_directory, None);
+
+        let models_config: ModelsConfig = serde_yaml::from_str(&format!(
+            "- id: {MODEL_ID}\n  \
+            name: Bacillus subtilis group\n  \
+            modelPath: ../../tests/models/bsub-gyrb-k35.cls\n",
+        ))
+        .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Mutex::new(fs_config)))
+                .app_data(web::Data::new(Mutex::new(models_config)))
+                .route(
+                    "/wd/{work_dir_id}/config",
+                    web::post().to(configure_placement_analysis),
+                ),
+        )
+        .await;
+
+        let config = serde_yaml::from_str::<PlacementConfig>(&format!(
+            "name: test-analysis\n\
+            email: test@example.com\n\
+            queryFileId: {query_file_id}\n\
+            modelId: {model_id}\n\
+            outputFormat: jsonl\n\
+            workDir: {work_dir_id}\n",
+        ))
+        .unwrap();
+
+        let response = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri(&format!("/wd/{work_dir_id}/config"))
+                .set_json(&config)
+                .to_request(),
+        )
+        .await;
+
+        read_error(response).await
+    }
+
+    fn init_work_dir(serve_directory: &str) -> (PathBuf, u64) {
+        let work_dir = PathBuf::from(serve_directory)
+            .join("public")
+            .join(WORK_DIR_ID);
+
+        let _ = std::fs::remove_dir_all(serve_directory);
+        std::fs::create_dir_all(work_dir.join("input")).unwrap();
+
+        let query_file = work_dir.join("input").join("query.fasta");
+        std::fs::write(&query_file, ">query\nACGT\n").unwrap();
+
+        (work_dir, std::fs::metadata(&query_file).unwrap().ino())
+    }
+
+    #[actix_web::test]
+    async fn test_configure_placement_analysis_with_unknown_model() {
+        let serve_directory = "/tmp/cls-api-config-unknown-model";
+        let (work_dir, inode) = init_work_dir(serve_directory);
+
+        let (status, err) =
+            post_config(serve_directory, &Uuid::new_v4().to_string(), inode)
+                .await;
+
+        let err = err.unwrap();
+
+        assert_eq!(status, 400);
+        assert_eq!(err.status, 400);
+        assert_eq!(err.code, ApiErrorCode::UnknownModel);
+        assert!(err.message.contains(MODEL_ID));
+        assert!(!work_dir.join("config.yaml").exists());
+    }
+
+    #[actix_web::test]
+    async fn test_configure_placement_analysis_with_missing_query_file() {
+        let serve_directory = "/tmp/cls-api-config-missing-query";
+        let (work_dir, inode) = init_work_dir(serve_directory);
+
+        //
+        // No file has the inode zero
+        //
+        let (status, err) = post_config(serve_directory, MODEL_ID, 0).await;
+
+        assert_eq!(status, 400);
+        assert_eq!(err.unwrap().code, ApiErrorCode::FileNotFound);
+        assert!(!work_dir.join("config.yaml").exists());
+
+        let (status, _) = post_config(serve_directory, MODEL_ID, inode).await;
+
+        assert_eq!(status, 201);
+        assert!(work_dir.join("config.yaml").exists());
+    }
+
+    async fn upload_file(
+        serve_directory: &str,
+        max_upload_bytes: Option<u64>,
+        file_name: &str,
+        content: &str,
+    ) -> (u16, Option<ApiError>) {
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Mutex::new(build_fs_config(
+                    serve_directory,
+                    max_upload_bytes,
+                ))))
+                .route(
+                    "/wd/{work_dir_id}",
+                    web::post().to(upload_analysis_file),
+                ),
+        )
+        .await;
+
+        let body = format!(
+            "--boundary\r\n\
+            Content-Disposition: form-data; name=\"file\"; \
+            filename=\"{file_name}\"\r\n\
+            Content-Type: application/octet-stream\r\n\r\n\
+            {content}\r\n\
+            --boundary--\r\n"
+        );
+
+        let response = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri(&format!("/wd/{WORK_DIR_ID}"))
+                .insert_header((
+                    "content-type",
+                    "multipart/form-data; boundary=boundary",
+                ))
+                .set_payload(body)
+                .to_request(),
+        )
+        .await;
+
+        read_error(response).await
+    }
+
+    #[actix_web::test]
+    async fn test_upload_analysis_file_limits() {
+        let serve_directory = "/tmp/cls-api-upload";
+        let (work_dir, _) = init_work_dir(serve_directory);
+        let input_dir = work_dir.join("input");
+        let content = ">query\nACGTACGTACGTACGTACGTACGTACGTACGTACGT\n";
+
+        let (status, err) =
+            upload_file(serve_directory, Some(16), "big.fasta", content).await;
+
+        assert_eq!(status, 413);
+        assert_eq!(err.unwrap().code, ApiErrorCode::PayloadTooLarge);
+        assert!(!input_dir.join("big.fasta").exists());
+
+        let (status, err) =
+            upload_file(serve_directory, None, "../escape.fasta", content)
+                .await;
+
+        assert_eq!(status, 400);
+        assert_eq!(err.unwrap().code, ApiErrorCode::InvalidFileName);
+        assert!(!work_dir.join("escape.fasta").exists());
+
+        let (status, err) =
+            upload_file(serve_directory, None, "query.txt", "not a fasta")
+                .await;
+
+        assert_eq!(status, 400);
+        assert_eq!(err.unwrap().code, ApiErrorCode::InvalidFileFormat);
+        assert!(!input_dir.join("query.txt").exists());
+
+        let (status, _) =
+            upload_file(serve_directory, Some(1024), "small.fasta", content)
+                .await;
+
+        assert_eq!(status, 201);
+        assert!(input_dir.join("small.fasta").exists());
+    }
+
+    #[actix_web::test]
+    async fn test_delete_wd() {
+        let serve_directory = "/tmp/cls-api-delete-wd";
+
+        let _ = std::fs::remove_dir_all(serve_directory);
+        std::fs::create_dir_all(serve_directory).unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Mutex::new(build_fs_config(
+                    serve_directory,
+                    None,
+                ))))
+                .route("/wd", web::post().to(init_wd))
+                .route("/wd/{work_dir_id}", web::get().to(list_wd_content))
+                .route("/wd/{work_dir_id}", web::delete().to(delete_wd)),
+        )
+        .await;
+
+        let response: HashMap<String, String> = test::call_and_read_body_json(
+            &app,
+            test::TestRequest::post().uri("/wd").to_request(),
+        )
+        .await;
+
+        let work_dir_id = response.get("workDirId").unwrap();
+
+        let delete = || {
+            test::TestRequest::delete()
+                .uri(&format!("/wd/{work_dir_id}"))
+                .to_request()
+        };
+
+        let response = test::call_service(&app, delete()).await;
+        assert_eq!(response.status().as_u16(), 204);
+
+        let response = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri(&format!("/wd/{work_dir_id}"))
+                .to_request(),
+        )
+        .await;
+
+        assert_eq!(response.status().as_u16(), 404);
+
+        let response = test::call_service(&app, delete()).await;
+        assert_eq!(response.status().as_u16(), 404);
+        assert!(PathBuf::from(serve_directory).join("public").exists());
+    }
+
+    #[actix_web::test]
+    async fn test_list_wd_content_pagination() {
+        let serve_directory = "/tmp/cls-api-list-wd";
+        let (work_dir, _) = init_work_dir(serve_directory);
+
+        std::fs::remove_file(work_dir.join("input").join("query.fasta"))
+            .unwrap();
+
+        for i in 0..100 {
+            std::fs::write(
+                work_dir.join("input").join(format!("file-{i:03}.txt")),
+                "content",
+            )
+            .unwrap();
+        }
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Mutex::new(build_fs_config(
+                    serve_directory,
+                    None,
+                ))))
+                .route("/wd/{work_dir_id}", web::get().to(list_wd_content)),
+        )
+        .await;
+
+        let page: NodesPage = test::call_and_read_body_json(
+            &app,
+            test::TestRequest::get()
+                .uri(&format!("/wd/{WORK_DIR_ID}?limit=10&offset=20"))
+                .to_request(),
+        )
+        .await;
+
+        assert_eq!(page.total, 100);
+        assert_eq!(page.records.len(), 10);
+        assert_eq!(page.records[0].name, "input/file-020.txt");
+        assert_eq!(page.records[9].name, "input/file-029.txt");
+
+        let page: NodesPage = test::call_and_read_body_json(
+            &app,
+            test::TestRequest::get()
+                .uri(&format!(
+                    "/wd/{WORK_DIR_ID}?limit=5&offset=8&name_contains=file-09"
+                ))
+                .to_request(),
+        )
+        .await;
+
+        assert_eq!(page.total, 10);
+        assert_eq!(page.records.len(), 2);
+        assert_eq!(page.records[1].name, "input/file-099.txt");
+    }
+
+    #[actix_web::test]
+    async fn test_init_wd_error_shape() {
+        //
+        // A regular file used as the serve directory prevents the work
+        // directory creation
+        //
+        let serve_directory = "/tmp/cls-api-init-wd-file";
+        std::fs::write(serve_directory, "").unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Mutex::new(build_fs_config(
+                    serve_directory,
+                    None,
+                ))))
+                .route("/wd", web::post().to(init_wd)),
+        )
+        .await;
+
+        let (status, err) = read_error(
+            test::call_service(
+                &app,
+                test::TestRequest::post().uri("/wd").to_request(),
+            )
+            .await,
+        )
+        .await;
+
+        let err = err.unwrap();
+
+        assert_eq!(status, 500);
+        assert_eq!(err.status, 500);
+        assert_eq!(err.code, ApiErrorCode::InternalError);
+    }
+
+    #[actix_web::test]
+    async fn test_get_file_content_by_id_error_shape() {
+        let serve_directory = "/tmp/cls-api-file-content";
+        let (_, inode) = init_work_dir(serve_directory);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Mutex::new(build_fs_config(
+                    serve_directory,
+                    None,
+                ))))
+                .route(
+                    "/wd/{work_dir_id}/{file_id}",
+                    web::get().to(get_file_content_by_id),
+                ),
+        )
+        .await;
+
+        let get_file = |work_dir_id: &str, file_id: u64| {
+            test::TestRequest::get()
+                .uri(&format!("/wd/{work_dir_id}/{file_id}"))
+                .to_request()
+        };
+
+        let response =
+            test::call_service(&app, get_file(WORK_DIR_ID, inode)).await;
+
+        assert_eq!(response.status().as_u16(), 200);
+
+        let (status, err) = read_error(
+            test::call_service(&app, get_file(WORK_DIR_ID, 0)).await,
+        )
+        .await;
+
+        assert_eq!(status, 404);
+        assert_eq!(err.unwrap().code, ApiErrorCode::FileNotFound);
+
+        let (status, err) = read_error(
+            test::call_service(&app, get_file(&Uuid::new_v4().to_string(), 0))
+                .await,
+        )
+        .await;
+
+        assert_eq!(status, 404);
+        assert_eq!(err.unwrap().code, ApiErrorCode::WorkDirNotFound);
+    }
+}
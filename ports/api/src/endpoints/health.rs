@@ -0,0 +1,288 @@
+use crate::models::api_error::ApiError;
+
+use actix_web::{web, HttpResponse};
+use classeq_ports_lib::{
+    DatabaseHeader, FileSystemConfig, ModelConfig, ModelsConfig,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tracing::instrument;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// The magic bytes of zstd frames, used by databases without header
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum HealthStatus {
+    Healthy,
+    Degraded,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ModelStatus {
+    /// The model file contains a valid database header
+    Ok,
+
+    /// The model file was built before the header introduction. Only the
+    /// file format is checked.
+    Legacy,
+
+    /// The model file does not exist
+    Missing,
+
+    /// The model file exists but does not look like a database
+    Invalid,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ModelHealth {
+    pub id: Uuid,
+    pub name: String,
+    pub status: ModelStatus,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ServeDirectoryHealth {
+    pub writable: bool,
+
+    /// The free space (in bytes) available under the serve directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available_bytes: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct HealthResponse {
+    pub status: HealthStatus,
+    pub serve_directory: ServeDirectoryHealth,
+    pub models: Vec<ModelHealth>,
+}
+
+/// Check if a file can be created in the directory
+fn is_writable(directory: &Path) -> bool {
+    let probe = directory.join(format!(".health-{}", Uuid::new_v4()));
+
+    match File::create(&probe) {
+        Err(_) => false,
+        Ok(_) => std::fs::remove_file(probe).is_ok(),
+    }
+}
+
+/// Check a model file reading its header only
+///
+/// The database content is not parsed, then the check is cheap enough to be
+/// used by readiness probes.
+fn check_model(model: &ModelConfig) -> ModelHealth {
+    let path = model.model_path();
+
+    let (status, message) = match File::open(&path) {
+        Err(err) => (ModelStatus::Missing, Some(err.to_string())),
+        Ok(file) => {
+            let mut reader = BufReader::new(file);
+
+            match DatabaseHeader::from_reader(&mut reader) {
+                Ok(Some(_)) => (ModelStatus::Ok, None),
+                Err(err) => (ModelStatus::Invalid, Some(err.to_string())),
+                Ok(None) => {
+                    //
+                    // Databases built before the header introduction are
+                    // zstd compressed or plain yaml files
+                    //
+                    let mut magic = [0u8; 4];
+
+                    let is_known_format = reader
+                        .seek(SeekFrom::Start(0))
+                        .and_then(|_| reader.read_exact(&mut magic))
+                        .map(|_| {
+                            magic == ZSTD_MAGIC
+                                || magic.iter().all(|i| i.is_ascii())
+                        })
+                        .unwrap_or(false);
+
+                    match is_known_format {
+                        true => (ModelStatus::Legacy, None),
+                        false => (
+                            ModelStatus::Invalid,
+                            Some("Unknown database format".to_string()),
+                        ),
+                    }
+                }
+            }
+        }
+    };
+
+    ModelHealth {
+        id: model.id,
+        name: model.name.to_owned(),
+        status,
+        message,
+    }
+}
+
+/// Check the service readiness
+///
+/// The serve directory should be writable and all configured models should be
+/// available. A service unavailable response is returned otherwise, including
+/// the health details.
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "health",
+    responses(
+        (
+            status = 200,
+            description = "The service is healthy",
+            body = HealthResponse
+        ),
+        (
+            status = 503,
+            description = "The service is degraded",
+            body = HealthResponse
+        ),
+    )
+)]
+#[instrument(name = "Check service health", skip(fs_config, models_config))]
+pub(crate) async fn check_health(
+    fs_config: web::Data<Mutex<FileSystemConfig>>,
+    models_config: web::Data<Mutex<ModelsConfig>>,
+) -> HttpResponse {
+    let serve_directory = match fs_config.lock() {
+        Err(err) => return ApiError::internal(err).into(),
+        Ok(res) => PathBuf::from(&res.serve_directory),
+    };
+
+    let models = match models_config.lock() {
+        Err(err) => return ApiError::internal(err).into(),
+        Ok(res) => res.get_models(),
+    };
+
+    let response = match web::block(move || {
+        let serve_directory = ServeDirectoryHealth {
+            writable: is_writable(&serve_directory),
+            available_bytes: fs2::available_space(&serve_directory).ok(),
+        };
+
+        let models = models.iter().map(check_model).collect::<Vec<_>>();
+
+        let is_healthy = serve_directory.writable
+            && models.iter().all(|model| {
+                [ModelStatus::Ok, ModelStatus::Legacy].contains(&model.status)
+            });
+
+        HealthResponse {
+            status: match is_healthy {
+                true => HealthStatus::Healthy,
+                false => HealthStatus::Degraded,
+            },
+            serve_directory,
+            models,
+        }
+    })
+    .await
+    {
+        Ok(response) => response,
+        Err(err) => return ApiError::internal(err).into(),
+    };
+
+    match response.status {
+        HealthStatus::Healthy => HttpResponse::Ok().json(response),
+        HealthStatus::Degraded => {
+            HttpResponse::ServiceUnavailable().json(response)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use actix_web::{test, App};
+    use classeq_ports_lib::{load_database, write_database};
+
+    #[actix_web::test]
+    async fn test_check_health_with_missing_model() {
+        let serve_directory = PathBuf::from("/tmp/cls-api-health");
+        let model_path = serve_directory.join("present.cls");
+
+        std::fs::create_dir_all(&serve_directory).unwrap();
+
+        let tree = load_database(PathBuf::from(
+            "../../tests/models/bsub-gyrb-k35.cls",
+        ))
+        .unwrap();
+
+        write_database(&tree, &model_path).unwrap();
+
+        let fs_config: FileSystemConfig = serde_yaml::from_str(&format!(
+            "publicDirectory: public\n\
+            serveDirectory: {serve}\n\
+            inputDirectory: input\n\
+            outputDirectory: output\n\
+            configFileName: config.yaml\n\
+            resultsFileName: results.json\n\
+            successFileName: success.lock\n\
+            runningFileName: running.lock\n\
+            errorFileName: error.lock\n\
+            loggingFileName: log.jsonl\n",
+            serve = serve_directory.to_str().unwrap()
+        ))
+        .unwrap();
+
+        let present_id = Uuid::new_v4();
+        let missing_id = Uuid::new_v4();
+
+        let models_config: ModelsConfig = serde_yaml::from_str(&format!(
+            "- id: {present_id}\n  name: Present\n  modelPath: {present}\n\
+            - id: {missing_id}\n  name: Missing\n  modelPath: {missing}\n",
+            present = model_path.to_str().unwrap(),
+            missing = serve_directory.join("missing.cls").to_str().unwrap(),
+        ))
+        .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Mutex::new(fs_config)))
+                .app_data(web::Data::new(Mutex::new(models_config)))
+                .route("/health", web::get().to(check_health)),
+        )
+        .await;
+
+        let response = test::call_service(
+            &app,
+            test::TestRequest::get().uri("/health").to_request(),
+        )
+        .await;
+
+        assert_eq!(response.status().as_u16(), 503);
+
+        let health: HealthResponse = test::read_body_json(response).await;
+
+        assert_eq!(health.status, HealthStatus::Degraded);
+        assert!(health.serve_directory.writable);
+        assert!(health.serve_directory.available_bytes.is_some());
+
+        let status_of = |id: Uuid| {
+            health
+                .models
+                .iter()
+                .find(|model| model.id == id)
+                .map(|model| model.status.to_owned())
+        };
+
+        assert_eq!(status_of(present_id), Some(ModelStatus::Ok));
+        assert_eq!(status_of(missing_id), Some(ModelStatus::Missing));
+    }
+}
@@ -1,2 +1,5 @@
 pub mod fs;
+pub mod health;
+pub mod openapi;
+pub mod placement;
 pub mod subjects;
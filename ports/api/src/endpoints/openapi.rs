@@ -0,0 +1,99 @@
+use super::{fs, health, placement, subjects};
+use crate::models::{
+    api_error::{ApiError, ApiErrorCode},
+    node::{Node, NodesPage},
+};
+
+use actix_web::HttpResponse;
+use classeq_ports_lib::{DatabaseStats, ModelConfig, PlacementConfig};
+use utoipa::OpenApi;
+
+/// The OpenAPI document of the API endpoints
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        health::check_health,
+        fs::init_wd,
+        fs::list_wd_content,
+        fs::delete_wd,
+        fs::upload_analysis_file,
+        fs::configure_placement_analysis,
+        fs::get_file_content_by_id,
+        placement::place_work_dir_sequences,
+        placement::get_placement_status,
+        placement::stream_sequences_placement,
+        subjects::list_available_models,
+        subjects::register_model,
+        subjects::get_model_stats,
+    ),
+    components(schemas(
+        ApiError,
+        ApiErrorCode,
+        DatabaseStats,
+        ModelConfig,
+        Node,
+        NodesPage,
+        PlacementConfig,
+        fs::DirResponse,
+        health::HealthResponse,
+        health::HealthStatus,
+        health::ModelHealth,
+        health::ModelStatus,
+        health::ServeDirectoryHealth,
+        placement::PlacementJobResponse,
+        placement::PlacementJobStatus,
+    ))
+)]
+pub(crate) struct ApiDoc;
+
+/// Get the OpenAPI document
+///
+/// Clients could generate typed bindings from the document instead of
+/// reverse-engineering the request and response bodies.
+pub(crate) async fn get_openapi_spec() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use actix_web::{test, web, App};
+    use serde_json::Value;
+
+    #[actix_web::test]
+    async fn test_get_openapi_spec() {
+        let app = test::init_service(
+            App::new().route("/openapi.json", web::get().to(get_openapi_spec)),
+        )
+        .await;
+
+        let spec: Value = test::call_and_read_body_json(
+            &app,
+            test::TestRequest::get().uri("/openapi.json").to_request(),
+        )
+        .await;
+
+        let paths = spec["paths"].as_object().unwrap();
+
+        assert!(paths.contains_key("/wd"));
+        assert!(paths.contains_key("/wd/{work_dir_id}"));
+        assert!(paths.contains_key("/models"));
+        assert!(paths.contains_key("/models/{id}"));
+
+        //
+        // The work directory path groups the list, upload and delete methods
+        //
+        let work_dir = &paths["/wd/{work_dir_id}"];
+
+        assert!(work_dir.get("get").is_some());
+        assert!(work_dir.get("post").is_some());
+        assert!(work_dir.get("delete").is_some());
+
+        let schemas = spec["components"]["schemas"].as_object().unwrap();
+
+        for schema in ["DirResponse", "Node", "PlacementConfig", "ApiError"] {
+            assert!(schemas.contains_key(schema), "missing schema {schema}");
+        }
+    }
+}
@@ -0,0 +1,979 @@
+use crate::models::api_error::{ApiError, ApiErrorCode};
+
+use actix_web::{
+    http::StatusCode, rt::task::spawn_blocking, web, HttpResponse,
+};
+use classeq_core::{
+    domain::dtos::{
+        file_or_stdin::FileOrStdin, output_format::OutputFormat,
+        placement_config::PlacementConfig as RunPlacementConfig,
+        placement_options::PlacementOptions, quality_filter::QualityFilter,
+    },
+    use_cases::{place_one, place_sequences},
+};
+use classeq_ports_lib::{
+    get_referenced_file_by_inode, DatabaseCache, FileSystemConfig,
+    ModelsConfig, PlacementCache, PlacementConfig,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::{ErrorKind, Write},
+    os::unix::fs::MetadataExt,
+    path::PathBuf,
+    sync::{mpsc::sync_channel, Mutex},
+    thread,
+};
+use tracing::{error, instrument, warn};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+/// The number of placements buffered before being sent to the client
+///
+/// The placement is paused while the buffer is full, then slow clients do not
+/// cause unbounded memory growth.
+const STREAM_BUFFER_SIZE: usize = 8;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum PlacementJobStatus {
+    /// The analysis is configured but not started yet
+    Pending,
+
+    /// The analysis was claimed and is running
+    Running,
+
+    /// The analysis finished successfully
+    Success,
+
+    /// The analysis finished with errors
+    Error,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PlacementJobResponse {
+    pub(crate) job_id: String,
+    pub(crate) status: PlacementJobStatus,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) msg: Option<String>,
+
+    /// The inode of the results file, available when the analysis succeeds
+    ///
+    /// The results content can be downloaded using the work directory file
+    /// endpoint.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) results_file_id: Option<u32>,
+}
+
+/// The record streamed in place of the placement of a failed query
+///
+/// Clients are able to match failures to queries, since failed queries are
+/// not silently omitted from the stream.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PlacementErrorRecord {
+    query: String,
+    error: String,
+}
+
+impl PlacementErrorRecord {
+    fn to_content(query: &str, error: String) -> String {
+        let record = PlacementErrorRecord {
+            query: query.to_owned(),
+            error,
+        };
+
+        match serde_json::to_string(&record) {
+            Ok(content) => content,
+            Err(err) => {
+                error!("Failed to serialize the error record {query}: {err}");
+                String::new()
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct PlaceSequencesArgs {
+    /// If true, the placement runs in background and a job id is returned
+    pub background: Option<bool>,
+}
+
+/// The execution message persisted into the lock files
+///
+/// This is the same structure persisted by the watcher, allowing both ports to
+/// share the same work directories.
+#[derive(Debug, Deserialize, Serialize)]
+struct ExecutionMsg {
+    msg: String,
+}
+
+impl ExecutionMsg {
+    fn write_file(path: &PathBuf, msg: &str) {
+        match serde_yaml::to_string(&ExecutionMsg {
+            msg: msg.to_owned(),
+        }) {
+            Ok(content) => {
+                if let Err(err) = std::fs::write(path, content) {
+                    error!("Failed to write the execution file: {err}");
+                }
+            }
+            Err(err) => error!("Failed to serialize the execution file: {err}"),
+        }
+    }
+
+    fn read_file(path: &PathBuf) -> Option<String> {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| {
+                serde_yaml::from_str::<ExecutionMsg>(&content).ok()
+            })
+            .map(|content| content.msg)
+    }
+}
+
+fn get_work_dir(fs_config: &FileSystemConfig, work_dir_id: &str) -> PathBuf {
+    PathBuf::from(&fs_config.serve_directory)
+        .join(fs_config.public_directory.to_owned())
+        .join(work_dir_id)
+}
+
+fn get_results_file(
+    fs_config: &FileSystemConfig,
+    work_dir: &PathBuf,
+    output_format: &OutputFormat,
+) -> PathBuf {
+    let mut results_file = work_dir
+        .join(fs_config.output_directory.to_owned())
+        .join(fs_config.results_file_name.to_owned());
+
+    results_file.set_extension(output_format.extension());
+
+    results_file
+}
+
+/// Claim the work directory creating the running file
+///
+/// The running file is created only if it does not exist, then directories
+/// processed by the watcher or by another request are never processed twice.
+fn claim_work_dir(
+    fs_config: &FileSystemConfig,
+    work_dir: &PathBuf,
+) -> Result<bool, std::io::Error> {
+    let running_file = work_dir.join(fs_config.running_file_name.to_owned());
+
+    let mut file = match OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(running_file)
+    {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => return Ok(false),
+        Err(err) => return Err(err),
+    };
+
+    let content = serde_yaml::to_string(&ExecutionMsg {
+        msg: "Analysis claimed by the API".to_string(),
+    })
+    .unwrap_or_default();
+
+    file.write_all(content.as_bytes())?;
+
+    Ok(true)
+}
+
+/// Run the placement of a previously configured work directory
+///
+/// Results are persisted into the work directory output folder in the format
+/// given. The path of the results file is returned.
+fn run_placement(
+    fs_config: &FileSystemConfig,
+    models_config: &ModelsConfig,
+    database_cache: &DatabaseCache,
+    work_dir: &PathBuf,
+    analysis_config: &PlacementConfig,
+    output_format: OutputFormat,
+) -> Result<PathBuf, String> {
+    let model = match models_config
+        .get_models()
+        .into_iter()
+        .find(|model| model.id == analysis_config.model_id)
+    {
+        Some(model) => model,
+        None => {
+            return Err(format!(
+                "Model with ID {id} not found",
+                id = analysis_config.model_id
+            ))
+        }
+    };
+
+    let tree = match database_cache.get_or_load(&model) {
+        Ok(tree) => tree,
+        Err(err) => {
+            return Err(format!(
+                "Failed to load the model with ID {id}: {err}",
+                id = model.id
+            ))
+        }
+    };
+
+    let query_file = match get_referenced_file_by_inode(
+        work_dir.join(fs_config.input_directory.to_owned()),
+        analysis_config.query_file_id,
+        analysis_config.query_file_identity.as_ref(),
+    ) {
+        Some(file) => file,
+        None => {
+            return Err(format!(
+                "Query file with inode {inode} not found",
+                inode = analysis_config.query_file_id
+            ))
+        }
+    };
+
+    let config = RunPlacementConfig::default()
+        .with_options(
+            PlacementOptions::default()
+                .with_remove_intersection(analysis_config.remove_intersection),
+        )
+        .with_overwrite(true)
+        .with_output_format(output_format.to_owned());
+
+    if let Err(err) = place_sequences(
+        FileOrStdin::from_file(query_file.to_str().unwrap_or_default()),
+        &tree,
+        &work_dir
+            .join(fs_config.output_directory.to_owned())
+            .join(fs_config.results_file_name.to_owned()),
+        &config,
+        &None,
+    ) {
+        return Err(format!("Failed to place sequences: {err}"));
+    }
+
+    Ok(get_results_file(fs_config, work_dir, &output_format))
+}
+
+/// Run the placement and persist the success or error files
+fn run_placement_job(
+    fs_config: FileSystemConfig,
+    models_config: ModelsConfig,
+    database_cache: web::Data<DatabaseCache>,
+    work_dir: PathBuf,
+    analysis_config: PlacementConfig,
+    output_format: OutputFormat,
+) -> Result<PathBuf, String> {
+    let response = run_placement(
+        &fs_config,
+        &models_config,
+        &database_cache,
+        &work_dir,
+        &analysis_config,
+        output_format,
+    );
+
+    match &response {
+        Ok(_) => ExecutionMsg::write_file(
+            &work_dir.join(fs_config.success_file_name.to_owned()),
+            "Query file processed successfully",
+        ),
+        Err(msg) => ExecutionMsg::write_file(
+            &work_dir.join(fs_config.error_file_name.to_owned()),
+            msg,
+        ),
+    };
+
+    response
+}
+
+/// Place the sequences of a configured work directory
+///
+/// The analysis configuration should be previously saved to the work
+/// directory. By default the placement runs synchronously and the results are
+/// returned as JSONL. If the `background` query parameter is true, a job id is
+/// returned and the job status can be checked using the status endpoint.
+#[utoipa::path(
+    post,
+    path = "/wd/{work_dir_id}/place",
+    tag = "placement",
+    params(
+        ("work_dir_id" = String, Path, description = "Work directory ID"),
+        PlaceSequencesArgs,
+    ),
+    responses(
+        (status = 200, description = "The placements as JSONL"),
+        (status = 202, description = "The ID of the background job"),
+        (
+            status = 404,
+            description = "Configuration not found",
+            body = ApiError
+        ),
+        (
+            status = 409,
+            description = "Analysis already started",
+            body = ApiError
+        ),
+        (status = 422, description = "Placement failed", body = ApiError),
+    )
+)]
+#[instrument(
+    name = "Place sequences",
+    skip(fs_config, models_config, database_cache, query)
+)]
+pub(crate) async fn place_work_dir_sequences(
+    work_dir_id: web::Path<String>,
+    fs_config: web::Data<Mutex<FileSystemConfig>>,
+    models_config: web::Data<Mutex<ModelsConfig>>,
+    database_cache: web::Data<DatabaseCache>,
+    query: web::Query<PlaceSequencesArgs>,
+) -> HttpResponse {
+    let work_dir_id = work_dir_id.into_inner();
+
+    let fs_config = match fs_config.lock() {
+        Ok(res) => res.to_owned(),
+        Err(err) => return ApiError::internal(err).into(),
+    };
+
+    let models_config = match models_config.lock() {
+        Ok(res) => res.to_owned(),
+        Err(err) => return ApiError::internal(err).into(),
+    };
+
+    let work_dir = get_work_dir(&fs_config, &work_dir_id);
+
+    if !work_dir.exists() {
+        return ApiError::not_found(
+            ApiErrorCode::WorkDirNotFound,
+            "Work directory not exists",
+        )
+        .into();
+    }
+
+    let analysis_config = match PlacementConfig::from_yaml_file(
+        &work_dir.join(fs_config.config_file_name.to_owned()),
+    ) {
+        Ok(config) => config,
+        Err(err) => {
+            warn!("{:?}", err);
+            return ApiError::bad_request(
+                ApiErrorCode::InvalidConfig,
+                "Analysis configuration not found or invalid",
+            )
+            .into();
+        }
+    };
+
+    match claim_work_dir(&fs_config, &work_dir) {
+        Ok(true) => (),
+        Ok(false) => {
+            return ApiError::conflict(
+                ApiErrorCode::AnalysisAlreadyStarted,
+                "Analysis already started for this work directory",
+            )
+            .into()
+        }
+        Err(err) => return ApiError::internal(err).into(),
+    };
+
+    if query.background.unwrap_or(false) {
+        let output_format = analysis_config.output_format.to_owned();
+
+        spawn_blocking(move || {
+            run_placement_job(
+                fs_config,
+                models_config,
+                database_cache,
+                work_dir,
+                analysis_config,
+                output_format,
+            )
+        });
+
+        return HttpResponse::Accepted()
+            .json(HashMap::from([("jobId".to_string(), work_dir_id)]));
+    }
+
+    let response = web::block(move || {
+        run_placement_job(
+            fs_config,
+            models_config,
+            database_cache,
+            work_dir,
+            analysis_config,
+            OutputFormat::Jsonl,
+        )
+    })
+    .await;
+
+    match response {
+        Ok(Ok(results_file)) => match std::fs::read(results_file) {
+            Ok(content) => HttpResponse::Ok()
+                .content_type("application/jsonl")
+                .body(content),
+            Err(err) => ApiError::internal(err).into(),
+        },
+        Ok(Err(msg)) => ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ApiErrorCode::PlacementFailed,
+            msg,
+        )
+        .into(),
+        Err(err) => ApiError::internal(err).into(),
+    }
+}
+
+/// Get the placement status of a work directory
+///
+/// The status is resolved from the lock files persisted into the work
+/// directory, then analyses started by the watcher are also reported.
+#[utoipa::path(
+    get,
+    path = "/wd/{work_dir_id}/place/status",
+    tag = "placement",
+    params(("work_dir_id" = String, Path, description = "Work directory ID")),
+    responses(
+        (
+            status = 200,
+            description = "The job status",
+            body = PlacementJobResponse
+        ),
+        (
+            status = 404,
+            description = "Work directory not found",
+            body = ApiError
+        ),
+    )
+)]
+#[instrument(name = "Get placement status", skip(fs_config))]
+pub(crate) async fn get_placement_status(
+    work_dir_id: web::Path<String>,
+    fs_config: web::Data<Mutex<FileSystemConfig>>,
+) -> HttpResponse {
+    let work_dir_id = work_dir_id.into_inner();
+
+    let fs_config = match fs_config.lock() {
+        Ok(res) => res.to_owned(),
+        Err(err) => return ApiError::internal(err).into(),
+    };
+
+    let work_dir = get_work_dir(&fs_config, &work_dir_id);
+
+    let config_file = work_dir.join(fs_config.config_file_name.to_owned());
+
+    if !config_file.exists() {
+        return ApiError::not_found(
+            ApiErrorCode::ConfigNotFound,
+            "Analysis configuration not found",
+        )
+        .into();
+    }
+
+    let error_file = work_dir.join(fs_config.error_file_name.to_owned());
+    let success_file = work_dir.join(fs_config.success_file_name.to_owned());
+    let running_file = work_dir.join(fs_config.running_file_name.to_owned());
+
+    let (status, msg) = if error_file.exists() {
+        (
+            PlacementJobStatus::Error,
+            ExecutionMsg::read_file(&error_file),
+        )
+    } else if success_file.exists() {
+        (
+            PlacementJobStatus::Success,
+            ExecutionMsg::read_file(&success_file),
+        )
+    } else if running_file.exists() {
+        (
+            PlacementJobStatus::Running,
+            ExecutionMsg::read_file(&running_file),
+        )
+    } else {
+        (PlacementJobStatus::Pending, None)
+    };
+
+    let results_file_id = match status {
+        PlacementJobStatus::Success => {
+            PlacementConfig::from_yaml_file(&config_file)
+                .ok()
+                .map(|config| {
+                    get_results_file(
+                        &fs_config,
+                        &work_dir,
+                        &config.output_format,
+                    )
+                })
+                .and_then(|path| path.metadata().ok())
+                .map(|metadata| metadata.ino() as u32)
+        }
+        _ => None,
+    };
+
+    HttpResponse::Ok().json(PlacementJobResponse {
+        job_id: work_dir_id,
+        status,
+        msg,
+        results_file_id,
+    })
+}
+
+/// Place sequences streaming results as they are produced
+///
+/// The request body should contain FASTA or FASTQ records. Placements are
+/// returned as newline delimited JSON, one line per query, in the same order
+/// of the request records. Queries failing to be placed result in a line
+/// containing the query header and the error message. Repeated queries are
+/// served from the placement cache, if configured.
+#[utoipa::path(
+    post,
+    path = "/models/{id}/place",
+    tag = "placement",
+    params(("id" = Uuid, Path, description = "Model ID")),
+    request_body(
+        content = String,
+        description = "FASTA or FASTQ records",
+        content_type = "text/plain",
+    ),
+    responses(
+        (
+            status = 200,
+            description = "The placements as newline delimited JSON"
+        ),
+        (
+            status = 400,
+            description = "Invalid query sequences",
+            body = ApiError
+        ),
+        (status = 404, description = "Model not found", body = ApiError),
+    )
+)]
+#[instrument(
+    name = "Stream sequences placement",
+    skip(models_config, database_cache, placement_cache, body)
+)]
+pub(crate) async fn stream_sequences_placement(
+    model_id: web::Path<Uuid>,
+    models_config: web::Data<Mutex<ModelsConfig>>,
+    database_cache: web::Data<DatabaseCache>,
+    placement_cache: web::Data<Option<PlacementCache>>,
+    body: web::Bytes,
+) -> HttpResponse {
+    let model_id = model_id.into_inner();
+
+    let model = match models_config.lock() {
+        Ok(res) => res
+            .get_models()
+            .into_iter()
+            .find(|model| model.id == model_id),
+        Err(err) => return ApiError::internal(err).into(),
+    };
+
+    let model = match model {
+        Some(model) => model,
+        None => {
+            return ApiError::not_found(
+                ApiErrorCode::ModelNotFound,
+                format!("Model with ID {model_id} not found"),
+            )
+            .into()
+        }
+    };
+
+    let cached_model = model.to_owned();
+
+    let tree =
+        match web::block(move || database_cache.get_or_load(&model)).await {
+            Ok(Ok(tree)) => tree,
+            Ok(Err(err)) => return ApiError::internal(err).into(),
+            Err(err) => return ApiError::internal(err).into(),
+        };
+
+    //
+    // Records are parsed while placed, then malformed records found after the
+    // first placements end the stream instead of failing the request
+    //
+    let starts_with_record = body
+        .split(|byte| *byte == b'\n')
+        .map(|line| line.trim_ascii())
+        .find(|line| !line.is_empty())
+        .is_some_and(|line| line.starts_with(b">") || line.starts_with(b"@"));
+
+    if !starts_with_record {
+        return ApiError::bad_request(
+            ApiErrorCode::InvalidRequest,
+            "Invalid query sequences: records should start with '>' or '@'",
+        )
+        .into();
+    }
+
+    let (sequence_sender, sequence_receiver) =
+        sync_channel(FileOrStdin::CHANNEL_BOUND);
+
+    let sequence_reader = thread::spawn(move || {
+        FileOrStdin::sequence_content_from_reader(
+            body.as_ref(),
+            sequence_sender,
+            &QualityFilter::default(),
+        )
+    });
+
+    let (line_sender, line_receiver) =
+        tokio::sync::mpsc::channel::<web::Bytes>(STREAM_BUFFER_SIZE);
+
+    spawn_blocking(move || {
+        for sequence in sequence_receiver {
+            let header = sequence.header_content();
+            let query = sequence.sequence_content();
+
+            let cached = placement_cache
+                .get_ref()
+                .as_ref()
+                .and_then(|cache| cache.get(&cached_model, header, query));
+
+            //
+            // Failed queries are streamed as error records, then they are not
+            // cached and clients are able to retry them
+            //
+            let content = match cached {
+                Some(content) => content,
+                None => match place_one(
+                    header,
+                    query,
+                    &tree,
+                    &PlacementOptions::default(),
+                    &None,
+                )
+                .map_err(|err| err.to_string())
+                .and_then(|response| {
+                    serde_json::to_string(&response)
+                        .map_err(|err| err.to_string())
+                }) {
+                    Ok(content) => {
+                        if let Some(cache) = placement_cache.get_ref() {
+                            if let Err(err) = cache.insert(
+                                &cached_model,
+                                header,
+                                query,
+                                &content,
+                            ) {
+                                warn!(
+                                    "Failed to cache placement {header}: {err}"
+                                );
+                            }
+                        }
+
+                        content
+                    }
+                    Err(err) => {
+                        warn!("Failed to place sequence {header}: {err}");
+                        PlacementErrorRecord::to_content(header, err)
+                    }
+                },
+            };
+
+            let line = format!("{content}\n");
+
+            //
+            // The send blocks while the buffer is full and fails when the
+            // client disconnects
+            //
+            if line_sender.blocking_send(web::Bytes::from(line)).is_err() {
+                break;
+            }
+        }
+
+        //
+        // The receiver is dropped with the loop, then the reader stops if the
+        // client disconnected
+        //
+        if let Ok(Err(err)) = sequence_reader.join() {
+            warn!("Invalid query sequences: {err}");
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(futures::stream::unfold(
+            line_receiver,
+            |mut receiver| async move {
+                receiver
+                    .recv()
+                    .await
+                    .map(|line| (Ok::<_, actix_web::Error>(line), receiver))
+            },
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoints::fs;
+
+    use actix_web::{rt::time::sleep, test, App};
+    use std::time::Duration;
+
+    #[actix_web::test]
+    async fn test_place_work_dir_sequences_in_background() {
+        let serve_directory = PathBuf::from("/tmp/cls-api-placement");
+        let work_dir_id = "019051d9-4c7a-7b2d-9dd1-66ef92236fd9";
+        let work_dir = serve_directory.join("public").join(work_dir_id);
+
+        let _ = std::fs::remove_dir_all(&serve_directory);
+        std::fs::create_dir_all(work_dir.join("input")).unwrap();
+
+        let query_file = work_dir.join("input").join("query.fasta");
+
+        std::fs::copy(
+            "../../tests/data/public/019051d9-4c7a-7b2d-9dd1-66ef92236fd7/input/bsub-refseq-sample50percentRemaining-clean-diamond.fasta",
+            &query_file,
+        )
+        .unwrap();
+
+        let fs_config = FileSystemConfig {
+            public_directory: "public".to_string(),
+            serve_directory: serve_directory.to_str().unwrap().to_string(),
+            input_directory: "input".to_string(),
+            output_directory: "output".to_string(),
+            config_file_name: "config.yaml".to_string(),
+            results_file_name: "result.yaml".to_string(),
+            success_file_name: "success.yaml".to_string(),
+            running_file_name: "running.yaml".to_string(),
+            error_file_name: "error.yaml".to_string(),
+            logging_file_name: "logging.jsonl".to_string(),
+            max_upload_bytes: None,
+        };
+
+        let models_config: ModelsConfig = serde_yaml::from_str(
+            "- id: ce47d8bc-2885-3d2c-8247-5b8c8b28fefe\n  \
+            name: Bacillus subtilis group\n  \
+            modelPath: ../../tests/models/bsub-gyrb-k35.cls\n",
+        )
+        .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Mutex::new(fs_config)))
+                .app_data(web::Data::new(Mutex::new(models_config)))
+                .app_data(web::Data::new(DatabaseCache::new(1)))
+                .route(
+                    "/wd/{work_dir_id}/config",
+                    web::post().to(fs::configure_placement_analysis),
+                )
+                .route(
+                    "/wd/{work_dir_id}/place",
+                    web::post().to(place_work_dir_sequences),
+                )
+                .route(
+                    "/wd/{work_dir_id}/place/status",
+                    web::get().to(get_placement_status),
+                ),
+        )
+        .await;
+
+        let config = serde_yaml::from_str::<PlacementConfig>(&format!(
+            "name: test-analysis\n\
+            email: test@example.com\n\
+            queryFileId: {inode}\n\
+            modelId: ce47d8bc-2885-3d2c-8247-5b8c8b28fefe\n\
+            outputFormat: jsonl\n\
+            workDir: {work_dir_id}\n",
+            inode = std::fs::metadata(&query_file).unwrap().ino(),
+        ))
+        .unwrap();
+
+        let response = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri(&format!("/wd/{work_dir_id}/config"))
+                .set_json(&config)
+                .to_request(),
+        )
+        .await;
+
+        assert_eq!(response.status().as_u16(), 201);
+
+        let response = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri(&format!("/wd/{work_dir_id}/place?background=true"))
+                .to_request(),
+        )
+        .await;
+
+        assert_eq!(response.status().as_u16(), 202);
+
+        let mut response: Option<PlacementJobResponse> = None;
+
+        for _ in 0..120 {
+            let job: PlacementJobResponse = test::call_and_read_body_json(
+                &app,
+                test::TestRequest::get()
+                    .uri(&format!("/wd/{work_dir_id}/place/status"))
+                    .to_request(),
+            )
+            .await;
+
+            if job.status != PlacementJobStatus::Running {
+                response = Some(job);
+                break;
+            }
+
+            sleep(Duration::from_millis(500)).await;
+        }
+
+        let response = response.expect("Placement job not finished");
+
+        assert_eq!(response.status, PlacementJobStatus::Success);
+        assert!(response.results_file_id.is_some());
+
+        let results = std::fs::read_to_string(
+            work_dir.join("output").join("result.jsonl"),
+        )
+        .unwrap();
+
+        assert!(results.lines().count() > 0);
+    }
+
+    /// Stream the placements of the first three sample queries
+    async fn stream_sample_placements(
+        placement_cache: web::Data<Option<PlacementCache>>,
+    ) -> Vec<String> {
+        //
+        // Take the first three records of the sample queries
+        //
+        let queries = std::fs::read_to_string(
+            "../../tests/data/public/019051d9-4c7a-7b2d-9dd1-66ef92236fd7/input/bsub-refseq-sample50percentRemaining-clean-diamond.fasta",
+        )
+        .unwrap()
+        .split('>')
+        .filter(|record| !record.is_empty())
+        .take(3)
+        .map(|record| format!(">{record}"))
+        .collect::<String>();
+
+        stream_placements(placement_cache, queries).await
+    }
+
+    async fn stream_placements(
+        placement_cache: web::Data<Option<PlacementCache>>,
+        queries: String,
+    ) -> Vec<String> {
+        use actix_web::body::MessageBody;
+        use futures::future::poll_fn;
+        use std::pin::Pin;
+
+        let models_config: ModelsConfig = serde_yaml::from_str(
+            "- id: ce47d8bc-2885-3d2c-8247-5b8c8b28fefe\n  \
+            name: Bacillus subtilis group\n  \
+            modelPath: ../../tests/models/bsub-gyrb-k35.cls\n",
+        )
+        .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Mutex::new(models_config)))
+                .app_data(web::Data::new(DatabaseCache::new(1)))
+                .app_data(placement_cache)
+                .route(
+                    "/models/{id}/place",
+                    web::post().to(stream_sequences_placement),
+                ),
+        )
+        .await;
+
+        let response = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/models/ce47d8bc-2885-3d2c-8247-5b8c8b28fefe/place")
+                .set_payload(queries)
+                .to_request(),
+        )
+        .await;
+
+        assert_eq!(response.status().as_u16(), 200);
+
+        //
+        // Each placement is sent as a separate chunk
+        //
+        let mut body = response.into_body();
+        let mut lines = Vec::<String>::new();
+
+        while let Some(chunk) =
+            poll_fn(|cx| Pin::new(&mut body).poll_next(cx)).await
+        {
+            lines.push(String::from_utf8(chunk.unwrap().to_vec()).unwrap());
+        }
+
+        lines
+    }
+
+    #[actix_web::test]
+    async fn test_stream_sequences_placement() {
+        use classeq_core::domain::dtos::placement_response::{
+            PlacementResponse, PlacementStatus,
+        };
+
+        let lines = stream_sample_placements(web::Data::new(None)).await;
+
+        assert_eq!(lines.len(), 3);
+
+        for line in lines {
+            assert_eq!(line.matches('\n').count(), 1);
+
+            serde_json::from_str::<PlacementResponse<PlacementStatus>>(&line)
+                .unwrap();
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_stream_sequences_placement_with_failed_queries() {
+        let queries = std::fs::read_to_string(
+            "../../tests/data/public/019051d9-4c7a-7b2d-9dd1-66ef92236fd7/input/bsub-refseq-sample50percentRemaining-clean-diamond.fasta",
+        )
+        .unwrap()
+        .split('>')
+        .filter(|record| !record.is_empty())
+        .take(1)
+        .map(|record| format!(">Too_short\nACGT\n>{record}"))
+        .collect::<String>();
+
+        let lines = stream_placements(web::Data::new(None), queries).await;
+
+        //
+        // The query without kmers is reported in place of being omitted
+        //
+        assert_eq!(lines.len(), 2);
+
+        let record =
+            serde_json::from_str::<PlacementErrorRecord>(&lines[0]).unwrap();
+
+        assert_eq!(record.query, "Too_short");
+        assert!(record.error.contains("enough kmers"));
+    }
+
+    #[actix_web::test]
+    async fn test_stream_sequences_placement_from_cache() {
+        let directory = PathBuf::from("/tmp/cls-api-placement-cache");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let placement_cache =
+            web::Data::new(Some(PlacementCache::new(directory)));
+
+        let first = stream_sample_placements(placement_cache.clone()).await;
+        let second = stream_sample_placements(placement_cache.clone()).await;
+
+        //
+        // The second placement of the same queries is served from cache
+        //
+        let hits = placement_cache.get_ref().as_ref().unwrap().hits_count();
+
+        assert_eq!(first.len(), 3);
+        assert_eq!(first, second);
+        assert_eq!(hits, 3);
+    }
+}
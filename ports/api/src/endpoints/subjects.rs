@@ -1,20 +1,555 @@
-use actix_web::{web, HttpResponse};
-use classeq_ports_lib::ModelsConfig;
-use std::sync::Mutex;
+use super::fs::reject_upload;
+use crate::models::{
+    api_config::ModelsRegistryConfig,
+    api_error::{ApiError, ApiErrorCode},
+};
+
+use actix_multipart::{Field, Multipart};
+use actix_web::{http::StatusCode, web, HttpResponse};
+use actix_web_httpauth::extractors::bearer::BearerAuth;
+use classeq_ports_lib::{
+    load_database, DatabaseStats, ModelConfig, ModelsConfig,
+};
+use futures::StreamExt;
+use std::{collections::HashMap, path::Path, sync::Mutex};
+use subtle::ConstantTimeEq;
+use tokio::io::AsyncWriteExt;
 use tracing::{error, instrument};
+use uuid::Uuid;
 
+/// List the available models
+#[utoipa::path(
+    get,
+    path = "/models",
+    tag = "models",
+    responses(
+        (
+            status = 200,
+            description = "The available models",
+            body = [ModelConfig]
+        ),
+    )
+)]
 #[instrument(name = "List available models", skip(config))]
 pub(crate) async fn list_available_models(
     config: web::Data<Mutex<ModelsConfig>>,
 ) -> HttpResponse {
     match config.lock() {
-        Err(err) => {
-            error!("{:?}", err);
-            return HttpResponse::InternalServerError().finish();
-        }
+        Err(err) => return ApiError::internal(err).into(),
         Ok(res) => {
             let trees = res.to_owned().get_models();
             HttpResponse::Ok().json(trees)
         }
     }
 }
+
+/// Get the statistics of a single model
+///
+/// Statistics are calculated once and cached by model ID, avoiding to reload
+/// the database file on subsequent calls.
+#[utoipa::path(
+    get,
+    path = "/models/{id}",
+    tag = "models",
+    params(("id" = Uuid, Path, description = "Model ID")),
+    responses(
+        (
+            status = 200,
+            description = "The model statistics",
+            body = DatabaseStats
+        ),
+        (status = 404, description = "Model not found", body = ApiError),
+    )
+)]
+#[instrument(name = "Get model stats", skip(config, cache))]
+pub(crate) async fn get_model_stats(
+    model_id: web::Path<Uuid>,
+    config: web::Data<Mutex<ModelsConfig>>,
+    cache: web::Data<Mutex<HashMap<Uuid, DatabaseStats>>>,
+) -> HttpResponse {
+    let model_id = model_id.into_inner();
+
+    match cache.lock() {
+        Err(err) => return ApiError::internal(err).into(),
+        Ok(res) => {
+            if let Some(stats) = res.get(&model_id) {
+                return HttpResponse::Ok().json(stats);
+            }
+        }
+    };
+
+    let model = match config.lock() {
+        Err(err) => return ApiError::internal(err).into(),
+        Ok(res) => res
+            .get_models()
+            .into_iter()
+            .find(|model| model.id == model_id),
+    };
+
+    let model = match model {
+        Some(model) => model,
+        None => {
+            return ApiError::not_found(
+                ApiErrorCode::ModelNotFound,
+                format!("Model with ID {model_id} not found"),
+            )
+            .into()
+        }
+    };
+
+    let stats = match web::block(move || {
+        load_database(model.model_path())
+            .map(|tree| DatabaseStats::from_tree(&tree))
+    })
+    .await
+    {
+        Ok(Ok(stats)) => stats,
+        Ok(Err(err)) => return ApiError::internal(err).into(),
+        Err(err) => return ApiError::internal(err).into(),
+    };
+
+    match cache.lock() {
+        Err(err) => error!("{:?}", err),
+        Ok(mut res) => {
+            res.insert(model_id, stats.to_owned());
+        }
+    };
+
+    HttpResponse::Ok().json(stats)
+}
+
+/// Read a text field of a multipart upload
+async fn read_text_field(field: &mut Field) -> Result<String, ApiError> {
+    let mut content = Vec::<u8>::new();
+
+    while let Some(chunk) = field.next().await {
+        match chunk {
+            Ok(chunk) => content.extend_from_slice(&chunk),
+            Err(err) => {
+                return Err(ApiError::bad_request(
+                    ApiErrorCode::InvalidRequest,
+                    format!("Invalid multipart content: {err}"),
+                ))
+            }
+        }
+    }
+
+    match String::from_utf8(content) {
+        Ok(text) => Ok(text.trim().to_string()),
+        Err(err) => Err(ApiError::bad_request(
+            ApiErrorCode::InvalidRequest,
+            format!("Invalid text field: {err}"),
+        )),
+    }
+}
+
+/// Read the model metadata, writing the database file to `upload_path`
+async fn read_model_upload(
+    payload: &mut Multipart,
+    upload_path: &Path,
+) -> Result<(String, Option<String>), ApiError> {
+    let mut name: Option<String> = None;
+    let mut gene: Option<String> = None;
+    let mut has_file = false;
+
+    while let Some(field) = payload.next().await {
+        let mut field = match field {
+            Ok(field) => field,
+            Err(err) => {
+                return Err(ApiError::bad_request(
+                    ApiErrorCode::InvalidRequest,
+                    format!("Invalid multipart content: {err}"),
+                ))
+            }
+        };
+
+        match field.name().to_owned().as_str() {
+            "name" => name = Some(read_text_field(&mut field).await?),
+            "gene" => gene = Some(read_text_field(&mut field).await?),
+            "file" => {
+                let mut file = tokio::fs::File::create(upload_path)
+                    .await
+                    .map_err(ApiError::internal)?;
+
+                while let Some(chunk) = field.next().await {
+                    let chunk = chunk.map_err(|err| {
+                        ApiError::bad_request(
+                            ApiErrorCode::InvalidRequest,
+                            format!("Invalid multipart content: {err}"),
+                        )
+                    })?;
+
+                    file.write_all(&chunk).await.map_err(ApiError::internal)?;
+                }
+
+                //
+                // Pending writes should be done before the database loading
+                //
+                file.flush().await.map_err(ApiError::internal)?;
+                has_file = true;
+            }
+            _ => continue,
+        }
+    }
+
+    let name = match name {
+        Some(name) if !name.is_empty() => name,
+        _ => {
+            return Err(ApiError::bad_request(
+                ApiErrorCode::InvalidRequest,
+                "The model name should be provided",
+            ))
+        }
+    };
+
+    if !has_file {
+        return Err(ApiError::bad_request(
+            ApiErrorCode::InvalidRequest,
+            "The database file should be provided",
+        ));
+    }
+
+    Ok((name, gene.filter(|gene| !gene.is_empty())))
+}
+
+/// Upload a reference database and register it as a model
+///
+/// Requests should be authenticated with the admin token of the models
+/// registry (`Authorization: Bearer <token>`). The uploaded file should be a
+/// loadable database, and the database ID is used as the model ID. Uploaded
+/// models are persisted in the registry file, then kept after restarts.
+#[utoipa::path(
+    post,
+    path = "/models",
+    tag = "models",
+    request_body(
+        content = String,
+        description = "The model name, gene (optional) and database file",
+        content_type = "multipart/form-data",
+    ),
+    responses(
+        (
+            status = 201,
+            description = "The model was registered",
+            body = ModelConfig
+        ),
+        (status = 400, description = "Invalid database", body = ApiError),
+        (
+            status = 401,
+            description = "Missing or invalid token",
+            body = ApiError
+        ),
+        (status = 403, description = "Uploads not enabled", body = ApiError),
+        (status = 409, description = "Model already exists", body = ApiError),
+    )
+)]
+#[instrument(name = "Register model", skip(config, registry, auth, payload))]
+pub(crate) async fn register_model(
+    config: web::Data<Mutex<ModelsConfig>>,
+    registry: web::Data<Option<ModelsRegistryConfig>>,
+    auth: Option<BearerAuth>,
+    mut payload: Multipart,
+) -> HttpResponse {
+    let registry = match registry.get_ref() {
+        Some(registry) => registry,
+        None => {
+            return ApiError::new(
+                StatusCode::FORBIDDEN,
+                ApiErrorCode::PermissionDenied,
+                "Model uploads are not enabled",
+            )
+            .into()
+        }
+    };
+
+    let authorized = auth.is_some_and(|auth| {
+        auth.token()
+            .as_bytes()
+            .ct_eq(registry.admin_token.as_bytes())
+            .into()
+    });
+
+    if !authorized {
+        return ApiError::unauthorized("Missing or invalid bearer token")
+            .into();
+    }
+
+    if let Err(err) = std::fs::create_dir_all(&registry.directory) {
+        return ApiError::internal(err).into();
+    };
+
+    //
+    // The database is saved with a temporary name until its ID is known
+    //
+    let upload_path = registry
+        .directory
+        .join(format!("{id}.upload", id = Uuid::new_v4()));
+
+    let (name, gene) = match read_model_upload(&mut payload, &upload_path).await
+    {
+        Ok(res) => res,
+        Err(err) if upload_path.exists() => {
+            return reject_upload(&upload_path, err)
+        }
+        Err(err) => return err.into(),
+    };
+
+    let model_id = match web::block({
+        let upload_path = upload_path.to_owned();
+        move || load_database(upload_path).map(|tree| tree.id)
+    })
+    .await
+    {
+        Ok(Ok(id)) => id,
+        Ok(Err(err)) => {
+            return reject_upload(
+                &upload_path,
+                ApiError::bad_request(
+                    ApiErrorCode::InvalidModel,
+                    format!("Uploaded file is not a valid database: {err}"),
+                ),
+            )
+        }
+        Err(err) => {
+            return reject_upload(&upload_path, ApiError::internal(err))
+        }
+    };
+
+    let model_path = registry.directory.join(format!("{model_id}.cls"));
+    let model =
+        ModelConfig::new(model_id, name, gene, model_path.to_owned(), None);
+
+    let mut models = match config.lock() {
+        Ok(res) => res,
+        Err(err) => {
+            return reject_upload(&upload_path, ApiError::internal(err))
+        }
+    };
+
+    if models.0.iter().any(|model| model.id == model_id) {
+        return reject_upload(
+            &upload_path,
+            ApiError::conflict(
+                ApiErrorCode::ModelAlreadyExists,
+                format!("Model with ID {model_id} already exists"),
+            ),
+        );
+    }
+
+    //
+    // Only uploaded models are persisted in the registry. Models of the
+    // configuration file are kept there.
+    //
+    let registry_path = registry.registry_path();
+
+    let mut registered = match ModelsConfig::load_registry(&registry_path) {
+        Ok(res) => res,
+        Err(err) => {
+            return reject_upload(&upload_path, ApiError::internal(err))
+        }
+    };
+
+    registered.0.push(model.to_owned());
+
+    if let Err(err) = std::fs::rename(&upload_path, &model_path) {
+        return reject_upload(&upload_path, ApiError::internal(err));
+    };
+
+    if let Err(err) = registered.write_registry(&registry_path) {
+        return reject_upload(&model_path, ApiError::internal(err));
+    };
+
+    models.0.push(model.to_owned());
+
+    HttpResponse::Created().json(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use actix_web::{test, App};
+    use classeq_core::{
+        domain::dtos::{
+            build_options::BuildOptions, file_or_stdin::FileOrStdin,
+        },
+        use_cases::map_kmers_to_tree,
+    };
+    use serde_json::Value;
+    use std::path::PathBuf;
+
+    #[actix_web::test]
+    async fn test_get_model_stats() {
+        let tree_path = PathBuf::from("/tmp/cls-api-model-stats.nwk");
+        let msa_path = PathBuf::from("/tmp/cls-api-model-stats.fasta");
+        let model_path = PathBuf::from("/tmp/cls-api-model-stats.yaml");
+
+        std::fs::write(&tree_path, "(Seq_a:0.1,Seq_b:0.2);").unwrap();
+        std::fs::write(
+            &msa_path,
+            ">Seq_a\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCATCGAT\n\
+            >Seq_b\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCTTTTTT\n",
+        )
+        .unwrap();
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        std::fs::write(&model_path, serde_yaml::to_string(&tree).unwrap())
+            .unwrap();
+
+        let models_config: ModelsConfig = serde_yaml::from_str(&format!(
+            "- id: {id}\n  name: Two leaves\n  modelPath: {path}\n",
+            id = tree.id,
+            path = model_path.to_str().unwrap()
+        ))
+        .unwrap();
+
+        let cache = HashMap::<Uuid, DatabaseStats>::new();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Mutex::new(models_config)))
+                .app_data(web::Data::new(Mutex::new(cache)))
+                .route("/models/{id}", web::get().to(get_model_stats)),
+        )
+        .await;
+
+        for _ in 0..2 {
+            let stats: DatabaseStats = test::call_and_read_body_json(
+                &app,
+                test::TestRequest::get()
+                    .uri(&format!("/models/{id}", id = tree.id))
+                    .to_request(),
+            )
+            .await;
+
+            assert_eq!(stats.id, tree.id);
+            assert_eq!(stats.leaf_count, 2);
+            assert_eq!(stats.kmer_size, Some(35));
+            assert_eq!(stats.minimizer_size, Some(4));
+            assert!(stats.kmer_count.unwrap_or(0) > 0);
+        }
+
+        let response = test::call_service(
+            &app,
+            test::TestRequest::get()
+                .uri(&format!("/models/{id}", id = Uuid::new_v4()))
+                .to_request(),
+        )
+        .await;
+
+        assert_eq!(response.status().as_u16(), 404);
+    }
+
+    #[actix_web::test]
+    async fn test_register_model() {
+        let directory = PathBuf::from("/tmp/cls-api-register-model");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let tree_path = directory.with_extension("nwk");
+        let msa_path = directory.with_extension("fasta");
+
+        std::fs::write(&tree_path, "(Seq_a:0.1,Seq_b:0.2);").unwrap();
+        std::fs::write(
+            &msa_path,
+            ">Seq_a\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCATCGAT\n\
+            >Seq_b\nACGTACGTTTGACCATGACGATCGATCGGGCTAGCTAGCTTTTTT\n",
+        )
+        .unwrap();
+
+        let tree = map_kmers_to_tree(
+            tree_path,
+            FileOrStdin::from_file(msa_path.to_str().unwrap()),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+        let database = serde_yaml::to_string(&tree).unwrap();
+
+        let registry = ModelsRegistryConfig {
+            admin_token: "secret".to_string(),
+            directory: directory.to_owned(),
+        };
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(Mutex::new(ModelsConfig(vec![]))))
+                .app_data(web::Data::new(Some(registry.to_owned())))
+                .route("/models", web::get().to(list_available_models))
+                .route("/models", web::post().to(register_model)),
+        )
+        .await;
+
+        let upload = |token: &str, content: &str| {
+            test::TestRequest::post()
+                .uri("/models")
+                .insert_header(("authorization", format!("Bearer {token}")))
+                .insert_header((
+                    "content-type",
+                    "multipart/form-data; boundary=boundary",
+                ))
+                .set_payload(format!(
+                    "--boundary\r\n\
+                    Content-Disposition: form-data; name=\"name\"\r\n\r\n\
+                    Two leaves\r\n\
+                    --boundary\r\n\
+                    Content-Disposition: form-data; name=\"gene\"\r\n\r\n\
+                    gapdh\r\n\
+                    --boundary\r\n\
+                    Content-Disposition: form-data; name=\"file\"; \
+                    filename=\"tiny.cls\"\r\n\
+                    Content-Type: application/octet-stream\r\n\r\n\
+                    {content}\r\n\
+                    --boundary--\r\n"
+                ))
+                .to_request()
+        };
+
+        let service = &app;
+        let status = |token: &str, content: &str| {
+            let request = upload(token, content);
+
+            async move {
+                test::call_service(service, request).await.status().as_u16()
+            }
+        };
+
+        assert_eq!(status("wrong", &database).await, 401);
+        assert_eq!(status("secret", "not a database").await, 400);
+        assert_eq!(std::fs::read_dir(&directory).unwrap().count(), 0);
+
+        let model: Value =
+            test::call_and_read_body_json(&app, upload("secret", &database))
+                .await;
+
+        assert_eq!(model["id"], tree.id.to_string());
+        assert_eq!(model["gene"], "gapdh");
+        assert!(model.get("modelPath").is_none());
+
+        assert_eq!(status("secret", &database).await, 409);
+
+        //
+        // The model is listed and persisted in the registry
+        //
+        let models: Vec<Value> = test::call_and_read_body_json(
+            &app,
+            test::TestRequest::get().uri("/models").to_request(),
+        )
+        .await;
+
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0]["id"], tree.id.to_string());
+        assert_eq!(models[0]["name"], "Two leaves");
+
+        let registered =
+            ModelsConfig::load_registry(&registry.registry_path()).unwrap();
+
+        assert_eq!(registered.0.len(), 1);
+        assert!(registered.0[0].model_path().exists());
+        assert!(load_database(registered.0[0].model_path()).is_ok());
+    }
+}
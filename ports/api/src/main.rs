@@ -1,17 +1,29 @@
 mod endpoints;
+mod middlewares;
 mod models;
 
 use endpoints::fs;
 
-use actix_web::{web, App, HttpResponse, HttpServer};
+use actix_web::{
+    error::JsonPayloadError, http::StatusCode, middleware::from_fn, web, App,
+    HttpResponse, HttpServer,
+};
 use actix_web_opentelemetry::RequestTracing;
-use models::api_config::ApiConfig;
-use std::{path::PathBuf, sync::Mutex};
+use classeq_ports_lib::{
+    DatabaseCache, DatabaseStats, ModelsConfig, PlacementCache,
+};
+use middlewares::request_limits::{limit_requests, RequestLimits};
+use models::{
+    api_config::ApiConfig,
+    api_error::{ApiError, ApiErrorCode},
+};
+use std::{collections::HashMap, path::PathBuf, sync::Mutex};
 use tracing::{info, subscriber::set_global_default};
 use tracing_actix_web::TracingLogger;
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
 use tracing_subscriber::{layer::SubscriberExt, EnvFilter, Registry};
+use uuid::Uuid;
 
 const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 
@@ -19,6 +31,25 @@ async fn health_check() -> HttpResponse {
     HttpResponse::Ok().body("healthy")
 }
 
+/// Map request extraction errors to the API error shape
+fn invalid_request_error(err: impl std::fmt::Display) -> actix_web::Error {
+    ApiError::bad_request(ApiErrorCode::InvalidRequest, err.to_string()).into()
+}
+
+/// Map JSON extraction errors, reporting oversized bodies as such
+fn invalid_json_error(err: JsonPayloadError) -> actix_web::Error {
+    match err {
+        JsonPayloadError::Overflow { .. }
+        | JsonPayloadError::OverflowKnownLength { .. } => ApiError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            ApiErrorCode::PayloadTooLarge,
+            err.to_string(),
+        )
+        .into(),
+        err => invalid_request_error(err),
+    }
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     // ? -----------------------------------------------------------------------
@@ -41,10 +72,69 @@ async fn main() -> std::io::Result<()> {
     };
 
     let server_config = config.to_owned().server;
-    let trees_config = config.to_owned().models;
+    let mut trees_config = config.to_owned().models;
     let fs_config = config.to_owned().fs;
     let workers = server_config.workers.unwrap_or(1);
 
+    //
+    // Models uploaded through the API are persisted apart from the
+    // configuration file
+    //
+    if let Some(registry) = server_config.models_registry.as_ref() {
+        match ModelsConfig::load_registry(&registry.registry_path()) {
+            Ok(registered) => trees_config.0.extend(registered.0),
+            Err(err) => panic!("Error on load models registry: {err}"),
+        }
+    }
+
+    //
+    // Models are shared between all server workers, then models registered
+    // by any worker are visible to the others
+    //
+    let trees_config = web::Data::new(Mutex::new(trees_config));
+    let models_registry =
+        web::Data::new(server_config.models_registry.to_owned());
+
+    //
+    // The models statistics cache is shared between all server workers
+    //
+    let models_stats_cache =
+        web::Data::new(Mutex::new(HashMap::<Uuid, DatabaseStats>::new()));
+
+    //
+    // Loaded databases are also shared between workers, avoiding to reload
+    // the same model on each placement request
+    //
+    let database_cache = web::Data::new(DatabaseCache::new(
+        server_config.max_cached_models.unwrap_or(1),
+    ));
+
+    //
+    // Placements of repeated queries are served from the on-disk cache, if
+    // configured
+    //
+    let placement_cache = web::Data::new(
+        server_config
+            .placement_cache_directory
+            .to_owned()
+            .map(PlacementCache::new),
+    );
+
+    //
+    // Limits are shared between workers, then the rate limit and concurrent
+    // uploads are counted for the whole server
+    //
+    let request_limits =
+        web::Data::new(RequestLimits::from_config(&server_config));
+
+    let json_config = web::JsonConfig::default()
+        .error_handler(|err, _| invalid_json_error(err));
+
+    let (json_config, payload_config) = match server_config.max_body_bytes {
+        Some(max) => (json_config.limit(max), web::PayloadConfig::new(max)),
+        None => (json_config, web::PayloadConfig::default()),
+    };
+
     let address = (
         server_config.to_owned().address,
         server_config.to_owned().port,
@@ -76,12 +166,34 @@ async fn main() -> std::io::Result<()> {
 
     HttpServer::new(move || {
         App::new()
+            .wrap(from_fn(limit_requests))
             .wrap(RequestTracing::new())
             .wrap(TracingLogger::default())
             .app_data(web::Data::new(Mutex::new(fs_config.clone())))
-            .app_data(web::Data::new(Mutex::new(trees_config.clone())))
+            .app_data(trees_config.clone())
+            .app_data(models_registry.clone())
+            .app_data(models_stats_cache.clone())
+            .app_data(database_cache.clone())
+            .app_data(placement_cache.clone())
+            .app_data(request_limits.clone())
+            .app_data(json_config.clone())
+            .app_data(payload_config.clone())
+            .app_data(
+                web::QueryConfig::default()
+                    .error_handler(|err, _| invalid_request_error(err)),
+            )
+            .app_data(
+                web::PathConfig::default()
+                    .error_handler(|err, _| invalid_request_error(err)),
+            )
+            .route("/health", web::get().to(endpoints::health::check_health))
+            .route(
+                "/openapi.json",
+                web::get().to(endpoints::openapi::get_openapi_spec),
+            )
             .route("/wd", web::post().to(fs::init_wd))
             .route("/wd/{work_dir_id}", web::get().to(fs::list_wd_content))
+            .route("/wd/{work_dir_id}", web::delete().to(fs::delete_wd))
             .route(
                 "/wd/{work_dir_id}",
                 web::post().to(fs::upload_analysis_file),
@@ -90,6 +202,14 @@ async fn main() -> std::io::Result<()> {
                 "/wd/{work_dir_id}/config",
                 web::post().to(fs::configure_placement_analysis),
             )
+            .route(
+                "/wd/{work_dir_id}/place",
+                web::post().to(endpoints::placement::place_work_dir_sequences),
+            )
+            .route(
+                "/wd/{work_dir_id}/place/status",
+                web::get().to(endpoints::placement::get_placement_status),
+            )
             .route(
                 "/wd/{work_dir_id}/{file_id}",
                 web::get().to(fs::get_file_content_by_id),
@@ -98,6 +218,19 @@ async fn main() -> std::io::Result<()> {
                 "/models",
                 web::get().to(endpoints::subjects::list_available_models),
             )
+            .route(
+                "/models",
+                web::post().to(endpoints::subjects::register_model),
+            )
+            .route(
+                "/models/{id}",
+                web::get().to(endpoints::subjects::get_model_stats),
+            )
+            .route(
+                "/models/{id}/place",
+                web::post()
+                    .to(endpoints::placement::stream_sequences_placement),
+            )
             .default_service(web::get().to(health_check))
     })
     .bind(address)?
@@ -0,0 +1,314 @@
+use crate::models::{
+    api_config::ServerConfig,
+    api_error::{ApiError, ApiErrorCode},
+};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    web, Error,
+};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Count requests per client IP in fixed time windows
+pub(crate) struct RateLimiter {
+    max_requests: u32,
+    window: Duration,
+    clients: Mutex<HashMap<Option<IpAddr>, (Instant, u32)>>,
+}
+
+impl RateLimiter {
+    /// The number of tracked clients before expired windows are dropped
+    const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+    pub(crate) fn new(max_requests: u32, window: Duration) -> Self {
+        RateLimiter {
+            max_requests,
+            window,
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Register a request returning false if the client exceeded the limit
+    fn allow(&self, client: Option<IpAddr>) -> bool {
+        let now = Instant::now();
+
+        let mut clients = match self.clients.lock() {
+            Ok(clients) => clients,
+            Err(_) => return true,
+        };
+
+        if clients.len() >= Self::MAX_TRACKED_CLIENTS {
+            clients.retain(|_, (start, _)| now - *start < self.window);
+        }
+
+        let (start, count) = clients.entry(client).or_insert((now, 0));
+
+        if now - *start >= self.window {
+            *start = now;
+            *count = 0;
+        }
+
+        *count += 1;
+        *count <= self.max_requests
+    }
+}
+
+/// A slot of a running upload, released on drop
+struct UploadSlot<'a>(&'a AtomicUsize);
+
+impl Drop for UploadSlot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The request limits shared by all server workers
+pub(crate) struct RequestLimits {
+    max_body_bytes: Option<usize>,
+    rate_limiter: Option<RateLimiter>,
+    max_concurrent_uploads: Option<usize>,
+    running_uploads: AtomicUsize,
+}
+
+impl RequestLimits {
+    pub(crate) fn from_config(config: &ServerConfig) -> Self {
+        RequestLimits {
+            max_body_bytes: config.max_body_bytes,
+            rate_limiter: config.rate_limit.as_ref().map(|limit| {
+                RateLimiter::new(
+                    limit.max_requests,
+                    Duration::from_secs(limit.window_secs),
+                )
+            }),
+            max_concurrent_uploads: config.max_concurrent_uploads,
+            running_uploads: AtomicUsize::new(0),
+        }
+    }
+
+    /// Check the rate and the body size limits
+    ///
+    /// Requests without the `Content-Length` header are limited by the JSON
+    /// and payload extractors configuration.
+    fn check(&self, req: &ServiceRequest) -> Option<ApiError> {
+        if let Some(limiter) = &self.rate_limiter {
+            if !limiter.allow(req.peer_addr().map(|addr| addr.ip())) {
+                return Some(ApiError::too_many_requests(
+                    ApiErrorCode::RateLimitExceeded,
+                    "Too many requests, try again later",
+                ));
+            }
+        }
+
+        let content_length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+
+        match (self.max_body_bytes, content_length) {
+            (Some(max), Some(length)) if length > max => Some(ApiError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ApiErrorCode::PayloadTooLarge,
+                format!("Request body exceeds the limit of {max} bytes"),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Take an upload slot, returning `None` if all slots are in use
+    fn acquire_upload(&self) -> Option<UploadSlot<'_>> {
+        let max = self.max_concurrent_uploads.unwrap_or(usize::MAX);
+
+        self.running_uploads
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |running| {
+                (running < max).then_some(running + 1)
+            })
+            .ok()
+            .map(|_| UploadSlot(&self.running_uploads))
+    }
+}
+
+/// Check if the request uploads files
+fn is_upload(req: &ServiceRequest) -> bool {
+    req.method() == Method::POST
+        && req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("multipart/form-data"))
+}
+
+/// Reject requests exceeding the configured limits
+///
+/// Rejected requests receive the structured API error, with the 429 status
+/// for rate limited clients and busy uploads, and 413 for oversized bodies.
+pub(crate) async fn limit_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let limits = match req.app_data::<web::Data<RequestLimits>>() {
+        Some(limits) => limits.clone(),
+        None => return Ok(next.call(req).await?.map_into_left_body()),
+    };
+
+    if let Some(err) = limits.check(&req) {
+        return Ok(req.error_response(err).map_into_right_body());
+    }
+
+    if !is_upload(&req) {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+
+    let slot = match limits.acquire_upload() {
+        Some(slot) => slot,
+        None => {
+            return Ok(req
+                .error_response(ApiError::too_many_requests(
+                    ApiErrorCode::TooManyUploads,
+                    "Too many uploads in progress, try again later",
+                ))
+                .map_into_right_body())
+        }
+    };
+
+    let response = next.call(req).await?;
+    drop(slot);
+
+    Ok(response.map_into_left_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::models::api_config::RateLimitConfig;
+
+    use actix_web::{middleware::from_fn, test, App, HttpResponse};
+
+    fn build_limits(
+        max_body_bytes: Option<usize>,
+        rate_limit: Option<RateLimitConfig>,
+        max_concurrent_uploads: Option<usize>,
+    ) -> web::Data<RequestLimits> {
+        web::Data::new(RequestLimits::from_config(&ServerConfig {
+            address: "0.0.0.0".to_string(),
+            port: 8080,
+            workers: None,
+            max_cached_models: None,
+            max_body_bytes,
+            rate_limit,
+            max_concurrent_uploads,
+            models_registry: None,
+            placement_cache_directory: None,
+        }))
+    }
+
+    async fn read_error<B: MessageBody>(
+        response: ServiceResponse<B>,
+    ) -> (u16, ApiError) {
+        let status = response.status().as_u16();
+        (status, test::read_body_json(response).await)
+    }
+
+    #[actix_web::test]
+    async fn test_rate_limit_per_client() {
+        let app = test::init_service(
+            App::new()
+                .app_data(build_limits(
+                    None,
+                    Some(RateLimitConfig {
+                        max_requests: 2,
+                        window_secs: 60,
+                    }),
+                    None,
+                ))
+                .wrap(from_fn(limit_requests))
+                .route("/", web::get().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let request = |addr: &str| {
+            test::TestRequest::get()
+                .uri("/")
+                .peer_addr(addr.parse().unwrap())
+                .to_request()
+        };
+
+        for _ in 0..2 {
+            let response =
+                test::call_service(&app, request("10.0.0.1:4000")).await;
+
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let (status, err) = read_error(
+            test::call_service(&app, request("10.0.0.1:4001")).await,
+        )
+        .await;
+
+        assert_eq!(status, 429);
+        assert_eq!(err.code, ApiErrorCode::RateLimitExceeded);
+
+        //
+        // Other clients are not affected
+        //
+        let response = test::call_service(&app, request("10.0.0.2:4000")).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_body_size_limit() {
+        let app = test::init_service(
+            App::new()
+                .app_data(build_limits(Some(16), None, None))
+                .wrap(from_fn(limit_requests))
+                .route("/", web::post().to(|| async { HttpResponse::Ok() })),
+        )
+        .await;
+
+        let request = |payload: &'static str| {
+            test::TestRequest::post()
+                .uri("/")
+                .set_payload(payload)
+                .to_request()
+        };
+
+        let response = test::call_service(&app, request("small body")).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let (status, err) = read_error(
+            test::call_service(&app, request("a body above the limit")).await,
+        )
+        .await;
+
+        assert_eq!(status, 413);
+        assert_eq!(err.code, ApiErrorCode::PayloadTooLarge);
+    }
+
+    #[test]
+    fn test_concurrent_upload_slots() {
+        let limits = build_limits(None, None, Some(1));
+
+        let slot = limits.acquire_upload();
+
+        assert!(slot.is_some());
+        assert!(limits.acquire_upload().is_none());
+
+        drop(slot);
+
+        assert!(limits.acquire_upload().is_some());
+    }
+}
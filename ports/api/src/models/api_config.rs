@@ -14,6 +14,54 @@ pub struct ServerConfig {
 
     /// The number of workers to use for the server.
     pub workers: Option<u16>,
+
+    /// The maximum number of databases kept in memory between placements.
+    pub max_cached_models: Option<usize>,
+
+    /// The maximum size (in bytes) of request bodies. Not limited by default.
+    pub max_body_bytes: Option<usize>,
+
+    /// The maximum number of requests per client IP. Not limited by default.
+    pub rate_limit: Option<RateLimitConfig>,
+
+    /// The maximum number of multipart uploads processed at once. Not limited
+    /// by default.
+    pub max_concurrent_uploads: Option<usize>,
+
+    /// The registry of models uploaded through the API. Uploads are disabled
+    /// by default.
+    pub models_registry: Option<ModelsRegistryConfig>,
+
+    /// The directory to cache placements of repeated queries. Not cached by
+    /// default.
+    pub placement_cache_directory: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelsRegistryConfig {
+    /// The bearer token required to upload models.
+    pub admin_token: String,
+
+    /// The directory to store uploaded models and the registry file.
+    pub directory: PathBuf,
+}
+
+impl ModelsRegistryConfig {
+    /// The registry file, listing the uploaded models
+    pub(crate) fn registry_path(&self) -> PathBuf {
+        self.directory.join("models-registry.yaml")
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitConfig {
+    /// The maximum number of requests accepted from a client in the window.
+    pub max_requests: u32,
+
+    /// The window length (in seconds) in which requests are counted.
+    pub window_secs: u64,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -0,0 +1,161 @@
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use mycelium_base::utils::errors::MappedErrors;
+use serde::{Deserialize, Serialize};
+use std::{fmt, io::ErrorKind};
+use tracing::error;
+use utoipa::ToSchema;
+
+/// The machine readable error codes returned by the API
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ApiErrorCode {
+    InvalidRequest,
+    InvalidFileName,
+    InvalidFileFormat,
+    InvalidWorkDir,
+    InvalidConfig,
+    UnknownModel,
+    WorkDirNotFound,
+    FileNotFound,
+    ConfigNotFound,
+    ModelNotFound,
+    ModelAlreadyExists,
+    InvalidModel,
+    FileAlreadyExists,
+    AnalysisAlreadyStarted,
+    PayloadTooLarge,
+    RateLimitExceeded,
+    TooManyUploads,
+    PlacementFailed,
+    PermissionDenied,
+    Unauthorized,
+    InternalError,
+}
+
+/// The error response shared by all API handlers
+///
+/// Errors are serialized as `{ "status": 404, "code": "...", "message": "..."
+/// }`, then clients can rely on the same shape for all endpoints.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+pub struct ApiError {
+    pub status: u16,
+    pub code: ApiErrorCode,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(
+        status: StatusCode,
+        code: ApiErrorCode,
+        message: impl Into<String>,
+    ) -> Self {
+        ApiError {
+            status: status.as_u16(),
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn bad_request(code: ApiErrorCode, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, message)
+    }
+
+    pub fn not_found(code: ApiErrorCode, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, code, message)
+    }
+
+    pub fn conflict(code: ApiErrorCode, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, code, message)
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::new(
+            StatusCode::UNAUTHORIZED,
+            ApiErrorCode::Unauthorized,
+            message,
+        )
+    }
+
+    pub fn too_many_requests(
+        code: ApiErrorCode,
+        message: impl Into<String>,
+    ) -> Self {
+        Self::new(StatusCode::TOO_MANY_REQUESTS, code, message)
+    }
+
+    /// Build an internal error logging the error details
+    ///
+    /// Details are logged only, avoiding to expose server paths and
+    /// configurations to clients.
+    pub fn internal(err: impl fmt::Debug) -> Self {
+        error!("{:?}", err);
+
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiErrorCode::InternalError,
+            "Unexpected error on processing the request",
+        )
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({:?}): {}", self.status, self.code, self.message)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.status)
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(self)
+    }
+}
+
+impl From<ApiError> for HttpResponse {
+    fn from(err: ApiError) -> Self {
+        err.error_response()
+    }
+}
+
+impl From<std::io::Error> for ApiError {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            ErrorKind::NotFound => {
+                Self::not_found(ApiErrorCode::FileNotFound, "File not found")
+            }
+            ErrorKind::PermissionDenied => Self::new(
+                StatusCode::FORBIDDEN,
+                ApiErrorCode::PermissionDenied,
+                "Permission denied",
+            ),
+            _ => Self::internal(err),
+        }
+    }
+}
+
+impl From<MappedErrors> for ApiError {
+    fn from(err: MappedErrors) -> Self {
+        Self::internal(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_error_shape() {
+        let err = ApiError::from(std::io::Error::from(ErrorKind::NotFound));
+
+        assert_eq!(
+            serde_yaml::to_string(&err).unwrap(),
+            "status: 404\ncode: FILE_NOT_FOUND\nmessage: File not found\n"
+        );
+
+        assert_eq!(HttpResponse::from(err).status(), StatusCode::NOT_FOUND);
+    }
+}
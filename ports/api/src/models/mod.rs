@@ -1,2 +1,3 @@
 pub mod api_config;
+pub mod api_error;
 pub mod node;
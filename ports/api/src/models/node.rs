@@ -2,8 +2,9 @@ use anyhow::Result;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::{os::unix::fs::MetadataExt, path::PathBuf};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Node {
     pub id: u32,
@@ -57,3 +58,18 @@ impl Node {
         })
     }
 }
+
+/// A window of the work directory nodes
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NodesPage {
+    /// The number of nodes matching the request filters
+    pub total: usize,
+
+    pub offset: usize,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+
+    pub records: Vec<Node>,
+}
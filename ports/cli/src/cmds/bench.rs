@@ -0,0 +1,247 @@
+use anyhow::Result;
+use clap::{ArgAction, Parser};
+use classeq_core::{
+    domain::dtos::{
+        file_or_stdin::FileOrStdin, output_format::OutputFormat,
+        placement_config::PlacementConfig, placement_options::PlacementOptions,
+    },
+    use_cases::place_sequences,
+};
+use classeq_ports_lib::{init_thread_pool, load_database};
+use serde::Serialize;
+use std::{path::PathBuf, time::Instant};
+use tracing::info;
+use uuid::Uuid;
+
+/// The nucleotides used to build synthetic queries
+const NUCLEOTIDES: [char; 4] = ['A', 'C', 'G', 'T'];
+
+#[derive(Parser, Debug)]
+pub(crate) struct Arguments {
+    /// Path to the classeq database
+    #[arg(short, long)]
+    pub(super) database_file_path: PathBuf,
+
+    /// Path to the FASTA file containing the query set
+    ///
+    /// If not provided, a synthetic query set is generated from a fixed seed,
+    /// then repeated runs place the same queries.
+    #[arg(short, long)]
+    pub(super) query_file_path: Option<PathBuf>,
+
+    /// Number of rounds
+    ///
+    /// The number of times the query set is placed.
+    #[arg(short, long, default_value = "3")]
+    pub(super) rounds: usize,
+
+    /// Number of synthetic queries
+    #[arg(long, default_value = "100")]
+    pub(super) synthetic_queries: usize,
+
+    /// Length of synthetic queries
+    #[arg(long, default_value = "500")]
+    pub(super) synthetic_length: usize,
+
+    /// Remove intersection
+    ///
+    /// If true, calculate the one-vs-rest difference without the shared kmers.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) remove_intersection: bool,
+
+    /// Minimum match coverage
+    #[arg(short, long)]
+    pub(super) match_coverage: Option<f64>,
+}
+
+/// The placement throughput measured by the bench
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BenchReport {
+    pub threads: usize,
+    pub rounds: usize,
+    pub sequences: usize,
+    pub sequences_per_second: f64,
+    pub p50_milliseconds: f64,
+    pub p95_milliseconds: f64,
+
+    /// The peak resident memory of the process, if available
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub peak_memory_kb: Option<u64>,
+}
+
+pub(crate) fn bench_cmd(args: Arguments, threads: usize) -> Result<()> {
+    let report = run_bench(args, threads)?;
+
+    print!("{}", serde_yaml::to_string(&report)?);
+
+    Ok(())
+}
+
+pub(crate) fn run_bench(
+    args: Arguments,
+    threads: usize,
+) -> Result<BenchReport> {
+    let threads = init_thread_pool(threads);
+    let tree = load_database(args.database_file_path)?;
+
+    let work_dir =
+        std::env::temp_dir().join(format!("cls-bench-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&work_dir)?;
+
+    let query_file_path = match args.query_file_path {
+        Some(path) => path,
+        None => {
+            let path = work_dir.join("synthetic.fasta");
+
+            std::fs::write(
+                &path,
+                synthetic_queries(
+                    args.synthetic_queries,
+                    args.synthetic_length,
+                ),
+            )?;
+
+            path
+        }
+    };
+
+    let config = PlacementConfig::default()
+        .with_options(
+            PlacementOptions::default()
+                .with_remove_intersection(Some(args.remove_intersection))
+                .with_min_match_coverage(args.match_coverage),
+        )
+        .with_overwrite(true)
+        .with_output_format(OutputFormat::Jsonl);
+
+    let mut times = Vec::new();
+    let now = Instant::now();
+
+    for round in 0..args.rounds {
+        let round_times = place_sequences(
+            FileOrStdin::from_file(
+                query_file_path.to_str().unwrap_or_default(),
+            ),
+            &tree,
+            &work_dir.join("results"),
+            &config,
+            &None,
+        )?;
+
+        info!(
+            "Round {round} placed {count} sequences",
+            round = round + 1,
+            count = round_times.len()
+        );
+
+        times.extend(
+            round_times
+                .into_iter()
+                .map(|time| time.milliseconds_time.as_secs_f64() * 1000.0),
+        );
+    }
+
+    let elapsed = now.elapsed().as_secs_f64();
+    std::fs::remove_dir_all(&work_dir)?;
+
+    times.sort_by(|a, b| a.total_cmp(b));
+
+    Ok(BenchReport {
+        threads,
+        rounds: args.rounds,
+        sequences: times.len(),
+        sequences_per_second: match elapsed > 0.0 {
+            true => times.len() as f64 / elapsed,
+            false => 0.0,
+        },
+        p50_milliseconds: percentile(&times, 0.5),
+        p95_milliseconds: percentile(&times, 0.95),
+        peak_memory_kb: peak_memory_kb(),
+    })
+}
+
+/// Build a FASTA content of random sequences from a fixed seed
+fn synthetic_queries(count: usize, length: usize) -> String {
+    //
+    // A linear congruential generator is enough to produce a reproducible
+    // query set
+    //
+    let mut state: u64 = 42;
+
+    (0..count)
+        .map(|index| {
+            let sequence = (0..length)
+                .map(|_| {
+                    state = state
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+
+                    NUCLEOTIDES[(state >> 62) as usize]
+                })
+                .collect::<String>();
+
+            format!(">synthetic_{index}\n{sequence}\n")
+        })
+        .collect()
+}
+
+/// Get the value at the given quantile of sorted values
+fn percentile(sorted: &[f64], quantile: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+
+    let index = ((sorted.len() - 1) as f64 * quantile).round() as usize;
+
+    sorted[index]
+}
+
+/// Get the peak resident memory of the process
+///
+/// Only available on Linux systems, from the `VmHWM` entry of the process
+/// status.
+fn peak_memory_kb() -> Option<u64> {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()?
+        .lines()
+        .find(|line| line.starts_with("VmHWM:"))?
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_on_sample_database() {
+        let report = run_bench(
+            Arguments {
+                database_file_path: PathBuf::from(
+                    "../../tests/models/bsub-gyrb-k35.cls",
+                ),
+                query_file_path: None,
+                rounds: 2,
+                synthetic_queries: 5,
+                synthetic_length: 200,
+                remove_intersection: false,
+                match_coverage: None,
+            },
+            1,
+        )
+        .unwrap();
+
+        assert_eq!(report.rounds, 2);
+        assert_eq!(report.sequences, 10);
+        assert!(report.p50_milliseconds <= report.p95_milliseconds);
+    }
+
+    #[test]
+    fn test_synthetic_queries_are_reproducible() {
+        assert_eq!(synthetic_queries(3, 50), synthetic_queries(3, 50));
+        assert_eq!(synthetic_queries(3, 50).lines().count(), 6);
+    }
+}
@@ -1,31 +1,41 @@
+use super::convert::write_database_with_format;
+use crate::dtos::output_format::DatabaseBuildFormat;
+
 use anyhow::Result;
-use clap::Parser;
-use classeq_core::use_cases::map_kmers_to_tree;
-use std::{fs::File, path::PathBuf};
+use clap::{ArgAction, Parser};
+use classeq_core::{
+    domain::dtos::{
+        build_options::BuildOptions, file_or_stdin::FileOrStdin,
+        kmers_map::KmersMap, msa_window::MsaWindow,
+    },
+    use_cases::map_kmers_to_tree,
+};
+use classeq_ports_lib::init_thread_pool;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 pub(crate) struct Arguments {
     /// Path to the tree file
     ///
-    /// The file should be in Newick format.
+    /// The file should be in Newick or Nexus format.
     pub(super) tree_file_path: PathBuf,
 
     /// Path to the msa file
     ///
-    /// The file should be in FASTA format.
-    pub(super) msa_file_path: PathBuf,
+    /// The file should be in FASTA format. Use `-` to read the MSA from stdin.
+    pub(super) msa_file_path: FileOrStdin,
 
     /// The kmer size
     ///
     /// The size of the kmers to be used in the tree.
-    #[arg(long, short, default_value = "35")]
-    pub(super) k_size: Option<u64>,
+    #[arg(long, short, default_value_t = KmersMap::DEFAULT_K)]
+    pub(super) k_size: u64,
 
     /// The minimizer size
     ///
     /// The size of the minimizer to be used in the tree.
-    #[arg(long, short, default_value = "4")]
-    pub(super) m_size: Option<u64>,
+    #[arg(long, short, default_value_t = KmersMap::DEFAULT_M)]
+    pub(super) m_size: u64,
 
     /// Output file path
     ///
@@ -34,11 +44,140 @@ pub(crate) struct Arguments {
     #[arg(short, long)]
     pub(super) output_file_path: Option<PathBuf>,
 
+    /// Output format
+    ///
+    /// The format in which the database will be serialized, as done by the
+    /// `convert database` command. Only formats loadable as databases are
+    /// accepted (`zstd`, `yaml` or `json`), and `binary` is only an alias of
+    /// `zstd`. If not provided, the format is detected from the output file
+    /// extension (`.yaml`, `.yml` or `.json`), falling back to `zstd`.
+    #[arg(long, short = 'f')]
+    pub(super) out_format: Option<DatabaseBuildFormat>,
+
     /// Minimum branch support
     ///
     /// The minimum branch support value to consider a branch in the tree.
     #[arg(short = 's', long, default_value = "70")]
     pub(super) min_branch_support: Option<f64>,
+
+    /// Homopolymer compression
+    ///
+    /// If true, runs of identical bases are collapsed before building kmers.
+    /// This is useful for noisy long reads (e.g. Nanopore and PacBio), but
+    /// reduces the resolution between taxa distinguished only by homopolymer
+    /// lengths. The option is persisted in the database and applied to queries
+    /// during the placement.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) homopolymer_compress: Option<bool>,
+
+    /// Exclude soft-masked kmers
+    ///
+    /// If true, kmers overlapping lowercase (soft-masked) bases of the MSA are
+    /// not indexed. Reference sequences commonly soft-mask repetitive
+    /// regions, which may drive false placements. The option is persisted in
+    /// the database.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) exclude_soft_masked: bool,
+
+    /// Index forward strand kmers only
+    ///
+    /// If true, reverse complement kmers are not indexed. Used for stranded
+    /// protocols, where the query orientation is known. The option is
+    /// persisted in the database and followed at the placement time.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) forward_only: bool,
+
+    /// Index an amino acid MSA
+    ///
+    /// If true, the MSA is read as aligned protein sequences. Protein
+    /// databases are forward only, and nucleotide queries should be translated
+    /// before placement.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) protein: bool,
+
+    /// First MSA column
+    ///
+    /// The one based first column of the MSA window to be indexed. Used
+    /// together with `--msa-end` to restrict the database to a region (e.g.
+    /// the amplified region of amplicon data). The window is persisted in the
+    /// database.
+    #[arg(long, requires = "msa_end")]
+    pub(super) msa_start: Option<usize>,
+
+    /// Last MSA column
+    ///
+    /// The one based last column (inclusive) of the MSA window to be indexed.
+    #[arg(long, requires = "msa_start")]
+    pub(super) msa_end: Option<usize>,
+
+    /// Maximum gap fraction
+    ///
+    /// MSA columns with a fraction of gaps (or undetermined bases) above this
+    /// value are not indexed, reducing the noise and the size of the database.
+    /// Columns are evaluated in a first pass, then the MSA should be read from
+    /// a file.
+    #[arg(long)]
+    pub(super) max_gap_fraction: Option<f64>,
+
+    /// Build kmers over aligned columns
+    ///
+    /// If true, MSA columns containing a gap (`-`, `.` or `*`) in any record
+    /// are skipped in all records, then kmers spanning short indels are shared
+    /// by homologous records. As done for the maximum gap fraction, the MSA
+    /// should be read from a file.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) aligned_kmers: bool,
+
+    /// Maximum ambiguity expansions
+    ///
+    /// If provided, kmers containing IUPAC ambiguity codes (e.g. `N`) are
+    /// expanded to their ACGT variants instead of skipped. Kmers with more
+    /// variants than this value are skipped, and the number of skipped kmers
+    /// is logged after the indexing.
+    #[arg(long)]
+    pub(super) max_ambiguity_expansions: Option<usize>,
+
+    /// Tree index
+    ///
+    /// The zero based index of the tree to be used from Nexus files
+    /// containing multiple trees. Ignored for Newick files.
+    #[arg(long)]
+    pub(super) tree_index: Option<usize>,
+
+    /// Retain kmer strings
+    ///
+    /// If true, a representative string of each kmer hash is stored in the
+    /// database, allowing the placement to report the kmers driving the
+    /// placement (see the `--explain-kmers` option of the `place` command).
+    /// The database size grows by roughly the kmer size in bytes per indexed
+    /// kmer.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) retain_kmer_strings: bool,
+
+    /// Detect kmer hash collisions
+    ///
+    /// Kmers are indexed by 64-bit hashes. If set, distinct kmers sharing the
+    /// same hash are counted and the collision rate is logged after the
+    /// indexing. Kmer strings are kept in memory during the build only.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) detect_collisions: bool,
+
+    /// Maximum resident kmers
+    ///
+    /// If provided, the partial index is spilled to disk whenever it holds
+    /// more than this number of kmers, and the spilled partial indexes are
+    /// merged after the MSA reading. It bounds the memory used while the MSA
+    /// is read, but the final index is still assembled in memory.
+    #[arg(long)]
+    pub(super) max_resident_kmers: Option<usize>,
+
+    /// Spill directory
+    ///
+    /// The directory of the partial indexes spilled by the
+    /// `--max-resident-kmers` option. The system temporary directory is used
+    /// if not provided.
+    #[arg(long, requires = "max_resident_kmers")]
+    pub(super) spill_dir: Option<PathBuf>,
 }
 
 pub(crate) fn build_database_cmd(
@@ -49,30 +188,153 @@ pub(crate) fn build_database_cmd(
     // ? Create a thread pool configured globally
     // ? -----------------------------------------------------------------------
 
-    if let Err(err) = rayon::ThreadPoolBuilder::new()
-        .num_threads(threads.unwrap_or(1))
-        .build_global()
-    {
-        panic!("Error creating thread pool: {err}");
-    };
+    init_thread_pool(threads.unwrap_or(1));
 
-    let tree = map_kmers_to_tree(
-        args.tree_file_path,
-        args.msa_file_path,
-        args.k_size,
-        args.m_size,
-        args.min_branch_support,
-    )?;
+    let options = BuildOptions::default()
+        .with_k_size(Some(args.k_size))
+        .with_m_size(Some(args.m_size))
+        .with_min_branch_support(args.min_branch_support)
+        .with_homopolymer_compress(args.homopolymer_compress.unwrap_or(false))
+        .with_exclude_soft_masked(args.exclude_soft_masked)
+        .with_forward_only(args.forward_only)
+        .with_protein(args.protein)
+        .with_msa_window(match (args.msa_start, args.msa_end) {
+            (Some(start), Some(end)) => Some(MsaWindow::new(start, end)),
+            _ => None,
+        })
+        .with_max_gap_fraction(args.max_gap_fraction)
+        .with_aligned_kmers(args.aligned_kmers)
+        .with_max_ambiguity_expansions(args.max_ambiguity_expansions)
+        .with_tree_index(args.tree_index)
+        .with_retain_kmer_strings(args.retain_kmer_strings)
+        .with_detect_collisions(args.detect_collisions)
+        .with_max_resident_kmers(args.max_resident_kmers)
+        .with_spill_directory(args.spill_dir);
+
+    let tree =
+        map_kmers_to_tree(args.tree_file_path, args.msa_file_path, &options)?;
 
-    let mut output_file_path = args
+    let output_file_path = args
         .output_file_path
         .unwrap_or_else(|| PathBuf::from("classeq-database.cls"));
 
-    output_file_path.set_extension("cls");
+    let out_format = args
+        .out_format
+        .unwrap_or_else(|| DatabaseBuildFormat::from_path(&output_file_path));
 
-    let writer = File::create(output_file_path)?;
-    let writer = zstd::Encoder::new(writer, 0)?.auto_finish();
-    serde_yaml::to_writer(writer, &tree)?;
+    write_database_with_format(
+        &tree,
+        output_file_path,
+        &out_format.into(),
+        false,
+    )?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmds::convert::GetKmersArguments;
+    use classeq_core::domain::dtos::tree::Tree;
+    use classeq_ports_lib::load_database;
+    use std::{
+        collections::{HashMap, HashSet},
+        path::Path,
+    };
+
+    #[test]
+    fn test_cli_kmer_defaults_match_core_defaults() {
+        let build_args =
+            Arguments::try_parse_from(["build-db", "tree.nwk", "msa.fasta"])
+                .unwrap();
+
+        assert_eq!(build_args.k_size, KmersMap::DEFAULT_K);
+        assert_eq!(build_args.m_size, KmersMap::DEFAULT_M);
+
+        let kmers_args =
+            GetKmersArguments::try_parse_from(["kmers", "ACGT"]).unwrap();
+
+        assert_eq!(kmers_args.kmer_length, KmersMap::DEFAULT_K);
+
+        let options = BuildOptions::default();
+
+        assert_eq!(options.k_size, KmersMap::DEFAULT_K);
+        assert_eq!(options.m_size, KmersMap::DEFAULT_M);
+    }
+
+    #[test]
+    fn test_build_database_to_each_format() {
+        let tree = map_kmers_to_tree(
+            PathBuf::from("../../core/src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk"),
+            FileOrStdin::from_file("../../core/src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta"),
+            &BuildOptions::default(),
+        )
+        .unwrap();
+
+        let kmers = |tree: &Tree| {
+            tree.kmers_map
+                .as_ref()
+                .unwrap()
+                .get_map()
+                .iter()
+                .map(|(key, value)| (key.0, value.0.to_owned()))
+                .collect::<HashMap<u64, HashMap<u64, HashSet<u64>>>>()
+        };
+
+        //
+        // The binary format is an alias of the zstd one
+        //
+        let args = Arguments::try_parse_from([
+            "build-db",
+            "tree.nwk",
+            "msa.fasta",
+            "--out-format",
+            "binary",
+        ])
+        .unwrap();
+
+        assert_eq!(args.out_format, Some(DatabaseBuildFormat::Zstd));
+
+        //
+        // Diagnostic exports are not loadable as databases
+        //
+        assert!(Arguments::try_parse_from([
+            "build-db",
+            "tree.nwk",
+            "msa.fasta",
+            "--out-format",
+            "newick-with-kmers",
+        ])
+        .is_err());
+
+        for out_format in [
+            DatabaseBuildFormat::Zstd,
+            DatabaseBuildFormat::Yaml,
+            DatabaseBuildFormat::Json,
+        ] {
+            let path = write_database_with_format(
+                &tree,
+                PathBuf::from("/tmp/cls-build-db-format"),
+                &out_format.into(),
+                false,
+            )
+            .unwrap();
+
+            let loaded = load_database(path).unwrap();
+
+            assert_eq!(loaded.id, tree.id);
+            assert_eq!(loaded.root, tree.root);
+            assert_eq!(kmers(&loaded), kmers(&tree));
+        }
+
+        assert_eq!(
+            DatabaseBuildFormat::from_path(Path::new("db.yml")),
+            DatabaseBuildFormat::Yaml
+        );
+        assert_eq!(
+            DatabaseBuildFormat::from_path(Path::new("db.cls")),
+            DatabaseBuildFormat::Zstd
+        );
+    }
+}
@@ -0,0 +1,212 @@
+use crate::dtos::output_format::DatabaseDescriptionOutputFormat;
+
+use anyhow::{bail, Result};
+use clap::{ArgAction, Parser};
+use classeq_core::domain::dtos::{kmers_map::KmersMap, tree::Tree};
+use classeq_ports_lib::load_database;
+use serde::Serialize;
+use std::{collections::HashSet, path::PathBuf};
+
+#[derive(Parser, Debug)]
+pub(crate) struct Arguments {
+    /// Path to the first classeq database
+    pub(super) first_database_path: PathBuf,
+
+    /// Path to the second classeq database
+    pub(super) second_database_path: PathBuf,
+
+    /// Compare the kmer sets
+    ///
+    /// If true, the overlap of the kmers indexed by both databases is also
+    /// reported. Databases should be built with the same kmer parameters.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) kmers: bool,
+
+    /// Output format
+    #[arg(long, short = 'f', default_value = "tsv")]
+    pub(super) out_format: DatabaseDescriptionOutputFormat,
+}
+
+/// The overlap of two sets
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SetOverlap {
+    pub(crate) first_count: usize,
+    pub(crate) second_count: usize,
+    pub(crate) shared_count: usize,
+    pub(crate) jaccard: f64,
+}
+
+impl SetOverlap {
+    fn from_sets<T: Eq + std::hash::Hash>(
+        first: &HashSet<T>,
+        second: &HashSet<T>,
+    ) -> Self {
+        let shared_count = first.intersection(second).count();
+        let union_count = first.len() + second.len() - shared_count;
+
+        SetOverlap {
+            first_count: first.len(),
+            second_count: second.len(),
+            shared_count,
+            jaccard: match union_count {
+                0 => 0.0,
+                count => shared_count as f64 / count as f64,
+            },
+        }
+    }
+}
+
+/// The similarity of two databases
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DatabaseComparison {
+    pub(crate) leaves: SetOverlap,
+
+    /// The sorted names of the leaves present in both databases
+    pub(crate) shared_leaves: Vec<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) kmers: Option<SetOverlap>,
+}
+
+/// Compare the leaf sets and, optionally, the kmer sets of two databases
+pub(crate) fn compare_databases(
+    first: &Tree,
+    second: &Tree,
+    with_kmers: bool,
+) -> Result<DatabaseComparison> {
+    let first_leaves = get_leaf_names(first);
+    let second_leaves = get_leaf_names(second);
+
+    let mut shared_leaves = first_leaves
+        .intersection(&second_leaves)
+        .cloned()
+        .collect::<Vec<String>>();
+
+    shared_leaves.sort();
+
+    let kmers = match with_kmers {
+        false => None,
+        true => match (&first.kmers_map, &second.kmers_map) {
+            (Some(first_map), Some(second_map)) => {
+                if !first_map.is_compatible_with(second_map) {
+                    bail!(
+                        "Kmers could not be compared, since databases were \
+                        built with distinct kmer parameters"
+                    );
+                }
+
+                Some(SetOverlap::from_sets(
+                    &get_kmer_hashes(first_map),
+                    &get_kmer_hashes(second_map),
+                ))
+            }
+            _ => bail!("Both databases should contain a kmers map"),
+        },
+    };
+
+    Ok(DatabaseComparison {
+        leaves: SetOverlap::from_sets(&first_leaves, &second_leaves),
+        shared_leaves,
+        kmers,
+    })
+}
+
+/// Collect the names of the tree leaves
+fn get_leaf_names(tree: &Tree) -> HashSet<String> {
+    tree.root
+        .iter()
+        .filter(|node| node.is_leaf())
+        .filter_map(|leaf| leaf.name.to_owned())
+        .collect()
+}
+
+/// Collect the kmer hashes of all minimizer buckets
+fn get_kmer_hashes(kmers_map: &KmersMap) -> HashSet<u64> {
+    kmers_map
+        .get_map()
+        .values()
+        .flat_map(|bucket| bucket.0.keys().copied())
+        .collect()
+}
+
+pub(crate) fn compare_databases_cmd(args: Arguments) -> Result<()> {
+    let first = load_database(args.first_database_path)?;
+    let second = load_database(args.second_database_path)?;
+
+    let comparison = compare_databases(&first, &second, args.kmers)?;
+
+    match args.out_format {
+        DatabaseDescriptionOutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&comparison)?);
+        }
+        DatabaseDescriptionOutputFormat::Yaml => {
+            println!("{}", serde_yaml::to_string(&comparison)?);
+        }
+        DatabaseDescriptionOutputFormat::Tsv => {
+            let mut overlaps = vec![("Leaf", &comparison.leaves)];
+
+            if let Some(kmers) = &comparison.kmers {
+                overlaps.push(("Kmer", kmers));
+            }
+
+            for (prefix, overlap) in overlaps {
+                println!("First{prefix}Count\t{}", overlap.first_count);
+                println!("Second{prefix}Count\t{}", overlap.second_count);
+                println!("Shared{prefix}Count\t{}", overlap.shared_count);
+                println!("{prefix}Jaccard\t{}", overlap.jaccard);
+            }
+
+            println!("SharedLeaves\t{}", comparison.shared_leaves.join(","));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use classeq_core::domain::dtos::clade::Clade;
+
+    fn rename_leaves(clade: &mut Clade) {
+        if clade.is_leaf() {
+            clade.name =
+                clade.name.as_ref().map(|name| format!("other_{name}"));
+        }
+
+        for child in clade.children.iter_mut().flatten() {
+            rename_leaves(child);
+        }
+    }
+
+    #[test]
+    fn test_compare_databases() {
+        let load = || {
+            load_database(PathBuf::from("../../tests/models/bsub-gyrb-k35.cls"))
+                .unwrap()
+        };
+
+        let tree = load();
+
+        let comparison = compare_databases(&tree, &tree, true).unwrap();
+
+        assert_eq!(comparison.leaves.jaccard, 1.0);
+        assert_eq!(comparison.kmers.unwrap().jaccard, 1.0);
+        assert_eq!(comparison.shared_leaves.len(), tree.root.count_leaves());
+
+        //
+        // Renamed leaves form a disjoint leaf set
+        //
+        let mut disjoint = load();
+        rename_leaves(&mut disjoint.root);
+
+        let comparison = compare_databases(&tree, &disjoint, false).unwrap();
+
+        assert_eq!(comparison.leaves.jaccard, 0.0);
+        assert!(comparison.shared_leaves.is_empty());
+        assert!(comparison.kmers.is_none());
+    }
+}
@@ -1,12 +1,24 @@
-use crate::dtos::output_format::DatabaseOutputFormat;
+use crate::dtos::output_format::{DatabaseOutputFormat, ItolDataset};
 
 use anyhow::Result;
 use clap::{ArgAction, Parser};
 use classeq_core::domain::dtos::{
-    kmers_map::KmersMap, output_format::OutputFormat, tree::Tree,
+    clade::{Clade, NodeType},
+    file_or_stdin::FileOrStdin,
+    kmers_map::KmersMap,
+    output_format::OutputFormat,
+    tree::Tree,
+};
+use classeq_ports_lib::{load_database, write_database};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    sync::mpsc::sync_channel,
+    thread,
 };
-use classeq_ports_lib::load_database;
-use std::{fs::File, path::PathBuf};
 
 #[derive(Parser, Debug)]
 pub(crate) struct Arguments {
@@ -24,13 +36,29 @@ pub(crate) enum Commands {
 
     /// Get sequence kmers
     ///
-    /// Extract kmers from a sequence.
+    /// Extract kmers from a sequence or from each record of a FASTA file.
     Kmers(GetKmersArguments),
 
     /// Convert Classeq database
     ///
     /// Convert a Classeq database between yaml and binary formats. This is util
+    /// to inspect the database content. The `newick-with-kmers` format exports
+    /// the tree labeled with the kmers count of each clade.
     Database(DatabaseArguments),
+
+    /// Export placements as an iTOL dataset
+    ///
+    /// Count the queries placed at each clade of the database tree and write
+    /// them as an iTOL annotation dataset, to be dropped over the tree in the
+    /// iTOL web interface.
+    PlacementsToItol(PlacementsToItolArguments),
+
+    /// Export the clade IDs of a database
+    ///
+    /// Write a TSV table with the ID, the type, the name, the branch support
+    /// and the descendant leaves count of each clade, allowing the clade IDs
+    /// of placements to be interpreted.
+    IdMap(IdMapArguments),
 }
 
 // ? ---------------------------------------------------------------------------
@@ -41,7 +69,7 @@ pub(crate) enum Commands {
 pub(crate) struct SerializeTreeArguments {
     /// Path to the tree file
     ///
-    /// The file should be in Newick format.
+    /// The file should be in Newick or Nexus format.
     pub(super) tree_file_path: PathBuf,
 
     /// Path to the output file
@@ -56,6 +84,13 @@ pub(crate) struct SerializeTreeArguments {
     #[arg(long)]
     pub(super) min_branch_support: Option<f64>,
 
+    /// Tree index
+    ///
+    /// The zero based index of the tree to be used from Nexus files
+    /// containing multiple trees. Ignored for Newick files.
+    #[arg(long)]
+    pub(super) tree_index: Option<usize>,
+
     /// Output format
     ///
     /// The format in which the tree will be serialized.
@@ -64,9 +99,10 @@ pub(crate) struct SerializeTreeArguments {
 }
 
 pub(crate) fn serialize_tree_cmd(args: SerializeTreeArguments) {
-    let tree = match Tree::init_from_file(
+    let tree = match Tree::init_from_file_with_index(
         args.tree_file_path.as_path(),
         args.min_branch_support.unwrap_or(95.0),
+        args.tree_index,
     ) {
         Ok(tree) => tree,
         Err(e) => {
@@ -83,13 +119,15 @@ pub(crate) fn serialize_tree_cmd(args: SerializeTreeArguments) {
             }
             Ok(content) => content,
         },
-        OutputFormat::Yaml => match serde_yaml::to_string(&tree) {
-            Err(err) => {
-                eprintln!("Error: {err}");
-                return;
+        OutputFormat::Yaml | OutputFormat::YamlArray => {
+            match serde_yaml::to_string(&tree) {
+                Err(err) => {
+                    eprintln!("Error: {err}");
+                    return;
+                }
+                Ok(content) => content,
             }
-            Ok(content) => content,
-        },
+        }
     };
 
     match args.output_file_path {
@@ -108,23 +146,137 @@ pub(crate) fn serialize_tree_cmd(args: SerializeTreeArguments) {
 
 #[derive(Parser, Debug)]
 pub(crate) struct GetKmersArguments {
-    /// Path to the MSA file
+    /// The sequence to extract kmers from
+    ///
+    /// A raw nucleotide sequence. Ignored if `--from-fasta` is provided.
+    #[arg(required_unless_present = "from_fasta")]
+    pub(super) sequence: Option<String>,
+
+    /// Path to a FASTA file
     ///
-    /// The file should be in FASTA format.
-    pub(super) sequence: String,
+    /// Kmers are extracted from each record and printed prefixed by the
+    /// record header, separated by a tab. Use `-` to read from stdin.
+    #[arg(long, conflicts_with = "sequence")]
+    pub(super) from_fasta: Option<FileOrStdin>,
 
     /// Kmer length
     ///
     /// The length of the kmers to be extracted.
-    #[arg(short, long, default_value = "31")]
+    #[arg(short, long, default_value_t = KmersMap::DEFAULT_K)]
     pub(super) kmer_length: u64,
+
+    /// Canonical kmers
+    ///
+    /// If true, only the lexicographically smallest kmer between each kmer and
+    /// its reverse complement is printed.
+    #[arg(long, action=ArgAction::SetTrue, conflicts_with = "no_revcomp")]
+    pub(super) canonical: bool,
+
+    /// Do not include reverse complement kmers
+    ///
+    /// As default, kmers of both strands are printed, as done during the
+    /// database indexing. If true, only the forward strand kmers are printed.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) no_revcomp: bool,
 }
 
-pub(crate) fn get_kmers_cmd(args: GetKmersArguments) {
+pub(crate) fn get_kmers_cmd(args: GetKmersArguments) -> Result<()> {
     let mapper = KmersMap::new(args.kmer_length, 0);
-    for (kmer, _) in mapper.build_kmer_from_string(args.sequence, None) {
-        println!("{}", kmer);
+
+    let source = match args.from_fasta {
+        Some(source) => source,
+        None => {
+            for (kmer, _) in select_strand_kmers(
+                mapper.build_kmer_from_string(
+                    args.sequence.unwrap_or_default(),
+                    None,
+                ),
+                args.canonical,
+                args.no_revcomp,
+            ) {
+                println!("{}", kmer);
+            }
+
+            return Ok(());
+        }
+    };
+
+    write_fasta_kmers(
+        source,
+        &mapper,
+        args.canonical,
+        args.no_revcomp,
+        &mut std::io::stdout().lock(),
+    )
+}
+
+/// Write kmers of each FASTA record prefixed by the record header
+///
+/// Records are streamed from the source, then the whole file is never loaded
+/// into memory.
+fn write_fasta_kmers(
+    source: FileOrStdin,
+    mapper: &KmersMap,
+    canonical: bool,
+    no_revcomp: bool,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let (sender, receiver) = sync_channel(FileOrStdin::CHANNEL_BOUND);
+    let reader =
+        thread::spawn(move || source.sequence_content_by_channel(sender));
+
+    for sequence in receiver {
+        for (kmer, _) in select_strand_kmers(
+            mapper.build_kmer_from_string(
+                sequence.sequence_content().to_string(),
+                None,
+            ),
+            canonical,
+            no_revcomp,
+        ) {
+            writeln!(writer, "{}\t{}", sequence.header_content(), kmer)?;
+        }
+    }
+
+    match reader.join() {
+        Ok(result) => result?,
+        Err(_) => {
+            return Err(anyhow::anyhow!("The FASTA reader thread panicked"))
+        }
+    };
+
+    Ok(())
+}
+
+/// Select kmers of the desired strands
+///
+/// Kmers returned by `KmersMap::build_kmer_from_string` contain the forward
+/// strand kmers followed by the reverse complement ones, in the reverse order
+/// of the forward kmers.
+fn select_strand_kmers(
+    kmers: Vec<(String, u64)>,
+    canonical: bool,
+    no_revcomp: bool,
+) -> Vec<(String, u64)> {
+    if !canonical && !no_revcomp {
+        return kmers;
     }
+
+    let mut kmers = kmers;
+    let reverse = kmers.split_off(kmers.len() / 2);
+
+    if no_revcomp {
+        return kmers;
+    }
+
+    kmers
+        .into_iter()
+        .zip(reverse.into_iter().rev())
+        .map(|(forward, reverse)| match forward.0 <= reverse.0 {
+            true => forward,
+            false => reverse,
+        })
+        .collect()
 }
 
 // ? ---------------------------------------------------------------------------
@@ -154,52 +306,558 @@ pub(crate) struct DatabaseArguments {
     /// Output format
     ///
     /// The format in which the database will be serialized.
-    #[arg(long, short = 'f', default_value = "yaml")]
+    #[arg(long, short = 'f', visible_alias = "to", default_value = "yaml")]
     pub(super) out_format: DatabaseOutputFormat,
 }
 
 pub(crate) fn convert_database_cmd(args: DatabaseArguments) -> Result<()> {
     let tree_content = load_database(args.database_file_path)?;
-    let mut output_file_path = args
+    let output_file_path = args
         .output_file_path
         .unwrap_or_else(|| PathBuf::from("classeq-database"));
 
-    //
-    // Serialize the content
-    //
-    match args.out_format {
+    write_database_with_format(
+        &tree_content,
+        output_file_path,
+        &args.out_format,
+        args.only_tree.unwrap_or(false),
+    )?;
+
+    Ok(())
+}
+
+/// Serialize a database in the given format
+///
+/// The extension of the output file is replaced by the format extension.
+/// Returns the path of the written file.
+pub(crate) fn write_database_with_format(
+    tree_content: &Tree,
+    mut output_file_path: PathBuf,
+    out_format: &DatabaseOutputFormat,
+    only_tree: bool,
+) -> Result<PathBuf> {
+    match out_format {
         DatabaseOutputFormat::Zstd => {
             output_file_path.set_extension("cls");
-            let writer = File::create(output_file_path)?;
-            let writer = zstd::Encoder::new(writer, 0)?.auto_finish();
 
-            if args.only_tree.unwrap_or(false) {
+            if only_tree {
+                let writer = File::create(&output_file_path)?;
+                let writer = zstd::Encoder::new(writer, 0)?.auto_finish();
                 serde_yaml::to_writer(writer, &tree_content.root)?;
             } else {
-                serde_yaml::to_writer(writer, &tree_content)?;
+                write_database(tree_content, &output_file_path)?;
             };
         }
         DatabaseOutputFormat::Yaml => {
             output_file_path.set_extension("cls.yaml");
-            let writer = File::create(output_file_path)?;
+            let writer = File::create(&output_file_path)?;
 
-            if args.only_tree.unwrap_or(false) {
+            if only_tree {
                 serde_yaml::to_writer(writer, &tree_content.root)?;
             } else {
-                serde_yaml::to_writer(writer, &tree_content)?;
+                serde_yaml::to_writer(writer, tree_content)?;
             };
         }
         DatabaseOutputFormat::Json => {
             output_file_path.set_extension("cls.json");
-            let writer = File::create(output_file_path)?;
+            let writer = File::create(&output_file_path)?;
 
-            if args.only_tree.unwrap_or(false) {
+            if only_tree {
                 serde_json::to_writer_pretty(writer, &tree_content.root)?;
             } else {
-                serde_json::to_writer_pretty(writer, &tree_content)?;
+                serde_json::to_writer_pretty(writer, tree_content)?;
             };
         }
+        DatabaseOutputFormat::NewickWithKmers => {
+            output_file_path.set_extension("nwk");
+            std::fs::write(
+                &output_file_path,
+                tree_content.to_newick_with_kmers()?,
+            )?;
+        }
     };
 
+    Ok(output_file_path)
+}
+
+// ? ---------------------------------------------------------------------------
+// ? Placements to iTOL
+// ? ---------------------------------------------------------------------------
+
+#[derive(Parser, Debug)]
+pub(crate) struct PlacementsToItolArguments {
+    /// Path to the placements file
+    ///
+    /// The file should contain the placements in JSONL format, as produced by
+    /// the `place` command.
+    pub(super) placements_file_path: PathBuf,
+
+    /// Path to the database file
+    ///
+    /// The database used to place the queries.
+    #[arg(short, long)]
+    pub(super) database_file_path: PathBuf,
+
+    /// Path to the output file
+    ///
+    /// If not provided, the output will be printed to the standard output.
+    #[arg(short, long)]
+    pub(super) output_file_path: Option<PathBuf>,
+
+    /// iTOL dataset type
+    #[arg(long, short = 'f', default_value = "simplebar")]
+    pub(super) dataset: ItolDataset,
+
+    /// Dataset label
+    ///
+    /// The label of the dataset shown in the iTOL legend.
+    #[arg(long, default_value = "Classeq placements")]
+    pub(super) label: String,
+
+    /// Dataset color
+    ///
+    /// The RGB color of the dataset, in hexadecimal format.
+    #[arg(long, default_value = "#ff0000")]
+    pub(super) color: String,
+}
+
+pub(crate) fn placements_to_itol_cmd(
+    args: PlacementsToItolArguments,
+) -> Result<()> {
+    let tree = load_database(args.database_file_path.to_owned())?;
+    let placements = BufReader::new(File::open(&args.placements_file_path)?);
+
+    match &args.output_file_path {
+        Some(path) => write_itol_dataset(
+            placements,
+            &tree,
+            &args,
+            &mut File::create(path)?,
+        ),
+        None => write_itol_dataset(
+            placements,
+            &tree,
+            &args,
+            &mut std::io::stdout().lock(),
+        ),
+    }
+}
+
+/// Write the count of placed queries per clade as an iTOL dataset
+///
+/// Leaves are identified by name and internal clades by the first and the
+/// last leaves under it (`leaf_a|leaf_b`), which iTOL resolves to their last
+/// common ancestor. Queries without a placed clade (e.g. inconclusive
+/// placements) are ignored.
+fn write_itol_dataset(
+    placements: impl BufRead,
+    tree: &Tree,
+    args: &PlacementsToItolArguments,
+    writer: &mut impl Write,
+) -> Result<()> {
+    let mut counts = BTreeMap::<u64, usize>::new();
+
+    for (index, line) in placements.lines().enumerate() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let placement: Value = serde_json::from_str(&line).map_err(|err| {
+            anyhow::anyhow!("Invalid placement at line {}: {err}", index + 1)
+        })?;
+
+        if let Some(clade) = placement.get("placement").and_then(placed_clade) {
+            *counts.entry(clade).or_default() += 1;
+        }
+    }
+
+    let max_count = counts.values().max().copied().unwrap_or(1);
+
+    match args.dataset {
+        ItolDataset::Simplebar => writeln!(writer, "DATASET_SIMPLEBAR")?,
+        ItolDataset::Colorstrip => writeln!(writer, "DATASET_COLORSTRIP")?,
+    };
+
+    writeln!(writer, "SEPARATOR TAB")?;
+    writeln!(writer, "DATASET_LABEL\t{}", args.label)?;
+    writeln!(writer, "COLOR\t{}", args.color)?;
+    writeln!(writer, "DATA")?;
+
+    for (clade, count) in counts {
+        let node_id = match itol_node_id(tree, clade) {
+            Some(node_id) => node_id,
+            None => {
+                eprintln!("Clade {clade} not found in the database tree");
+                continue;
+            }
+        };
+
+        match args.dataset {
+            ItolDataset::Simplebar => {
+                writeln!(writer, "{node_id}\t{count}")?;
+            }
+            ItolDataset::Colorstrip => {
+                writeln!(
+                    writer,
+                    "{node_id}\t{}\t{count}",
+                    rgba_color(&args.color, count as f64 / max_count as f64)
+                )?;
+            }
+        };
+    }
+
     Ok(())
 }
+
+/// Get the placed clade from a serialized placement
+///
+/// Placements at internal clades are serialized as the clade ID, and exact
+/// matches as an adherence test containing the clade or its ID.
+fn placed_clade(placement: &Value) -> Option<u64> {
+    match placement {
+        Value::Number(id) => id.as_u64(),
+        Value::Object(test) => match test.get("clade")? {
+            Value::Number(id) => id.as_u64(),
+            Value::Object(clade) => clade.get("id")?.as_u64(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Get the iTOL node ID of a clade
+fn itol_node_id(tree: &Tree, clade: u64) -> Option<String> {
+    let leaves = tree.leaves_under(clade);
+
+    match leaves.as_slice() {
+        [] => None,
+        [leaf] => Some(leaf.to_owned()),
+        [first, .., last] => Some(format!("{first}|{last}")),
+    }
+}
+
+/// Convert a hexadecimal color to the iTOL RGBA notation
+fn rgba_color(hex: &str, alpha: f64) -> String {
+    let channel = |range: std::ops::Range<usize>| {
+        hex.trim_start_matches('#')
+            .get(range)
+            .and_then(|value| u8::from_str_radix(value, 16).ok())
+            .unwrap_or(0)
+    };
+
+    format!(
+        "rgba({},{},{},{alpha:.2})",
+        channel(0..2),
+        channel(2..4),
+        channel(4..6),
+    )
+}
+
+// ? ---------------------------------------------------------------------------
+// ? Clade IDs map
+// ? ---------------------------------------------------------------------------
+
+#[derive(Parser, Debug)]
+pub(crate) struct IdMapArguments {
+    /// Path to the database file
+    #[arg(short, long)]
+    pub(super) database_file_path: PathBuf,
+
+    /// Path to the output file
+    ///
+    /// If not provided, the output will be printed to the standard output.
+    #[arg(short, long)]
+    pub(super) output_file_path: Option<PathBuf>,
+}
+
+pub(crate) fn id_map_cmd(args: IdMapArguments) -> Result<()> {
+    let tree = load_database(args.database_file_path)?;
+
+    match args.output_file_path {
+        Some(path) => write_id_map(&tree, &mut File::create(path)?),
+        None => write_id_map(&tree, &mut std::io::stdout().lock()),
+    }
+}
+
+/// Write the clades of the tree as TSV, in pre-order
+///
+/// Names are available for leaves only, and supports for clades with branch
+/// support in the original tree. Missing values are written as empty cells.
+fn write_id_map(tree: &Tree, writer: &mut impl Write) -> Result<()> {
+    let mut leaf_counts = HashMap::<u64, usize>::new();
+    count_leaves(&tree.root, &mut leaf_counts);
+
+    writeln!(writer, "clade\ttype\tname\tsupport\tleaves")?;
+
+    for clade in tree.root.iter() {
+        writeln!(
+            writer,
+            "{id}\t{kind}\t{name}\t{support}\t{leaves}",
+            id = clade.id,
+            kind = match clade.kind {
+                NodeType::Root => "Root",
+                NodeType::Node => "Node",
+                NodeType::Leaf => "Leaf",
+            },
+            name = clade.name.as_deref().unwrap_or_default(),
+            support = clade
+                .support
+                .map(|support| support.to_string())
+                .unwrap_or_default(),
+            leaves = leaf_counts.get(&clade.id).copied().unwrap_or_default(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Count the leaves under each clade in a single pass
+fn count_leaves(clade: &Clade, counts: &mut HashMap<u64, usize>) -> usize {
+    let count = match clade.children.as_deref() {
+        None | Some([]) => 1,
+        Some(children) => children
+            .iter()
+            .map(|child| count_leaves(child, counts))
+            .sum(),
+    };
+
+    counts.insert(clade.id, count);
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_fasta_kmers_from_msa() {
+        let msa_path = "../../core/src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta";
+        let mapper = KmersMap::new(31, 0);
+
+        let count_record_kmers = |canonical: bool, no_revcomp: bool| {
+            let mut output = Vec::<u8>::new();
+
+            write_fasta_kmers(
+                FileOrStdin::from_file(msa_path),
+                &mapper,
+                canonical,
+                no_revcomp,
+                &mut output,
+            )
+            .unwrap();
+
+            String::from_utf8(output)
+                .unwrap()
+                .lines()
+                .filter(|line| line.starts_with("Col_orchidophilum_BJ103_2\t"))
+                .count()
+        };
+
+        //
+        // The record contains 245 nucleotides after the gaps removal, then 215
+        // kmers of size 31 are expected for each strand.
+        //
+        assert_eq!(count_record_kmers(false, false), 430);
+        assert_eq!(count_record_kmers(true, false), 215);
+        assert_eq!(count_record_kmers(false, true), 215);
+    }
+
+    #[test]
+    fn test_convert_database_to_newick_with_kmers() {
+        let output_file_path = PathBuf::from("/tmp/cls-newick-with-kmers");
+
+        convert_database_cmd(DatabaseArguments {
+            database_file_path: PathBuf::from(
+                "../../tests/models/bsub-gyrb-k35.cls",
+            ),
+            only_tree: None,
+            output_file_path: Some(output_file_path.to_owned()),
+            out_format: DatabaseOutputFormat::NewickWithKmers,
+        })
+        .unwrap();
+
+        let newick =
+            std::fs::read_to_string(output_file_path.with_extension("nwk"))
+                .unwrap();
+
+        assert!(newick.ends_with(';'));
+
+        //
+        // Internal nodes are labeled as 'clade_id:kmer_count'
+        //
+        let counts = newick
+            .split('\'')
+            .skip(1)
+            .step_by(2)
+            .map(|label| {
+                let (_, count) = label.split_once(':').unwrap();
+                count.parse::<usize>().unwrap()
+            })
+            .collect::<Vec<usize>>();
+
+        assert!(!counts.is_empty());
+        assert!(counts.iter().all(|count| *count > 0));
+    }
+
+    #[test]
+    fn test_write_itol_dataset_counts_placements_per_clade() {
+        let tree = load_database(PathBuf::from(
+            "../../tests/models/bsub-gyrb-k35.cls",
+        ))
+        .unwrap();
+
+        let leaf = tree.root.iter().find(|clade| clade.is_leaf()).unwrap();
+        let leaf_name = leaf.name.to_owned().unwrap();
+
+        //
+        // Two queries at the leaf (as identity and as max resolution), one at
+        // the root, and one inconclusive query
+        //
+        let placements = [
+            format!(
+                r#"{{"query":"q1","code":"IdentityFound","placement":{{"clade":{},"one":10,"rest":0}}}}"#,
+                leaf.id
+            ),
+            format!(
+                r#"{{"query":"q2","code":"MaxResolutionReached: ok","placement":{}}}"#,
+                leaf.id
+            ),
+            format!(
+                r#"{{"query":"q3","code":"MaxResolutionReached: ok","placement":{}}}"#,
+                tree.root.id
+            ),
+            r#"{"query":"q4","code":"Inconclusive: tie"}"#.to_string(),
+        ]
+        .join("\n");
+
+        let write = |dataset: ItolDataset| {
+            let mut output = Vec::<u8>::new();
+
+            write_itol_dataset(
+                placements.as_bytes(),
+                &tree,
+                &PlacementsToItolArguments {
+                    placements_file_path: PathBuf::new(),
+                    database_file_path: PathBuf::new(),
+                    output_file_path: None,
+                    dataset,
+                    label: "placements".to_string(),
+                    color: "#ff0000".to_string(),
+                },
+                &mut output,
+            )
+            .unwrap();
+
+            String::from_utf8(output).unwrap()
+        };
+
+        let root_leaves = tree.leaves_under(tree.root.id);
+        let root_node_id = format!(
+            "{}|{}",
+            root_leaves.first().unwrap(),
+            root_leaves.last().unwrap()
+        );
+
+        let simplebar = write(ItolDataset::Simplebar);
+        let data = simplebar
+            .split_once("DATA\n")
+            .unwrap()
+            .1
+            .lines()
+            .collect::<Vec<&str>>();
+
+        assert!(simplebar.starts_with("DATASET_SIMPLEBAR\n"));
+        assert_eq!(data.len(), 2);
+        assert!(data.contains(&format!("{leaf_name}\t2").as_str()));
+        assert!(data.contains(&format!("{root_node_id}\t1").as_str()));
+
+        let colorstrip = write(ItolDataset::Colorstrip);
+
+        assert!(colorstrip.starts_with("DATASET_COLORSTRIP\n"));
+        assert!(
+            colorstrip.contains(&format!("{leaf_name}\trgba(255,0,0,1.00)\t2"))
+        );
+        assert!(colorstrip
+            .contains(&format!("{root_node_id}\trgba(255,0,0,0.50)\t1")));
+    }
+
+    #[test]
+    fn test_id_map_contains_the_placed_clades() {
+        use classeq_core::{
+            domain::dtos::{
+                placement_options::PlacementOptions,
+                placement_response::PlacementStatus,
+            },
+            use_cases::place_one,
+        };
+
+        let tree = load_database(PathBuf::from(
+            "../../tests/models/bsub-gyrb-k35.cls",
+        ))
+        .unwrap();
+
+        let mut output = Vec::<u8>::new();
+        write_id_map(&tree, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let rows = output
+            .lines()
+            .skip(1)
+            .map(|line| line.split('\t').collect::<Vec<&str>>())
+            .collect::<Vec<_>>();
+
+        assert_eq!(rows.len(), tree.root.iter().count());
+        assert_eq!(rows[0][1], "Root");
+        assert_eq!(
+            rows[0][4].parse::<usize>().unwrap(),
+            tree.leaves_under(tree.root.id).len()
+        );
+
+        let ids = rows
+            .iter()
+            .map(|row| row[0].parse::<u64>().unwrap())
+            .collect::<Vec<u64>>();
+
+        //
+        // Place the first sample queries, collecting all clades reported by
+        // the placements
+        //
+        let queries = std::fs::read_to_string(
+            "../../tests/data/public/019051d9-4c7a-7b2d-9dd1-66ef92236fd7/input/bsub-refseq-sample50percentRemaining-clean-diamond.fasta",
+        )
+        .unwrap();
+
+        let mut placed = Vec::<u64>::new();
+
+        for record in queries.split('>').filter(|i| !i.is_empty()).take(10) {
+            let (header, sequence) = record.split_once('\n').unwrap();
+
+            let response = place_one(
+                header,
+                &sequence.replace('\n', ""),
+                &tree,
+                &PlacementOptions::default(),
+                &None,
+            )
+            .unwrap();
+
+            match response.placement() {
+                Some(PlacementStatus::IdentityFound(test)) => {
+                    placed.push(test.clade_id())
+                }
+                Some(PlacementStatus::MaxResolutionReached(id, _)) => {
+                    placed.push(*id)
+                }
+                Some(PlacementStatus::Inconclusive(tests, _)) => {
+                    placed.extend(tests.iter().map(|test| test.clade_id()))
+                }
+                _ => (),
+            }
+        }
+
+        assert!(!placed.is_empty());
+        assert!(placed.iter().all(|id| ids.contains(id)));
+    }
+}
@@ -2,8 +2,8 @@ use crate::dtos::output_format::DatabaseDescriptionOutputFormat;
 
 use anyhow::Result;
 use clap::Parser;
-use classeq_ports_lib::load_database;
-use std::{collections::HashMap, path::PathBuf};
+use classeq_ports_lib::{check_database_memory, load_database, DatabaseHeader};
+use std::{collections::HashMap, fs::File, io::BufReader, path::PathBuf};
 
 #[derive(Parser, Debug)]
 pub(crate) struct Arguments {
@@ -13,6 +13,14 @@ pub(crate) struct Arguments {
     #[arg(short, long)]
     pub(super) database_file_path: PathBuf,
 
+    /// Maximum memory (in megabytes)
+    ///
+    /// If provided, the database is not loaded when the estimated memory
+    /// footprint stored in the database header exceeds this value or the
+    /// memory available in the system.
+    #[arg(long)]
+    pub(super) max_memory: Option<u64>,
+
     /// Output format
     ///
     /// The format in which the database will be serialized.
@@ -21,10 +29,37 @@ pub(crate) struct Arguments {
 }
 
 pub(crate) fn describe_database_cmd(args: Arguments) -> Result<()> {
-    let tree = load_database(args.database_file_path)?;
-
     let mut stats = HashMap::new();
 
+    //
+    // The header is available only for binary databases built with the
+    // current format
+    //
+    if let Ok(file) = File::open(&args.database_file_path) {
+        if let Ok(Some(header)) =
+            DatabaseHeader::from_reader(&mut BufReader::new(file))
+        {
+            stats.insert("FormatVersion", header.version.to_string());
+            stats.insert("Checksum", header.checksum);
+
+            if let Some(bytes) = header.estimated_memory_bytes {
+                stats.insert(
+                    "EstimatedMemoryMb",
+                    format!("{:.6}", bytes as f64 / 1_000_000.0),
+                );
+            }
+        }
+    }
+
+    if let Some(max_memory) = args.max_memory {
+        check_database_memory(
+            &args.database_file_path,
+            max_memory.saturating_mul(1_000_000),
+        )?;
+    }
+
+    let tree = load_database(args.database_file_path)?;
+
     let id = tree.id.to_string().to_owned();
     let name = tree.name.to_owned();
     let min_branch_support = tree.min_branch_support.to_string().to_owned();
@@ -32,6 +67,9 @@ pub(crate) fn describe_database_cmd(args: Arguments) -> Result<()> {
     stats.insert("ID", id.to_owned());
     stats.insert("Name", name.to_owned());
     stats.insert("MinBranchSupport", min_branch_support);
+    stats.insert("LeafCount", tree.root.count_leaves().to_string());
+    stats.insert("InternalNodeCount", tree.root.count_internal().to_string());
+    stats.insert("MaxDepth", tree.depth().to_string());
 
     if let Some(size) = tree.get_in_memory_size() {
         let binding = size.to_string();
@@ -43,36 +81,37 @@ pub(crate) fn describe_database_cmd(args: Arguments) -> Result<()> {
     }
 
     if let Some(kmers_map) = &tree.kmers_map {
-        let minimized_kmers =
-            kmers_map.get_map().into_iter().map(|(_, v)| v.0.len());
+        let kmer_count = kmers_map.total_kmers();
+        let minimizer_count = kmers_map.minimizer_count();
+        let histogram = kmers_map.minimizer_size_histogram();
 
         stats.insert("KmerSize", kmers_map.get_kmer_size().to_string());
-        stats.insert(
-            "kmerCount",
-            minimized_kmers.to_owned().sum::<usize>().to_string(),
-        );
+        stats.insert("kmerCount", kmer_count.to_string());
 
         stats.insert(
             "MinimizerSize",
             kmers_map.get_minimizer_size().to_string(),
         );
 
-        stats.insert("MinimizerCount", kmers_map.get_map().len().to_string());
+        stats.insert("MinimizerCount", minimizer_count.to_string());
         stats.insert(
             "MinimizerAvgKmers",
-            (minimized_kmers.to_owned().sum::<usize>()
-                / kmers_map.get_map().len())
-            .to_string(),
+            (kmer_count / minimizer_count.max(1)).to_string(),
         );
 
         stats.insert(
             "LargestMinimizer",
-            minimized_kmers.to_owned().max().unwrap().to_string(),
+            histogram
+                .keys()
+                .next_back()
+                .copied()
+                .unwrap_or(0)
+                .to_string(),
         );
 
         stats.insert(
             "SmallestMinimizer",
-            minimized_kmers.to_owned().min().unwrap().to_string(),
+            histogram.keys().next().copied().unwrap_or(0).to_string(),
         );
     }
 
@@ -0,0 +1,52 @@
+use anyhow::Result;
+use clap::Parser;
+use classeq_core::use_cases::merge_databases;
+use classeq_ports_lib::{load_database, write_database};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+pub(crate) struct Arguments {
+    /// Path to the first classeq database
+    pub(super) first_database_path: PathBuf,
+
+    /// Path to the second classeq database
+    ///
+    /// The database should be built from leaves not included in the first
+    /// database and with the same kmer parameters.
+    pub(super) second_database_path: PathBuf,
+
+    /// Output file path
+    ///
+    /// If not provided, the output will be saved in the current directory with
+    /// the name `classeq-database.cls`.
+    #[arg(short, long)]
+    pub(super) output_file_path: Option<PathBuf>,
+
+    /// The merged database name
+    ///
+    /// If not provided, the name is composed from the names of both
+    /// databases.
+    #[arg(short, long)]
+    pub(super) name: Option<String>,
+}
+
+/// Merge two databases grafting both trees under a new shared root
+///
+/// The leaves of each database are expected to form a monophyletic group of
+/// the combined phylogeny. See `merge_databases` for details.
+pub(crate) fn merge_database_cmd(args: Arguments) -> Result<()> {
+    let first = load_database(args.first_database_path)?;
+    let second = load_database(args.second_database_path)?;
+
+    let tree = merge_databases(first, second, args.name)?;
+
+    let mut output_file_path = args
+        .output_file_path
+        .unwrap_or_else(|| PathBuf::from("classeq-database.cls"));
+
+    output_file_path.set_extension("cls");
+
+    write_database(&tree, &output_file_path)?;
+
+    Ok(())
+}
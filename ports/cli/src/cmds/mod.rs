@@ -1,4 +1,9 @@
+pub mod bench;
 pub mod build_db;
+pub mod compare_db;
 pub mod convert;
 pub mod describe_db;
+pub mod merge_db;
 pub mod place_sequences;
+pub mod relabel_db;
+pub mod update_db;
@@ -1,17 +1,31 @@
-use crate::dtos::telemetry_code::TelemetryCode;
+use crate::dtos::{query_format::QueryFormat, telemetry_code::TelemetryCode};
 
 use anyhow::Result;
 use clap::{ArgAction, Parser};
 use classeq_core::{
     domain::dtos::{
-        annotation::Annotation, file_or_stdin::FileOrStdin,
+        annotation::Annotation,
+        chunk_by::ChunkBy,
+        file_or_stdin::{FileOrStdin, Source},
+        genetic_code::GeneticCode,
+        match_coverage::MatchCoverage,
+        output_field::OutputField,
         output_format::OutputFormat,
+        output_order::OutputOrder,
+        placement_config::PlacementConfig,
+        placement_options::PlacementOptions,
+        tree::Tree,
     },
-    use_cases::place_sequences,
+    use_cases::{place_kmers, place_sequences},
+};
+use classeq_ports_lib::{
+    check_database_memory, init_thread_pool, load_database,
 };
-use classeq_ports_lib::load_database;
 use std::time::Instant;
-use std::{path::PathBuf, time::Duration};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use tracing::{info, info_span};
 use uuid::Uuid;
 
@@ -22,15 +36,33 @@ pub(crate) struct Arguments {
     #[clap(default_value = "-")]
     pub(super) query: FileOrStdin,
 
+    /// Query format
+    ///
+    /// The format of the query content. With `kmers`, the query content is a
+    /// single query given as pre-extracted kmers (or kmer hashes), one per
+    /// line, placed without parsing sequences. The query is named after the
+    /// query file.
+    #[arg(long, default_value = "sequences")]
+    pub(super) query_format: QueryFormat,
+
     /// Path to the classeq database
     ///
     /// The file should be in JSON or YAML format.
     #[arg(short, long)]
     pub(super) database_file_path: PathBuf,
 
+    /// Maximum memory (in megabytes)
+    ///
+    /// If provided, the database is not loaded when the estimated memory
+    /// footprint stored in the database header exceeds this value or the
+    /// memory available in the system.
+    #[arg(long)]
+    pub(super) max_memory: Option<u64>,
+
     /// Output file path
     ///
-    /// The file will be saved in JSON or YAML format.
+    /// The file will be saved in JSON or YAML format. Paths ending with `.gz`
+    /// or `.zst` are compressed with gzip or zstd.
     #[arg(short, long)]
     pub(super) output_file_path: PathBuf,
 
@@ -46,18 +78,76 @@ pub(crate) struct Arguments {
     #[arg(long, default_value = "yaml")]
     pub(super) out_format: OutputFormat,
 
+    /// Output fields
+    ///
+    /// A comma separated list of the placement fields to be included in the
+    /// output (e.g. `query,code`). All fields are included if not provided.
+    /// The query and code fields are always included.
+    #[arg(long, value_delimiter = ',')]
+    pub(super) output_fields: Option<Vec<OutputField>>,
+
+    /// Output order
+    ///
+    /// Results are written in arrival order by default. Sorting by header or
+    /// input order makes outputs reproducible between runs, but buffers all
+    /// results in memory before writing.
+    #[arg(long, default_value = "arrival")]
+    pub(super) output_order: OutputOrder,
+
+    /// Chunk the results by status or by placed clade
+    ///
+    /// If provided, results are partitioned into separate files of a
+    /// directory named as the output file (e.g. `-o results` writes
+    /// `results/IdentityFound.jsonl`). Resume is not supported for chunked
+    /// outputs.
+    #[arg(long)]
+    pub(super) chunk_reads: Option<ChunkBy>,
+
+    /// Lineage delimiter
+    ///
+    /// The delimiter of the scientific names in the taxonomic lineage of
+    /// placements, reported when annotations are available. Defaults to `;`.
+    #[arg(long)]
+    pub(super) lineage_delimiter: Option<String>,
+
     /// Maximum number of iterations
     ///
     /// The maximum number of iterations to traverse the tree.
     #[arg(short, long)]
     pub(super) iterations: Option<i32>,
 
+    /// Per-sequence timeout (in seconds)
+    ///
+    /// If provided, queries exceeding this wall-clock time during the tree
+    /// introspection are reported as `Unclassifiable: Timeout`, while the
+    /// remaining queries are still placed.
+    #[arg(long, value_parser = parse_timeout)]
+    pub(super) per_sequence_timeout: Option<Duration>,
+
     /// Minimum match coverage
     ///
     /// The minimum match coverage between the query and the database sequences.
     #[arg(short, long)]
     pub(super) match_coverage: Option<f64>,
 
+    /// Minimum match kmers
+    ///
+    /// The minimum number of query kmers overlapping the database. If
+    /// provided, it is used instead of the minimum match coverage fraction.
+    /// This is useful for short queries, where a single kmer changes the
+    /// coverage fraction.
+    #[arg(long, conflicts_with = "match_coverage")]
+    pub(super) min_match_kmers: Option<usize>,
+
+    /// Classify low coverage queries as partial
+    ///
+    /// If true, queries below the minimum match coverage are placed at the
+    /// first accepted clade, with the `Partial overlap accepted` message,
+    /// instead of being unclassifiable. Queries sharing no kmers with the
+    /// database are still unclassifiable.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) classify_low_coverage_as_partial: bool,
+
     /// Remove intersection
     ///
     /// If true, calculate the one-vs-rest difference without the shared kmers.
@@ -65,12 +155,217 @@ pub(crate) struct Arguments {
     #[arg(short, long, action=ArgAction::SetTrue)]
     pub(super) remove_intersection: Option<bool>,
 
+    /// Query forward strand only
+    ///
+    /// Overrides the database strand mode when building the query kmers. If
+    /// true, reverse complement kmers of queries are skipped. Otherwise, both
+    /// strands are used. Queries follow the database strand mode if not
+    /// provided.
+    #[arg(long)]
+    pub(super) query_forward_only: Option<bool>,
+
+    /// Translate queries
+    ///
+    /// If true, nucleotide queries are translated in the six reading frames,
+    /// each frame is placed, and the best scoring placement is reported with
+    /// its frame. Requires a database built with `--protein`.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) translate: bool,
+
+    /// Genetic code
+    ///
+    /// The NCBI translation table used to translate queries (1 to 6, or 11).
+    /// The standard code is used if not provided.
+    #[arg(long, requires = "translate")]
+    pub(super) genetic_code: Option<GeneticCode>,
+
+    /// Minimum adherence margin
+    ///
+    /// The minimum one-vs-rest kmers difference required to accept a clade.
+    /// Levels without accepted clades end the introspection, and queries
+    /// rejected at the first level are inconclusive at the root. Expressed in
+    /// parts per million with `--normalize-by-clade-size`.
+    #[arg(long)]
+    pub(super) min_adherence_margin: Option<i32>,
+
+    /// Minimum adherence ratio
+    ///
+    /// If provided, a clade is only accepted if its kmers matches are at least
+    /// this number of times the matches of the sibling clades.
+    #[arg(long)]
+    pub(super) min_adherence_ratio: Option<f64>,
+
+    /// Normalize by clade size
+    ///
+    /// If true, the one-vs-rest kmers matches are divided by the number of
+    /// kmers indexed for the respective clades, reducing the bias towards
+    /// clades with many reference sequences. Normalized matches and margins
+    /// are expressed in parts per million.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) normalize_by_clade_size: bool,
+
+    /// Stop at rank
+    ///
+    /// If provided, the placement stops at the first accepted clade annotated
+    /// with this rank (e.g. genus), instead of descending to the leaves.
+    /// Clades without rank annotations are traversed as usual.
+    #[arg(long)]
+    pub(super) stop_at_rank: Option<String>,
+
     /// Force overwrite
     ///
     /// If the output file already exists, it will be overwritten.
     #[arg(short, long, default_value = "false")]
     pub(super) force_overwrite: bool,
 
+    /// Resume an interrupted placement
+    ///
+    /// If the output file already exists, queries already placed are skipped
+    /// and new results are appended to it. Only available for the JSONL output
+    /// format.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) resume: bool,
+
+    /// Append results to the output file
+    ///
+    /// Results are appended to the output file as placed, keeping existing
+    /// records. Otherwise, results are written to a temporary file moved to
+    /// the output path when the run completes. Not available for chunked
+    /// outputs.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) append: bool,
+
+    /// Report the placement margin
+    ///
+    /// If true, the weakest `one - rest` margin of the decisions taken along
+    /// the placement path is included in the output as `weakest_margin`. Low
+    /// margins indicate placements which traversed a weak decision.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) report_margin: bool,
+
+    /// Report the leaves under the placed clade
+    ///
+    /// If true, the names of the reference leaves under the placed clade are
+    /// included in the output as `leaves`. For placements at internal clades
+    /// the leaves are the candidate taxa.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) report_leaves: bool,
+
+    /// Report the placement evidence
+    ///
+    /// If true, the `one` and `rest` kmer counts of all candidate clades of
+    /// the level deciding the placement, including the rejected ones, are
+    /// included in the output as `evidence`.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) report_evidence: bool,
+
+    /// Report the query strand
+    ///
+    /// If true, the forward and the reverse complement kmers of each query are
+    /// overlapped with the database separately, and the orientation with more
+    /// overlapping kmers is included in the output as `strand`. Only
+    /// informative for databases built with forward kmers only, since both
+    /// orientations match databases indexing both strands (`unknown`).
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) report_strand: bool,
+
+    /// Report the branch support of each level of the placement path
+    ///
+    /// If true, the clades from the root to the placed clade are included in
+    /// the output as `path_support`, each one with the branch support of the
+    /// reference tree. Queries descending through weakly supported branches
+    /// should be considered less confident.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) min_support_per_level: bool,
+
+    /// Explain placements with kmers
+    ///
+    /// If provided, up to this number of query kmers discriminating the placed
+    /// clade from its siblings are included in the output as
+    /// `discriminating_kmers`. Requires databases built with the
+    /// `--retain-kmer-strings` option.
+    #[arg(long)]
+    pub(super) explain_kmers: Option<usize>,
+
+    /// Explain placements
+    ///
+    /// If true, a human-readable summary of the decisions taken at each
+    /// introspected level is written for each query to a file named as the
+    /// output file with the `explain.txt` extension. Single kmer queries are
+    /// explained to the standard error.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) explain: bool,
+
+    /// Minimum base quality
+    ///
+    /// Bases of FASTQ queries with Phred score below this value are dropped
+    /// before building kmers, as done with non-IUPAC characters. Ignored for
+    /// FASTA queries.
+    #[arg(long)]
+    pub(super) min_base_quality: Option<u8>,
+
+    /// Minimum read quality
+    ///
+    /// FASTQ queries with mean Phred score below this value are skipped.
+    /// Ignored for FASTA queries.
+    #[arg(long)]
+    pub(super) min_read_quality: Option<f64>,
+
+    /// Unclassifiable queries FASTA
+    ///
+    /// If provided, the header and sequence of each query resulting in an
+    /// `Unclassifiable` placement are written to this file in FASTA format,
+    /// then they can be placed against a different database.
+    #[arg(long)]
+    pub(super) unclassifiable_fasta: Option<PathBuf>,
+
+    /// Query QC report
+    ///
+    /// If provided, the length and the GC content of each query are written
+    /// to this TSV file alongside the placement status.
+    #[arg(long)]
+    pub(super) qc_report: Option<PathBuf>,
+
+    /// SQLite output
+    ///
+    /// If provided, the placements are also written to this SQLite database,
+    /// with queries, placed clades, and annotations in separate tables.
+    /// Requires the CLI to be built with the `sqlite` feature.
+    #[arg(long)]
+    pub(super) sqlite_out: Option<PathBuf>,
+
+    /// Drop unclassifiable queries
+    ///
+    /// If true, `Unclassifiable` placements are not written to the output
+    /// file.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) drop_unclassifiable: bool,
+
+    /// Rename duplicated queries
+    ///
+    /// If true, repeated query headers are renamed appending the occurrence
+    /// index (e.g. `query_2`). Otherwise, repeated queries are flagged with
+    /// `duplicate: true` in the output.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) rename_duplicates: bool,
+
+    /// Trace a single query
+    ///
+    /// If provided, the full introspection trace (clade proposals and
+    /// one-vs-rest counts of each level) of the query with this header is
+    /// written to a `.trace.jsonl` file next to the output file, without
+    /// changing the log level of other queries.
+    #[arg(long)]
+    pub(super) trace_query: Option<String>,
+
+    /// Query buffer size
+    ///
+    /// The maximum number of query records read ahead of the placement. The
+    /// reader waits while the buffer is full, then the memory used by queries
+    /// not yet placed stays bounded for very large query files.
+    #[arg(long)]
+    pub(super) query_buffer_size: Option<usize>,
+
     /// Generate profiling
     ///
     /// If true, generate a classeq-profile.pb file used to profile the
@@ -122,16 +417,18 @@ pub(crate) fn place_sequences_cmd(
     // ? Create a thread pool configured globally
     // ? -----------------------------------------------------------------------
 
-    if let Err(err) = rayon::ThreadPoolBuilder::new()
-        .num_threads(threads.to_owned())
-        .build_global()
-    {
-        panic!("Error creating thread pool: {err}");
-    };
+    init_thread_pool(threads);
 
     let now = Instant::now();
 
     let per_seq_time = {
+        if let Some(max_memory) = args.max_memory {
+            check_database_memory(
+                &args.database_file_path,
+                max_memory.saturating_mul(1_000_000),
+            )?;
+        }
+
         let mut tree = load_database(args.database_file_path)?;
 
         if let Some(path) = args.annotations_file_path {
@@ -143,15 +440,70 @@ pub(crate) fn place_sequences_cmd(
             }
         }
 
+        let mut options = PlacementOptions::default()
+            .with_max_iterations(args.iterations)
+            .with_per_sequence_timeout(args.per_sequence_timeout)
+            .with_min_match_coverage(args.match_coverage)
+            .with_remove_intersection(args.remove_intersection)
+            .with_min_adherence_margin(args.min_adherence_margin)
+            .with_query_forward_only(args.query_forward_only)
+            .with_translate(
+                args.translate
+                    .then(|| args.genetic_code.unwrap_or_default()),
+            )
+            .with_min_adherence_ratio(args.min_adherence_ratio)
+            .with_normalize_by_clade_size(args.normalize_by_clade_size)
+            .with_stop_at_rank(args.stop_at_rank)
+            .with_classify_low_coverage_as_partial(
+                args.classify_low_coverage_as_partial,
+            )
+            .with_report_margin(args.report_margin)
+            .with_report_leaves(args.report_leaves)
+            .with_report_evidence(args.report_evidence)
+            .with_report_strand(args.report_strand)
+            .with_report_path_support(args.min_support_per_level)
+            .with_explain_kmers(args.explain_kmers)
+            .with_explain(args.explain)
+            .with_output_fields(args.output_fields)
+            .with_lineage_delimiter(args.lineage_delimiter);
+
+        if let Some(kmers) = args.min_match_kmers {
+            options =
+                options.with_match_coverage(MatchCoverage::Absolute(kmers));
+        }
+
+        let config = PlacementConfig::default()
+            .with_options(options)
+            .with_overwrite(args.force_overwrite)
+            .with_resume(args.resume)
+            .with_append(args.append)
+            .with_unclassifiable_fasta(args.unclassifiable_fasta)
+            .with_qc_report(args.qc_report)
+            .with_sqlite_out(args.sqlite_out)
+            .with_drop_unclassifiable(args.drop_unclassifiable)
+            .with_rename_duplicates(args.rename_duplicates)
+            .with_min_base_quality(args.min_base_quality)
+            .with_min_read_quality(args.min_read_quality)
+            .with_output_format(args.out_format)
+            .with_output_order(args.output_order)
+            .with_chunk_by(args.chunk_reads)
+            .with_trace_query(args.trace_query)
+            .with_query_buffer_size(args.query_buffer_size);
+
+        if args.query_format == QueryFormat::Kmers {
+            return place_kmers_query(
+                args.query,
+                &tree,
+                &args.output_file_path,
+                &config,
+            );
+        }
+
         match place_sequences(
             args.query,
             &tree,
             &args.output_file_path,
-            &args.iterations,
-            &args.match_coverage,
-            &args.force_overwrite,
-            &args.out_format,
-            &args.remove_intersection,
+            &config,
             &Some(&span),
         ) {
             Ok(buffer) => buffer,
@@ -166,7 +518,7 @@ pub(crate) fn place_sequences_cmd(
         .into_iter()
         .map(|i| i.milliseconds_time)
         .sum::<Duration>()
-        / per_seq_time.len() as u32;
+        / per_seq_time.len().max(1) as u32;
 
     let max = per_seq_time
         .to_owned()
@@ -221,3 +573,64 @@ pub(crate) fn place_sequences_cmd(
 
     Ok(())
 }
+
+/// Place a single query given as pre-extracted kmers
+///
+/// The placement response is written to the output file in the configured
+/// output format.
+fn place_kmers_query(
+    query: FileOrStdin,
+    tree: &Tree,
+    output_file_path: &Path,
+    config: &PlacementConfig,
+) -> Result<()> {
+    let header = match &query.source {
+        Source::Stdin => "stdin".to_string(),
+        Source::Arg(path) | Source::Url(path) => Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_owned()),
+    };
+
+    let response =
+        place_kmers(&header, &query.kmers_content()?, tree, &config.options)?;
+
+    if let Some(explanation) = response.explanation() {
+        eprint!("{explanation}");
+    }
+
+    let response = match config.options.includes_field(&OutputField::Placement)
+    {
+        true => response,
+        false => response.without_placement(),
+    };
+
+    let mut output_file_path = output_file_path.to_path_buf();
+    output_file_path.set_extension(config.output_format.extension());
+
+    if output_file_path.exists() && !config.overwrite {
+        return Err(anyhow::anyhow!(
+            "Could not overwrite existing file {output_file_path:?} when \
+            overwrite option is `false`."
+        ));
+    }
+
+    std::fs::write(
+        output_file_path,
+        config.output_format.format_record(&response)?,
+    )?;
+
+    Ok(())
+}
+
+/// Parse a positive timeout given in (possibly fractional) seconds
+fn parse_timeout(value: &str) -> Result<Duration, String> {
+    let seconds = value
+        .parse::<f64>()
+        .map_err(|err| format!("Invalid timeout {value}: {err}"))?;
+
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(timeout) if !timeout.is_zero() => Ok(timeout),
+        _ => Err(format!("The timeout should be positive: {value}")),
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::{bail, Result};
+use clap::Parser;
+use classeq_ports_lib::{load_database, write_database};
+use std::{collections::HashMap, fs::read_to_string, path::PathBuf};
+use tracing::{info, warn};
+
+#[derive(Parser, Debug)]
+pub(crate) struct Arguments {
+    /// Path to the classeq database
+    pub(super) database_file_path: PathBuf,
+
+    /// Path to the leaf names mapping
+    ///
+    /// A tab-separated file with the current leaf names in the first column
+    /// and the new names in the second one. Empty lines and lines starting
+    /// with `#` are ignored.
+    pub(super) mapping_file_path: PathBuf,
+
+    /// Output file path
+    ///
+    /// If not provided, the output will be saved in the current directory with
+    /// the name `classeq-database.cls`.
+    #[arg(short, long)]
+    pub(super) output_file_path: Option<PathBuf>,
+}
+
+/// Rename the database leaves without rebuilding it
+///
+/// Leaves not included in the mapping keep their names. Names of the mapping
+/// not found in the database are reported as warnings.
+pub(crate) fn relabel_database_cmd(args: Arguments) -> Result<()> {
+    let mapping = parse_mapping(&read_to_string(&args.mapping_file_path)?)?;
+
+    let mut tree = load_database(args.database_file_path)?;
+
+    let missing = tree.rename_leaves(&mapping);
+
+    for name in missing.iter() {
+        warn!("Leaf not found in the database: {name}");
+    }
+
+    info!(
+        "Renamed {renamed} of {total} leaves in the mapping",
+        renamed = mapping.len() - missing.len(),
+        total = mapping.len()
+    );
+
+    let mut output_file_path = args
+        .output_file_path
+        .unwrap_or_else(|| PathBuf::from("classeq-database.cls"));
+
+    output_file_path.set_extension("cls");
+
+    write_database(&tree, &output_file_path)?;
+
+    Ok(())
+}
+
+/// Parse the two-column mapping from the current to the new leaf names
+fn parse_mapping(content: &str) -> Result<HashMap<String, String>> {
+    let mut mapping = HashMap::<String, String>::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let columns = line.split('\t').collect::<Vec<&str>>();
+
+        let (old_name, new_name) = match columns[..] {
+            [old_name, new_name] if !old_name.is_empty() => {
+                (old_name, new_name.trim())
+            }
+            _ => bail!(
+                "Expected two tab-separated columns at line {}",
+                index + 1
+            ),
+        };
+
+        if new_name.is_empty() {
+            bail!("Empty new name at line {}", index + 1);
+        }
+
+        if mapping
+            .insert(old_name.to_string(), new_name.to_string())
+            .is_some()
+        {
+            bail!("Duplicated leaf name at line {}: {old_name}", index + 1);
+        }
+    }
+
+    Ok(mapping)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mapping() {
+        let mapping = parse_mapping(
+            "# accession\tname\nAB123\tC. acutatum\r\n\nCD456\tC. fioriniae\n",
+        )
+        .unwrap();
+
+        assert_eq!(mapping.len(), 2);
+        assert_eq!(mapping["AB123"], "C. acutatum");
+        assert_eq!(mapping["CD456"], "C. fioriniae");
+
+        assert!(parse_mapping("AB123\n").is_err());
+        assert!(parse_mapping("AB123\tx\tC. acutatum\n").is_err());
+        assert!(parse_mapping("AB123\tx\nAB123\ty\n").is_err());
+    }
+}
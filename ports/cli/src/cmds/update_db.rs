@@ -0,0 +1,50 @@
+use anyhow::Result;
+use clap::Parser;
+use classeq_core::{
+    domain::dtos::file_or_stdin::FileOrStdin, use_cases::update_database,
+};
+use classeq_ports_lib::{load_database, write_database};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+pub(crate) struct Arguments {
+    /// Path to the classeq database
+    pub(super) database_file_path: PathBuf,
+
+    /// Path to the updated tree file
+    ///
+    /// The tree should contain all leaves of the database and the added ones.
+    pub(super) tree_file_path: PathBuf,
+
+    /// Path to the msa file of the added leaves
+    ///
+    /// Rows should be aligned to the columns of the MSA used to build the
+    /// database. Use `-` to read the MSA from stdin.
+    pub(super) msa_file_path: FileOrStdin,
+
+    /// Output file path
+    ///
+    /// If not provided, the output will be saved in the current directory with
+    /// the name `classeq-database.cls`.
+    #[arg(short, long)]
+    pub(super) output_file_path: Option<PathBuf>,
+}
+
+/// Add new reference sequences to an existing database
+///
+/// See `update_database` for details about the leaves insertion.
+pub(crate) fn update_database_cmd(args: Arguments) -> Result<()> {
+    let tree = load_database(args.database_file_path)?;
+
+    let tree = update_database(tree, args.tree_file_path, args.msa_file_path)?;
+
+    let mut output_file_path = args
+        .output_file_path
+        .unwrap_or_else(|| PathBuf::from("classeq-database.cls"));
+
+    output_file_path.set_extension("cls");
+
+    write_database(&tree, &output_file_path)?;
+
+    Ok(())
+}
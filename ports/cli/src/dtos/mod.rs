@@ -1,2 +1,3 @@
 pub mod output_format;
+pub mod query_format;
 pub mod telemetry_code;
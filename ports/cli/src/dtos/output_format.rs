@@ -1,12 +1,15 @@
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
-#[derive(Clone, Debug, Serialize, Deserialize, clap::ValueEnum)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, clap::ValueEnum)]
 #[serde(rename_all = "camelCase")]
 pub(crate) enum DatabaseOutputFormat {
     /// Zstandard format
     ///
     /// The file will be compressed using Zstandard. See
-    /// `<https://github.com/facebook/zstd>` for more information.
+    /// `<https://github.com/facebook/zstd>` for more information. This is the
+    /// binary `.cls` format, also accepted as `binary`.
+    #[value(alias = "binary")]
     Zstd,
 
     /// YAML format
@@ -20,6 +23,56 @@ pub(crate) enum DatabaseOutputFormat {
     /// No compression will be applied to the file. The file will be saved in
     /// JSON format.
     Json,
+
+    /// Newick format labeled with kmers counts
+    ///
+    /// A diagnostic export of the tree only. Internal nodes are labeled as
+    /// `'clade_id:kmer_count'`, to spot clades with few or no indexed kmers.
+    NewickWithKmers,
+}
+
+/// The database formats written by the `build-db` command
+///
+/// Only formats readable by the database loading are accepted, then the
+/// diagnostic exports of `convert database` are not included.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum DatabaseBuildFormat {
+    /// Zstandard format
+    ///
+    /// The binary `.cls` format. `binary` is only an alias of this format.
+    #[value(alias = "binary")]
+    Zstd,
+
+    /// YAML format
+    Yaml,
+
+    /// JSON format
+    Json,
+}
+
+impl DatabaseBuildFormat {
+    /// Detect the format from the extension of the output file
+    ///
+    /// Files ending with `.yaml`, `.yml` or `.json` are written as plain text,
+    /// while any other file is written in the binary format.
+    pub(crate) fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml" | "yml") => DatabaseBuildFormat::Yaml,
+            Some("json") => DatabaseBuildFormat::Json,
+            _ => DatabaseBuildFormat::Zstd,
+        }
+    }
+}
+
+impl From<DatabaseBuildFormat> for DatabaseOutputFormat {
+    fn from(format: DatabaseBuildFormat) -> Self {
+        match format {
+            DatabaseBuildFormat::Zstd => DatabaseOutputFormat::Zstd,
+            DatabaseBuildFormat::Yaml => DatabaseOutputFormat::Yaml,
+            DatabaseBuildFormat::Json => DatabaseOutputFormat::Json,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, clap::ValueEnum)]
@@ -40,3 +93,18 @@ pub(crate) enum DatabaseDescriptionOutputFormat {
     /// The file will be saved in TSV format.
     Tsv,
 }
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ItolDataset {
+    /// iTOL simple bar chart
+    ///
+    /// Each clade receives a bar proportional to the number of placed queries.
+    Simplebar,
+
+    /// iTOL color strip
+    ///
+    /// Each clade receives a strip colored with an opacity proportional to the
+    /// number of placed queries, labeled with the count.
+    Colorstrip,
+}
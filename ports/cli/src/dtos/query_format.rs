@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum QueryFormat {
+    /// Sequences format
+    ///
+    /// FASTA or FASTQ records, each record being placed as a query.
+    Sequences,
+
+    /// Kmers format
+    ///
+    /// Pre-extracted kmers of a single query, one kmer or kmer hash per line.
+    /// Extra columns (e.g. kmer counts) are ignored.
+    Kmers,
+}
@@ -18,11 +18,26 @@ enum Opts {
     /// Build the index database
     BuildDb(cmds::build_db::Arguments),
 
+    /// Merge databases built from disjoint leaf sets
+    MergeDb(cmds::merge_db::Arguments),
+
+    /// Add new reference sequences to an existing database
+    UpdateDb(cmds::update_db::Arguments),
+
+    /// Rename the database leaves from a mapping file
+    Relabel(cmds::relabel_db::Arguments),
+
     /// Place sequences on the tree
     Place(cmds::place_sequences::Arguments),
 
     /// Describe the database
     DescribeDb(cmds::describe_db::Arguments),
+
+    /// Compare the leaves and kmers of two databases
+    Compare(cmds::compare_db::Arguments),
+
+    /// Measure the placement throughput of a database
+    Bench(cmds::bench::Arguments),
 }
 
 fn main() -> Result<()> {
@@ -106,15 +121,30 @@ fn main() -> Result<()> {
                 cmds::convert::serialize_tree_cmd(tree_args);
             }
             cmds::convert::Commands::Kmers(kmers_args) => {
-                cmds::convert::get_kmers_cmd(kmers_args);
+                cmds::convert::get_kmers_cmd(kmers_args)?;
             }
             cmds::convert::Commands::Database(db_args) => {
                 cmds::convert::convert_database_cmd(db_args)?;
             }
+            cmds::convert::Commands::PlacementsToItol(itol_args) => {
+                cmds::convert::placements_to_itol_cmd(itol_args)?;
+            }
+            cmds::convert::Commands::IdMap(id_map_args) => {
+                cmds::convert::id_map_cmd(id_map_args)?;
+            }
         },
         BuildDb(db_args) => {
             cmds::build_db::build_database_cmd(db_args, args.threads)?;
         }
+        MergeDb(db_args) => {
+            cmds::merge_db::merge_database_cmd(db_args)?;
+        }
+        UpdateDb(db_args) => {
+            cmds::update_db::update_database_cmd(db_args)?;
+        }
+        Relabel(relabel_args) => {
+            cmds::relabel_db::relabel_database_cmd(relabel_args)?;
+        }
         Place(place_args) => cmds::place_sequences::place_sequences_cmd(
             place_args,
             args.threads.unwrap_or(1),
@@ -122,6 +152,12 @@ fn main() -> Result<()> {
         DescribeDb(db_args) => {
             cmds::describe_db::describe_database_cmd(db_args)?;
         }
+        Compare(compare_args) => {
+            cmds::compare_db::compare_databases_cmd(compare_args)?;
+        }
+        Bench(bench_args) => {
+            cmds::bench::bench_cmd(bench_args, args.threads.unwrap_or(1))?;
+        }
     }
 
     Ok(())
@@ -0,0 +1,46 @@
+use classeq_ports_lib::load_database;
+use std::{
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+const TREE_PATH: &str = "../../core/src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh-PhyML.nwk";
+const MSA_PATH: &str = "../../core/src/tests/data/colletotrichum-acutatom-complex/inputs/Colletotrichum_acutatum_gapdh_mafft.fasta";
+
+#[test]
+fn test_build_db_with_msa_from_stdin() {
+    let file_output = PathBuf::from("/tmp/cls-build-db-from-file.cls");
+    let stdin_output = PathBuf::from("/tmp/cls-build-db-from-stdin.cls");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_cls"))
+        .args(["build-db", TREE_PATH, MSA_PATH, "-o"])
+        .arg(&file_output)
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+
+    assert!(status.success());
+
+    let status = Command::new(env!("CARGO_BIN_EXE_cls"))
+        .args(["build-db", TREE_PATH, "-", "-o"])
+        .arg(&stdin_output)
+        .stdin(std::fs::File::open(MSA_PATH).unwrap())
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+
+    assert!(status.success());
+
+    let from_file = load_database(file_output).unwrap();
+    let from_stdin = load_database(stdin_output).unwrap();
+
+    let file_map = from_file.kmers_map.unwrap();
+    let stdin_map = from_stdin.kmers_map.unwrap();
+
+    assert!(!file_map.get_map().is_empty());
+    assert_eq!(file_map.get_map().len(), stdin_map.get_map().len());
+
+    for (key, value) in file_map.get_map() {
+        assert_eq!(stdin_map.get_map().get(key).map(|i| &i.0), Some(&value.0));
+    }
+}
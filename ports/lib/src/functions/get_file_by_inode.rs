@@ -1,13 +1,185 @@
-use std::{os::unix::fs::MetadataExt, path::PathBuf};
+use crate::FileIdentity;
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 use walkdir::WalkDir;
 
+/// An index of the files of a directory by their inode numbers
+///
+/// The directory is walked once when the index is built, then multiple
+/// lookups (e.g. one per analysis found during a watcher scan) do not walk
+/// the directory again. Files created after the index was built are not
+/// resolved.
+#[derive(Debug, Default)]
+pub struct InodeIndex {
+    files: HashMap<u64, Vec<(PathBuf, FileIdentity)>>,
+}
+
+impl InodeIndex {
+    pub fn from_directory(directory: &Path) -> Self {
+        let mut files = HashMap::<u64, Vec<(PathBuf, FileIdentity)>>::new();
+
+        for entry in WalkDir::new(directory)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+        {
+            if let Ok(metadata) = entry.metadata() {
+                let identity = FileIdentity::from_metadata(&metadata);
+
+                files
+                    .entry(identity.inode)
+                    .or_default()
+                    .push((entry.path().to_path_buf(), identity));
+            }
+        }
+
+        InodeIndex { files }
+    }
+
+    /// Get a file of the directory by its inode number
+    ///
+    /// Only files inside `directory` are resolved. If an identity is given,
+    /// files with the same inode but a different size or modification time
+    /// are ignored, since they inherited the inode of the referenced file.
+    pub fn get(
+        &self,
+        directory: &Path,
+        inode: u32,
+        identity: Option<&FileIdentity>,
+    ) -> Option<PathBuf> {
+        self.files
+            .get(&(inode as u64))?
+            .iter()
+            .find(|(path, candidate)| {
+                path.starts_with(directory)
+                    && identity.map_or(true, |identity| identity == candidate)
+            })
+            .map(|(path, _)| path.to_owned())
+    }
+}
+
+/// Get a file by its inode number
+///
+/// Inodes are used as public file identifiers by the API. The directory is
+/// walked on each call, then lookups should target the smallest directory
+/// possible (e.g. the work directory input folder), and repeated lookups
+/// should use an `InodeIndex`.
+///
+/// Note that inodes are reused by the file system after the file deletion,
+/// then a file created after the identifier was issued may inherit it. Use
+/// `get_referenced_file_by_inode` to resolve identifiers persisted with the
+/// file identity.
 pub fn get_file_by_inode(directory: PathBuf, inode: u32) -> Option<PathBuf> {
-    WalkDir::new(directory)
-        .into_iter()
-        .filter_map(|entry| entry.ok())
-        .find(|entry| {
-            let metadata = entry.metadata().expect("Failed to read metadata");
-            metadata.ino() == inode as u64
-        })
-        .map(|entry| entry.path().to_path_buf())
+    InodeIndex::from_directory(&directory).get(&directory, inode, None)
+}
+
+/// Get a file by the inode number persisted with the file identity
+///
+/// Files inheriting the inode after the identity was recorded are ignored.
+/// Identifiers persisted without the identity (e.g. analyses configured by
+/// previous versions) are resolved by the inode only.
+pub fn get_referenced_file_by_inode(
+    directory: PathBuf,
+    inode: u32,
+    identity: Option<&FileIdentity>,
+) -> Option<PathBuf> {
+    InodeIndex::from_directory(&directory).get(&directory, inode, identity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{os::unix::fs::MetadataExt, thread::sleep, time::Duration};
+
+    #[test]
+    fn test_get_referenced_file_with_reused_inode() {
+        let directory = PathBuf::from("/tmp/cls-get-file-by-inode");
+        let input_directory = directory.join("input");
+
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&input_directory).unwrap();
+
+        let original = input_directory.join("original.fasta");
+        std::fs::write(&original, ">original\nACGT\n").unwrap();
+        let inode = std::fs::metadata(&original).unwrap().ino() as u32;
+        let identity = FileIdentity::from_path(&original).unwrap();
+
+        assert_eq!(
+            get_referenced_file_by_inode(
+                input_directory.to_owned(),
+                inode,
+                Some(&identity)
+            ),
+            Some(original.to_owned())
+        );
+
+        //
+        // The file system commonly reuses the inode of a deleted file for the
+        // next created one. The replacement should not be resolved from the
+        // identifier issued to the original file.
+        //
+        std::fs::remove_file(&original).unwrap();
+        sleep(Duration::from_millis(10));
+
+        let replacement = input_directory.join("replacement.fasta");
+        std::fs::write(&replacement, ">replacement\nTTTT\n").unwrap();
+
+        assert_eq!(
+            get_referenced_file_by_inode(
+                input_directory.to_owned(),
+                inode,
+                Some(&identity)
+            ),
+            None
+        );
+
+        let replacement_inode =
+            std::fs::metadata(&replacement).unwrap().ino() as u32;
+
+        assert_eq!(
+            get_file_by_inode(input_directory, replacement_inode),
+            Some(replacement)
+        );
+    }
+
+    #[test]
+    fn test_inode_index_resolves_files_of_the_given_directory() {
+        let directory = PathBuf::from("/tmp/cls-inode-index");
+
+        let _ = std::fs::remove_dir_all(&directory);
+
+        let files = ["a", "b"]
+            .iter()
+            .map(|name| {
+                let input_directory = directory.join(name).join("input");
+                std::fs::create_dir_all(&input_directory).unwrap();
+
+                let file = input_directory.join("query.fasta");
+                std::fs::write(&file, format!(">{name}\nACGT\n")).unwrap();
+
+                file
+            })
+            .collect::<Vec<PathBuf>>();
+
+        let index = InodeIndex::from_directory(&directory);
+
+        for file in files.iter() {
+            let inode = std::fs::metadata(file).unwrap().ino() as u32;
+            let input_directory = file.parent().unwrap();
+
+            assert_eq!(
+                index.get(input_directory, inode, None),
+                Some(file.to_owned())
+            );
+        }
+
+        //
+        // Files are not resolved from other directories
+        //
+        let inode = std::fs::metadata(&files[0]).unwrap().ino() as u32;
+
+        assert_eq!(index.get(files[1].parent().unwrap(), inode, None), None);
+    }
 }
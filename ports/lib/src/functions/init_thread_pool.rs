@@ -0,0 +1,50 @@
+use std::{num::NonZeroUsize, thread::available_parallelism};
+use tracing::{info, warn};
+
+/// Initialize the global thread pool
+///
+/// The requested number of threads is clamped to the available CPUs. If the
+/// global pool was already built (e.g. by a previous command in the same
+/// process), the existing pool is kept. Returns the effective number of
+/// threads.
+pub fn init_thread_pool(threads: usize) -> usize {
+    let available = available_parallelism().map(NonZeroUsize::get).unwrap_or(1);
+    let requested = threads.clamp(1, available);
+
+    if requested != threads {
+        warn!(
+            "Requested {threads} threads but only {available} CPUs are \
+            available. Using {requested} threads."
+        );
+    }
+
+    if let Err(err) = rayon::ThreadPoolBuilder::new()
+        .num_threads(requested)
+        .build_global()
+    {
+        warn!("Global thread pool not rebuilt: {err}");
+    };
+
+    let effective = rayon::current_num_threads();
+
+    info!("Running with {effective} threads");
+
+    effective
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_thread_pool_twice() {
+        let available =
+            available_parallelism().map(NonZeroUsize::get).unwrap_or(1);
+
+        let first = init_thread_pool(usize::MAX);
+        let second = init_thread_pool(1);
+
+        assert!(first <= available);
+        assert_eq!(first, second);
+    }
+}
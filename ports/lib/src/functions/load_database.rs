@@ -1,12 +1,106 @@
+use crate::{ChecksumReader, DatabaseHeader};
+
 use anyhow::{Error, Result};
 use classeq_core::domain::dtos::tree::Tree;
 use std::{
     fs::{read_to_string, File},
-    path::PathBuf,
+    io::{copy, sink, BufReader},
+    path::{Path, PathBuf},
 };
+use tracing::warn;
 use zstd::Decoder;
 
+/// Check if a database fits the memory limit before loading it
+///
+/// The limit is the smallest of `max_memory_bytes` and the memory available
+/// in the system. Databases without the estimated size in the header (e.g.
+/// built with previous versions) are not checked.
+pub fn check_database_memory(path: &Path, max_memory_bytes: u64) -> Result<()> {
+    let header =
+        DatabaseHeader::from_reader(&mut BufReader::new(File::open(path)?))?;
+
+    let estimated = match header.and_then(|i| i.estimated_memory_bytes) {
+        Some(estimated) => estimated,
+        None => {
+            warn!(
+                "The database {path:?} does not contain the estimated memory \
+                size. The memory limit is not checked."
+            );
+
+            return Ok(());
+        }
+    };
+
+    let limit = match get_available_memory_bytes() {
+        Some(available) => available.min(max_memory_bytes),
+        None => max_memory_bytes,
+    };
+
+    if estimated > limit {
+        return Err(Error::msg(format!(
+            "The database requires about {estimated} bytes of memory, which \
+            exceeds the limit of {limit} bytes"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Get the memory available in the system
+///
+/// Only available on Linux systems, from the `MemAvailable` entry of the
+/// memory information.
+fn get_available_memory_bytes() -> Option<u64> {
+    std::fs::read_to_string("/proc/meminfo")
+        .ok()?
+        .lines()
+        .find(|line| line.starts_with("MemAvailable:"))?
+        .split_whitespace()
+        .nth(1)?
+        .parse::<u64>()
+        .ok()
+        .map(|kb| kb * 1024)
+}
+
 pub fn load_database(path: PathBuf) -> Result<Tree> {
+    //
+    // Read from binary file with header
+    //
+    // The content checksum is verified before the parsed content is returned,
+    // then truncated or corrupted files result in a precise error.
+    //
+    let read_from_checked_zstd = |path: PathBuf| -> Result<Option<Tree>> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let header = match DatabaseHeader::from_reader(&mut reader)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+
+        //
+        // Bytes are hashed as decoded. Remaining bytes not consumed by the
+        // decoder are also hashed, then the checksum covers the whole content
+        // even if the decoding fails.
+        //
+        let mut reader = ChecksumReader::new(reader);
+
+        let tree = Decoder::new(&mut reader).map_err(Error::from).and_then(
+            |decoder| {
+                serde_yaml::from_reader::<_, Tree>(decoder).map_err(Error::from)
+            },
+        );
+
+        copy(&mut reader, &mut sink())?;
+
+        if reader.checksum() != header.checksum {
+            return Err(Error::msg(
+                "Database corrupt or truncated (checksum mismatch)",
+            ));
+        }
+
+        tree.map(Some)
+    };
+
     //
     // Read from yaml file
     //
@@ -34,6 +128,10 @@ pub fn load_database(path: PathBuf) -> Result<Tree> {
     // Load the database content
     //
     let tree_caller = |path: PathBuf| -> Result<Tree> {
+        if let Some(tree) = read_from_checked_zstd(path.to_owned())? {
+            return Ok(tree);
+        }
+
         let bin_err = match read_from_zstd(path.to_owned()) {
             Ok(tree) => return Ok(tree),
             Err(err) => err,
@@ -51,3 +149,61 @@ pub fn load_database(path: PathBuf) -> Result<Tree> {
 
     tree_caller(path)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{write_database, DATABASE_MAGIC};
+
+    #[test]
+    fn test_load_database_with_corrupted_content() {
+        let path = PathBuf::from("/tmp/cls-load-database-checksum.cls");
+
+        let tree = load_database(PathBuf::from(
+            "../../tests/models/bsub-gyrb-k35.cls",
+        ))
+        .unwrap();
+
+        write_database(&tree, &path).unwrap();
+
+        assert_eq!(load_database(path.to_owned()).unwrap().id, tree.id);
+
+        let mut content = std::fs::read(&path).unwrap();
+        let index = content.len() / 2;
+        content[index] = content[index].wrapping_add(1);
+        std::fs::write(&path, content).unwrap();
+
+        let err = load_database(path).unwrap_err();
+
+        assert!(err.to_string().contains("checksum mismatch"));
+    }
+
+    #[test]
+    fn test_load_database_with_oversized_header_length() {
+        let path = PathBuf::from("/tmp/cls-load-database-header-length.cls");
+
+        let mut content = DATABASE_MAGIC.to_vec();
+        content.extend(u32::MAX.to_le_bytes());
+        std::fs::write(&path, content).unwrap();
+
+        let err = load_database(path).unwrap_err();
+
+        assert!(err.to_string().contains("header length"));
+    }
+
+    #[test]
+    fn test_check_database_memory_with_tiny_limit() {
+        let path = PathBuf::from("/tmp/cls-load-database-memory.cls");
+
+        let tree = load_database(PathBuf::from(
+            "../../tests/models/bsub-gyrb-k35.cls",
+        ))
+        .unwrap();
+
+        write_database(&tree, &path).unwrap();
+
+        let err = check_database_memory(&path, 1024).unwrap_err();
+
+        assert!(err.to_string().contains("exceeds the limit of"));
+    }
+}
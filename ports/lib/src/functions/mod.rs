@@ -1,7 +1,11 @@
 mod export_runtime_arguments;
 mod get_file_by_inode;
+mod init_thread_pool;
 mod load_database;
+mod write_database;
 
 pub use export_runtime_arguments::*;
 pub use get_file_by_inode::*;
+pub use init_thread_pool::*;
 pub use load_database::*;
+pub use write_database::*;
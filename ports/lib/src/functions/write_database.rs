@@ -0,0 +1,23 @@
+use crate::DatabaseHeader;
+
+use anyhow::Result;
+use classeq_core::domain::dtos::tree::Tree;
+use std::{fs::File, io::Write, path::Path};
+
+/// Write a database in the binary format
+///
+/// The tree is serialized and compressed, then persisted after a header
+/// containing the database parameters and the content checksum.
+pub fn write_database(tree: &Tree, path: &Path) -> Result<()> {
+    let mut encoder = zstd::Encoder::new(Vec::new(), 0)?;
+    serde_yaml::to_writer(&mut encoder, tree)?;
+    let content = encoder.finish()?;
+
+    let header = DatabaseHeader::new(tree, &content);
+
+    let mut writer = File::create(path)?;
+    writer.write_all(&header.to_bytes()?)?;
+    writer.write_all(&content)?;
+
+    Ok(())
+}
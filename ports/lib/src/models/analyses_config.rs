@@ -1,15 +1,18 @@
+use super::file_identity::FileIdentity;
+
 use anyhow::Result;
 use apalis::prelude::*;
 use classeq_core::domain::dtos::output_format::OutputFormat;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 impl Message for PlacementConfig {
     const NAME: &'static str = "watcher:blu-analysis";
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PlacementConfig {
     pub name: String,
@@ -21,6 +24,13 @@ pub struct PlacementConfig {
 
     pub query_file_id: u32,
 
+    /// The identity of the query file when the analysis was configured
+    ///
+    /// Recorded by the API, then query files inheriting the inode after the
+    /// configuration are not placed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_file_identity: Option<FileIdentity>,
+
     pub model_id: Uuid,
 
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -29,6 +39,7 @@ pub struct PlacementConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub remove_intersection: Option<bool>,
 
+    #[schema(value_type = String, example = "jsonl")]
     pub output_format: OutputFormat,
 
     pub work_dir: String,
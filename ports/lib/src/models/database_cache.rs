@@ -0,0 +1,162 @@
+use super::models_config::ModelConfig;
+use crate::load_database;
+
+use anyhow::{Error, Result};
+use classeq_core::domain::dtos::{annotation::Annotation, tree::Tree};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::SystemTime,
+};
+use tracing::warn;
+use uuid::Uuid;
+
+struct CachedDatabase {
+    id: Uuid,
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    tree: Arc<Tree>,
+}
+
+/// A least recently used cache of loaded databases
+///
+/// Databases are keyed by the model ID and shared as `Arc<Tree>`, then a
+/// database loaded once is reused across analyses. Cached entries are
+/// invalidated when the model file modification time changes.
+pub struct DatabaseCache {
+    max_count: usize,
+    entries: Mutex<Vec<CachedDatabase>>,
+    loads: AtomicUsize,
+}
+
+impl DatabaseCache {
+    /// Create a new cache holding at most `max_count` databases
+    pub fn new(max_count: usize) -> Self {
+        DatabaseCache {
+            max_count: max_count.max(1),
+            entries: Mutex::new(Vec::new()),
+            loads: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get the number of databases loaded from disk since the cache creation
+    pub fn loads_count(&self) -> usize {
+        self.loads.load(Ordering::SeqCst)
+    }
+
+    /// Get a database from cache or load it from disk
+    ///
+    /// Annotations of the model, if available, are loaded together with the
+    /// database.
+    pub fn get_or_load(&self, model: &ModelConfig) -> Result<Arc<Tree>> {
+        let path = model.model_path();
+
+        let modified = path
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        let mut entries = match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(err) => {
+                return Err(Error::msg(format!(
+                    "Failed to lock the database cache: {err}"
+                )))
+            }
+        };
+
+        //
+        // Entries are kept in the usage order. The most recently used entry is
+        // the last one.
+        //
+        if let Some(index) =
+            entries.iter().position(|entry| entry.id == model.id)
+        {
+            let entry = entries.remove(index);
+
+            if entry.path == path && entry.modified == modified {
+                let tree = entry.tree.to_owned();
+                entries.push(entry);
+                return Ok(tree);
+            }
+        }
+
+        let tree = Arc::new(Self::load(model)?);
+        self.loads.fetch_add(1, Ordering::SeqCst);
+
+        if entries.len() >= self.max_count {
+            entries.remove(0);
+        }
+
+        entries.push(CachedDatabase {
+            id: model.id,
+            path,
+            modified,
+            tree: tree.to_owned(),
+        });
+
+        Ok(tree)
+    }
+
+    fn load(model: &ModelConfig) -> Result<Tree> {
+        let mut tree = load_database(model.model_path())?;
+
+        if let Some(path) = model.annotations_path() {
+            if let Ok(file) = std::fs::File::open(path) {
+                match serde_yaml::from_reader::<_, Vec<Annotation>>(file) {
+                    Ok(annotations) => tree.annotations = Some(annotations),
+                    Err(err) => {
+                        warn!("Failed to parse the annotations file: {err}")
+                    }
+                }
+            }
+        }
+
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ModelsConfig;
+
+    #[test]
+    fn test_database_cache_invalidation() {
+        let model_path = PathBuf::from("/tmp/cls-database-cache.cls");
+
+        std::fs::copy("../../tests/models/bsub-gyrb-k35.cls", &model_path)
+            .unwrap();
+
+        let models: ModelsConfig = serde_yaml::from_str(&format!(
+            "- id: ce47d8bc-2885-3d2c-8247-5b8c8b28fefe\n  \
+            name: Bacillus subtilis group\n  \
+            modelPath: {path}\n",
+            path = model_path.to_str().unwrap()
+        ))
+        .unwrap();
+
+        let model = models.get_models().first().unwrap().to_owned();
+        let cache = DatabaseCache::new(1);
+
+        let first = cache.get_or_load(&model).unwrap();
+        let second = cache.get_or_load(&model).unwrap();
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(cache.loads_count(), 1);
+
+        std::fs::File::options()
+            .write(true)
+            .open(&model_path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH)
+            .unwrap();
+
+        cache.get_or_load(&model).unwrap();
+
+        assert_eq!(cache.loads_count(), 2);
+    }
+}
@@ -0,0 +1,180 @@
+use anyhow::{Error, Result};
+use classeq_core::domain::dtos::{clade::Clade, tree::Tree};
+use mur3::{murmurhash3_x64_128, Hasher128};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, hash::Hasher, io::Read, mem::size_of};
+
+/// The current version of the binary database format
+pub const DATABASE_FORMAT_VERSION: u32 = 1;
+
+/// The magic bytes prefixing binary databases containing a header
+pub const DATABASE_MAGIC: &[u8; 6] = b"CLSDB\0";
+
+/// The maximum length (in bytes) accepted for the serialized header
+///
+/// Headers contain a few scalar fields only, then larger lengths indicate a
+/// corrupted file and are rejected before the header buffer allocation.
+pub const MAX_DATABASE_HEADER_BYTES: usize = 64 * 1024;
+
+/// The header of a binary classeq database
+///
+/// The header is persisted before the compressed database content and allows
+/// the database integrity to be verified before the content parsing. It also
+/// allows inspecting the database parameters without a full parse.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseHeader {
+    pub version: u32,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k_size: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub m_size: Option<u64>,
+
+    pub leaf_count: usize,
+
+    /// The murmur3 hash (hex encoded) of the compressed database content
+    pub checksum: String,
+
+    /// The estimated size (in bytes) of the database once loaded in memory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_memory_bytes: Option<u64>,
+}
+
+impl DatabaseHeader {
+    pub fn new(tree: &Tree, content: &[u8]) -> Self {
+        DatabaseHeader {
+            version: DATABASE_FORMAT_VERSION,
+            k_size: tree.kmers_map.as_ref().map(|map| map.get_kmer_size()),
+            m_size: tree.kmers_map.as_ref().map(|map| map.get_minimizer_size()),
+            leaf_count: tree.root.count_leaves(),
+            checksum: Self::checksum(content),
+            estimated_memory_bytes: Some(Self::estimate_memory_bytes(tree)),
+        }
+    }
+
+    /// Estimate the memory footprint of a loaded database
+    ///
+    /// Hash table entries are counted with one control byte and the spare
+    /// capacity of the table. Allocator overheads are ignored, then the
+    /// estimate should be read as a lower bound.
+    pub fn estimate_memory_bytes(tree: &Tree) -> u64 {
+        let table_entry = |size: usize| size + size / 8 + 1;
+
+        let clades = tree.root.iter().count() * size_of::<Clade>();
+
+        let kmers = match &tree.kmers_map {
+            None => 0,
+            Some(kmers_map) => {
+                let kmer_string = match kmers_map.retains_kmer_strings() {
+                    true => {
+                        table_entry(size_of::<(u64, String)>())
+                            + kmers_map.get_kmer_size() as usize
+                    }
+                    false => 0,
+                };
+
+                kmers_map
+                    .get_map()
+                    .values()
+                    .flat_map(|bucket| bucket.0.values())
+                    .map(|nodes| {
+                        table_entry(size_of::<(u64, HashSet<u64>)>())
+                            + table_entry(size_of::<u64>()) * nodes.len()
+                            + kmer_string
+                    })
+                    .sum()
+            }
+        };
+
+        (clades + kmers) as u64
+    }
+
+    /// Calculate the checksum of the compressed database content
+    pub fn checksum(content: &[u8]) -> String {
+        let (high, low) = murmurhash3_x64_128(content, 0);
+        format!("{high:016x}{low:016x}")
+    }
+
+    /// Serialize the header including the magic bytes and the header length
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let header = serde_yaml::to_string(self)?.into_bytes();
+
+        let mut bytes = DATABASE_MAGIC.to_vec();
+        bytes.extend((header.len() as u32).to_le_bytes());
+        bytes.extend(header);
+
+        Ok(bytes)
+    }
+
+    /// Read the header from the beginning of a database file
+    ///
+    /// Returns `None` if the file does not start with the magic bytes, as
+    /// expected for databases built before the header introduction.
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Option<Self>> {
+        let mut magic = [0u8; 6];
+
+        if reader.read_exact(&mut magic).is_err() || &magic != DATABASE_MAGIC {
+            return Ok(None);
+        }
+
+        let mut length = [0u8; 4];
+        reader.read_exact(&mut length).map_err(|_| {
+            Error::msg("Database corrupt or truncated (invalid header)")
+        })?;
+
+        let length = u32::from_le_bytes(length) as usize;
+
+        if length > MAX_DATABASE_HEADER_BYTES {
+            return Err(Error::msg(format!(
+                "Database corrupt or truncated (header length {length} \
+                exceeds {MAX_DATABASE_HEADER_BYTES} bytes)"
+            )));
+        }
+
+        let mut header = vec![0u8; length];
+        reader.read_exact(&mut header).map_err(|_| {
+            Error::msg("Database corrupt or truncated (invalid header)")
+        })?;
+
+        match serde_yaml::from_slice::<DatabaseHeader>(&header) {
+            Ok(header) => Ok(Some(header)),
+            Err(err) => Err(Error::msg(format!(
+                "Database corrupt or truncated (invalid header): {err}"
+            ))),
+        }
+    }
+}
+
+/// A reader calculating the database checksum of the bytes read through it
+///
+/// Allows the checksum to be verified while the content is decoded, without
+/// buffering the whole compressed content in memory.
+pub struct ChecksumReader<R: Read> {
+    inner: R,
+    hasher: Hasher128,
+}
+
+impl<R: Read> ChecksumReader<R> {
+    pub fn new(inner: R) -> Self {
+        ChecksumReader {
+            inner,
+            hasher: Hasher128::with_seed(0),
+        }
+    }
+
+    /// The checksum of the bytes read so far, as in `DatabaseHeader::checksum`
+    pub fn checksum(&self) -> String {
+        let (high, low) = self.hasher.finish128();
+        format!("{high:016x}{low:016x}")
+    }
+}
+
+impl<R: Read> Read for ChecksumReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.hasher.write(&buf[..count]);
+        Ok(count)
+    }
+}
@@ -0,0 +1,50 @@
+use classeq_core::domain::dtos::tree::Tree;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Summary statistics of a classeq database
+///
+/// Statistics are calculated from the loaded database, then clients can
+/// inspect a model without downloading the full database file.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseStats {
+    pub id: Uuid,
+    pub name: String,
+    pub min_branch_support: f64,
+    pub leaf_count: usize,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations_count: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kmer_size: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimizer_size: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kmer_count: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minimizer_count: Option<usize>,
+}
+
+impl DatabaseStats {
+    pub fn from_tree(tree: &Tree) -> Self {
+        let kmers_map = tree.kmers_map.as_ref();
+
+        DatabaseStats {
+            id: tree.id,
+            name: tree.name.to_owned(),
+            min_branch_support: tree.min_branch_support,
+            leaf_count: tree.root.count_leaves(),
+            annotations_count: tree.annotations.as_ref().map(|i| i.len()),
+            kmer_size: kmers_map.map(|map| map.get_kmer_size()),
+            minimizer_size: kmers_map.map(|map| map.get_minimizer_size()),
+            kmer_count: kmers_map.map(|map| map.total_kmers()),
+            minimizer_count: kmers_map.map(|map| map.minimizer_count()),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::Metadata, os::unix::fs::MetadataExt, path::Path, time::UNIX_EPOCH,
+};
+use utoipa::ToSchema;
+
+/// The identity of a file referenced by its inode
+///
+/// Inodes are reused by the file system after the file deletion, then the
+/// inode alone does not identify a file. The size and the modification time
+/// recorded when the identifier was issued allow a file that inherited the
+/// inode to be told apart from the referenced one.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileIdentity {
+    pub inode: u64,
+
+    pub size: u64,
+
+    /// The modification time in nanoseconds since the Unix epoch
+    pub modified_nanos: u64,
+}
+
+impl FileIdentity {
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        FileIdentity {
+            inode: metadata.ino(),
+            size: metadata.size(),
+            modified_nanos: metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_nanos() as u64)
+                .unwrap_or(0),
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.metadata()
+            .ok()
+            .map(|metadata| Self::from_metadata(&metadata))
+    }
+}
@@ -38,4 +38,10 @@ pub struct FileSystemConfig {
 
     /// The name of the lock file generated to store logging information.
     pub logging_file_name: String,
+
+    /// The maximum size (in bytes) of files uploaded by users
+    ///
+    /// If not provided, uploads are not limited.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_upload_bytes: Option<u64>,
 }
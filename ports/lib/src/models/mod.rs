@@ -1,11 +1,21 @@
 mod analyses_config;
 mod cli_launcher;
+mod database_cache;
+mod database_header;
+mod database_stats;
+mod file_identity;
 mod file_system_config;
 mod log_format;
 mod models_config;
+mod placement_cache;
 
 pub use analyses_config::*;
 pub use cli_launcher::*;
+pub use database_cache::*;
+pub use database_header::*;
+pub use database_stats::*;
+pub use file_identity::*;
 pub use file_system_config::*;
 pub use log_format::*;
 pub use models_config::*;
+pub use placement_cache::*;
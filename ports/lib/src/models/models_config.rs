@@ -1,8 +1,13 @@
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ModelConfig {
     /// The model ID
@@ -38,6 +43,22 @@ pub struct ModelConfig {
 }
 
 impl ModelConfig {
+    pub fn new(
+        id: Uuid,
+        name: String,
+        gene: Option<String>,
+        model_path: PathBuf,
+        annotations_path: Option<PathBuf>,
+    ) -> Self {
+        ModelConfig {
+            id,
+            name,
+            gene,
+            model_path,
+            annotations_path,
+        }
+    }
+
     pub fn model_path(&self) -> PathBuf {
         self.model_path.clone()
     }
@@ -56,3 +77,90 @@ impl ModelsConfig {
         self.0.clone()
     }
 }
+
+/// A registry entry, including the paths skipped in API responses
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RegistryEntry<'a> {
+    id: &'a Uuid,
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gene: Option<&'a str>,
+    model_path: &'a Path,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    annotations_path: Option<&'a Path>,
+}
+
+impl ModelsConfig {
+    /// Load models registered at runtime
+    ///
+    /// Missing registry files are handled as empty registries.
+    pub fn load_registry(path: &Path) -> Result<ModelsConfig> {
+        if !path.exists() {
+            return Ok(ModelsConfig(vec![]));
+        }
+
+        Ok(serde_yaml::from_reader(File::open(path)?)?)
+    }
+
+    /// Write the models registry, including the model paths
+    ///
+    /// Content is written to a temporary file and then renamed, avoiding to
+    /// leave a truncated registry on failures.
+    pub fn write_registry(&self, path: &Path) -> Result<()> {
+        let entries = self
+            .0
+            .iter()
+            .map(|model| RegistryEntry {
+                id: &model.id,
+                name: &model.name,
+                gene: model.gene.as_deref(),
+                model_path: &model.model_path,
+                annotations_path: model.annotations_path.as_deref(),
+            })
+            .collect::<Vec<_>>();
+
+        let temp_path = path.with_extension("tmp");
+        serde_yaml::to_writer(File::create(&temp_path)?, &entries)?;
+        std::fs::rename(temp_path, path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_models_registry_round_trip() {
+        let registry_path = PathBuf::from("/tmp/cls-lib-models-registry.yaml");
+        let _ = std::fs::remove_file(&registry_path);
+
+        assert!(ModelsConfig::load_registry(&registry_path)
+            .unwrap()
+            .0
+            .is_empty());
+
+        let model = ModelConfig::new(
+            Uuid::new_v4(),
+            "Bacillus subtilis group".to_string(),
+            Some("gyrB".to_string()),
+            PathBuf::from("/tmp/bsub-gyrb-k35.cls"),
+            None,
+        );
+
+        ModelsConfig(vec![model.to_owned()])
+            .write_registry(&registry_path)
+            .unwrap();
+
+        let models = ModelsConfig::load_registry(&registry_path)
+            .unwrap()
+            .get_models();
+
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].id, model.id);
+        assert_eq!(models[0].gene, model.gene);
+        assert_eq!(models[0].model_path(), model.model_path());
+    }
+}
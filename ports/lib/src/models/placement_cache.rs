@@ -0,0 +1,169 @@
+use super::{database_header::DatabaseHeader, models_config::ModelConfig};
+
+use anyhow::Result;
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::UNIX_EPOCH,
+};
+
+/// An on-disk cache of serialized placements
+///
+/// Entries are keyed by the database ID and the hash of the query header and
+/// sequence, since the header is part of the placement response. Each entry
+/// is stamped with the database file modification time, then entries of
+/// changed databases are ignored and overwritten on the next placement.
+///
+/// Placement options are not part of the key, then a cache directory should
+/// be used with a single set of options.
+pub struct PlacementCache {
+    directory: PathBuf,
+    hits: AtomicUsize,
+}
+
+impl PlacementCache {
+    /// Create a new cache storing entries in `directory`
+    pub fn new(directory: PathBuf) -> Self {
+        PlacementCache {
+            directory,
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get the number of placements served from cache since the creation
+    pub fn hits_count(&self) -> usize {
+        self.hits.load(Ordering::SeqCst)
+    }
+
+    /// Get the cached placement of a query, if any
+    pub fn get(
+        &self,
+        model: &ModelConfig,
+        header: &str,
+        sequence: &str,
+    ) -> Option<String> {
+        let stamp = Self::database_stamp(model)?;
+
+        let content =
+            std::fs::read_to_string(self.entry_path(model, header, sequence))
+                .ok()?;
+
+        match content.split_once('\n') {
+            Some((entry_stamp, placement)) if entry_stamp == stamp => {
+                self.hits.fetch_add(1, Ordering::SeqCst);
+                Some(placement.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Store the serialized placement of a query
+    ///
+    /// Placements are not cached if the database modification time is not
+    /// available.
+    pub fn insert(
+        &self,
+        model: &ModelConfig,
+        header: &str,
+        sequence: &str,
+        placement: &str,
+    ) -> Result<()> {
+        let stamp = match Self::database_stamp(model) {
+            Some(stamp) => stamp,
+            None => return Ok(()),
+        };
+
+        let entry_path = self.entry_path(model, header, sequence);
+
+        if let Some(parent) = entry_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        //
+        // Entries are renamed after written, then concurrent readers never see
+        // partial entries
+        //
+        let temp_path = entry_path.with_extension("tmp");
+        std::fs::write(&temp_path, format!("{stamp}\n{placement}"))?;
+        std::fs::rename(temp_path, entry_path)?;
+
+        Ok(())
+    }
+
+    fn entry_path(
+        &self,
+        model: &ModelConfig,
+        header: &str,
+        sequence: &str,
+    ) -> PathBuf {
+        let hash = DatabaseHeader::checksum(
+            format!("{header}\n{sequence}").as_bytes(),
+        );
+
+        self.directory
+            .join(model.id.to_string())
+            .join(format!("{hash}.json"))
+    }
+
+    fn database_stamp(model: &ModelConfig) -> Option<String> {
+        let modified = model
+            .model_path()
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()?;
+
+        let elapsed = modified.duration_since(UNIX_EPOCH).ok()?;
+
+        Some(format!(
+            "{}.{:09}",
+            elapsed.as_secs(),
+            elapsed.subsec_nanos()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ModelsConfig;
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_placement_cache_invalidation() {
+        let model_path = PathBuf::from("/tmp/cls-placement-cache.cls");
+        let directory = PathBuf::from("/tmp/cls-placement-cache");
+
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::copy("../../tests/models/bsub-gyrb-k35.cls", &model_path)
+            .unwrap();
+
+        let models: ModelsConfig = serde_yaml::from_str(&format!(
+            "- id: ce47d8bc-2885-3d2c-8247-5b8c8b28fefe\n  \
+            name: Bacillus subtilis group\n  \
+            modelPath: {path}\n",
+            path = model_path.to_str().unwrap()
+        ))
+        .unwrap();
+
+        let model = models.get_models().first().unwrap().to_owned();
+        let cache = PlacementCache::new(directory);
+
+        assert!(cache.get(&model, "query", "ACGT").is_none());
+
+        cache.insert(&model, "query", "ACGT", "{}").unwrap();
+
+        assert_eq!(cache.get(&model, "query", "ACGT"), Some("{}".to_string()));
+        assert!(cache.get(&model, "other", "ACGT").is_none());
+        assert_eq!(cache.hits_count(), 1);
+
+        std::fs::File::options()
+            .write(true)
+            .open(&model_path)
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(60))
+            .unwrap();
+
+        assert!(cache.get(&model, "query", "ACGT").is_none());
+        assert_eq!(cache.hits_count(), 1);
+    }
+}
@@ -1,14 +1,15 @@
-mod context;
-
 use crate::{
     dtos::{
-        log_writer::VectorWriter, placement_result::PlacementResult,
+        graceful_shutdown::GracefulShutdown, log_writer::VectorWriter,
+        model_limiter::ModelLimiter, placement_result::PlacementResult,
+        queue_status::QueueStatus, scan_delay::ScanDelay,
         telemetry_code::TelemetryCode,
     },
     models::{
-        config_file::ConfigFile,
+        config_file::{ConfigFile, WatchConfig},
         execution_msg::ExecutionMsg,
         reminder::{Reminder, ReminderSpan},
+        running_owner::RunningOwner,
     },
 };
 
@@ -27,18 +28,29 @@ use apalis_core::{
     monitor::Monitor,
 };
 use async_std::task::sleep;
-use clap::Parser;
+use clap::{ArgAction, Parser};
 use classeq_core::{
-    domain::dtos::{annotation::Annotation, file_or_stdin::FileOrStdin},
+    domain::dtos::{
+        file_or_stdin::FileOrStdin,
+        placement_config::PlacementConfig as RunPlacementConfig,
+        placement_options::PlacementOptions,
+    },
     use_cases::place_sequences,
 };
 use classeq_ports_lib::{
-    get_file_by_inode, load_database, FileSystemConfig, ModelsConfig,
-    PlacementConfig,
+    init_thread_pool, DatabaseCache, FileSystemConfig, InodeIndex,
+    ModelsConfig, PlacementConfig,
+};
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
 };
-use context::WorkerCtx;
-use rand::{thread_rng, Rng};
-use std::{path::PathBuf, str::FromStr, time::Duration};
 use tracing::{
     error, info, info_span, subscriber::with_default, trace, warn, Instrument,
     Level,
@@ -53,6 +65,22 @@ pub(crate) struct Arguments {
     /// Configuration file in YAML format.
     #[arg(short, long)]
     pub(super) config_file: PathBuf,
+
+    /// Seed of the scan delay
+    ///
+    /// If provided, the random delay before each scan is generated from this
+    /// seed, overriding the `seed` configuration of the watcher section.
+    #[arg(long)]
+    pub(super) seed: Option<u64>,
+
+    /// Dry-run mode
+    ///
+    /// If set, pending analyses are logged with the model they would use, but
+    /// placements are not run and no success, running or error files are
+    /// written. Useful to validate a configuration against a populated serve
+    /// directory. Overrides the `dryRun` configuration of the watcher section.
+    #[arg(long, action=ArgAction::SetTrue)]
+    pub(super) dry_run: bool,
 }
 
 pub(crate) async fn start_watch_directory_cmd(args: Arguments) -> Result<()> {
@@ -76,12 +104,18 @@ pub(crate) async fn start_watch_directory_cmd(args: Arguments) -> Result<()> {
     // ? Create a thread pool configured globally
     // ? -----------------------------------------------------------------------
 
-    if let Err(err) = rayon::ThreadPoolBuilder::new()
-        .num_threads(config.watcher.max_threads.to_owned() as usize)
-        .build_global()
-    {
-        error!("Error creating thread pool: {err}");
-    };
+    init_thread_pool(config.watcher.max_threads.to_owned() as usize);
+
+    // ? -----------------------------------------------------------------------
+    // ? Reclaim analyses interrupted by previous runs
+    // ? -----------------------------------------------------------------------
+
+    reconcile_orphaned_analyses(
+        &config.fs,
+        Duration::from_secs(
+            config.watcher.orphaned_running_secs.unwrap_or(3600),
+        ),
+    );
 
     // ? -----------------------------------------------------------------------
     // ? Setup the dir-watcher worker
@@ -97,6 +131,38 @@ pub(crate) async fn start_watch_directory_cmd(args: Arguments) -> Result<()> {
         }
     };
 
+    let scan_delay = ScanDelay::new(
+        config.watcher.interval,
+        config.watcher.jitter.unwrap_or(true),
+        config.watcher.fixed_delay,
+        args.seed.or(config.watcher.seed),
+    );
+
+    let mut watch_config = config.watcher.to_owned();
+
+    if args.dry_run {
+        watch_config.dry_run = Some(true);
+    }
+
+    if watch_config.dry_run.unwrap_or(false) {
+        warn!("Dry-run mode enabled. Pending analyses will not be processed");
+    }
+
+    let database_cache = Arc::new(DatabaseCache::new(
+        config.watcher.max_cached_models.unwrap_or(1) as usize,
+    ));
+
+    let model_limiter = Arc::new(ModelLimiter::new(
+        config
+            .watcher
+            .max_concurrent_analyses_per_model
+            .map(|limit| limit as usize),
+    ));
+
+    let shutdown = Arc::new(GracefulShutdown::default());
+    let shutdown_timeout =
+        Duration::from_secs(config.watcher.shutdown_timeout_secs.unwrap_or(60));
+
     let worker = WorkerBuilder::new(config.watcher.worker_name)
         .layer(RetryLayer::new(RetryPolicy::retries(
             config.watcher.retries as usize,
@@ -104,7 +170,11 @@ pub(crate) async fn start_watch_directory_cmd(args: Arguments) -> Result<()> {
         .layer(TraceLayer::new().make_span_with(ReminderSpan::new()))
         .data(config.fs)
         .data(config.models)
-        .data(config.watcher.interval as i32)
+        .data(scan_delay)
+        .data(watch_config)
+        .data(database_cache)
+        .data(model_limiter)
+        .data(shutdown.to_owned())
         .stream(CronStream::new(schedule).into_stream())
         .build_fn(scan_dispatcher);
 
@@ -115,9 +185,30 @@ pub(crate) async fn start_watch_directory_cmd(args: Arguments) -> Result<()> {
     Monitor::<AsyncStdExecutor>::new()
         .register_with_count(config.watcher.workers as usize, worker)
         .on_event(|e| trace!("Worker event: {e:?}"))
-        .run_with_signal(async {
+        .run_with_signal(async move {
             ctrl_c.recv().await.ok();
-            info!("Shutting down");
+
+            //
+            // Running analyses are finished before the monitor stops, then
+            // their directories are not left with a running file only.
+            //
+            info!(
+                code = TelemetryCode::WTHPLACE0011.to_string(),
+                "Shutting down. Waiting for the running analyses"
+            );
+
+            let remaining = async_std::task::spawn_blocking(move || {
+                shutdown.shutdown(shutdown_timeout)
+            })
+            .await;
+
+            if remaining > 0 {
+                warn!(
+                    code = TelemetryCode::WTHPLACE0011.to_string(),
+                    "Shutdown timeout reached with {remaining} analyses running"
+                );
+            }
+
             Ok(())
         })
         .await?;
@@ -127,36 +218,127 @@ pub(crate) async fn start_watch_directory_cmd(args: Arguments) -> Result<()> {
 
 /// Dispatches the scan task to the worker
 ///
-/// This function dispatches the scan task to the worker, allowing the scan to
-/// be executed in the background.
+/// The scan runs before the dispatch finishes. Analyses failing with
+/// retriable errors are released to be claimed again, then the dispatch fails
+/// and the worker retry layer dispatches a new scan, up to the configured
+/// number of retries.
 ///
 async fn scan_dispatcher(
     _: Reminder,
-    worker: WorkerCtx,
     fs_data: Data<FileSystemConfig>,
     models_data: Data<ModelsConfig>,
-    interval: Data<i32>,
-) -> bool {
-    let max_delay = interval.to_owned().abs();
-    let rand_delay = thread_rng().gen_range(1..=max_delay);
-    sleep(Duration::from_secs(rand_delay as u64)).await;
-
-    worker.spawn(
-        scan_directories_in_background(fs_data, models_data).in_current_span(),
-    );
+    scan_delay: Data<ScanDelay>,
+    watch_data: Data<WatchConfig>,
+    database_cache: Data<Arc<DatabaseCache>>,
+    model_limiter: Data<Arc<ModelLimiter>>,
+    shutdown: Data<Arc<GracefulShutdown>>,
+) -> Result<bool> {
+    sleep(scan_delay.next_delay()).await;
+
+    let released = scan_directories_in_background(
+        fs_data,
+        models_data,
+        watch_data,
+        database_cache,
+        model_limiter,
+        shutdown,
+    )
+    .in_current_span()
+    .await;
+
+    if released > 0 {
+        return Err(anyhow::anyhow!(
+            "{released} analyses failed with retriable errors"
+        ));
+    }
 
-    true
+    Ok(true)
 }
 
-/// Scans the directories and dispatches the tasks
+/// Scans the directories and processes the pending analyses
 ///
-/// This function scans the directories and dispatches the tasks to the worker
-/// for processing.
+/// Returns the number of analyses released after retriable failures.
 ///
 async fn scan_directories_in_background(
     fs_config: Data<FileSystemConfig>,
     models_data: Data<ModelsConfig>,
+    watch_config: Data<WatchConfig>,
+    database_cache: Data<Arc<DatabaseCache>>,
+    model_limiter: Data<Arc<ModelLimiter>>,
+    shutdown: Data<Arc<GracefulShutdown>>,
+) -> usize {
+    report_queue_status(
+        &fs_config,
+        watch_config.queue_status_file_name.as_deref(),
+    );
+
+    scan_directories(
+        &fs_config,
+        &models_data,
+        &database_cache,
+        &model_limiter,
+        &shutdown,
+        watch_config.max_concurrent_analyses.unwrap_or(1) as usize,
+        watch_config.dry_run.unwrap_or(false),
+    )
+}
+
+/// Logs the analyses queue status
+///
+/// If a status file name is given, the status is also written to the serve
+/// directory, allowing operators to detect a stuck queue without parsing the
+/// watcher logs.
+///
+fn report_queue_status(
+    fs_config: &FileSystemConfig,
+    status_file_name: Option<&str>,
 ) {
+    let status = QueueStatus::from_serve_directory(fs_config);
+
+    info!(
+        code = TelemetryCode::WTHPLACE0010.to_string(),
+        pending = status.pending,
+        running = status.running,
+        succeeded = status.succeeded,
+        failed = status.failed,
+        oldest_pending_age_secs = status.oldest_pending_age_secs,
+        "Analyses queue status"
+    );
+
+    let status_file_name = match status_file_name {
+        Some(name) => name,
+        None => return,
+    };
+
+    let status_file =
+        PathBuf::from(&fs_config.serve_directory).join(status_file_name);
+
+    match serde_yaml::to_string(&status) {
+        Ok(content) => {
+            if let Err(err) = std::fs::write(&status_file, content) {
+                error!("Failed to write the queue status file: {err}");
+            }
+        }
+        Err(err) => error!("Failed to serialize the queue status: {err}"),
+    }
+}
+
+/// Scans the directories and processes the pending analyses
+///
+/// Pending analyses are processed in parallel by a pool of at most
+/// `concurrency` threads. Each directory is taken from a shared queue, then it
+/// is processed by a single thread. In dry-run mode, pending analyses are only
+/// logged. Directories are not claimed after the shutdown starts.
+///
+fn scan_directories(
+    fs_config: &FileSystemConfig,
+    models_data: &ModelsConfig,
+    database_cache: &DatabaseCache,
+    model_limiter: &ModelLimiter,
+    shutdown: &GracefulShutdown,
+    concurrency: usize,
+    dry_run: bool,
+) -> usize {
     //
     // Scan public directory
     //
@@ -165,8 +347,10 @@ async fn scan_directories_in_background(
     // include the success, running, and error files, indicating pending
     // analysis.
     //
-    for path in PathBuf::from(&fs_config.serve_directory)
-        .join(fs_config.public_directory.to_owned())
+    let public_directory = PathBuf::from(&fs_config.serve_directory)
+        .join(fs_config.public_directory.to_owned());
+
+    let pending = public_directory
         .read_dir()
         .into_iter()
         .flat_map(|entry| entry)
@@ -185,113 +369,389 @@ async fn scan_directories_in_background(
                 None
             }
         })
+        .collect::<Vec<PathBuf>>();
+
+    if pending.is_empty() {
+        return 0;
+    }
+
+    //
+    // Query files are resolved by their inode numbers. The public directory
+    // is walked once per scan, instead of once per analysis.
+    //
+    let inode_index = InodeIndex::from_directory(&public_directory);
+
+    if dry_run {
+        for path in pending {
+            plan_directory(&path, fs_config, models_data, &inode_index);
+        }
+
+        return 0;
+    }
+
+    let pool_size = concurrency.max(1).min(pending.len());
+    let queue = Mutex::new(pending.into_iter());
+    let released = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        for _ in 0..pool_size {
+            scope.spawn(|| loop {
+                let _in_flight = match shutdown.enter() {
+                    Some(guard) => guard,
+                    None => break,
+                };
+
+                let path = match queue.lock() {
+                    Ok(mut queue) => queue.next(),
+                    Err(err) => {
+                        error!("Failed to lock the analyses queue: {err}");
+                        None
+                    }
+                };
+
+                let path = match path {
+                    Some(path) => path,
+                    None => break,
+                };
+
+                if !process_directory(
+                    path,
+                    fs_config,
+                    models_data,
+                    &inode_index,
+                    database_cache,
+                    model_limiter,
+                ) {
+                    released.fetch_add(1, Ordering::SeqCst);
+                }
+            });
+        }
+    });
+
+    released.into_inner()
+}
+
+/// Removes the running files of analyses interrupted by previous runs
+///
+/// Analyses killed before writing the success or error files keep the running
+/// file forever, then they are never scanned again. Running files are removed
+/// if their owner process is gone or their heartbeat is older than `max_age`,
+/// returning their analyses to the pending state. Running files without owner
+/// are removed if modified before `max_age`. Returns the number of reclaimed
+/// analyses.
+///
+fn reconcile_orphaned_analyses(
+    fs_config: &FileSystemConfig,
+    max_age: Duration,
+) -> usize {
+    let now = SystemTime::now();
+
+    PathBuf::from(&fs_config.serve_directory)
+        .join(fs_config.public_directory.to_owned())
+        .read_dir()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            !path.join(fs_config.success_file_name.to_owned()).exists()
+                && !path.join(fs_config.error_file_name.to_owned()).exists()
+        })
+        .map(|path| path.join(fs_config.running_file_name.to_owned()))
+        .filter(|running_file| {
+            if !running_file.exists() {
+                return false;
+            }
+
+            if let Some(owner) = RunningOwner::from_file(running_file) {
+                return owner.is_abandoned(max_age);
+            }
+
+            running_file
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .map(|modified| {
+                    now.duration_since(modified).unwrap_or_default() >= max_age
+                })
+                .unwrap_or(false)
+        })
+        .filter(|running_file| match std::fs::remove_file(running_file) {
+            Ok(_) => {
+                warn!(
+                    code = TelemetryCode::WTHPLACE0011.to_string(),
+                    "Reclaimed the interrupted analysis {running_file:?}"
+                );
+
+                true
+            }
+            Err(err) => {
+                error!(
+                    "Failed to remove the running file {running_file:?}: {err}"
+                );
+                false
+            }
+        })
+        .count()
+}
+
+/// Logs the analysis which would be processed from a directory
+///
+/// The configuration, the model and the query file are resolved as done by
+/// `do_placement`, but nothing is written to the directory.
+///
+fn plan_directory(
+    path: &PathBuf,
+    fs_config: &FileSystemConfig,
+    models_data: &ModelsConfig,
+    inode_index: &InodeIndex,
+) {
+    let code = TelemetryCode::WTHPLACE0009.to_string();
+
+    let cls_config = match PlacementConfig::from_yaml_file(path) {
+        Ok(config_content) => config_content,
+        Err(err) => {
+            warn!(
+                code,
+                "Dry-run: analysis {path:?} would fail with an invalid \
+                configuration: {err}"
+            );
+
+            return;
+        }
+    };
+
+    let model = match models_data
+        .get_models()
         .into_iter()
+        .find(|model| model.id == cls_config.model_id)
     {
-        let span = info_span!(
-            "PlacingSequenceWatcher",
-            run_id = Uuid::new_v4().to_string().replace("-", "")
-        );
+        Some(model) => model,
+        None => {
+            warn!(
+                code,
+                "Dry-run: analysis {path:?} would fail with unknown model \
+                {id}",
+                id = cls_config.model_id
+            );
 
-        let _span_guard = span.enter();
+            return;
+        }
+    };
 
-        info!(
-            code = TelemetryCode::WTHPLACE0001.to_string(),
-            "Processing the directory {path:?}",
-            path = path
-        );
+    let query_file = path.parent().and_then(|parent| {
+        inode_index.get(
+            &parent.join(fs_config.input_directory.to_owned()),
+            cls_config.query_file_id,
+            cls_config.query_file_identity.as_ref(),
+        )
+    });
+
+    match query_file {
+        Some(query_file) => info!(
+            code,
+            "Dry-run: analysis {path:?} would be claimed to place \
+            {query_file:?} with model {id} ({name})",
+            id = model.id,
+            name = model.name
+        ),
+        None => warn!(
+            code,
+            "Dry-run: analysis {path:?} would fail with query file inode \
+            {inode} not found",
+            inode = cls_config.query_file_id
+        ),
+    }
+}
 
-        let writer = VectorWriter::new();
-        let (non_blocking, _guard) =
-            tracing_appender::non_blocking(writer.to_owned());
+/// Processes a single analysis directory
+///
+/// The analysis logs are collected and persisted together with the success or
+/// error files into the analysis directory. Terminal failures are written to
+/// the error file, while analyses failing with retriable errors are released
+/// to be claimed again by a following scan. Returns `false` if the analysis
+/// was released.
+///
+fn process_directory(
+    path: PathBuf,
+    fs_config: &FileSystemConfig,
+    models_data: &ModelsConfig,
+    inode_index: &InodeIndex,
+    database_cache: &DatabaseCache,
+    model_limiter: &ModelLimiter,
+) -> bool {
+    //
+    // Claim the directory
+    //
+    // The running file is created atomically before the placement starts.
+    // Directories claimed by other workers (or other watcher processes sharing
+    // the same file system) since the scan are skipped.
+    //
+    let running_file = match path.parent() {
+        Some(parent) => parent.join(fs_config.running_file_name.to_owned()),
+        None => {
+            error!("Unable to get the parent directory for {path:?}");
+            return true;
+        }
+    };
+
+    match ExecutionMsg::claim_file(&running_file, "Analysis claimed") {
+        Ok(true) => (),
+        Ok(false) => {
+            trace!("Directory {path:?} already claimed by another worker");
+            return true;
+        }
+        Err(err) => {
+            error!("Failed to claim the directory {path:?}: {err}");
+            return true;
+        }
+    }
+
+    let span = info_span!(
+        "PlacingSequenceWatcher",
+        run_id = Uuid::new_v4().to_string().replace("-", "")
+    );
+
+    let _span_guard = span.enter();
+
+    info!(
+        code = TelemetryCode::WTHPLACE0001.to_string(),
+        "Processing the directory {path:?}",
+        path = path
+    );
+
+    let writer = VectorWriter::new();
+    let (non_blocking, _guard) =
+        tracing_appender::non_blocking(writer.to_owned());
+
+    let subscriber = fmt::Subscriber::builder()
+        .with_max_level(Level::TRACE)
+        .with_writer(non_blocking.to_owned()) // Usa o appender como writer
+        .json()
+        .finish();
+
+    //
+    // The running file heartbeat is refreshed while the placement runs, then
+    // other watchers do not reclaim the analysis. The heartbeat stops when
+    // the sender is dropped at the end of the scope.
+    //
+    let response = thread::scope(|scope| {
+        let (heartbeat_sender, heartbeat_receiver) = mpsc::channel::<()>();
 
-        let subscriber = fmt::Subscriber::builder()
-            .with_max_level(Level::TRACE)
-            .with_writer(non_blocking.to_owned()) // Usa o appender como writer
-            .json()
-            .finish();
+        scope.spawn(|| {
+            RunningOwner::keep_alive(&running_file, heartbeat_receiver)
+        });
 
         let response = with_default(subscriber, || {
-            do_placement(path.to_owned(), &fs_config, &models_data, &span)
+            do_placement(
+                path.to_owned(),
+                fs_config,
+                models_data,
+                inode_index,
+                database_cache,
+                model_limiter,
+                &span,
+            )
         });
 
-        let writer = writer.get_buffer();
+        drop(heartbeat_sender);
+        response
+    });
 
-        let lock_writer = match writer.lock() {
-            Ok(writer) => writer.to_owned(),
-            Err(err) => {
-                error!("Failed to lock the writer: {err}");
-                return;
-            }
-        };
+    let writer = writer.get_buffer();
 
-        match response {
-            PlacementResult::Success((msg, parent)) => {
-                info!(
-                    code = TelemetryCode::WTHPLACE0002.to_string(),
-                    "Placement processed successfully",
-                );
+    let lock_writer = match writer.lock() {
+        Ok(writer) => writer.to_owned(),
+        Err(err) => {
+            error!("Failed to lock the writer: {err}");
+            return true;
+        }
+    };
 
-                //
-                // Persist the analysis logging to file
-                //
-                if let Err(err) = ExecutionMsg::write_file(
-                    &parent.join(fs_config.logging_file_name.to_owned()),
-                    String::from_utf8(lock_writer)
-                        .unwrap_or(msg.to_owned())
-                        .as_str(),
-                    Some(true),
-                ) {
-                    error!("Failed to write the logging file: {err}");
-                }
+    match response {
+        PlacementResult::Success((msg, parent)) => {
+            info!(
+                code = TelemetryCode::WTHPLACE0002.to_string(),
+                "Placement processed successfully",
+            );
 
-                //
-                // Persist the analysis success file
-                //
-                if let Err(err) = ExecutionMsg::write_file(
-                    &parent.join(fs_config.success_file_name.to_owned()),
-                    msg.as_str(),
-                    None,
-                ) {
-                    error!("Failed to write the success file: {err}");
-                }
+            //
+            // Persist the analysis logging to file
+            //
+            if let Err(err) = ExecutionMsg::write_file(
+                &parent.join(fs_config.logging_file_name.to_owned()),
+                String::from_utf8(lock_writer)
+                    .unwrap_or(msg.to_owned())
+                    .as_str(),
+                Some(true),
+            ) {
+                error!("Failed to write the logging file: {err}");
             }
-            PlacementResult::Error((msg, parent)) => {
-                let default_path = PathBuf::new();
-                let parent = parent.unwrap_or(
-                    path.as_path()
-                        .parent()
-                        .unwrap_or(&default_path)
-                        .to_path_buf(),
-                );
 
-                //
-                // Persist the analysis logging to file
-                //
-                if let Err(err) = ExecutionMsg::write_file(
-                    &parent.join(fs_config.logging_file_name.to_owned()),
-                    String::from_utf8(lock_writer)
-                        .unwrap_or(msg.to_owned())
-                        .as_str(),
-                    Some(true),
-                ) {
-                    error!("Failed to write the logging file: {err}");
-                }
+            //
+            // Persist the analysis success file
+            //
+            if let Err(err) = ExecutionMsg::write_file(
+                &parent.join(fs_config.success_file_name.to_owned()),
+                msg.as_str(),
+                None,
+            ) {
+                error!("Failed to write the success file: {err}");
+            }
+        }
+        PlacementResult::RetriableError((msg, _)) => {
+            //
+            // Release the claim
+            //
+            // The error file is not written, then the analysis is claimed
+            // again by a following scan.
+            //
+            warn!(
+                code = TelemetryCode::WTHPLACE0012.to_string(),
+                "Retriable placement failure, releasing the analysis: {msg}"
+            );
 
-                //
-                // Persist the analysis error file
-                //
-                if let Err(err) = ExecutionMsg::write_file(
-                    &parent
-                        .to_owned()
-                        .join(fs_config.error_file_name.to_owned()),
-                    msg.as_str(),
-                    None,
-                ) {
-                    error!("Failed to write the error file: {err}");
-                };
+            if let Err(err) = std::fs::remove_file(&running_file) {
+                error!("Failed to release the running file: {err}");
             }
+
+            return false;
+        }
+        PlacementResult::Error((msg, parent)) => {
+            let default_path = PathBuf::new();
+            let parent = parent.unwrap_or(
+                path.as_path()
+                    .parent()
+                    .unwrap_or(&default_path)
+                    .to_path_buf(),
+            );
+
+            //
+            // Persist the analysis logging to file
+            //
+            if let Err(err) = ExecutionMsg::write_file(
+                &parent.join(fs_config.logging_file_name.to_owned()),
+                String::from_utf8(lock_writer)
+                    .unwrap_or(msg.to_owned())
+                    .as_str(),
+                Some(true),
+            ) {
+                error!("Failed to write the logging file: {err}");
+            }
+
+            //
+            // Persist the analysis error file
+            //
+            if let Err(err) = ExecutionMsg::write_file(
+                &parent.to_owned().join(fs_config.error_file_name.to_owned()),
+                msg.as_str(),
+                None,
+            ) {
+                error!("Failed to write the error file: {err}");
+            };
         }
     }
+
+    true
 }
 
 #[tracing::instrument(name = "DoPlacement", skip_all)]
@@ -299,6 +759,9 @@ fn do_placement(
     path: PathBuf,
     fs_config: &FileSystemConfig,
     models_data: &ModelsConfig,
+    inode_index: &InodeIndex,
+    database_cache: &DatabaseCache,
+    model_limiter: &ModelLimiter,
     span: &tracing::Span,
 ) -> PlacementResult<(String, PathBuf), (String, Option<PathBuf>)> {
     // ? -----------------------------------------------------------------------
@@ -348,11 +811,15 @@ fn do_placement(
     // ? Load the model artifacts
     //
     // The model artifact is a Tree struct containing the model used for
-    // predictions.
+    // predictions. Models are cached between analyses, then the same model is
+    // not reloaded for each analysis. The permit is held until the placement
+    // ends, bounding the analyses running with the same model.
     //
     // ? -----------------------------------------------------------------------
 
-    let mut tree_model = match load_database(database_config.model_path()) {
+    let _permit = model_limiter.acquire(database_config.id);
+
+    let tree_model = match database_cache.get_or_load(&database_config) {
         Ok(tree) => tree,
         Err(e) => {
             let msg = format!(
@@ -362,34 +829,17 @@ fn do_placement(
 
             warn!(code = TelemetryCode::WTHPLACE0005.to_string(), "{msg}");
 
-            return PlacementResult::Error((msg, None));
+            //
+            // Only I/O failures may succeed if retried. Corrupted databases
+            // fail again at every attempt.
+            //
+            return match e.downcast_ref::<std::io::Error>() {
+                Some(_) => PlacementResult::RetriableError((msg, None)),
+                None => PlacementResult::Error((msg, None)),
+            };
         }
     };
 
-    if let Some(path) = database_config.annotations_path() {
-        if let Ok(file) = std::fs::File::open(path) {
-            let content: Option<Vec<Annotation>> =
-                match serde_yaml::from_reader(file) {
-                    Ok(content) => Some(content),
-                    Err(err) => {
-                        warn!(
-                            code = TelemetryCode::WTHPLACE0006.to_string(),
-                            "{msg}",
-                            msg = format!(
-                                "Failed to parse the annotations file: {err}"
-                            )
-                        );
-
-                        None
-                    }
-                };
-
-            if let Some(content) = content {
-                tree_model.annotations = Some(content.clone());
-            }
-        };
-    }
-
     // ? -----------------------------------------------------------------------
     // ? Load the Query file
     //
@@ -399,9 +849,14 @@ fn do_placement(
 
     let (query_file_path, parent) = match path.parent() {
         Some(parent) => {
-            let inode_file = get_file_by_inode(
-                parent.join(fs_config.input_directory.to_owned()),
+            //
+            // Files inheriting the query file inode after the configuration
+            // do not match the recorded identity, then they are ignored
+            //
+            let inode_file = inode_index.get(
+                &parent.join(fs_config.input_directory.to_owned()),
                 cls_config.query_file_id,
+                cls_config.query_file_identity.as_ref(),
             );
 
             match inode_file {
@@ -450,13 +905,9 @@ fn do_placement(
 
     info!(code = TelemetryCode::WTHPLACE0007.to_string(), "{msg}");
 
-    if let Err(err) = ExecutionMsg::write_file(
-        &parent
-            .to_owned()
-            .join(fs_config.running_file_name.to_owned()),
-        msg.as_str(),
-        None,
-    ) {
+    if let Err(err) = RunningOwner::current(&msg)
+        .write_file(&parent.join(fs_config.running_file_name.to_owned()))
+    {
         let msg = format!(
                 "Failed to write the running file for the query file {query_file:?} with model {model_id:?}: {err}",
                 query_file = query_file_path.file_name().to_owned(),
@@ -465,7 +916,7 @@ fn do_placement(
 
         warn!(code = TelemetryCode::WTHPLACE0007.to_string(), "{msg}");
 
-        return PlacementResult::Error((msg, Some(parent.into())));
+        return PlacementResult::RetriableError((msg, Some(parent.into())));
     };
 
     // ? -----------------------------------------------------------------------
@@ -477,15 +928,19 @@ fn do_placement(
         .join(fs_config.output_directory.to_owned().as_str())
         .join(fs_config.results_file_name.to_owned().as_str());
 
+    let placement_config = RunPlacementConfig::default()
+        .with_options(
+            PlacementOptions::default()
+                .with_remove_intersection(cls_config.remove_intersection),
+        )
+        .with_overwrite(true)
+        .with_output_format(cls_config.output_format.to_owned());
+
     if let Err(err) = place_sequences(
         query_file,
         &tree_model,
         &output_file,
-        &None,
-        &None,
-        &true,
-        &cls_config.output_format,
-        &cls_config.remove_intersection,
+        &placement_config,
         &Some(span),
     ) {
         let msg = format!(
@@ -496,6 +951,10 @@ fn do_placement(
 
         warn!(code = TelemetryCode::WTHPLACE0008.to_string(), "{msg}");
 
+        //
+        // Placement failures result from invalid query files, then they are
+        // not retried
+        //
         return PlacementResult::Error((msg, Some(parent.into())));
     }
 
@@ -514,3 +973,386 @@ fn do_placement(
 
     PlacementResult::Success((msg, parent.into()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::MetadataExt;
+
+    /// Build configured work directories in a temporary serve directory
+    fn build_work_dirs(
+        serve_directory: &str,
+        names: &[&str],
+    ) -> (FileSystemConfig, ModelsConfig, Vec<PathBuf>) {
+        let serve_directory = PathBuf::from(serve_directory);
+        let source = PathBuf::from("../../tests/data/public/019051d9-4c7a-7b2d-9dd1-66ef92236fd7/input/bsub-refseq-sample50percentRemaining-clean-diamond.fasta");
+
+        let _ = std::fs::remove_dir_all(&serve_directory);
+
+        let work_dirs = names
+            .iter()
+            .map(|name| serve_directory.join("public").join(name))
+            .collect::<Vec<PathBuf>>();
+
+        for work_dir in work_dirs.iter() {
+            std::fs::create_dir_all(work_dir.join("input")).unwrap();
+            std::fs::create_dir_all(work_dir.join("output")).unwrap();
+
+            let query_file = work_dir.join("input").join("query.fasta");
+            std::fs::copy(&source, &query_file).unwrap();
+
+            std::fs::write(
+                work_dir.join("config.yaml"),
+                format!(
+                    "name: test-analysis\n\
+                    email: test@example.com\n\
+                    queryFileId: {inode}\n\
+                    modelId: ce47d8bc-2885-3d2c-8247-5b8c8b28fefe\n\
+                    outputFormat: yaml\n\
+                    workDir: {work_dir}\n",
+                    inode = std::fs::metadata(&query_file).unwrap().ino(),
+                    work_dir = work_dir.file_name().unwrap().to_str().unwrap()
+                ),
+            )
+            .unwrap();
+        }
+
+        let fs_config = FileSystemConfig {
+            public_directory: "public".to_string(),
+            serve_directory: serve_directory.to_str().unwrap().to_string(),
+            input_directory: "input".to_string(),
+            output_directory: "output".to_string(),
+            config_file_name: "config.yaml".to_string(),
+            results_file_name: "result.yaml".to_string(),
+            success_file_name: "success.yaml".to_string(),
+            running_file_name: "running.yaml".to_string(),
+            error_file_name: "error.yaml".to_string(),
+            logging_file_name: "logging.jsonl".to_string(),
+            max_upload_bytes: None,
+        };
+
+        let models_data: ModelsConfig = serde_yaml::from_str(
+            "- id: ce47d8bc-2885-3d2c-8247-5b8c8b28fefe\n  \
+            name: Bacillus subtilis group\n  \
+            modelPath: ../../tests/models/bsub-gyrb-k35.cls\n",
+        )
+        .unwrap();
+
+        (fs_config, models_data, work_dirs)
+    }
+
+    #[test]
+    fn test_scan_directories_with_concurrent_analyses() {
+        let (fs_config, models_data, work_dirs) =
+            build_work_dirs("/tmp/cls-watcher-concurrent", &["a", "b", "c"]);
+
+        scan_directories(
+            &fs_config,
+            &models_data,
+            &DatabaseCache::new(1),
+            &ModelLimiter::new(None),
+            &GracefulShutdown::default(),
+            2,
+            false,
+        );
+
+        for work_dir in work_dirs.iter() {
+            assert!(work_dir.join("success.yaml").exists());
+            assert!(!work_dir.join("error.yaml").exists());
+        }
+    }
+
+    #[test]
+    fn test_scan_directories_loads_the_database_once() {
+        let (fs_config, models_data, work_dirs) =
+            build_work_dirs("/tmp/cls-watcher-cache", &["a", "b"]);
+
+        let database_cache = DatabaseCache::new(1);
+
+        scan_directories(
+            &fs_config,
+            &models_data,
+            &database_cache,
+            &ModelLimiter::new(Some(1)),
+            &GracefulShutdown::default(),
+            1,
+            false,
+        );
+
+        for work_dir in work_dirs.iter() {
+            assert!(work_dir.join("success.yaml").exists());
+        }
+
+        assert_eq!(database_cache.loads_count(), 1);
+    }
+
+    #[test]
+    fn test_scan_directories_in_dry_run() {
+        let (fs_config, models_data, work_dirs) =
+            build_work_dirs("/tmp/cls-watcher-dry-run", &["a", "b"]);
+
+        let database_cache = DatabaseCache::new(1);
+
+        scan_directories(
+            &fs_config,
+            &models_data,
+            &database_cache,
+            &ModelLimiter::new(None),
+            &GracefulShutdown::default(),
+            2,
+            true,
+        );
+
+        for work_dir in work_dirs.iter() {
+            assert!(!work_dir.join("running.yaml").exists());
+            assert!(!work_dir.join("success.yaml").exists());
+            assert!(!work_dir.join("error.yaml").exists());
+            assert!(!work_dir.join("output").join("result.yaml").exists());
+        }
+
+        assert_eq!(database_cache.loads_count(), 0);
+    }
+
+    #[test]
+    fn test_terminal_failures_are_not_retried() {
+        let (fs_config, _, work_dirs) =
+            build_work_dirs("/tmp/cls-watcher-terminal-failure", &["a"]);
+
+        //
+        // The configured model is unknown, then the analysis fails with a
+        // terminal error and it is not released to be retried
+        //
+        let released = scan_directories(
+            &fs_config,
+            &ModelsConfig(vec![]),
+            &DatabaseCache::new(1),
+            &ModelLimiter::new(None),
+            &GracefulShutdown::default(),
+            1,
+            false,
+        );
+
+        assert_eq!(released, 0);
+
+        let error_file = work_dirs[0].join("error.yaml");
+
+        assert!(error_file.exists());
+        assert!(!work_dirs[0].join("success.yaml").exists());
+        assert!(std::fs::read_to_string(error_file)
+            .unwrap()
+            .contains("not found"));
+    }
+
+    #[test]
+    fn test_retriable_failures_are_released() {
+        let (fs_config, _, work_dirs) =
+            build_work_dirs("/tmp/cls-watcher-retriable-failure", &["a"]);
+
+        //
+        // The model file is missing, then loading it fails with an I/O error
+        //
+        let models_data: ModelsConfig = serde_yaml::from_str(
+            "- id: ce47d8bc-2885-3d2c-8247-5b8c8b28fefe\n  \
+            name: Bacillus subtilis group\n  \
+            modelPath: /tmp/cls-watcher-missing-model.cls\n",
+        )
+        .unwrap();
+
+        let released = scan_directories(
+            &fs_config,
+            &models_data,
+            &DatabaseCache::new(1),
+            &ModelLimiter::new(None),
+            &GracefulShutdown::default(),
+            1,
+            false,
+        );
+
+        //
+        // The analysis is pending again, then a following scan claims it
+        //
+        assert_eq!(released, 1);
+        assert!(!work_dirs[0].join("running.yaml").exists());
+        assert!(!work_dirs[0].join("error.yaml").exists());
+    }
+
+    #[test]
+    fn test_orphaned_running_files_are_reclaimed() {
+        let (fs_config, models_data, work_dirs) =
+            build_work_dirs("/tmp/cls-watcher-orphaned", &["a", "b"]);
+
+        //
+        // The first analysis was interrupted, while the second one ended
+        // before the running file was reconciled
+        //
+        for work_dir in work_dirs.iter() {
+            std::fs::write(work_dir.join("running.yaml"), "msg: running\n")
+                .unwrap();
+        }
+
+        std::fs::write(work_dirs[1].join("error.yaml"), "msg: failed\n")
+            .unwrap();
+
+        //
+        // Recent running files may belong to analyses still running
+        //
+        assert_eq!(
+            reconcile_orphaned_analyses(&fs_config, Duration::from_secs(3600)),
+            0
+        );
+
+        assert_eq!(reconcile_orphaned_analyses(&fs_config, Duration::ZERO), 1);
+        assert!(!work_dirs[0].join("running.yaml").exists());
+        assert!(work_dirs[1].join("running.yaml").exists());
+
+        scan_directories(
+            &fs_config,
+            &models_data,
+            &DatabaseCache::new(1),
+            &ModelLimiter::new(None),
+            &GracefulShutdown::default(),
+            1,
+            false,
+        );
+
+        assert!(work_dirs[0].join("success.yaml").exists());
+        assert!(!work_dirs[1].join("success.yaml").exists());
+    }
+
+    #[test]
+    fn test_new_directories_are_processed_within_one_interval() {
+        let (fs_config, models_data, work_dirs) =
+            build_work_dirs("/tmp/cls-watcher-interval", &["a"]);
+
+        //
+        // The analysis directory is moved out of the public directory, then
+        // it is created again while scans are running
+        //
+        let staged = PathBuf::from("/tmp/cls-watcher-interval-staged");
+        let _ = std::fs::remove_dir_all(&staged);
+        std::fs::rename(&work_dirs[0], &staged).unwrap();
+
+        let interval = Duration::from_secs(1);
+        let scan_delay = ScanDelay::new(interval.as_secs(), false, None, None);
+        let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        let scans = {
+            let stop = stop.to_owned();
+            let fs_config = fs_config.to_owned();
+
+            //
+            // Each tick waits for the scan delay and scans the directories,
+            // as the scan dispatcher does on each cron tick
+            //
+            thread::spawn(move || {
+                let database_cache = DatabaseCache::new(1);
+
+                while !stop.load(std::sync::atomic::Ordering::SeqCst) {
+                    let tick = std::time::Instant::now();
+                    thread::sleep(scan_delay.next_delay());
+
+                    scan_directories(
+                        &fs_config,
+                        &models_data,
+                        &database_cache,
+                        &ModelLimiter::new(None),
+                        &GracefulShutdown::default(),
+                        1,
+                        false,
+                    );
+
+                    thread::sleep(interval.saturating_sub(tick.elapsed()));
+                }
+            })
+        };
+
+        thread::sleep(interval / 2);
+
+        std::fs::rename(&staged, &work_dirs[0]).unwrap();
+        let created = std::time::Instant::now();
+
+        let claimed = loop {
+            if work_dirs[0].join("running.yaml").exists()
+                || work_dirs[0].join("success.yaml").exists()
+            {
+                break created.elapsed();
+            }
+
+            if created.elapsed() > interval * 5 {
+                break created.elapsed();
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        };
+
+        while !work_dirs[0].join("success.yaml").exists()
+            && created.elapsed() < Duration::from_secs(60)
+        {
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        stop.store(true, std::sync::atomic::Ordering::SeqCst);
+        scans.join().unwrap();
+
+        assert!(claimed <= interval + Duration::from_millis(200));
+        assert!(work_dirs[0].join("success.yaml").exists());
+    }
+
+    #[test]
+    fn test_live_running_files_are_not_reclaimed() {
+        let (fs_config, _, work_dirs) =
+            build_work_dirs("/tmp/cls-watcher-live-owner", &["a", "b", "c"]);
+
+        //
+        // The first analysis is owned by a running process, the second one by
+        // a watcher in another host with a recent heartbeat, and the third one
+        // by a watcher in another host with a stale heartbeat
+        //
+        let current = RunningOwner::current("running");
+
+        let remote = RunningOwner {
+            host: format!("{}-remote", current.host),
+            ..current.to_owned()
+        };
+
+        let stale = RunningOwner {
+            heartbeat: current.heartbeat - 7200,
+            ..remote.to_owned()
+        };
+
+        for (work_dir, owner) in work_dirs.iter().zip([current, remote, stale])
+        {
+            owner.write_file(&work_dir.join("running.yaml")).unwrap();
+        }
+
+        assert_eq!(
+            reconcile_orphaned_analyses(&fs_config, Duration::from_secs(3600)),
+            1
+        );
+
+        assert!(work_dirs[0].join("running.yaml").exists());
+        assert!(work_dirs[1].join("running.yaml").exists());
+        assert!(!work_dirs[2].join("running.yaml").exists());
+    }
+
+    #[test]
+    fn test_scan_directories_after_shutdown() {
+        let (fs_config, models_data, work_dirs) =
+            build_work_dirs("/tmp/cls-watcher-shutdown", &["a"]);
+
+        let shutdown = GracefulShutdown::default();
+        shutdown.shutdown(Duration::ZERO);
+
+        scan_directories(
+            &fs_config,
+            &models_data,
+            &DatabaseCache::new(1),
+            &ModelLimiter::new(None),
+            &shutdown,
+            1,
+            false,
+        );
+
+        assert!(!work_dirs[0].join("running.yaml").exists());
+    }
+}
@@ -0,0 +1,108 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Condvar, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+/// Tracks the analyses running when the watcher is asked to shut down
+///
+/// After the shutdown starts, no new analysis is claimed, while analyses
+/// already running are allowed to finish, then their success or error files
+/// are written before the watcher exits.
+#[derive(Debug, Default)]
+pub(crate) struct GracefulShutdown {
+    shutting_down: AtomicBool,
+    in_flight: Mutex<usize>,
+    finished: Condvar,
+}
+
+/// A running analysis, released on drop
+pub(crate) struct InFlightGuard<'a> {
+    shutdown: &'a GracefulShutdown,
+}
+
+impl GracefulShutdown {
+    /// Register a new analysis
+    ///
+    /// Returns `None` if the shutdown already started, then the analysis
+    /// should not be claimed.
+    pub(crate) fn enter(&self) -> Option<InFlightGuard<'_>> {
+        let mut in_flight = self.lock_in_flight();
+
+        if self.shutting_down.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        *in_flight += 1;
+
+        Some(InFlightGuard { shutdown: self })
+    }
+
+    /// Stop claiming analyses and wait for the running ones
+    ///
+    /// Returns the number of analyses still running after the timeout.
+    pub(crate) fn shutdown(&self, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        let mut in_flight = self.lock_in_flight();
+
+        self.shutting_down.store(true, Ordering::SeqCst);
+
+        while *in_flight > 0 {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+
+            if remaining.is_zero() {
+                break;
+            }
+
+            in_flight = self
+                .finished
+                .wait_timeout(in_flight, remaining)
+                .map(|(in_flight, _)| in_flight)
+                .unwrap_or_else(|err| err.into_inner().0);
+        }
+
+        *in_flight
+    }
+
+    /// A poisoned lock only indicates a panicked analysis. The counter is
+    /// still consistent, since guards are released on unwinding.
+    fn lock_in_flight(&self) -> MutexGuard<'_, usize> {
+        self.in_flight.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        *self.shutdown.lock_in_flight() -= 1;
+        self.shutdown.finished.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_shutdown_waits_for_in_flight_analyses() {
+        let shutdown = GracefulShutdown::default();
+
+        thread::scope(|scope| {
+            let guard = shutdown.enter().unwrap();
+
+            scope.spawn(move || {
+                thread::sleep(Duration::from_millis(100));
+                drop(guard);
+            });
+
+            assert_eq!(shutdown.shutdown(Duration::from_secs(5)), 0);
+        });
+
+        //
+        // No analysis is claimed after the shutdown starts
+        //
+        assert!(shutdown.enter().is_none());
+    }
+}
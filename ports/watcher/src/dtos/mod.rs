@@ -1,3 +1,7 @@
+pub mod graceful_shutdown;
 pub mod log_writer;
+pub mod model_limiter;
 pub mod placement_result;
+pub mod queue_status;
+pub mod scan_delay;
 pub mod telemetry_code;
@@ -0,0 +1,129 @@
+use std::{
+    collections::HashMap,
+    sync::{Condvar, Mutex, MutexGuard},
+};
+use uuid::Uuid;
+
+/// Limits the number of analyses running at once with the same model
+///
+/// Each analysis holds a loaded database while running, then analyses using
+/// many large models at once may exhaust the memory. Analyses exceeding the
+/// limit wait until a running analysis of the same model finishes. Analyses
+/// of distinct models are not limited by each other.
+#[derive(Debug, Default)]
+pub(crate) struct ModelLimiter {
+    limit: Option<usize>,
+    running: Mutex<HashMap<Uuid, usize>>,
+    released: Condvar,
+}
+
+/// A running analysis slot, released on drop
+pub(crate) struct ModelPermit<'a> {
+    limiter: &'a ModelLimiter,
+    model_id: Uuid,
+}
+
+impl ModelLimiter {
+    /// Build a limiter allowing `limit` analyses per model at once
+    ///
+    /// Analyses are not limited if `None` is given.
+    pub(crate) fn new(limit: Option<usize>) -> Self {
+        ModelLimiter {
+            limit: limit.map(|limit| limit.max(1)),
+            ..Default::default()
+        }
+    }
+
+    /// Wait for a free slot of the model
+    pub(crate) fn acquire(&self, model_id: Uuid) -> ModelPermit<'_> {
+        let mut running = self.lock_running();
+
+        if let Some(limit) = self.limit {
+            while running.get(&model_id).copied().unwrap_or(0) >= limit {
+                running = self
+                    .released
+                    .wait(running)
+                    .unwrap_or_else(|err| err.into_inner());
+            }
+        }
+
+        *running.entry(model_id).or_insert(0) += 1;
+
+        ModelPermit {
+            limiter: self,
+            model_id,
+        }
+    }
+
+    /// A poisoned lock only indicates a panicked analysis. The counters are
+    /// still consistent, since permits are released on unwinding.
+    fn lock_running(&self) -> MutexGuard<'_, HashMap<Uuid, usize>> {
+        self.running.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl Drop for ModelPermit<'_> {
+    fn drop(&mut self) {
+        let mut running = self.limiter.lock_running();
+
+        if let Some(count) = running.get_mut(&self.model_id) {
+            *count -= 1;
+
+            if *count == 0 {
+                running.remove(&self.model_id);
+            }
+        }
+
+        self.limiter.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{atomic::AtomicUsize, atomic::Ordering, Barrier},
+        thread,
+        time::Duration,
+    };
+
+    /// Run two analyses in parallel returning the max of simultaneous ones
+    fn run_analyses(limiter: &ModelLimiter, models: [Uuid; 2]) -> usize {
+        let active = AtomicUsize::new(0);
+        let max_active = AtomicUsize::new(0);
+        let started = Barrier::new(2);
+
+        let (active, max_active, started) = (&active, &max_active, &started);
+
+        thread::scope(|scope| {
+            for model_id in models {
+                scope.spawn(move || {
+                    started.wait();
+
+                    let _permit = limiter.acquire(model_id);
+                    let current = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_active.fetch_max(current, Ordering::SeqCst);
+
+                    thread::sleep(Duration::from_millis(100));
+                    active.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        max_active.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_model_limiter_serializes_analyses_of_the_same_model() {
+        let limiter = ModelLimiter::new(Some(1));
+        let model_id = Uuid::new_v4();
+
+        assert_eq!(run_analyses(&limiter, [model_id, model_id]), 1);
+
+        //
+        // Analyses of distinct models may overlap
+        //
+        assert_eq!(run_analyses(&limiter, [model_id, Uuid::new_v4()]), 2);
+        assert!(limiter.lock_running().is_empty());
+    }
+}
@@ -1,4 +1,10 @@
 pub(crate) enum PlacementResult<T, U> {
     Success(T),
+
+    /// A terminal failure, as invalid configurations or missing models, which
+    /// would fail again if retried
     Error(U),
+
+    /// A failure which may succeed if retried, as I/O errors
+    RetriableError(U),
 }
@@ -0,0 +1,154 @@
+use classeq_ports_lib::FileSystemConfig;
+use serde::Serialize;
+use std::{path::PathBuf, time::SystemTime};
+
+/// A summary of the analyses found in the serve directory
+///
+/// Analyses are classified from the lock files of each work directory. A
+/// growing number of pending analyses, or an old pending analysis, indicates a
+/// stuck queue.
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct QueueStatus {
+    pub(crate) pending: usize,
+    pub(crate) running: usize,
+    pub(crate) succeeded: usize,
+    pub(crate) failed: usize,
+
+    /// The age (in seconds) of the oldest pending analysis configuration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) oldest_pending_age_secs: Option<u64>,
+}
+
+impl QueueStatus {
+    /// Summarize the analyses of the public directory
+    pub(crate) fn from_serve_directory(fs_config: &FileSystemConfig) -> Self {
+        Self::from_serve_directory_at(fs_config, SystemTime::now())
+    }
+
+    /// Summarize the analyses computing the pending ages relative to `now`
+    ///
+    /// Success and error files take precedence over the running file, since
+    /// the running file is kept after the analysis ends. Directories without
+    /// the analysis configuration file are ignored.
+    fn from_serve_directory_at(
+        fs_config: &FileSystemConfig,
+        now: SystemTime,
+    ) -> Self {
+        let mut status = QueueStatus::default();
+
+        let work_dirs = PathBuf::from(&fs_config.serve_directory)
+            .join(fs_config.public_directory.to_owned())
+            .read_dir()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path());
+
+        for work_dir in work_dirs {
+            let config_file = work_dir.join(&fs_config.config_file_name);
+
+            if !config_file.exists() {
+                continue;
+            }
+
+            if work_dir.join(&fs_config.success_file_name).exists() {
+                status.succeeded += 1;
+            } else if work_dir.join(&fs_config.error_file_name).exists() {
+                status.failed += 1;
+            } else if work_dir.join(&fs_config.running_file_name).exists() {
+                status.running += 1;
+            } else {
+                status.pending += 1;
+
+                let age = config_file
+                    .metadata()
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .and_then(|modified| now.duration_since(modified).ok())
+                    .map(|age| age.as_secs());
+
+                status.oldest_pending_age_secs =
+                    status.oldest_pending_age_secs.max(age);
+            }
+        }
+
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_queue_status_counts_lock_file_states() {
+        let serve_directory = PathBuf::from("/tmp/cls-watcher-queue-status");
+        let _ = std::fs::remove_dir_all(&serve_directory);
+
+        //
+        // Lock files of each work directory. The running file is kept after
+        // the analysis ends, then finished analyses also contain it.
+        //
+        let work_dirs: [(&str, &[&str]); 7] = [
+            ("pending-a", &["config.yaml"]),
+            ("pending-b", &["config.yaml"]),
+            ("running", &["config.yaml", "running.yaml"]),
+            (
+                "succeeded",
+                &["config.yaml", "running.yaml", "success.yaml"],
+            ),
+            ("failed-a", &["config.yaml", "running.yaml", "error.yaml"]),
+            ("failed-b", &["config.yaml", "error.yaml"]),
+            ("not-configured", &[]),
+        ];
+
+        for (name, files) in work_dirs {
+            let work_dir = serve_directory.join("public").join(name);
+            std::fs::create_dir_all(&work_dir).unwrap();
+
+            for file in files {
+                std::fs::write(work_dir.join(file), "msg: test\n").unwrap();
+            }
+        }
+
+        let fs_config = FileSystemConfig {
+            public_directory: "public".to_string(),
+            serve_directory: serve_directory.to_str().unwrap().to_string(),
+            input_directory: "input".to_string(),
+            output_directory: "output".to_string(),
+            config_file_name: "config.yaml".to_string(),
+            results_file_name: "result.yaml".to_string(),
+            success_file_name: "success.yaml".to_string(),
+            running_file_name: "running.yaml".to_string(),
+            error_file_name: "error.yaml".to_string(),
+            logging_file_name: "logging.jsonl".to_string(),
+            max_upload_bytes: None,
+        };
+
+        let status = QueueStatus::from_serve_directory_at(
+            &fs_config,
+            SystemTime::now() + Duration::from_secs(120),
+        );
+
+        assert_eq!(status.pending, 2);
+        assert_eq!(status.running, 1);
+        assert_eq!(status.succeeded, 1);
+        assert_eq!(status.failed, 2);
+        assert!((120..180).contains(&status.oldest_pending_age_secs.unwrap()));
+
+        //
+        // Without pending analyses, the oldest pending age is not reported
+        //
+        std::fs::remove_dir_all(serve_directory.join("public/pending-a"))
+            .unwrap();
+        std::fs::remove_dir_all(serve_directory.join("public/pending-b"))
+            .unwrap();
+
+        let status = QueueStatus::from_serve_directory(&fs_config);
+
+        assert_eq!(status.pending, 0);
+        assert_eq!(status.oldest_pending_age_secs, None);
+    }
+}
@@ -0,0 +1,82 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// The delay applied before each directory scan
+///
+/// Scans are randomly delayed by default to avoid multiple workers scanning
+/// the same directories at the same time. Random delays make the watcher
+/// behavior hard to reproduce, then the delay can be fixed or seeded.
+#[derive(Clone, Debug)]
+pub(crate) enum ScanDelay {
+    /// A fixed delay (in seconds) applied before each scan
+    Fixed(u64),
+
+    /// A random delay between one second and the max value (in seconds)
+    Random { max: u64, rng: Arc<Mutex<StdRng>> },
+}
+
+impl ScanDelay {
+    pub(crate) fn new(
+        interval: u64,
+        jitter: bool,
+        fixed_delay: Option<u64>,
+        seed: Option<u64>,
+    ) -> Self {
+        if !jitter {
+            return ScanDelay::Fixed(fixed_delay.unwrap_or(0));
+        }
+
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        ScanDelay::Random {
+            max: interval.max(1),
+            rng: Arc::new(Mutex::new(rng)),
+        }
+    }
+
+    /// Get the delay to be applied before the next scan
+    pub(crate) fn next_delay(&self) -> Duration {
+        match self {
+            ScanDelay::Fixed(seconds) => Duration::from_secs(*seconds),
+            ScanDelay::Random { max, rng } => {
+                let seconds = match rng.lock() {
+                    Ok(mut rng) => rng.gen_range(1..=*max),
+                    Err(_) => *max,
+                };
+
+                Duration::from_secs(seconds)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_delay_without_jitter() {
+        let interval = 5;
+        let delay = ScanDelay::new(interval, false, None, None);
+
+        for _ in 0..10 {
+            assert!(delay.next_delay() < Duration::from_secs(interval));
+        }
+    }
+
+    #[test]
+    fn test_scan_delay_with_seed() {
+        let first = ScanDelay::new(60, true, None, Some(42));
+        let second = ScanDelay::new(60, true, None, Some(42));
+
+        for _ in 0..10 {
+            assert_eq!(first.next_delay(), second.next_delay());
+        }
+    }
+}
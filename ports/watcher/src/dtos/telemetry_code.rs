@@ -43,6 +43,27 @@ pub(crate) enum TelemetryCode {
     ///
     /// Messages related to the placement run.
     WTHPLACE0008,
+
+    /// Dry-run planning
+    ///
+    /// Messages related to analyses planned in dry-run mode.
+    WTHPLACE0009,
+
+    /// Queue status
+    ///
+    /// Messages related to the summary of the analyses queue.
+    WTHPLACE0010,
+
+    /// Graceful shutdown
+    ///
+    /// Messages related to the shutdown and to analyses interrupted by
+    /// previous shutdowns.
+    WTHPLACE0011,
+
+    /// Analysis release
+    ///
+    /// Messages related to analyses released after retriable failures.
+    WTHPLACE0012,
 }
 
 impl Display for TelemetryCode {
@@ -9,8 +9,55 @@ pub(crate) struct WatchConfig {
     pub(crate) worker_name: String,
     pub(crate) workers: u32,
     pub(crate) interval: u64,
+
+    /// The number of scans dispatched again when analyses fail with retriable
+    /// errors (e.g. I/O errors). Terminal errors are not retried.
     pub(crate) retries: u32,
     pub(crate) max_threads: u32,
+
+    /// The maximum number of analyses processed in parallel by each scan
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) max_concurrent_analyses: Option<u32>,
+
+    /// The maximum number of analyses using the same model running at once,
+    /// shared by all workers. Not limited if not provided.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) max_concurrent_analyses_per_model: Option<u32>,
+
+    /// The maximum number of databases kept in memory between analyses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) max_cached_models: Option<u32>,
+
+    /// If false, the random delay before each scan is disabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) jitter: Option<bool>,
+
+    /// The delay (in seconds) used before each scan when jitter is disabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) fixed_delay: Option<u64>,
+
+    /// The seed used to generate deterministic random delays
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) seed: Option<u64>,
+
+    /// If true, pending analyses are only logged, without running placements
+    /// or writing lock files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) dry_run: Option<bool>,
+
+    /// The name of the queue status file written to the serve directory
+    /// before each scan. The status is only logged if not provided.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) queue_status_file_name: Option<String>,
+
+    /// The time (in seconds) waited for running analyses on shutdown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) shutdown_timeout_secs: Option<u64>,
+
+    /// The heartbeat age (in seconds) of running files without success or
+    /// error files reclaimed on startup, since their analyses were interrupted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) orphaned_running_secs: Option<u64>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
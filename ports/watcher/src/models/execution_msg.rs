@@ -1,6 +1,13 @@
+use super::running_owner::RunningOwner;
+
 use anyhow::Result;
 use serde::Serialize;
-use std::{fs::File, io::Write, path::PathBuf};
+use std::{
+    fs::{File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::PathBuf,
+};
+use tracing::error;
 
 #[derive(Clone, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -55,4 +62,72 @@ impl ExecutionMsg {
             },
         }
     }
+
+    /// Atomically claim a lock file
+    ///
+    /// The file is created only if it does not exist yet, then only a single
+    /// caller is able to claim it, even between concurrent processes sharing
+    /// the same file system. Returns `false` if the file was already claimed.
+    /// The lock file records the current process as the owner.
+    pub(crate) fn claim_file(path: &PathBuf, msg: &str) -> Result<bool> {
+        let mut output =
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    return Ok(false)
+                }
+                Err(e) => {
+                    error!("Failed to create the lock file {path:?}: {e}");
+                    return Err(e.into());
+                }
+            };
+
+        let content = serde_yaml::to_string(&RunningOwner::current(msg))?;
+
+        match write!(output, "{}", content) {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                error!("Failed to write the lock file {path:?}: {e}");
+                Err(e.into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{Arc, Barrier},
+        thread,
+    };
+
+    #[test]
+    fn test_claim_file_is_exclusive() {
+        let path = PathBuf::from("/tmp/cls-watcher-claim-running.yaml");
+        let _ = std::fs::remove_file(&path);
+
+        let barrier = Arc::new(Barrier::new(2));
+
+        let handles = (0..2)
+            .map(|i| {
+                let path = path.to_owned();
+                let barrier = barrier.to_owned();
+
+                thread::spawn(move || {
+                    barrier.wait();
+                    ExecutionMsg::claim_file(&path, &format!("worker {i}"))
+                        .unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let claims = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .filter(|claimed| *claimed)
+            .count();
+
+        assert_eq!(claims, 1);
+    }
 }
@@ -1,3 +1,4 @@
 pub mod config_file;
 pub mod execution_msg;
 pub mod reminder;
+pub mod running_owner;
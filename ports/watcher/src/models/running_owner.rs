@@ -0,0 +1,146 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, RecvTimeoutError},
+    time::Duration,
+};
+use tracing::warn;
+use uuid::Uuid;
+
+/// The owner of a running file
+///
+/// Running files are written with the host and the process claiming the
+/// analysis, and the heartbeat is refreshed while the analysis runs. Running
+/// files are reclaimed only if the owner process is gone or the heartbeat is
+/// stale, then analyses running in other watchers are not processed twice.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RunningOwner {
+    pub(crate) msg: String,
+    pub(crate) host: String,
+    pub(crate) pid: u32,
+
+    /// The last heartbeat as a Unix timestamp (in seconds)
+    pub(crate) heartbeat: i64,
+}
+
+impl RunningOwner {
+    /// The interval between heartbeats of running analyses
+    pub(crate) const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+    /// The owner of running files written by the current process
+    pub(crate) fn current(msg: &str) -> Self {
+        RunningOwner {
+            msg: msg.to_owned(),
+            host: current_host(),
+            pid: std::process::id(),
+            heartbeat: Utc::now().timestamp(),
+        }
+    }
+
+    /// Read the owner from a running file
+    ///
+    /// Returns `None` for running files without owner, as written by previous
+    /// versions or by the API.
+    pub(crate) fn from_file(path: &Path) -> Option<Self> {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_yaml::from_str(&content).ok())
+    }
+
+    /// Write the running file replacing the previous content at once
+    pub(crate) fn write_file(&self, path: &Path) -> Result<()> {
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(format!(".{}", Uuid::new_v4().simple()));
+        let temp_path = PathBuf::from(temp_path);
+
+        std::fs::write(&temp_path, serde_yaml::to_string(self)?)?;
+
+        if let Err(err) = std::fs::rename(&temp_path, path) {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
+    /// Check if the running file was abandoned by its owner
+    ///
+    /// Owners in the current host are checked through the process table.
+    /// Owners in other hosts are considered gone when the heartbeat is older
+    /// than `max_age`.
+    pub(crate) fn is_abandoned(&self, max_age: Duration) -> bool {
+        if self.host == current_host() {
+            return !Path::new("/proc").join(self.pid.to_string()).exists();
+        }
+
+        let age = Utc::now().timestamp().saturating_sub(self.heartbeat);
+
+        age >= max_age.as_secs() as i64
+    }
+
+    /// Refresh the heartbeat of the running file until stopped
+    ///
+    /// The heartbeat stops when the sender side of `stop` is dropped.
+    pub(crate) fn keep_alive(path: &Path, stop: Receiver<()>) {
+        while let Err(RecvTimeoutError::Timeout) =
+            stop.recv_timeout(Self::HEARTBEAT_INTERVAL)
+        {
+            let mut owner = Self::from_file(path)
+                .unwrap_or_else(|| Self::current("Analysis claimed"));
+
+            owner.heartbeat = Utc::now().timestamp();
+
+            if let Err(err) = owner.write_file(path) {
+                warn!("Failed to refresh the running file {path:?}: {err}");
+            }
+        }
+    }
+}
+
+/// The name of the current host
+///
+/// Read from the kernel on Linux, falling back to the `HOSTNAME` variable.
+fn current_host() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|host| host.trim().to_string())
+        .ok()
+        .filter(|host| !host.is_empty())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_running_owner_is_abandoned() {
+        let current = RunningOwner::current("running");
+
+        assert!(!current.is_abandoned(Duration::ZERO));
+
+        //
+        // Owners in other hosts are alive while the heartbeat is recent
+        //
+        let remote = RunningOwner {
+            host: format!("{}-remote", current.host),
+            ..current.to_owned()
+        };
+
+        assert!(!remote.is_abandoned(Duration::from_secs(3600)));
+        assert!(remote.is_abandoned(Duration::ZERO));
+
+        //
+        // Owners in the current host are gone with their process
+        //
+        let gone = RunningOwner {
+            pid: u32::MAX,
+            ..current
+        };
+
+        assert!(gone.is_abandoned(Duration::from_secs(3600)));
+    }
+}